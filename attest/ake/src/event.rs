@@ -239,8 +239,10 @@ impl MealyOutput for AuthResponse {}
 impl MealyInput for AuthResponse {}
 
 /// The output of an initiator when the responder has successfully
-/// authenticated itself and the key exchange is complete.
-pub type AuthSuccess = ();
+/// authenticated itself and the key exchange is complete. This is the
+/// responder's verification report, which has been checked against the
+/// expected measurement.
+pub type AuthSuccess = VerificationReport;
 
 /// Authentication success is output from an initiator
 impl MealyOutput for AuthSuccess {}
@@ -211,7 +211,7 @@ where
                         reader: result.responder_cipher,
                         binding: result.channel_binding,
                     },
-                    (),
+                    remote_report,
                 ))
             }
         }
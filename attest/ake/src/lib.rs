@@ -114,16 +114,18 @@ mod test {
 
         // initiator = authpending, responder = start
 
-        let auth_request_input = AuthRequestInput::new(auth_request_output, identity, ias_report);
+        let auth_request_input =
+            AuthRequestInput::new(auth_request_output, identity, ias_report.clone());
         let (responder, auth_response) = responder
             .try_next(&mut csprng, auth_request_input)
             .expect("Responder could not process auth request");
 
         // initiator = authpending, responder = ready
 
-        let (initiator, _) = initiator
+        let (initiator, remote_report) = initiator
             .try_next(&mut csprng, auth_response)
             .expect("Initiator not process auth response");
+        assert_eq!(remote_report, ias_report);
 
         // initiator = ready, responder = ready
 
@@ -23,6 +23,7 @@ use std::{
 use transaction::{
     amount::Amount,
     encrypted_fog_hint::EncryptedFogHint,
+    memo::EncryptedMemo,
     range::Range,
     ring_signature::{
         Blinding, Commitment, CurvePoint, CurveScalar, Error as RingSigError, KeyImage, RingMLSAG,
@@ -31,7 +32,7 @@ use transaction::{
     tx,
     tx::{TxOutMembershipElement, TxOutMembershipHash, TxOutMembershipProof},
     validation::TransactionValidationError,
-    BlockSignature, RedactedTx,
+    BlockSignature, BlockSignatureSet, RedactedTx,
 };
 
 #[derive(Debug, Eq, PartialEq, Copy, Clone)]
@@ -355,6 +356,34 @@ impl TryFrom<&blockchain::BlockSignature> for BlockSignature {
     }
 }
 
+/// Convert BlockSignatureSet --> blockchain::BlockSignatureSet.
+impl From<&BlockSignatureSet> for blockchain::BlockSignatureSet {
+    fn from(src: &BlockSignatureSet) -> Self {
+        let mut dst = blockchain::BlockSignatureSet::new();
+        let signatures: Vec<blockchain::BlockSignature> = src
+            .signatures()
+            .iter()
+            .map(blockchain::BlockSignature::from)
+            .collect();
+        dst.set_signatures(RepeatedField::from_vec(signatures));
+        dst
+    }
+}
+
+/// Convert blockchain::BlockSignatureSet --> BlockSignatureSet.
+impl TryFrom<&blockchain::BlockSignatureSet> for BlockSignatureSet {
+    type Error = ConversionError;
+
+    fn try_from(source: &blockchain::BlockSignatureSet) -> Result<Self, Self::Error> {
+        let signatures = source
+            .get_signatures()
+            .iter()
+            .map(BlockSignature::try_from)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(BlockSignatureSet::new(signatures))
+    }
+}
+
 /// Convert KeyImage -->  tx_grpc::KeyImage.
 impl From<&KeyImage> for tx_grpc::KeyImage {
     fn from(other: &KeyImage) -> Self {
@@ -627,6 +656,9 @@ impl From<&tx::TxOut> for tx_grpc::TxOut {
         tx_out.set_masked_blinding(masked_blinding_bytes);
         tx_out.set_commitment(source.amount.commitment.to_bytes().to_vec());
         tx_out.set_e_account_hint(source.e_account_hint.as_ref().to_vec());
+        if let Some(e_memo) = &source.e_memo {
+            tx_out.set_e_memo(e_memo.as_ref().to_vec());
+        }
         tx_out
     }
 }
@@ -663,6 +695,9 @@ impl From<&tx::TxOut> for external::TxOut {
         tx_out
             .mut_e_account_hint()
             .set_data(source.e_account_hint.as_ref().to_vec());
+        if let Some(e_memo) = &source.e_memo {
+            tx_out.mut_e_memo().set_data(e_memo.as_ref().to_vec());
+        }
         tx_out
     }
 }
@@ -710,12 +745,21 @@ impl TryFrom<&tx_grpc::TxOut> for tx::TxOut {
             .into();
         let e_account_hint = EncryptedFogHint::try_from(source.get_e_account_hint())
             .map_err(|_| ConversionError::ArrayCastError)?;
+        let e_memo = if source.get_e_memo().is_empty() {
+            None
+        } else {
+            Some(
+                EncryptedMemo::try_from(source.get_e_memo())
+                    .map_err(|_| ConversionError::ArrayCastError)?,
+            )
+        };
 
         let tx_out_stored = tx::TxOut {
             amount,
             target_key,
             public_key,
             e_account_hint,
+            e_memo,
         };
         Ok(tx_out_stored)
     }
@@ -764,12 +808,21 @@ impl TryFrom<&external::TxOut> for tx::TxOut {
             .into();
         let e_account_hint = EncryptedFogHint::try_from(source.get_e_account_hint().get_data())
             .map_err(|_| ConversionError::ArrayCastError)?;
+        let e_memo = if source.has_e_memo() {
+            Some(
+                EncryptedMemo::try_from(source.get_e_memo().get_data())
+                    .map_err(|_| ConversionError::ArrayCastError)?,
+            )
+        } else {
+            None
+        };
 
         let tx_out_stored = tx::TxOut {
             amount,
             target_key,
             public_key,
             e_account_hint,
+            e_memo,
         };
         Ok(tx_out_stored)
     }
@@ -950,6 +1003,13 @@ impl TryInto<TransactionValidationError> for ProposeTxResult {
             }
             Self::TxFeeError => Ok(TransactionValidationError::TxFeeError),
             Self::KeyError => Ok(TransactionValidationError::KeyError),
+            Self::FeeTooLowForCurrentLoad => {
+                Err("FeeTooLowForCurrentLoad is not a TransactionValidationError")
+            }
+            Self::OverCapacity => Err("OverCapacity is not a TransactionValidationError"),
+            Self::ClientRateLimited => Err("ClientRateLimited is not a TransactionValidationError"),
+            Self::SourceRateLimited => Err("SourceRateLimited is not a TransactionValidationError"),
+            Self::TxCacheFull => Err("TxCacheFull is not a TransactionValidationError"),
         }
     }
 }
@@ -972,9 +1032,10 @@ mod conversion_tests {
     use self::rand::{rngs::StdRng, SeedableRng};
     use super::*;
     use curve25519_dalek::ristretto::RistrettoPoint;
-    use keys::FromRandom;
+    use keys::{Ed25519Pair, FromRandom};
     use transaction::{
         account_keys::{AccountKey, PublicAddress},
+        memo::MEMO_LEN,
         onetime_keys::recover_onetime_private_key,
         ring_signature::Blinding,
         tx::{Tx, TxOut, TxOutMembershipProof},
@@ -1151,6 +1212,26 @@ mod conversion_tests {
         assert_eq!(block.contents_hash.as_ref(), [66u8; 32]);
     }
 
+    #[test]
+    // BlockSignatureSet --> blockchain::BlockSignatureSet --> BlockSignatureSet
+    fn test_block_signature_set_round_trip() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let block = transaction::Block::new_origin_block(&[]);
+        let signatures: Vec<BlockSignature> = (0..3)
+            .map(|_| {
+                BlockSignature::from_block_and_keypair(&block, &Ed25519Pair::from_random(&mut rng))
+                    .unwrap()
+            })
+            .collect();
+        let source = BlockSignatureSet::new(signatures);
+
+        let proto = blockchain::BlockSignatureSet::from(&source);
+        assert_eq!(proto.get_signatures().len(), 3);
+
+        let recovered = BlockSignatureSet::try_from(&proto).unwrap();
+        assert_eq!(recovered, source);
+    }
+
     #[test]
     // tx::TxOutStored -> blockchain::TxOut
     fn test_tx_out_from_tx_out_stored() {
@@ -1166,12 +1247,14 @@ mod conversion_tests {
             target_key: RistrettoPublic::from_random(&mut rng).into(),
             public_key: RistrettoPublic::from_random(&mut rng).into(),
             e_account_hint: (&[0u8; 128]).into(),
+            e_memo: Some(EncryptedMemo::try_from(&[3u8; MEMO_LEN][..]).unwrap()),
         };
 
         let tx_out = tx_grpc::TxOut::from(&tx_out_stored);
         let tx_out_ledger = tx::TxOut::try_from(&tx_out).unwrap();
 
         assert_eq!(tx_out_stored.amount, tx_out_ledger.amount);
+        assert_eq!(tx_out_stored.e_memo, tx_out_ledger.e_memo);
 
         assert_eq!(
             tx_out.target_key,
@@ -1206,6 +1289,7 @@ mod conversion_tests {
             target_key: RistrettoPublic::from_random(&mut rng).into(),
             public_key: RistrettoPublic::from_random(&mut rng).into(),
             e_account_hint: (&[0u8; 128]).into(),
+            e_memo: None,
         };
 
         let tx_out_b = tx::TxOut {
@@ -1218,6 +1302,7 @@ mod conversion_tests {
             target_key: RistrettoPublic::from_random(&mut rng).into(),
             public_key: RistrettoPublic::from_random(&mut rng).into(),
             e_account_hint: (&[0u8; 128]).into(),
+            e_memo: None,
         };
 
         let outputs = vec![tx_out_a, tx_out_b];
@@ -1357,7 +1442,7 @@ mod conversion_tests {
         transaction_builder.add_input(input_credentials);
         transaction_builder.set_fee(0);
         transaction_builder
-            .add_output(65536, &bob.default_subaddress(), None, &mut rng)
+            .add_output(65536, &bob.default_subaddress(), None, None, &mut rng)
             .unwrap();
 
         let tx = transaction_builder.build(&mut rng).unwrap();
@@ -54,6 +54,10 @@ pub enum Error {
     /// Signature error
     #[fail(display = "Signature error")]
     Signature,
+
+    /// There was an error sealing or unsealing data.
+    #[fail(display = "Sealing error")]
+    Sealing,
 }
 
 impl From<MessageCipherError> for Error {
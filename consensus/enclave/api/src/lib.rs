@@ -39,6 +39,11 @@ pub struct LocallyEncryptedTx(pub Vec<u8>);
 #[derive(Clone, Debug, Default, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
 pub struct WellFormedEncryptedTx(pub Vec<u8>);
 
+/// A `WellformedTx` sealed to the enclave's identity, so that it can be persisted and read back by
+/// a later instance of the same enclave.
+#[derive(Clone, Debug, Default, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct SealedWellFormedTx(pub Vec<u8>);
+
 /// Tx data we wish to expose to untrusted from well-formed Txs
 #[derive(Clone, Debug, Default, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
 pub struct WellFormedTxContext {
@@ -215,6 +220,17 @@ pub trait ConsensusEnclave {
         peer: &PeerSession,
     ) -> Result<EnclaveMessage<PeerSession>>;
 
+    /// Seal well-formed transactions so that they can be written to disk and survive an enclave
+    /// restart.
+    fn seal_txs(&self, encrypted_txs: &[WellFormedEncryptedTx]) -> Result<Vec<SealedWellFormedTx>>;
+
+    /// Unseal transactions previously sealed by `seal_txs`, re-encrypting them for the current
+    /// enclave.
+    fn unseal_txs(
+        &self,
+        sealed_txs: Vec<SealedWellFormedTx>,
+    ) -> Result<Vec<(WellFormedEncryptedTx, WellFormedTxContext)>>;
+
     /// Redact txs in order to form a new block.
     /// Returns a block, the set of redacted transactions included in it, and a signature over the
     /// block's digest.
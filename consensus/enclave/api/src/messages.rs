@@ -2,7 +2,10 @@
 
 //! The message types used by the consensus_enclave_api.
 
-use crate::{LocallyEncryptedTx, ResponderId, SealedBlockSigningKey, WellFormedEncryptedTx};
+use crate::{
+    LocallyEncryptedTx, ResponderId, SealedBlockSigningKey, SealedWellFormedTx,
+    WellFormedEncryptedTx,
+};
 use alloc::vec::Vec;
 use attest::{Quote, Report, TargetInfo, VerificationReport};
 use attest_enclave_api::{
@@ -106,6 +109,16 @@ pub enum EnclaveCall {
     /// Re-encrypt the given transactions for transmission to a peer.
     TxsForPeer(Vec<WellFormedEncryptedTx>, Vec<u8>, PeerSession),
 
+    /// The [ConsensusEnclave::seal_txs()] method.
+    ///
+    /// Seal the given transactions for persistence.
+    SealTxs(Vec<WellFormedEncryptedTx>),
+
+    /// The [ConsensusEnclave::unseal_txs()] method.
+    ///
+    /// Unseal previously persisted transactions.
+    UnsealTxs(Vec<SealedWellFormedTx>),
+
     /// The [ConsensusEnclave::form_block()] method.
     ///
     /// Converts a list of well-formed, encrypted txs + proofs into a block, a set of redacted transactions
@@ -24,8 +24,8 @@ use attest_enclave_api::{
 use attest_trusted::SealAlgo;
 use common::ResponderId;
 use consensus_enclave_api::{
-    ConsensusEnclave, Error, LocallyEncryptedTx, Result, SealedBlockSigningKey, TxContext,
-    WellFormedEncryptedTx, WellFormedTxContext,
+    ConsensusEnclave, Error, LocallyEncryptedTx, Result, SealedBlockSigningKey, SealedWellFormedTx,
+    TxContext, WellFormedEncryptedTx, WellFormedTxContext,
 };
 use core::convert::{TryFrom, TryInto};
 use digest::Input;
@@ -350,6 +350,40 @@ impl ConsensusEnclave for SgxConsensusEnclave {
         Ok(self.ake.peer_encrypt(peer, aad, &serialized_txs)?)
     }
 
+    fn seal_txs(&self, encrypted_txs: &[WellFormedEncryptedTx]) -> Result<Vec<SealedWellFormedTx>> {
+        encrypted_txs
+            .iter()
+            .map(|encrypted_tx| {
+                let well_formed_tx = self.decrypt_well_formed_tx(encrypted_tx)?;
+                let sealed = IntelSealed::seal_raw(&mcserial::encode(&well_formed_tx), &[])
+                    .map_err(|_| Error::Sealing)?;
+                Ok(SealedWellFormedTx(sealed.as_ref().to_vec()))
+            })
+            .collect()
+    }
+
+    fn unseal_txs(
+        &self,
+        sealed_txs: Vec<SealedWellFormedTx>,
+    ) -> Result<Vec<(WellFormedEncryptedTx, WellFormedTxContext)>> {
+        let mut csprng = McRng::default();
+        sealed_txs
+            .into_iter()
+            .map(|sealed_tx| {
+                let sealed = IntelSealed::try_from(sealed_tx.0).map_err(|_| Error::Sealing)?;
+                let (plaintext, _mac) = sealed.unseal_raw()?;
+                let well_formed_tx: WellFormedTx = mcserial::decode(&plaintext)?;
+
+                // Only well-formed transactions are ever sealed, so there is no need to validate
+                // them again. Whether they are still valid to append is up to untrusted to check.
+                let well_formed_tx_context = WellFormedTxContext::from(well_formed_tx.tx());
+                let well_formed_encrypted_tx =
+                    self.encrypt_well_formed_tx(&well_formed_tx, &mut csprng)?;
+                Ok((well_formed_encrypted_tx, well_formed_tx_context))
+            })
+            .collect()
+    }
+
     fn form_block(
         &self,
         parent_block: &Block,
@@ -523,6 +557,7 @@ fn mint_aggregate_fee(
             target_key,
             public_key,
             e_account_hint: Default::default(),
+            e_memo: None,
         }
     };
 
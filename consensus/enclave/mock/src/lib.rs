@@ -4,7 +4,8 @@
 
 pub use consensus_enclave_api::{
     ConsensusEnclave, ConsensusEnclaveProxy, Error, LocallyEncryptedTx, Result,
-    SealedBlockSigningKey, TxContext, WellFormedEncryptedTx, WellFormedTxContext,
+    SealedBlockSigningKey, SealedWellFormedTx, TxContext, WellFormedEncryptedTx,
+    WellFormedTxContext,
 };
 
 use attest::{IasNonce, Quote, QuoteNonce, Report, TargetInfo, VerificationReport};
@@ -121,12 +122,18 @@ impl ConsensusEnclave for ConsensusServiceMockEnclave {
         Ok(())
     }
 
-    fn client_tx_propose(&self, _msg: EnclaveMessage<ClientSession>) -> Result<TxContext> {
-        Ok(TxContext::default())
+    fn client_tx_propose(&self, msg: EnclaveMessage<ClientSession>) -> Result<TxContext> {
+        // Client messages are expected to hold a plaintext serialized Tx, since `client_accept`
+        // does not establish a session to encrypt them for. Clients of the mock enclave, such as
+        // the consensus service test network's `TestClient`, send them unencrypted.
+        let tx: Tx = mcserial::decode(&msg.data)?;
+        Ok(Self::tx_to_tx_context(&tx))
     }
 
-    fn peer_tx_propose(&self, _msg: EnclaveMessage<PeerSession>) -> Result<Vec<TxContext>> {
-        Ok(Vec::default())
+    fn peer_tx_propose(&self, msg: EnclaveMessage<PeerSession>) -> Result<Vec<TxContext>> {
+        // Peer messages hold plaintext serialized Txs, as produced by `txs_for_peer`.
+        let txs: Vec<Tx> = mcserial::decode(&msg.data)?;
+        Ok(txs.iter().map(Self::tx_to_tx_context).collect())
     }
 
     fn tx_is_well_formed(
@@ -144,11 +151,44 @@ impl ConsensusEnclave for ConsensusServiceMockEnclave {
 
     fn txs_for_peer(
         &self,
-        _encrypted_txs: &[WellFormedEncryptedTx],
-        _aad: &[u8],
-        _peer: &PeerSession,
+        encrypted_txs: &[WellFormedEncryptedTx],
+        aad: &[u8],
+        peer: &PeerSession,
     ) -> Result<EnclaveMessage<PeerSession>> {
-        Ok(EnclaveMessage::default())
+        let txs = encrypted_txs
+            .iter()
+            .map(|encrypted_tx| Ok(mcserial::decode::<Tx>(&encrypted_tx.0)?))
+            .collect::<Result<Vec<Tx>>>()?;
+
+        Ok(EnclaveMessage {
+            aad: aad.to_vec(),
+            channel_id: peer.clone(),
+            data: mcserial::encode(&txs),
+        })
+    }
+
+    fn seal_txs(&self, encrypted_txs: &[WellFormedEncryptedTx]) -> Result<Vec<SealedWellFormedTx>> {
+        // Encrypted transactions are just serialized, and so are sealed ones.
+        Ok(encrypted_txs
+            .iter()
+            .map(|encrypted_tx| SealedWellFormedTx(encrypted_tx.0.clone()))
+            .collect())
+    }
+
+    fn unseal_txs(
+        &self,
+        sealed_txs: Vec<SealedWellFormedTx>,
+    ) -> Result<Vec<(WellFormedEncryptedTx, WellFormedTxContext)>> {
+        sealed_txs
+            .into_iter()
+            .map(|sealed_tx| {
+                let tx: Tx = mcserial::decode(&sealed_tx.0)?;
+                Ok((
+                    WellFormedEncryptedTx(sealed_tx.0),
+                    WellFormedTxContext::from(&tx),
+                ))
+            })
+            .collect()
     }
 
     fn form_block(
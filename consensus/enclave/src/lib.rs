@@ -4,7 +4,7 @@
 
 pub use consensus_enclave_api::{
     ConsensusEnclave, ConsensusEnclaveProxy, EnclaveCall, Error, LocallyEncryptedTx, Result,
-    TxContext, WellFormedEncryptedTx, WellFormedTxContext,
+    SealedWellFormedTx, TxContext, WellFormedEncryptedTx, WellFormedTxContext,
 };
 
 use attest::{
@@ -223,6 +223,21 @@ impl ConsensusEnclave for ConsensusServiceSgxEnclave {
         mcserial::deserialize(&outbuf[..])?
     }
 
+    fn seal_txs(&self, encrypted_txs: &[WellFormedEncryptedTx]) -> Result<Vec<SealedWellFormedTx>> {
+        let inbuf = mcserial::serialize(&EnclaveCall::SealTxs(encrypted_txs.to_vec()))?;
+        let outbuf = self.enclave_call(&inbuf)?;
+        mcserial::deserialize(&outbuf[..])?
+    }
+
+    fn unseal_txs(
+        &self,
+        sealed_txs: Vec<SealedWellFormedTx>,
+    ) -> Result<Vec<(WellFormedEncryptedTx, WellFormedTxContext)>> {
+        let inbuf = mcserial::serialize(&EnclaveCall::UnsealTxs(sealed_txs))?;
+        let outbuf = self.enclave_call(&inbuf)?;
+        mcserial::deserialize(&outbuf[..])?
+    }
+
     fn form_block(
         &self,
         parent_block: &Block,
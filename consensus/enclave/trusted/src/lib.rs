@@ -91,6 +91,12 @@ pub fn ecall_dispatcher(inbuf: &[u8]) -> Result<Vec<u8>, sgx_status_t> {
                 .or(Err(sgx_status_t::SGX_ERROR_UNEXPECTED))?
         }
 
+        EnclaveCall::SealTxs(encrypted_txs) => serialize(&ENCLAVE.seal_txs(&encrypted_txs))
+            .or(Err(sgx_status_t::SGX_ERROR_UNEXPECTED))?,
+
+        EnclaveCall::UnsealTxs(sealed_txs) => serialize(&ENCLAVE.unseal_txs(sealed_txs))
+            .or(Err(sgx_status_t::SGX_ERROR_UNEXPECTED))?,
+
         EnclaveCall::FormBlock(parent_block, encrypted_txs_with_proofs) => {
             serialize(&ENCLAVE.form_block(&parent_block, &encrypted_txs_with_proofs))
                 .or(Err(sgx_status_t::SGX_ERROR_UNEXPECTED))?
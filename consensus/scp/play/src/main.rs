@@ -167,6 +167,11 @@ fn main() {
             LoggedMsg::Marker(s) => {
                 log::info!(logger, "MARKER: {}", s);
             }
+
+            LoggedMsg::SlotEvent(slot_index, record) => {
+                // Events are regenerated by the simulated node, so they are only informational.
+                log::debug!(logger, "Slot {} event: {}", slot_index, record.event);
+            }
         }
     }
 
@@ -0,0 +1,67 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! A utility that converts a slot logged by `LoggingScpNode` into a Mermaid sequence diagram,
+//! showing the messages exchanged and the slot events (phase transitions, ballot counter changes,
+//! timeouts and federated voting thresholds) along a single timeline.
+
+use scp::{
+    scp_log::{LoggedMsg, ScpLogReader, StoredMsg},
+    slot_events::{render_sequence_diagram, TimelineEntry},
+};
+use std::{fs::File, io::Write, path::PathBuf};
+use structopt::StructOpt;
+use transaction::tx::TxHash;
+
+#[derive(Debug, StructOpt)]
+pub struct Config {
+    /// SCP debug dump.
+    #[structopt(long, parse(from_os_str))]
+    pub scp_debug_dump: PathBuf,
+
+    /// Output file. The diagram is written to stdout if omitted.
+    #[structopt(long, parse(from_os_str))]
+    pub out: Option<PathBuf>,
+}
+
+fn main() {
+    let config = Config::from_args();
+
+    let mut scp_reader =
+        ScpLogReader::<TxHash>::new(&config.scp_debug_dump).expect("failed creating ScpLogReader");
+
+    // The first entry is expected to be a NodeSettings entry.
+    let local_node_id = match scp_reader.next() {
+        Some(StoredMsg {
+            msg: LoggedMsg::NodeSettings(node_id, _quorum_set),
+            ..
+        }) => node_id,
+        _ => panic!("failed getting NodeSettings entry"),
+    };
+
+    let mut entries = Vec::new();
+    for stored_msg in scp_reader {
+        let msec = stored_msg.msec_since_start;
+        match stored_msg.msg {
+            LoggedMsg::IncomingMsg(msg) => entries.push((msec, TimelineEntry::Received(msg))),
+            LoggedMsg::OutgoingMsg(msg) => entries.push((msec, TimelineEntry::Sent(msg))),
+            LoggedMsg::ProcessTimeouts(msgs) => {
+                entries.extend(msgs.into_iter().map(|msg| (msec, TimelineEntry::Sent(msg))))
+            }
+            LoggedMsg::SlotEvent(_slot_index, record) => {
+                entries.push((msec, TimelineEntry::Event(record.event)))
+            }
+            LoggedMsg::NodeSettings(..) | LoggedMsg::Nominate(..) | LoggedMsg::Marker(_) => {}
+        }
+    }
+
+    let diagram = render_sequence_diagram(&local_node_id, &entries);
+    match config.out {
+        Some(path) => {
+            let mut file = File::create(&path)
+                .unwrap_or_else(|e| panic!("failed creating {:?}: {:?}", path, e));
+            file.write_all(diagram.as_bytes())
+                .unwrap_or_else(|e| panic!("failed writing {:?}: {:?}", path, e));
+        }
+        None => print!("{}", diagram),
+    }
+}
@@ -6,6 +6,7 @@ use crate::{
     msg::{ExternalizePayload, Msg, Topic},
     quorum_set::QuorumSet,
     slot::{Slot, SlotMetrics},
+    slot_events::SlotEventRecord,
 };
 use common::{
     fast_hash,
@@ -138,6 +139,10 @@ pub trait ScpNode<V: Value>: Send {
     /// Get local node quorum set.
     fn quorum_set(&self) -> QuorumSet;
 
+    /// Replace the local node quorum set. Slots that are already in progress keep using the
+    /// quorum set they were created with.
+    fn set_quorum_set(&mut self, quorum_set: QuorumSet);
+
     /// Submit a list of values of nomination.
     fn nominate(
         &mut self,
@@ -160,6 +165,9 @@ pub trait ScpNode<V: Value>: Send {
     /// Get metrics for a specific slot.
     fn get_slot_metrics(&mut self, slot_index: SlotIndex) -> Option<SlotMetrics>;
 
+    /// Get the events recorded so far for a specific slot.
+    fn get_slot_events(&self, slot_index: SlotIndex) -> Option<Vec<SlotEventRecord>>;
+
     /// Clear the list of pending slots. This is useful if the user of this object realizes they
     /// have fallen behind their peers, and as such they want to abort processing of current slots.
     fn clear_pending_slots(&mut self);
@@ -174,6 +182,10 @@ impl<V: Value, ValidationError: Display> ScpNode<V> for Node<V, ValidationError>
         self.Q.clone()
     }
 
+    fn set_quorum_set(&mut self, quorum_set: QuorumSet) {
+        self.Q = quorum_set;
+    }
+
     /// Submit a list of values of nomination.
     fn nominate(
         &mut self,
@@ -300,6 +312,13 @@ impl<V: Value, ValidationError: Display> ScpNode<V> for Node<V, ValidationError>
         self.pending.get(&slot_index).map(|slot| slot.get_metrics())
     }
 
+    /// Get the events recorded so far for a specific slot.
+    fn get_slot_events(&self, slot_index: SlotIndex) -> Option<Vec<SlotEventRecord>> {
+        self.pending
+            .peek(&slot_index)
+            .map(|slot| slot.get_events().to_vec())
+    }
+
     /// Clear the list of pending slots. This is useful if the user of this object realizes they
     /// have fallen behind their peers, and as such they want to abort processing of current slots.
     fn clear_pending_slots(&mut self) {
@@ -314,6 +333,45 @@ mod tests {
     use common::logger::test_with_logger;
     use std::{iter::FromIterator, sync::Arc};
 
+    #[test_with_logger]
+    /// A new quorum set only applies to slots created after it was set.
+    fn set_quorum_set_applies_to_new_slots(logger: Logger) {
+        let old_quorum_set = QuorumSet::new_with_node_ids(1, vec![test_node_id(2)]);
+        let new_quorum_set =
+            QuorumSet::new_with_node_ids(2, vec![test_node_id(2), test_node_id(3)]);
+
+        let mut node = Node::<u32, TransactionValidationError>::new(
+            test_node_id(1),
+            old_quorum_set.clone(),
+            Arc::new(trivial_validity_fn),
+            Arc::new(trivial_combine_fn),
+            logger.clone(),
+        );
+
+        let msg = node
+            .nominate(1, BTreeSet::from_iter(vec![1000]))
+            .expect("error handling msg")
+            .expect("no msg?");
+        assert_eq!(msg.quorum_set, old_quorum_set);
+
+        node.set_quorum_set(new_quorum_set.clone());
+        assert_eq!(node.quorum_set(), new_quorum_set);
+
+        // The slot in progress keeps its quorum set.
+        let msg = node
+            .nominate(1, BTreeSet::from_iter(vec![2000]))
+            .expect("error handling msg")
+            .expect("no msg?");
+        assert_eq!(msg.quorum_set, old_quorum_set);
+
+        // New slots use the new one.
+        let msg = node
+            .nominate(2, BTreeSet::from_iter(vec![3000]))
+            .expect("error handling msg")
+            .expect("no msg?");
+        assert_eq!(msg.quorum_set, new_quorum_set);
+    }
+
     #[test_with_logger]
     /// Steps through a sequence of messages that allow a two-node network to reach consensus.
     fn basic_two_node_consensus(logger: Logger) {
@@ -1,7 +1,9 @@
 // Copyright (c) 2018-2020 MobileCoin Inc.
 
 //! This crate provides a logging framework for recording and replaying SCP messages.
-use crate::{slot::SlotMetrics, Msg, QuorumSet, ScpNode, SlotIndex, Value};
+use crate::{
+    slot::SlotMetrics, slot_events::SlotEventRecord, Msg, QuorumSet, ScpNode, SlotIndex, Value,
+};
 use common::NodeID;
 use serde::{Deserialize, Serialize};
 use std::{
@@ -28,6 +30,9 @@ pub struct LoggingScpNode<V: Value, N: ScpNode<V>> {
     /// Time when we started logging for current slot.
     slot_start_time: Instant,
 
+    /// Number of slot events of the current slot that have already been logged.
+    slot_event_count: usize,
+
     /// Underlying node implementation.
     node: N,
 
@@ -54,6 +59,9 @@ pub enum LoggedMsg<V: Value> {
 
     /// A message container for an arbitrary string.
     Marker(String),
+
+    /// An event recorded by the slot.
+    SlotEvent(SlotIndex, SlotEventRecord),
 }
 
 /// A stored message.
@@ -82,6 +90,7 @@ impl<V: Value, N: ScpNode<V>> LoggingScpNode<V, N> {
             highest_slot_index: 0,
             msg_count: 0,
             slot_start_time: Instant::now(),
+            slot_event_count: 0,
             _v: Default::default(),
         })
     }
@@ -89,7 +98,7 @@ impl<V: Value, N: ScpNode<V>> LoggingScpNode<V, N> {
     fn write(&mut self, msg: LoggedMsg<V>) -> Result<(), String> {
         let msg_slot_index = match &msg {
             LoggedMsg::IncomingMsg(msg) | LoggedMsg::OutgoingMsg(msg) => msg.slot_index,
-            LoggedMsg::Nominate(slot_index, _) => *slot_index,
+            LoggedMsg::Nominate(slot_index, _) | LoggedMsg::SlotEvent(slot_index, _) => *slot_index,
             _ => self.highest_slot_index,
         };
 
@@ -103,6 +112,7 @@ impl<V: Value, N: ScpNode<V>> LoggingScpNode<V, N> {
             self.highest_slot_index = msg_slot_index;
             self.msg_count = 0;
             self.slot_start_time = Instant::now();
+            self.slot_event_count = 0;
 
             let n: NodeID = self.node.node_id();
             self.write(LoggedMsg::NodeSettings(n, self.node.quorum_set()))?;
@@ -131,6 +141,22 @@ impl<V: Value, N: ScpNode<V>> LoggingScpNode<V, N> {
 
         Ok(())
     }
+
+    /// Log any events the current slot has recorded since the last call.
+    fn write_slot_events(&mut self) -> Result<(), String> {
+        let slot_index = self.highest_slot_index;
+        let new_events: Vec<SlotEventRecord> = match self.node.get_slot_events(slot_index) {
+            Some(events) => events.into_iter().skip(self.slot_event_count).collect(),
+            None => return Ok(()),
+        };
+
+        self.slot_event_count += new_events.len();
+        for event in new_events {
+            self.write(LoggedMsg::SlotEvent(slot_index, event))?;
+        }
+
+        Ok(())
+    }
 }
 
 impl<V: Value, N: ScpNode<V>> ScpNode<V> for LoggingScpNode<V, N> {
@@ -142,6 +168,10 @@ impl<V: Value, N: ScpNode<V>> ScpNode<V> for LoggingScpNode<V, N> {
         self.node.quorum_set()
     }
 
+    fn set_quorum_set(&mut self, quorum_set: QuorumSet) {
+        self.node.set_quorum_set(quorum_set)
+    }
+
     fn nominate(
         &mut self,
         slot_index: SlotIndex,
@@ -150,6 +180,7 @@ impl<V: Value, N: ScpNode<V>> ScpNode<V> for LoggingScpNode<V, N> {
         self.write(LoggedMsg::Nominate(slot_index, values.clone()))?;
 
         let out_msg = self.node.nominate(slot_index, values)?;
+        self.write_slot_events()?;
 
         if let Some(ref msg) = out_msg {
             self.write(LoggedMsg::OutgoingMsg(msg.clone()))?;
@@ -162,6 +193,7 @@ impl<V: Value, N: ScpNode<V>> ScpNode<V> for LoggingScpNode<V, N> {
         self.write(LoggedMsg::IncomingMsg(msg.clone()))?;
 
         let out_msg = self.node.handle(msg)?;
+        self.write_slot_events()?;
 
         if let Some(ref msg) = out_msg {
             self.write(LoggedMsg::OutgoingMsg(msg.clone()))?;
@@ -180,6 +212,7 @@ impl<V: Value, N: ScpNode<V>> ScpNode<V> for LoggingScpNode<V, N> {
 
     fn process_timeouts(&mut self) -> Vec<Msg<V>> {
         let out_msgs = self.node.process_timeouts();
+        self.write_slot_events().expect("failed writing");
 
         if !out_msgs.is_empty() {
             self.write(LoggedMsg::ProcessTimeouts(out_msgs.clone()))
@@ -193,6 +226,10 @@ impl<V: Value, N: ScpNode<V>> ScpNode<V> for LoggingScpNode<V, N> {
         self.node.get_slot_metrics(slot_index)
     }
 
+    fn get_slot_events(&self, slot_index: SlotIndex) -> Option<Vec<SlotEventRecord>> {
+        self.node.get_slot_events(slot_index)
+    }
+
     fn clear_pending_slots(&mut self) {
         self.node.clear_pending_slots()
     }
@@ -10,6 +10,7 @@ use crate::{
         BallotRangePredicate, BallotSetPredicate, FuncPredicate, Predicate, ValueSetPredicate,
     },
     quorum_set::QuorumSet,
+    slot_events::{SlotEvent, SlotEventRecord, VotingStatement, MAX_EVENTS_PER_SLOT},
     utils,
 };
 use common::{
@@ -43,6 +44,28 @@ pub enum Phase {
     Externalize,
 }
 
+impl Phase {
+    /// The position of this phase in the protocol.
+    fn ordinal(self) -> usize {
+        match self {
+            Phase::NominatePrepare => 0,
+            Phase::Prepare => 1,
+            Phase::Commit => 2,
+            Phase::Externalize => 3,
+        }
+    }
+
+    /// The phase that follows this one, if any.
+    fn next(self) -> Option<Phase> {
+        match self {
+            Phase::NominatePrepare => Some(Phase::Prepare),
+            Phase::Prepare => Some(Phase::Commit),
+            Phase::Commit => Some(Phase::Externalize),
+            Phase::Externalize => None,
+        }
+    }
+}
+
 /// The SCP slot.
 pub struct Slot<V: Value, ValidationError: Display> {
     /// Current slot number.
@@ -117,6 +140,12 @@ pub struct Slot<V: Value, ValidationError: Display> {
     /// We can cache this and save on validation calls since the ledger doesn't change during a slot.
     valid_values: BTreeSet<V>,
 
+    /// Events recorded during the lifetime of this slot.
+    events: Vec<SlotEventRecord>,
+
+    /// When this slot was created. Event timestamps are relative to this.
+    created_at: Instant,
+
     /// Logger.
     logger: Logger,
 
@@ -150,6 +179,17 @@ pub struct SlotMetrics {
     pub bN: u32,
 }
 
+/// The parts of the slot state whose changes are recorded as `SlotEvent`s.
+struct EventSnapshot {
+    phase: Phase,
+    bN: u32,
+    num_accepted_nominated: usize,
+    num_confirmed_nominated: usize,
+    PN: Option<u32>,
+    HN: Option<u32>,
+    CN: Option<u32>,
+}
+
 impl<V: Value, ValidationError: Display> Slot<V, ValidationError> {
     ///////////////////////////////////////////////////////////////////////////
     // Public methods (how the Slot interfaces with the Node)
@@ -187,6 +227,8 @@ impl<V: Value, ValidationError: Display> Slot<V, ValidationError> {
             validity_fn,
             combine_fn,
             valid_values: BTreeSet::default(),
+            events: Vec::new(),
+            created_at: Instant::now(),
             logger: logger.new(o!("mc.scp.slot" => slot_index)),
             base_round_interval: Duration::from_millis(1000),
             base_ballot_interval: Duration::from_millis(1000),
@@ -210,10 +252,16 @@ impl<V: Value, ValidationError: Display> Slot<V, ValidationError> {
         }
     }
 
+    /// Get the events recorded so far for this slot, in chronological order.
+    pub fn get_events(&self) -> &[SlotEventRecord] {
+        &self.events
+    }
+
     /// Processes any timeouts that may have occurred.
     /// Returns list of messages to broadcast to network.
     pub fn process_timeouts(&mut self) -> Vec<Msg<V>> {
         let mut msgs = Vec::<Msg<V>>::new();
+        let snapshot = self.event_snapshot();
 
         let mut timeout_occurred = false;
 
@@ -222,6 +270,9 @@ impl<V: Value, ValidationError: Display> Slot<V, ValidationError> {
             && Instant::now() > self.next_nominate_round_at.unwrap()
         {
             timeout_occurred = true;
+            self.record_event(SlotEvent::NominationRoundTimeout {
+                round: self.nominate_round,
+            });
             // Canceling is required since schedule_next_nomination_round will not schedule a round
             // if one is already scheduled.
             self.cancel_next_nomination_round();
@@ -251,6 +302,10 @@ impl<V: Value, ValidationError: Display> Slot<V, ValidationError> {
             );
 
             timeout_occurred = true;
+            self.record_event(SlotEvent::BallotTimeout {
+                counter: self.B.N,
+                phase: self.phase,
+            });
             self.cancel_next_ballot_timer();
             let next_counter = self.B.N + 1;
 
@@ -288,6 +343,7 @@ impl<V: Value, ValidationError: Display> Slot<V, ValidationError> {
         }
 
         if timeout_occurred {
+            self.record_transitions(snapshot);
             if let Some(emitted) = self.out_msg() {
                 msgs.push(emitted);
             }
@@ -308,9 +364,11 @@ impl<V: Value, ValidationError: Display> Slot<V, ValidationError> {
             self.is_valid(value)?;
         }
 
+        let snapshot = self.event_snapshot();
         self.W.extend(values.iter().cloned());
         self.do_nominate_phase();
         self.do_ballot_protocol();
+        self.record_transitions(snapshot);
         Ok(self.out_msg())
     }
 
@@ -342,6 +400,7 @@ impl<V: Value, ValidationError: Display> Slot<V, ValidationError> {
             }
         }
 
+        let snapshot = self.event_snapshot();
         self.M.insert(msg.sender_id.clone(), msg.clone());
 
         if self.phase == Phase::NominatePrepare {
@@ -349,6 +408,7 @@ impl<V: Value, ValidationError: Display> Slot<V, ValidationError> {
         }
 
         self.do_ballot_protocol();
+        self.record_transitions(snapshot);
 
         Ok(self.out_msg())
     }
@@ -1042,6 +1102,114 @@ impl<V: Value, ValidationError: Display> Slot<V, ValidationError> {
         self.check_externalize_phase_invariants();
     }
 
+    ///////////////////////////////////////////////////////////////////////////
+    // Event-recording methods
+    ///////////////////////////////////////////////////////////////////////////
+
+    /// Record an event, timestamped relative to the creation of the slot.
+    fn record_event(&mut self, event: SlotEvent) {
+        log::debug!(self.logger, "Slot event: {}", event);
+
+        if self.events.len() >= MAX_EVENTS_PER_SLOT {
+            return;
+        }
+
+        self.events.push(SlotEventRecord {
+            msec_since_start: Instant::now()
+                .saturating_duration_since(self.created_at)
+                .as_millis() as u64,
+            event,
+        });
+    }
+
+    /// Capture the state needed to detect which events occur during a state update.
+    fn event_snapshot(&self) -> EventSnapshot {
+        EventSnapshot {
+            phase: self.phase,
+            bN: self.B.N,
+            num_accepted_nominated: self.Y.len(),
+            num_confirmed_nominated: self.Z.len(),
+            PN: self.P.as_ref().map(|p| p.N),
+            HN: self.H.as_ref().map(|h| h.N),
+            CN: self.C.as_ref().map(|c| c.N),
+        }
+    }
+
+    /// Record events for everything that changed since `before` was captured.
+    fn record_transitions(&mut self, before: EventSnapshot) {
+        let after = self.event_snapshot();
+
+        if after.num_accepted_nominated > before.num_accepted_nominated {
+            self.record_event(SlotEvent::ThresholdCrossed {
+                statement: VotingStatement::AcceptNominate,
+                low: after.num_accepted_nominated as u32,
+                high: after.num_accepted_nominated as u32,
+            });
+        }
+
+        if after.num_confirmed_nominated > before.num_confirmed_nominated {
+            self.record_event(SlotEvent::ThresholdCrossed {
+                statement: VotingStatement::ConfirmNominate,
+                low: after.num_confirmed_nominated as u32,
+                high: after.num_confirmed_nominated as u32,
+            });
+        }
+
+        if let Some(PN) = after.PN {
+            if before.PN.map_or(true, |prev| PN > prev) {
+                self.record_event(SlotEvent::ThresholdCrossed {
+                    statement: VotingStatement::AcceptPrepare,
+                    low: PN,
+                    high: PN,
+                });
+            }
+        }
+
+        // The meaning of C and H depends on the phase.
+        if let Some(HN) = after.HN {
+            let CN = after.CN.unwrap_or(HN);
+            let phase_changed = after.phase != before.phase;
+            let range_changed = after.HN != before.HN || after.CN != before.CN;
+            let crossed = match after.phase {
+                Phase::NominatePrepare | Phase::Prepare if after.HN != before.HN => {
+                    Some((VotingStatement::ConfirmPrepare, HN, HN))
+                }
+                Phase::Commit if phase_changed || range_changed => {
+                    Some((VotingStatement::AcceptCommit, CN, HN))
+                }
+                Phase::Externalize if phase_changed => {
+                    Some((VotingStatement::ConfirmCommit, CN, HN))
+                }
+                _ => None,
+            };
+            if let Some((statement, low, high)) = crossed {
+                self.record_event(SlotEvent::ThresholdCrossed {
+                    statement,
+                    low,
+                    high,
+                });
+            }
+        }
+
+        // A single update may pass through several phases.
+        let mut phase = before.phase;
+        while phase.ordinal() < after.phase.ordinal() {
+            let next = phase.next().expect("Externalize is the last phase");
+            self.record_event(SlotEvent::PhaseTransition {
+                from: phase,
+                to: next,
+            });
+            phase = next;
+        }
+
+        if after.bN != before.bN {
+            self.record_event(SlotEvent::BallotCounterChanged {
+                from: before.bN,
+                to: after.bN,
+            });
+        }
+    }
+
     ///////////////////////////////////////////////////////////////////////////
     // Helper methods
     ///////////////////////////////////////////////////////////////////////////
@@ -2320,6 +2488,65 @@ mod ballot_protocol_tests {
         assert_eq!(emitted_msg, expected);
     }
 
+    #[test_with_logger]
+    // A node that externalizes in a single step should record every phase it passed through.
+    fn test_events_trivial_quorum_set(logger: Logger) {
+        let mut slot = Slot::<u32, TransactionValidationError>::new(
+            test_node_id(1),
+            QuorumSet::empty(),
+            10,
+            Arc::new(trivial_validity_fn),
+            Arc::new(trivial_combine_fn),
+            logger,
+        );
+
+        let values = BTreeSet::from_iter(vec![5678, 1234]);
+        slot.propose_values(&values).unwrap();
+
+        let events: Vec<SlotEvent> = slot
+            .get_events()
+            .iter()
+            .map(|record| record.event.clone())
+            .collect();
+
+        let phase_transitions: Vec<&SlotEvent> = events
+            .iter()
+            .filter(|event| match event {
+                SlotEvent::PhaseTransition { .. } => true,
+                _ => false,
+            })
+            .collect();
+        assert_eq!(
+            phase_transitions,
+            vec![
+                &SlotEvent::PhaseTransition {
+                    from: Phase::NominatePrepare,
+                    to: Phase::Prepare,
+                },
+                &SlotEvent::PhaseTransition {
+                    from: Phase::Prepare,
+                    to: Phase::Commit,
+                },
+                &SlotEvent::PhaseTransition {
+                    from: Phase::Commit,
+                    to: Phase::Externalize,
+                },
+            ]
+        );
+
+        assert!(events.contains(&SlotEvent::ThresholdCrossed {
+            statement: VotingStatement::ConfirmNominate,
+            low: 2,
+            high: 2,
+        }));
+        assert!(events.contains(&SlotEvent::ThresholdCrossed {
+            statement: VotingStatement::ConfirmCommit,
+            low: 1,
+            high: 1,
+        }));
+        assert!(events.contains(&SlotEvent::BallotCounterChanged { from: 0, to: 1 }));
+    }
+
     #[test_with_logger]
     // An "uncommitted" node should issue `vote-or-accept prepare <1,V>` when nomination produces values V.
     fn test_uncommitted_to_votes(logger: Logger) {
@@ -0,0 +1,231 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Structured events emitted by a `Slot` as it makes progress, and utilities for turning them into
+//! a human-readable timeline.
+//!
+//! Events are recorded with the number of milliseconds elapsed since the slot was created, which
+//! makes it possible to see where the time in a slow slot was spent without reading raw logs.
+use crate::{slot::Phase, Msg, Value};
+use common::NodeID;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Maximum number of events a single slot keeps. Further events are dropped.
+pub const MAX_EVENTS_PER_SLOT: usize = 10_000;
+
+/// The federated voting statements whose threshold crossings are recorded.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum VotingStatement {
+    /// One or more values were accepted nominated.
+    AcceptNominate,
+
+    /// One or more values were confirmed nominated.
+    ConfirmNominate,
+
+    /// A higher ballot was accepted prepared.
+    AcceptPrepare,
+
+    /// A higher ballot was confirmed prepared.
+    ConfirmPrepare,
+
+    /// A range of ballots was accepted committed.
+    AcceptCommit,
+
+    /// A range of ballots was confirmed committed.
+    ConfirmCommit,
+}
+
+/// An event in the lifetime of a slot.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum SlotEvent {
+    /// The slot moved from one phase to another.
+    PhaseTransition {
+        /// Previous phase.
+        from: Phase,
+        /// New phase.
+        to: Phase,
+    },
+
+    /// The counter of the current ballot changed.
+    BallotCounterChanged {
+        /// Previous counter.
+        from: u32,
+        /// New counter.
+        to: u32,
+    },
+
+    /// The nomination round timer fired.
+    NominationRoundTimeout {
+        /// The round that timed out.
+        round: u32,
+    },
+
+    /// The ballot timer fired.
+    BallotTimeout {
+        /// The ballot counter that timed out.
+        counter: u32,
+        /// The phase the slot was in when the timer fired.
+        phase: Phase,
+    },
+
+    /// A federated voting threshold (blocking set or quorum) was crossed.
+    ThresholdCrossed {
+        /// The statement that reached its threshold.
+        statement: VotingStatement,
+        /// For nomination statements, the total number of values. For ballot statements, the
+        /// lowest counter.
+        low: u32,
+        /// For ballot statements, the highest counter. Equal to `low` otherwise.
+        high: u32,
+    },
+}
+
+impl fmt::Display for SlotEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SlotEvent::PhaseTransition { from, to } => write!(f, "phase {:?} -> {:?}", from, to),
+            SlotEvent::BallotCounterChanged { from, to } => {
+                write!(f, "ballot counter {} -> {}", from, to)
+            }
+            SlotEvent::NominationRoundTimeout { round } => {
+                write!(f, "nomination round {} timed out", round)
+            }
+            SlotEvent::BallotTimeout { counter, phase } => {
+                write!(f, "ballot {} timed out in {:?}", counter, phase)
+            }
+            SlotEvent::ThresholdCrossed {
+                statement,
+                low,
+                high,
+            } => match statement {
+                VotingStatement::AcceptNominate | VotingStatement::ConfirmNominate => {
+                    write!(f, "{:?} ({} values)", statement, low)
+                }
+                _ if low == high => write!(f, "{:?} N={}", statement, low),
+                _ => write!(f, "{:?} N={}..{}", statement, low, high),
+            },
+        }
+    }
+}
+
+/// A slot event, along with when it happened.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SlotEventRecord {
+    /// Milliseconds since the slot was created.
+    pub msec_since_start: u64,
+
+    /// The event.
+    pub event: SlotEvent,
+}
+
+/// A single entry on a slot's timeline.
+#[derive(Clone, Debug)]
+pub enum TimelineEntry<V: Value> {
+    /// A message received by the local node.
+    Received(Msg<V>),
+
+    /// A message emitted by the local node.
+    Sent(Msg<V>),
+
+    /// A local slot event.
+    Event(SlotEvent),
+}
+
+/// Renders a slot timeline as a Mermaid sequence diagram.
+///
+/// # Arguments
+/// * `local_node_id` - The node whose point of view the timeline describes.
+/// * `entries` - (milliseconds since start of slot, entry) pairs, in chronological order.
+pub fn render_sequence_diagram<V: Value>(
+    local_node_id: &NodeID,
+    entries: &[(u64, TimelineEntry<V>)],
+) -> String {
+    let mut lines = vec![
+        "sequenceDiagram".to_string(),
+        format!("    participant local as {}", local_node_id.responder_id),
+    ];
+
+    for (msec, entry) in entries {
+        let line = match entry {
+            TimelineEntry::Received(msg) => format!(
+                "    {}->>local: [{}ms] {}",
+                participant_name(&msg.sender_id),
+                msec,
+                topic_summary(msg)
+            ),
+            TimelineEntry::Sent(msg) => {
+                format!("    local->>network: [{}ms] {}", msec, topic_summary(msg))
+            }
+            TimelineEntry::Event(event) => format!("    Note over local: [{}ms] {}", msec, event),
+        };
+        lines.push(line);
+    }
+
+    lines.join("\n") + "\n"
+}
+
+/// Mermaid participant names may not contain most punctuation.
+fn participant_name(node_id: &NodeID) -> String {
+    node_id
+        .responder_id
+        .to_string()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+/// A short, diagram-friendly description of a message.
+fn topic_summary<V: Value>(msg: &Msg<V>) -> String {
+    // `to_display` includes the sender and slot index, which are implied by the diagram.
+    msg.to_display()
+        .splitn(3, ' ')
+        .nth(2)
+        .map(|s| s.trim_end_matches(')').replace(';', ","))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{msg::*, test_utils::test_node_id, QuorumSet};
+    use std::{collections::BTreeSet, iter::FromIterator};
+
+    #[test]
+    fn render_sequence_diagram_includes_all_entries() {
+        let local = test_node_id(1);
+        let remote = test_node_id(2);
+        let msg = Msg::new(
+            remote.clone(),
+            QuorumSet::new_with_node_ids(1, vec![local.clone()]),
+            7,
+            Topic::Nominate(NominatePayload {
+                X: BTreeSet::from_iter(vec![1u32, 2]),
+                Y: BTreeSet::default(),
+            }),
+        );
+
+        let entries = vec![
+            (0, TimelineEntry::Received(msg)),
+            (
+                5,
+                TimelineEntry::Event(SlotEvent::PhaseTransition {
+                    from: Phase::NominatePrepare,
+                    to: Phase::Prepare,
+                }),
+            ),
+        ];
+
+        let diagram = render_sequence_diagram(&local, &entries);
+        let lines: Vec<&str> = diagram.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], "sequenceDiagram");
+        assert!(lines[2].starts_with(&format!(
+            "    {}->>local: [0ms] NOM",
+            participant_name(&remote)
+        )));
+        assert_eq!(
+            lines[3],
+            "    Note over local: [5ms] phase NominatePrepare -> Prepare"
+        );
+    }
+}
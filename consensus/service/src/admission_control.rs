@@ -0,0 +1,403 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Admission control for client-submitted transactions.
+//!
+//! Proposals are rate limited per client session and per source IP address using token buckets,
+//! so that a single misbehaving client cannot fill the pending values queue for everyone else.
+//! Operators can also pause intake altogether.
+
+use attest_enclave_api::ClientSession;
+use common::HashMap;
+use std::{
+    collections::BTreeMap,
+    hash::Hash,
+    net::{IpAddr, SocketAddr},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::Instant,
+};
+
+/// Number of buckets tracked per key type. Beyond it, the least recently used buckets are
+/// forgotten.
+const MAX_TRACKED_BUCKETS: usize = 10_000;
+
+/// Why a proposal was not admitted.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AdmissionRejection {
+    /// Client transaction intake was paused by an operator.
+    Paused,
+
+    /// The node has too many pending values.
+    OverCapacity,
+
+    /// The client session exceeded its rate limit.
+    ClientRateLimited,
+
+    /// The source IP address exceeded its rate limit.
+    SourceRateLimited,
+}
+
+/// A sustained rate together with the burst allowed on top of it.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct RateLimit {
+    /// Proposals allowed per second.
+    pub per_second: u32,
+
+    /// Proposals allowed in a burst.
+    pub burst: u32,
+}
+
+impl RateLimit {
+    /// A limit of zero proposals per second means unlimited.
+    pub fn new(per_second: u32, burst: u32) -> Option<Self> {
+        if per_second == 0 {
+            None
+        } else {
+            Some(Self {
+                per_second,
+                burst: burst.max(1),
+            })
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct TokenBucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(limit: &RateLimit, now: Instant) -> Self {
+        Self {
+            tokens: f64::from(limit.burst),
+            last_refill: now,
+        }
+    }
+
+    fn refill(&mut self, limit: &RateLimit, now: Instant) {
+        let elapsed = now
+            .saturating_duration_since(self.last_refill)
+            .as_secs_f64();
+        self.tokens =
+            (self.tokens + elapsed * f64::from(limit.per_second)).min(f64::from(limit.burst));
+        self.last_refill = now;
+    }
+
+    /// Takes a token if one is available.
+    fn try_take(&mut self, limit: &RateLimit, now: Instant) -> bool {
+        self.refill(limit, now);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Token buckets keyed by `K`, all sharing the same limit. At most `MAX_TRACKED_BUCKETS` buckets
+/// are kept, so that clients cycling through keys, e.g. source IPv6 addresses, cannot grow it
+/// without bound.
+struct RateLimiter<K: Clone + Eq + Hash> {
+    limit: RateLimit,
+
+    /// Each key's bucket, and its last use.
+    buckets: HashMap<K, (TokenBucket, u64)>,
+
+    /// Keys, from least to most recently used.
+    by_last_use: BTreeMap<u64, K>,
+
+    /// Incremented on every use of a bucket.
+    use_counter: u64,
+}
+
+impl<K: Clone + Eq + Hash> RateLimiter<K> {
+    fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            buckets: HashMap::default(),
+            by_last_use: BTreeMap::new(),
+            use_counter: 0,
+        }
+    }
+
+    fn try_take(&mut self, key: K, now: Instant) -> bool {
+        let limit = self.limit;
+        self.use_counter += 1;
+
+        let mut bucket = match self.buckets.remove(&key) {
+            Some((bucket, last_use)) => {
+                self.by_last_use.remove(&last_use);
+                bucket
+            }
+            None => {
+                // Make room by forgetting the least recently used buckets.
+                while self.buckets.len() >= MAX_TRACKED_BUCKETS {
+                    let least_recently_used = match self.by_last_use.keys().next() {
+                        Some(last_use) => *last_use,
+                        None => break,
+                    };
+                    if let Some(key) = self.by_last_use.remove(&least_recently_used) {
+                        self.buckets.remove(&key);
+                    }
+                }
+                TokenBucket::new(&limit, now)
+            }
+        };
+
+        let taken = bucket.try_take(&limit, now);
+        self.by_last_use.insert(self.use_counter, key.clone());
+        self.buckets.insert(key, (bucket, self.use_counter));
+        taken
+    }
+}
+
+/// Decides whether a client proposal should be processed.
+#[derive(Clone)]
+pub struct AdmissionControl {
+    /// Maximum number of pending values before rejecting proposals.
+    pending_limit: i64,
+
+    per_client_session: Option<Arc<Mutex<RateLimiter<ClientSession>>>>,
+
+    per_source_ip: Option<Arc<Mutex<RateLimiter<IpAddr>>>>,
+
+    /// Whether intake is paused. Shared by all clones.
+    paused: Arc<AtomicBool>,
+}
+
+impl AdmissionControl {
+    pub fn new(
+        pending_limit: i64,
+        per_client_session: Option<RateLimit>,
+        per_source_ip: Option<RateLimit>,
+    ) -> Self {
+        Self {
+            pending_limit,
+            per_client_session: per_client_session
+                .map(|limit| Arc::new(Mutex::new(RateLimiter::new(limit)))),
+            per_source_ip: per_source_ip.map(|limit| Arc::new(Mutex::new(RateLimiter::new(limit)))),
+            paused: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Reject all proposals until `resume` is called.
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::SeqCst);
+    }
+
+    /// Undo `pause`.
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::SeqCst);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    /// Checks whether a proposal should be processed, consuming rate limit tokens if so.
+    ///
+    /// # Arguments
+    /// * `num_pending_values` - Number of values currently pending consensus.
+    /// * `session` - The client session the proposal was received on.
+    /// * `source_ip` - The address the proposal was received from, if known.
+    pub fn check(
+        &self,
+        num_pending_values: i64,
+        session: &ClientSession,
+        source_ip: Option<IpAddr>,
+    ) -> Result<(), AdmissionRejection> {
+        self.check_at(num_pending_values, session, source_ip, Instant::now())
+    }
+
+    fn check_at(
+        &self,
+        num_pending_values: i64,
+        session: &ClientSession,
+        source_ip: Option<IpAddr>,
+        now: Instant,
+    ) -> Result<(), AdmissionRejection> {
+        if self.is_paused() {
+            return Err(AdmissionRejection::Paused);
+        }
+
+        if num_pending_values > self.pending_limit {
+            return Err(AdmissionRejection::OverCapacity);
+        }
+
+        // The source is checked first so that a client cannot dodge its limit by opening new
+        // sessions.
+        if let (Some(limiter), Some(source_ip)) = (&self.per_source_ip, source_ip) {
+            let mut limiter = limiter.lock().expect("mutex poisoned");
+            if !limiter.try_take(source_ip, now) {
+                return Err(AdmissionRejection::SourceRateLimited);
+            }
+        }
+
+        if let Some(limiter) = &self.per_client_session {
+            let mut limiter = limiter.lock().expect("mutex poisoned");
+            if !limiter.try_take(session.clone(), now) {
+                return Err(AdmissionRejection::ClientRateLimited);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Extracts the IP address from a gRPC peer string, e.g. `ipv4:127.0.0.1:3223` or
+/// `ipv6:[::1]:3223`.
+pub fn source_ip_from_grpc_peer(peer: &str) -> Option<IpAddr> {
+    let addr = peer.trim_start_matches("ipv4:").trim_start_matches("ipv6:");
+    SocketAddr::from_str(addr).ok().map(|addr| addr.ip())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn session(id: u8) -> ClientSession {
+        ClientSession::from(vec![id; 32])
+    }
+
+    #[test]
+    // Proposals should be rejected once the pending limit is exceeded.
+    fn test_pending_limit() {
+        let admission_control = AdmissionControl::new(10, None, None);
+        assert_eq!(admission_control.check(10, &session(1), None), Ok(()));
+        assert_eq!(
+            admission_control.check(11, &session(1), None),
+            Err(AdmissionRejection::OverCapacity)
+        );
+    }
+
+    #[test]
+    // Each client session has its own bucket, which refills over time.
+    fn test_client_session_rate_limit() {
+        let admission_control = AdmissionControl::new(10, RateLimit::new(1, 2), None);
+        let now = Instant::now();
+
+        assert_eq!(
+            admission_control.check_at(0, &session(1), None, now),
+            Ok(())
+        );
+        assert_eq!(
+            admission_control.check_at(0, &session(1), None, now),
+            Ok(())
+        );
+        assert_eq!(
+            admission_control.check_at(0, &session(1), None, now),
+            Err(AdmissionRejection::ClientRateLimited)
+        );
+
+        // Other sessions are unaffected.
+        assert_eq!(
+            admission_control.check_at(0, &session(2), None, now),
+            Ok(())
+        );
+
+        // One token is regained per second.
+        let later = now + Duration::from_secs(1);
+        assert_eq!(
+            admission_control.check_at(0, &session(1), None, later),
+            Ok(())
+        );
+        assert_eq!(
+            admission_control.check_at(0, &session(1), None, later),
+            Err(AdmissionRejection::ClientRateLimited)
+        );
+    }
+
+    #[test]
+    // A source IP is limited across all of its sessions.
+    fn test_source_ip_rate_limit() {
+        let admission_control = AdmissionControl::new(10, None, RateLimit::new(1, 2));
+        let now = Instant::now();
+        let ip = Some(IpAddr::from([10, 0, 0, 1]));
+        let other_ip = Some(IpAddr::from([10, 0, 0, 2]));
+
+        assert_eq!(admission_control.check_at(0, &session(1), ip, now), Ok(()));
+        assert_eq!(admission_control.check_at(0, &session(2), ip, now), Ok(()));
+        assert_eq!(
+            admission_control.check_at(0, &session(3), ip, now),
+            Err(AdmissionRejection::SourceRateLimited)
+        );
+        assert_eq!(
+            admission_control.check_at(0, &session(3), other_ip, now),
+            Ok(())
+        );
+    }
+
+    #[test]
+    // Beyond `MAX_TRACKED_BUCKETS`, the least recently used buckets are forgotten, even if they
+    // are not full.
+    fn test_tracked_buckets_are_capped() {
+        let mut limiter = RateLimiter::new(RateLimit::new(1, 1).unwrap());
+        let now = Instant::now();
+        let ip = |n: usize| IpAddr::from((n as u32).to_be_bytes());
+
+        // Each bucket is left empty.
+        for n in 0..=MAX_TRACKED_BUCKETS {
+            assert!(limiter.try_take(ip(n), now));
+        }
+        assert_eq!(limiter.buckets.len(), MAX_TRACKED_BUCKETS);
+        assert_eq!(limiter.by_last_use.len(), MAX_TRACKED_BUCKETS);
+
+        // The most recently used buckets are kept.
+        assert!(!limiter.try_take(ip(MAX_TRACKED_BUCKETS), now));
+        assert!(!limiter.try_take(ip(1), now));
+
+        // The least recently used one was forgotten.
+        assert!(limiter.try_take(ip(0), now));
+        assert_eq!(limiter.buckets.len(), MAX_TRACKED_BUCKETS);
+        assert!(!limiter.try_take(ip(0), now));
+
+        // Making room for `ip(0)` forgot the next least recently used bucket.
+        assert!(limiter.try_take(ip(2), now));
+    }
+
+    #[test]
+    // Pausing applies to every clone, and takes precedence over the other checks.
+    fn test_pause_resume() {
+        let admission_control = AdmissionControl::new(10, None, None);
+        let clone = admission_control.clone();
+
+        clone.pause();
+        assert!(admission_control.is_paused());
+        assert_eq!(
+            admission_control.check(0, &session(1), None),
+            Err(AdmissionRejection::Paused)
+        );
+        assert_eq!(
+            admission_control.check(11, &session(1), None),
+            Err(AdmissionRejection::Paused)
+        );
+
+        clone.resume();
+        assert_eq!(admission_control.check(0, &session(1), None), Ok(()));
+    }
+
+    #[test]
+    // A rate of zero disables the limit.
+    fn test_zero_rate_is_unlimited() {
+        assert_eq!(RateLimit::new(0, 100), None);
+    }
+
+    #[test]
+    fn test_source_ip_from_grpc_peer() {
+        assert_eq!(
+            source_ip_from_grpc_peer("ipv4:127.0.0.1:3223"),
+            Some(IpAddr::from([127, 0, 0, 1]))
+        );
+        assert_eq!(
+            source_ip_from_grpc_peer("ipv6:[::1]:3223"),
+            Some(IpAddr::from([0, 0, 0, 0, 0, 0, 0, 1u16]))
+        );
+        assert_eq!(source_ip_from_grpc_peer("unix:/tmp/socket"), None);
+    }
+}
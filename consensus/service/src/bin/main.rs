@@ -87,10 +87,11 @@ fn main() -> Result<(), ConsensusServiceError> {
 
     log::info!(logger, "Listening...");
 
-    consensus_service.wait_for_all_threads()?;
+    // Runs until an operator drains the node through the management server.
+    consensus_service.wait_for_drain();
 
-    // Should never get here since our threads are not expected to die
-    panic!("Oh oh, our threads died");
+    log::info!(logger, "Node drained, shutting down.");
+    consensus_service.stop()
 }
 
 fn setup_ledger_dir(config_origin_path: &Option<PathBuf>, ledger_path: &PathBuf) {
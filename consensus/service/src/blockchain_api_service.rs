@@ -3,17 +3,44 @@
 //! Serves blockchain-related API requests.
 
 use common::logger::{log, Logger};
+use futures::{sync::mpsc, Future, Sink, Stream};
 use grpc_util::{rpc_logger, send_result};
-use grpcio::{RpcContext, RpcStatus, RpcStatusCode, UnarySink};
+use grpcio::{
+    Error as GrpcError, RpcContext, RpcStatus, RpcStatusCode, ServerStreamingSink, UnarySink,
+    WriteFlags,
+};
 use ledger_db::Ledger;
 use metrics::{self, SVC_COUNTERS};
 use mobilecoin_api::{
-    blockchain::{self, BlocksRequest, BlocksResponse, LastBlockInfoResponse},
+    blockchain::{
+        self, BlockByIDRequest, BlocksRequest, BlocksResponse, BlocksWithTransactionsResponse,
+        LastBlockInfoResponse, S3Block, SubscribeBlocksRequest,
+    },
     blockchain_grpc::BlockchainApi,
     empty::Empty,
+    transaction as tx_grpc,
 };
 use protobuf::RepeatedField;
-use std::{cmp, convert::From};
+use std::{
+    cmp,
+    convert::{From, TryFrom},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+use transaction::BlockID;
+
+/// How often a subscription checks the ledger for new blocks.
+const SUBSCRIPTION_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Number of blocks a subscription reads ahead of the client.
+const SUBSCRIPTION_BUFFER_SIZE: usize = 16;
+
+/// Default maximal number of concurrent block subscriptions. Each one is served by its own thread.
+const DEFAULT_MAX_SUBSCRIPTIONS: usize = 64;
 
 #[derive(Clone)]
 pub struct BlockchainApiService<L: Ledger + Clone> {
@@ -23,6 +50,12 @@ pub struct BlockchainApiService<L: Ledger + Clone> {
     /// Maximal number of results to return in API calls that return multiple results.
     max_page_size: u16,
 
+    /// Maximal number of concurrent block subscriptions.
+    max_subscriptions: usize,
+
+    /// Number of block subscriptions currently open. Shared by all clones.
+    num_subscriptions: Arc<AtomicUsize>,
+
     /// Logger.
     logger: Logger,
 }
@@ -32,6 +65,8 @@ impl<L: Ledger + Clone> BlockchainApiService<L> {
         BlockchainApiService {
             ledger,
             max_page_size: 2000,
+            max_subscriptions: DEFAULT_MAX_SUBSCRIPTIONS,
+            num_subscriptions: Arc::new(AtomicUsize::new(0)),
             logger,
         }
     }
@@ -42,6 +77,23 @@ impl<L: Ledger + Clone> BlockchainApiService<L> {
         self.max_page_size = max_page_size;
     }
 
+    // Set the maximum number of concurrent block subscriptions.
+    #[allow(dead_code)]
+    pub fn set_max_subscriptions(&mut self, max_subscriptions: usize) {
+        self.max_subscriptions = max_subscriptions;
+    }
+
+    /// Reserves room for a new block subscription, unless `max_subscriptions` are already open.
+    /// The room is freed when the returned slot is dropped.
+    fn try_open_subscription(&self) -> Option<SubscriptionSlot> {
+        let prev = self.num_subscriptions.fetch_add(1, Ordering::SeqCst);
+        let slot = SubscriptionSlot(self.num_subscriptions.clone());
+        if prev >= self.max_subscriptions {
+            return None;
+        }
+        Some(slot)
+    }
+
     /// Returns information about the last block.
     fn get_last_block_info_helper(&mut self) -> Result<LastBlockInfoResponse, ledger_db::Error> {
         let num_blocks = self.ledger.num_blocks()?;
@@ -92,9 +144,123 @@ impl<L: Ledger + Clone> BlockchainApiService<L> {
         response.set_blocks(RepeatedField::from_vec(blocks));
         Ok(response)
     }
+
+    /// Returns the contents of the block with the given ID.
+    fn get_block_by_id_helper(&mut self, id: &[u8]) -> Result<S3Block, RpcStatus> {
+        let block_id = BlockID::try_from(id).map_err(|_| {
+            RpcStatus::new(
+                RpcStatusCode::INVALID_ARGUMENT,
+                Some("Invalid block ID".into()),
+            )
+        })?;
+
+        let to_rpc_status = |error| match error {
+            ledger_db::Error::NotFound => RpcStatus::new(RpcStatusCode::NOT_FOUND, None),
+            _ => RpcStatus::new(RpcStatusCode::INTERNAL, None),
+        };
+        let block_index = self
+            .ledger
+            .get_block_index_by_block_id(&block_id)
+            .map_err(to_rpc_status)?;
+        get_s3_block(&self.ledger, block_index).map_err(to_rpc_status)
+    }
+
+    /// Returns the contents of the blocks in the range [offset, offset + limit).
+    ///
+    /// The range is limited the same way as in `get_blocks_helper`.
+    fn get_blocks_with_transactions_helper(
+        &mut self,
+        offset: u64,
+        limit: u32,
+    ) -> Result<BlocksWithTransactionsResponse, ()> {
+        let start_index = offset;
+        let end_index = offset + cmp::min(limit, self.max_page_size as u32) as u64;
+
+        let mut s3_blocks: Vec<S3Block> = vec![];
+        for block_index in start_index..end_index {
+            match get_s3_block(&self.ledger, block_index) {
+                Ok(s3_block) => s3_blocks.push(s3_block),
+                Err(ledger_db::Error::NotFound) => {
+                    // This is okay - it means we have reached the last block in the ledger in the
+                    // previous loop iteration.
+                    break;
+                }
+                Err(error) => {
+                    log::error!(
+                        self.logger,
+                        "Error getting block {}: {:?}",
+                        block_index,
+                        error
+                    );
+                    break;
+                }
+            }
+        }
+
+        let mut response = BlocksWithTransactionsResponse::new();
+        response.set_blocks(RepeatedField::from_vec(s3_blocks));
+        Ok(response)
+    }
+}
+
+/// Reads a block, its transactions and its signatures from the ledger.
+fn get_s3_block<L: Ledger>(ledger: &L, block_index: u64) -> Result<S3Block, ledger_db::Error> {
+    let block = ledger.get_block(block_index)?;
+    let transactions = ledger.get_transactions_by_block(block_index)?;
+    let signature_set = ledger.get_block_signature_set(block_index)?;
+
+    let mut s3_block = S3Block::new();
+    s3_block.set_block(blockchain::Block::from(&block));
+    s3_block.set_transactions(transactions.iter().map(tx_grpc::RedactedTx::from).collect());
+    match ledger.get_block_signature(block_index) {
+        Ok(signature) => s3_block.set_signature(blockchain::BlockSignature::from(&signature)),
+        Err(ledger_db::Error::NotFound) => {}
+        Err(error) => return Err(error),
+    }
+    if !signature_set.is_empty() {
+        s3_block.set_signature_set(blockchain::BlockSignatureSet::from(&signature_set));
+    }
+    Ok(s3_block)
+}
+
+/// Counts as an open block subscription until dropped.
+struct SubscriptionSlot(Arc<AtomicUsize>);
+
+impl Drop for SubscriptionSlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Sends blocks to `sender` as they are appended to the ledger, starting at `from_index`. Returns
+/// once the subscriber goes away.
+fn tail_ledger<L: Ledger>(
+    ledger: L,
+    from_index: u64,
+    mut sender: mpsc::Sender<S3Block>,
+    logger: Logger,
+) {
+    let mut next_index = from_index;
+    while !sender.is_closed() {
+        match get_s3_block(&ledger, next_index) {
+            Ok(s3_block) => {
+                sender = match sender.send(s3_block).wait() {
+                    Ok(sender) => sender,
+                    Err(_) => break,
+                };
+                next_index += 1;
+            }
+            Err(ledger_db::Error::NotFound) => thread::sleep(SUBSCRIPTION_POLL_INTERVAL),
+            Err(error) => {
+                log::error!(logger, "Error getting block {}: {:?}", next_index, error);
+                break;
+            }
+        }
+    }
+    log::debug!(logger, "Block subscription ended at block {}", next_index);
 }
 
-impl<L: Ledger + Clone> BlockchainApi for BlockchainApiService<L> {
+impl<L: Ledger + Clone + 'static> BlockchainApi for BlockchainApiService<L> {
     /// Gets the last block.
     fn get_last_block_info(
         &mut self,
@@ -135,6 +301,109 @@ impl<L: Ledger + Clone> BlockchainApi for BlockchainApiService<L> {
             send_result(ctx, sink, resp, &logger);
         });
     }
+
+    /// Gets the contents of a single block.
+    fn get_block_by_id(
+        &mut self,
+        ctx: RpcContext,
+        request: BlockByIDRequest,
+        sink: UnarySink<S3Block>,
+    ) {
+        let _timer = SVC_COUNTERS.req(&ctx);
+
+        common::logger::scoped_global_logger(&rpc_logger(&ctx, &self.logger), |logger| {
+            let resp = self.get_block_by_id_helper(request.get_id());
+            send_result(ctx, sink, resp, &logger);
+        });
+    }
+
+    /// Gets the contents of a range [offset, offset+limit) of Blocks.
+    fn get_blocks_with_transactions(
+        &mut self,
+        ctx: RpcContext,
+        request: BlocksRequest,
+        sink: UnarySink<BlocksWithTransactionsResponse>,
+    ) {
+        let _timer = SVC_COUNTERS.req(&ctx);
+
+        common::logger::scoped_global_logger(&rpc_logger(&ctx, &self.logger), |logger| {
+            log::trace!(
+                logger,
+                "Received BlocksRequest with transactions for offset {} and limit {})",
+                request.offset,
+                request.limit
+            );
+
+            let resp = self
+                .get_blocks_with_transactions_helper(request.offset, request.limit)
+                .map_err(|_| RpcStatus::new(RpcStatusCode::INTERNAL, None));
+            send_result(ctx, sink, resp, &logger);
+        });
+    }
+
+    /// Streams the contents of every block from `from_index` on, as they are appended.
+    fn subscribe_blocks(
+        &mut self,
+        ctx: RpcContext,
+        request: SubscribeBlocksRequest,
+        sink: ServerStreamingSink<S3Block>,
+    ) {
+        let _timer = SVC_COUNTERS.req(&ctx);
+
+        let logger = rpc_logger(&ctx, &self.logger);
+        log::debug!(
+            logger,
+            "Received block subscription from block {}",
+            request.from_index
+        );
+
+        let subscription_slot = match self.try_open_subscription() {
+            Some(subscription_slot) => subscription_slot,
+            None => {
+                log::debug!(
+                    logger,
+                    "Rejecting block subscription: too many subscriptions"
+                );
+                let resp = sink
+                    .fail(RpcStatus::new(
+                        RpcStatusCode::RESOURCE_EXHAUSTED,
+                        Some("Too many block subscriptions".into()),
+                    ))
+                    .map_err(move |err| log::error!(logger, "failed to reply: {:?}", err));
+                ctx.spawn(resp);
+                return;
+            }
+        };
+
+        // Ledger reads block, so the ledger is tailed on a dedicated thread rather than on the gRPC
+        // completion queue.
+        let (sender, receiver) = mpsc::channel(SUBSCRIPTION_BUFFER_SIZE);
+        let ledger = self.ledger.clone();
+        let thread_logger = logger.clone();
+        let spawn_result = thread::Builder::new()
+            .name("SubscribeBlocks".into())
+            .spawn(move || {
+                tail_ledger(ledger, request.from_index, sender, thread_logger);
+                drop(subscription_slot);
+            });
+        if let Err(err) = spawn_result {
+            log::error!(logger, "Failed spawning subscription thread: {}", err);
+            let resp = sink
+                .fail(RpcStatus::new(RpcStatusCode::INTERNAL, None))
+                .map_err(move |err| log::error!(logger, "failed to reply: {:?}", err));
+            ctx.spawn(resp);
+            return;
+        }
+
+        let blocks = receiver
+            .map(|s3_block| (s3_block, WriteFlags::default()))
+            .map_err(|()| GrpcError::RemoteStopped);
+        let resp = sink
+            .send_all(blocks)
+            .map(|_| ())
+            .map_err(move |err| log::debug!(logger, "Block subscription closed: {:?}", err));
+        ctx.spawn(resp);
+    }
 }
 
 #[cfg(test)]
@@ -312,4 +581,118 @@ mod tests {
         assert_eq!(expected_blocks.get(0).unwrap(), blocks.get(0).unwrap());
         assert_eq!(expected_blocks.get(4).unwrap(), blocks.get(4).unwrap());
     }
+
+    #[test_with_logger]
+    // `get_block_by_id` should return the contents of the block with the given ID.
+    fn test_get_block_by_id(logger: Logger) {
+        let mut ledger_db = create_db();
+        let block_entities = populate_db(&mut ledger_db, 20);
+        let expected_transactions = ledger_db.get_transactions_by_block(7).unwrap();
+
+        let mut blockchain_api_service = BlockchainApiService::new(ledger_db, logger);
+
+        let s3_block = blockchain_api_service
+            .get_block_by_id_helper(&block_entities[7].id.0)
+            .unwrap();
+        assert_eq!(
+            blockchain::Block::from(&block_entities[7]),
+            *s3_block.get_block()
+        );
+        let transactions: Vec<RedactedTx> = s3_block
+            .get_transactions()
+            .iter()
+            .map(|tx| RedactedTx::try_from(tx).unwrap())
+            .collect();
+        assert_eq!(expected_transactions, transactions);
+
+        // Unknown block IDs are not found.
+        let status = blockchain_api_service
+            .get_block_by_id_helper(&[7u8; 32])
+            .unwrap_err();
+        assert_eq!(status.status, RpcStatusCode::NOT_FOUND);
+
+        // Malformed block IDs are rejected.
+        let status = blockchain_api_service
+            .get_block_by_id_helper(&[7u8; 3])
+            .unwrap_err();
+        assert_eq!(status.status, RpcStatusCode::INVALID_ARGUMENT);
+    }
+
+    #[test_with_logger]
+    // `get_blocks_with_transactions` should return the contents of the requested range of blocks,
+    // limited to `max_page_size` blocks.
+    fn test_get_blocks_with_transactions(logger: Logger) {
+        let mut ledger_db = create_db();
+        let block_entities = populate_db(&mut ledger_db, 20);
+
+        let mut blockchain_api_service = BlockchainApiService::new(ledger_db, logger);
+        blockchain_api_service.set_max_page_size(5);
+
+        let response = blockchain_api_service
+            .get_blocks_with_transactions_helper(10, 100)
+            .unwrap();
+        let blocks: Vec<&blockchain::Block> = response
+            .blocks
+            .iter()
+            .map(|s3_block| s3_block.get_block())
+            .collect();
+        let expected_blocks: Vec<blockchain::Block> = block_entities[10..15]
+            .iter()
+            .map(blockchain::Block::from)
+            .collect();
+        assert_eq!(expected_blocks.iter().collect::<Vec<_>>(), blocks);
+        assert!(response
+            .blocks
+            .iter()
+            .all(|s3_block| s3_block.get_transactions().len() == 1));
+
+        // Requests past the end of the ledger return the blocks that exist.
+        let response = blockchain_api_service
+            .get_blocks_with_transactions_helper(18, 5)
+            .unwrap();
+        assert_eq!(2, response.blocks.len());
+    }
+
+    #[test_with_logger]
+    // A subscription should send every block from the requested index on, and stop once the
+    // subscriber goes away.
+    fn test_tail_ledger(logger: Logger) {
+        let mut ledger_db = create_db();
+        let block_entities = populate_db(&mut ledger_db, 20);
+
+        let (sender, receiver) = mpsc::channel(SUBSCRIPTION_BUFFER_SIZE);
+        let join_handle = thread::spawn(move || tail_ledger(ledger_db, 15, sender, logger));
+
+        let blocks: Vec<blockchain::Block> = receiver
+            .wait()
+            .take(5)
+            .map(|s3_block| s3_block.unwrap().get_block().clone())
+            .collect();
+        let expected_blocks: Vec<blockchain::Block> = block_entities[15..]
+            .iter()
+            .map(blockchain::Block::from)
+            .collect();
+        assert_eq!(expected_blocks, blocks);
+
+        // `take` dropped the receiver, which ends the subscription.
+        join_handle.join().unwrap();
+    }
+
+    #[test_with_logger]
+    // No more than `max_subscriptions` subscriptions may be open at once, across clones of the
+    // service.
+    fn test_max_subscriptions(logger: Logger) {
+        let mut blockchain_api_service = BlockchainApiService::new(create_db(), logger);
+        blockchain_api_service.set_max_subscriptions(2);
+        let clone = blockchain_api_service.clone();
+
+        let first = blockchain_api_service.try_open_subscription().unwrap();
+        let _second = clone.try_open_subscription().unwrap();
+        assert!(blockchain_api_service.try_open_subscription().is_none());
+        assert!(clone.try_open_subscription().is_none());
+
+        // Closing a subscription makes room for a new one.
+        drop(first);
+        assert!(clone.try_open_subscription().is_some());
+    }
 }
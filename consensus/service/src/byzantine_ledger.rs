@@ -16,11 +16,11 @@ use consensus_enclave::ConsensusEnclaveProxy;
 use keys::Ed25519Pair;
 use ledger_db::Ledger;
 use ledger_sync::{LedgerSyncService, ReqwestTransactionsFetcher, SCPNetworkState};
-use mcconnection::{BlockchainConnection, ConnectionManager};
+use mcconnection::{BlockchainConnection, ConnectionManager, SyncConnection};
 use metered_channel::{self, Receiver, Sender};
 use peers::{
-    ConsensusConnection, ConsensusMsg, RetryableConsensusConnection, ThreadedBroadcaster,
-    VerifiedConsensusMsg,
+    ConsensusConnection, ConsensusMsg, PeerHealthScores, RetryableConsensusConnection,
+    ThreadedBroadcaster, VerifiedConsensusMsg,
 };
 use rayon::{iter::ParallelIterator, prelude::IntoParallelIterator};
 use retry::delay::Fibonacci;
@@ -36,7 +36,7 @@ use std::{
     thread::{self, JoinHandle},
     time::{Duration, Instant},
 };
-use transaction::{tx::TxHash, BlockID};
+use transaction::{constants::MAX_TRANSACTIONS_PER_BLOCK, tx::TxHash, BlockID};
 
 /// Time we're allowed to stay behind before we initiate catchup.
 /// This reduces the amount of unnecessary catchups due to minor network issues.
@@ -57,6 +57,12 @@ enum ByzantineLedgerTaskMessage {
     /// SCP Statement.
     ConsensusMsg(VerifiedConsensusMsg, ResponderId),
 
+    /// Stop nominating values, and become drained once the current slot is over.
+    Drain,
+
+    /// A new quorum set and list of transaction source URLs, to be used from the next slot.
+    UpdateNetwork(QuorumSet, Vec<String>),
+
     /// Stop trigger, used for notifying the worker thread to terminate.
     StopTrigger,
 }
@@ -65,6 +71,7 @@ pub struct ByzantineLedger {
     sender: Sender<ByzantineLedgerTaskMessage>,
     thread_handle: Option<JoinHandle<()>>,
     is_behind: Arc<AtomicBool>,
+    is_drained: Arc<AtomicBool>,
     highest_outgoing_consensus_msg: Arc<Mutex<Option<ConsensusMsg>>>,
 }
 
@@ -110,6 +117,7 @@ impl ByzantineLedger {
             sender,
             thread_handle: None,
             is_behind: Arc::new(AtomicBool::new(false)),
+            is_drained: Arc::new(AtomicBool::new(false)),
             highest_outgoing_consensus_msg: highest_outgoing_consensus_msg.clone(),
         };
 
@@ -163,6 +171,7 @@ impl ByzantineLedger {
 
         // Start worker thread
         let thread_is_behind = node.is_behind.clone();
+        let thread_is_drained = node.is_drained.clone();
         let thread_handle = Some(
             thread::Builder::new()
                 .name(format!("ByzantineLedger{:?}", node_id))
@@ -173,6 +182,7 @@ impl ByzantineLedger {
                         receiver,
                         wrapped_scp_node,
                         thread_is_behind,
+                        thread_is_drained,
                         send_scp_message,
                         ledger,
                         peer_manager,
@@ -210,6 +220,30 @@ impl ByzantineLedger {
             .expect("Could not send consensus msg");
     }
 
+    /// Stop nominating values. The node keeps taking part in the current slot until it is
+    /// complete, after which `is_drained` returns true and no further slots are processed.
+    pub fn drain(&self) {
+        self.sender
+            .send(ByzantineLedgerTaskMessage::Drain)
+            .expect("Could not send drain");
+    }
+
+    /// Switch to a new quorum set and transaction sources. Slots already in progress complete
+    /// with the quorum set they started with.
+    pub fn update_network(&self, quorum_set: QuorumSet, tx_source_urls: Vec<String>) {
+        self.sender
+            .send(ByzantineLedgerTaskMessage::UpdateNetwork(
+                quorum_set,
+                tx_source_urls,
+            ))
+            .expect("Could not send network update");
+    }
+
+    /// Check if the node has finished draining.
+    pub fn is_drained(&self) -> bool {
+        self.is_drained.load(Ordering::SeqCst)
+    }
+
     pub fn stop(&mut self) {
         let _ = self.sender.send(ByzantineLedgerTaskMessage::StopTrigger);
         self.join();
@@ -266,6 +300,7 @@ struct ByzantineLedgerThread<
     receiver: Receiver<ByzantineLedgerTaskMessage>,
     scp: Box<dyn ScpNode<TxHash>>,
     is_behind: Arc<AtomicBool>,
+    is_drained: Arc<AtomicBool>,
     send_scp_message: F,
     ledger: L,
     peer_manager: ConnectionManager<PC>,
@@ -273,6 +308,9 @@ struct ByzantineLedgerThread<
     broadcaster: Arc<Mutex<ThreadedBroadcaster>>,
     logger: Logger,
 
+    // Health of our peers, used to pick which peer to fetch transactions from.
+    health_scores: PeerHealthScores,
+
     // Current slot (the one that is not yet in the ledger / the one currently being worked on).
     cur_slot: SlotIndex,
 
@@ -305,6 +343,12 @@ struct ByzantineLedgerThread<
 
     // Ledger sync state.
     ledger_sync_state: LedgerSyncState,
+
+    // The slot that was current when draining was requested, if it was.
+    drain_slot: Option<SlotIndex>,
+
+    // Network update waiting for the next slot boundary.
+    pending_network_update: Option<(QuorumSet, Vec<String>)>,
 }
 
 impl<
@@ -321,6 +365,7 @@ impl<
         receiver: Receiver<ByzantineLedgerTaskMessage>,
         scp: Box<dyn ScpNode<TxHash>>,
         is_behind: Arc<AtomicBool>,
+        is_drained: Arc<AtomicBool>,
         send_scp_message: F,
         ledger: L,
         peer_manager: ConnectionManager<PC>,
@@ -344,16 +389,24 @@ impl<
 
         let network_state = SCPNetworkState::new(node_id, quorum_set, logger.clone());
 
+        let health_scores = {
+            let mut broadcaster = broadcaster.lock().expect("mutex poisoned");
+            broadcaster.set_cur_slot(cur_slot);
+            broadcaster.health_scores()
+        };
+
         let mut instance = Self {
             receiver,
             scp,
             is_behind,
+            is_drained,
             send_scp_message,
             ledger,
             tx_manager,
             broadcaster,
             peer_manager,
             logger,
+            health_scores,
 
             cur_slot,
             prev_block_id,
@@ -364,6 +417,8 @@ impl<
             network_state,
             ledger_sync_service,
             ledger_sync_state: LedgerSyncState::InSync,
+            drain_slot: None,
+            pending_network_update: None,
         };
 
         loop {
@@ -386,6 +441,27 @@ impl<
             return false;
         }
 
+        // Under fee-priority selection, new transactions must pay enough to make it into the next
+        // block.
+        self.tx_manager.set_admission_fee_floor(
+            self.tx_manager
+                .fee_floor(&self.pending_values, MAX_TRANSACTIONS_PER_BLOCK),
+        );
+
+        // Persist the transactions received since the last tick.
+        if let Err(err) = self.tx_manager.flush_to_store() {
+            log::error!(
+                self.logger,
+                "Failed persisting cached transactions: {}",
+                err
+            );
+        }
+
+        // Once drained, do nothing but wait to be stopped.
+        if self.check_drained() {
+            return true;
+        }
+
         // See if network state thinks we're behind.
         let sync_service_is_behind = self.ledger_sync_service.is_behind(&self.network_state);
 
@@ -469,6 +545,8 @@ impl<
                     );
                 }
                 self.pending_values_map = new_pending_values_map;
+                self.tx_manager
+                    .set_held_for_consensus(self.pending_values.iter().cloned());
 
                 // Nominate if needed.
                 if !self.pending_values.is_empty() {
@@ -480,6 +558,10 @@ impl<
                 self.ledger_sync_state = LedgerSyncState::InSync;
                 self.cur_slot = self.ledger.num_blocks().unwrap();
                 self.prev_block_id = self.ledger.get_block(self.cur_slot - 1).unwrap().id;
+                self.broadcaster
+                    .lock()
+                    .expect("mutex poisoned")
+                    .set_cur_slot(self.cur_slot);
 
                 // Clear old entries from pending_consensus_msgs
                 let cur_slot = self.cur_slot;
@@ -492,6 +574,9 @@ impl<
         assert!(!self.is_behind.load(Ordering::SeqCst));
         assert!(self.ledger_sync_state != LedgerSyncState::IsBehind);
 
+        // Switch to an updated network configuration before the current slot begins.
+        self.apply_pending_network_update();
+
         // Nominate values for current slot.
         self.nominate_pending_values();
 
@@ -518,6 +603,10 @@ impl<
             match task_msg {
                 // Values submitted by a client
                 ByzantineLedgerTaskMessage::Values(timestamp, new_values) => {
+                    // Keep them in the cache until they have been through consensus.
+                    self.tx_manager
+                        .hold_for_consensus(new_values.iter().cloned());
+
                     // Collect.
                     for value in new_values {
                         // IF we don't already know of this value, add it to the pending list and
@@ -549,6 +638,27 @@ impl<
                     }
                 }
 
+                // Request to drain
+                ByzantineLedgerTaskMessage::Drain => {
+                    if self.drain_slot.is_none() {
+                        log::info!(
+                            self.logger,
+                            "Draining, will stop after slot {}",
+                            self.cur_slot
+                        );
+                        self.drain_slot = Some(self.cur_slot);
+                    }
+                }
+
+                // Network configuration changed
+                ByzantineLedgerTaskMessage::UpdateNetwork(quorum_set, tx_source_urls) => {
+                    log::info!(
+                        self.logger,
+                        "Received network update, will apply at the next slot boundary"
+                    );
+                    self.pending_network_update = Some((quorum_set, tx_source_urls));
+                }
+
                 // Request to stop thread
                 ByzantineLedgerTaskMessage::StopTrigger => {
                     return false;
@@ -558,8 +668,46 @@ impl<
         true
     }
 
+    // Applies a pending network update, unless the current slot has already started.
+    fn apply_pending_network_update(&mut self) {
+        if self.pending_network_update.is_none()
+            || self.scp.get_slot_metrics(self.cur_slot).is_some()
+        {
+            return;
+        }
+
+        let (quorum_set, tx_source_urls) = self.pending_network_update.take().unwrap();
+
+        match ReqwestTransactionsFetcher::new(tx_source_urls, self.logger.clone()) {
+            Ok(transactions_fetcher) => {
+                self.ledger_sync_service = LedgerSyncService::new(
+                    self.ledger.clone(),
+                    self.peer_manager.clone(),
+                    transactions_fetcher,
+                    self.logger.clone(),
+                );
+            }
+            Err(e) => {
+                log::error!(
+                    self.logger,
+                    "Failed creating transaction fetcher, keeping previous tx sources: {:?}",
+                    e
+                );
+            }
+        }
+
+        log::info!(
+            self.logger,
+            "Applying quorum set {:?} from slot {}",
+            quorum_set,
+            self.cur_slot
+        );
+        self.network_state.set_quorum_set(quorum_set.clone());
+        self.scp.set_quorum_set(quorum_set);
+    }
+
     fn nominate_pending_values(&mut self) {
-        if !self.need_nominate {
+        if !self.need_nominate || self.drain_slot.is_some() {
             return;
         }
 
@@ -582,6 +730,30 @@ impl<
         self.need_nominate = false;
     }
 
+    // Returns true if draining was requested and the slot that was current at the time is over.
+    // A slot we were not taking part in, or any slot while we are behind, counts as over.
+    fn check_drained(&mut self) -> bool {
+        let drain_slot = match self.drain_slot {
+            Some(drain_slot) => drain_slot,
+            None => return false,
+        };
+
+        if self.is_drained.load(Ordering::SeqCst) {
+            return true;
+        }
+
+        if self.cur_slot > drain_slot
+            || self.is_behind.load(Ordering::SeqCst)
+            || self.scp.get_slot_metrics(drain_slot).is_none()
+        {
+            log::info!(self.logger, "Drained at slot {}", self.cur_slot);
+            self.is_drained.store(true, Ordering::SeqCst);
+            return true;
+        }
+
+        false
+    }
+
     fn process_consensus_msgs_for_cur_slot(&mut self) {
         if let Some(consensus_msgs) = self.pending_consensus_msgs.remove(&self.cur_slot) {
             for (consensus_msg, from_responder_id) in consensus_msgs {
@@ -591,8 +763,25 @@ impl<
                 if self.prev_block_id != *their_prev_block_id {
                     log::warn!(self.logger, "Received message {:?} that refers to an invalid block id {:?} != {:?} (cur_slot = {})",
                     scp_msg, their_prev_block_id, self.prev_block_id, self.cur_slot);
+                } else if let Some(block_signature) = consensus_msg.prev_block_signature() {
+                    // The sender externalized the same block we did, collect its signature.
+                    if let Err(err) = self
+                        .ledger
+                        .add_block_signature(self.cur_slot - 1, block_signature)
+                    {
+                        log::warn!(
+                            self.logger,
+                            "Failed adding block signature from {}: {:?}",
+                            from_responder_id,
+                            err
+                        );
+                    }
                 }
 
+                // The slot may externalize any of these values, so they must stay in the cache
+                // once fetched.
+                self.tx_manager.hold_for_consensus(scp_msg.values());
+
                 if !self.fetch_missing_txs(scp_msg, &from_responder_id) {
                     continue;
                 }
@@ -669,6 +858,19 @@ impl<
             self.pending_values.len(),
         );
 
+        // Record how the slot progressed, for investigating slow slots.
+        if let Some(events) = self.scp.get_slot_events(self.cur_slot) {
+            for record in events {
+                log::debug!(
+                    self.logger,
+                    "Slot {} +{}ms: {}",
+                    self.cur_slot,
+                    record.msec_since_start,
+                    record.event,
+                );
+            }
+        }
+
         // Write to ledger.
         {
             let (block, transactions, signature) = self
@@ -705,6 +907,10 @@ impl<
 
         self.cur_slot = cur_slot;
         self.prev_block_id = self.ledger.get_block(cur_slot - 1).unwrap().id;
+        self.broadcaster
+            .lock()
+            .expect("mutex poisoned")
+            .set_cur_slot(cur_slot);
 
         // Evacuate transactions that are no longer valid based on their
         // tombstone block.
@@ -728,6 +934,10 @@ impl<
         }
         self.pending_values_map = new_pending_values_map;
 
+        // Values of the finished slot no longer need to be held in the cache.
+        self.tx_manager
+            .set_held_for_consensus(self.pending_values.iter().cloned());
+
         log::info!(
             self.logger,
             "number of pending values post cleanup: {} ({} expired)",
@@ -768,18 +978,30 @@ impl<
 
         let mut all_missing_hashes = self.tx_manager.missing_hashes(&tx_hashes);
 
-        // Get the connection we'll be working with
-        let conn = match self.peer_manager.conn(from_responder_id) {
-            Some(conn) => conn,
-            None => {
-                log::error!(
-                    self.logger,
-                    "Unable to get connection for peer {}",
-                    from_responder_id
-                );
-                return false;
-            }
-        };
+        // Peers that should have the transactions: the one that sent us the message, and the one
+        // that issued it. Healthier peers are asked first.
+        let mut responder_ids = vec![from_responder_id.clone()];
+        if scp_msg.sender_id.responder_id != *from_responder_id {
+            responder_ids.push(scp_msg.sender_id.responder_id.clone());
+        }
+        self.health_scores.sort_by_score(&mut responder_ids);
+
+        let conns: Vec<(ResponderId, SyncConnection<PC>)> = responder_ids
+            .into_iter()
+            .filter_map(|responder_id| {
+                self.peer_manager
+                    .conn(&responder_id)
+                    .map(|conn| (responder_id, conn))
+            })
+            .collect();
+        if conns.is_empty() {
+            log::error!(
+                self.logger,
+                "Unable to get connection for peer {}",
+                from_responder_id
+            );
+            return false;
+        }
 
         loop {
             if all_missing_hashes.is_empty() {
@@ -799,51 +1021,61 @@ impl<
                 missing_hashes,
             );
 
-            match conn.fetch_txs(&missing_hashes, Fibonacci::from_millis(100).take(10)) {
-                Ok(tx_contexts) => {
-                    if tx_contexts.len() != missing_hashes.len() {
+            let tx_contexts = conns.iter().find_map(|(responder_id, conn)| {
+                match conn.fetch_txs(&missing_hashes, Fibonacci::from_millis(100).take(10)) {
+                    Ok(tx_contexts) => {
+                        if tx_contexts.len() == missing_hashes.len() {
+                            self.health_scores.record_success(responder_id);
+                            return Some(tx_contexts);
+                        }
                         log::error!(
                             self.logger,
                             "Failed resolving transactions {:?} from {}: expected {}, got {}. local num blocks: {}. msg slot is {}",
                             missing_hashes,
-                            from_responder_id,
+                            responder_id,
                             missing_hashes.len(),
                             tx_contexts.len(),
                             self.ledger.num_blocks().unwrap(),
                             scp_msg.slot_index,
                         );
-                        return false;
                     }
-                    tx_contexts.into_par_iter().for_each_with(
-                        (self.tx_manager.clone(), self.logger.clone()),
-                        move |(tx_manager, logger), tx_context| {
-                            match tx_manager.insert_proposed_tx(tx_context) {
-                                Ok(_) | Err(TxManagerError::AlreadyInCache) => {}
-                                Err(err) => {
-                                    // Not currently logging the malformed transaction to save a
-                                    // `.clone()`. We'll see if this ever happens.
-                                    log::crit!(
-                                        logger,
-                                        "Received malformed transaction from node {}: {:?}",
-                                        from_responder_id,
-                                        err,
-                                    );
-                                }
-                            }
-                        },
-                    );
-                }
-                Err(err) => {
-                    log::error!(
-                        self.logger,
-                        "Failed resolving transactions {:?} from {}: {:?}",
-                        missing_hashes,
-                        from_responder_id,
-                        err
-                    );
-                    return false;
+                    Err(err) => {
+                        log::error!(
+                            self.logger,
+                            "Failed resolving transactions {:?} from {}: {:?}",
+                            missing_hashes,
+                            responder_id,
+                            err
+                        );
+                    }
                 }
-            }
+                self.health_scores.record_failure(responder_id);
+                None
+            });
+
+            let tx_contexts = match tx_contexts {
+                Some(tx_contexts) => tx_contexts,
+                None => return false,
+            };
+
+            tx_contexts.into_par_iter().for_each_with(
+                (self.tx_manager.clone(), self.logger.clone()),
+                move |(tx_manager, logger), tx_context| {
+                    match tx_manager.insert_referenced_tx(tx_context) {
+                        Ok(_) | Err(TxManagerError::AlreadyInCache) => {}
+                        Err(err) => {
+                            // Not currently logging the malformed transaction to save a
+                            // `.clone()`. We'll see if this ever happens.
+                            log::crit!(
+                                logger,
+                                "Received malformed transaction from node {}: {:?}",
+                                from_responder_id,
+                                err,
+                            );
+                        }
+                    }
+                },
+            );
         }
 
         true
@@ -852,6 +1084,7 @@ impl<
     fn update_cur_metrics(&mut self) {
         let slot_metrics = self.scp.get_slot_metrics(self.cur_slot);
         counters::CUR_NUM_PENDING_VALUES.set(self.pending_values.len() as i64);
+        counters::CUR_PENDING_VALUES_FEE_FLOOR.set(self.tx_manager.admission_fee_floor() as i64);
         counters::CUR_SLOT_NUM.set(self.cur_slot as i64);
         counters::CUR_SLOT_PHASE.set(match slot_metrics.as_ref().map(|m| m.phase) {
             None => 0,
@@ -995,7 +1228,7 @@ mod tests {
 
         let broadcaster = Arc::new(Mutex::new(ThreadedBroadcaster::new(
             &peer_manager,
-            &peers::ThreadedBroadcasterFibonacciRetryPolicy::default(),
+            &peers::ThreadedBroadcasterDynamicRetryPolicy::default(),
             logger.clone(),
         )));
 
@@ -3,12 +3,15 @@
 //! Serves client-to-node gRPC requests.
 
 use crate::{
+    admission_control::{source_ip_from_grpc_peer, AdmissionControl, AdmissionRejection},
     consensus_service::ProposeTxCallback,
     counters,
     grpc_error::ConsensusGrpcError,
     tx_manager::{TxManager, TxManagerError},
+    validators::TxSelectionPolicy,
 };
 use attest_api::attest::Message;
+use attest_enclave_api::ClientSession;
 use common::logger::{log, Logger};
 use consensus_enclave::ConsensusEnclaveProxy;
 use grpc_util::{rpc_logger, send_result};
@@ -18,12 +21,9 @@ use metrics::{self, SVC_COUNTERS};
 use mobilecoin_api::{
     consensus_client_grpc::ConsensusClientApi, consensus_common::ProposeTxResponse,
 };
-use std::sync::Arc;
+use std::{net::IpAddr, sync::Arc};
 use transaction::validation::TransactionValidationError;
 
-/// Maximum number of pending values for consensus service before rejecting add_transaction requests.
-const PENDING_LIMIT: i64 = 500;
-
 #[derive(Clone)]
 pub struct ClientApiService<E: ConsensusEnclaveProxy, L: Ledger + Clone> {
     enclave: E,
@@ -31,6 +31,8 @@ pub struct ClientApiService<E: ConsensusEnclaveProxy, L: Ledger + Clone> {
     ledger: L,
     tx_manager: TxManager<E, L>,
     is_serving_fn: Arc<(dyn Fn() -> bool + Sync + Send)>,
+    tx_selection_policy: TxSelectionPolicy,
+    admission_control: AdmissionControl,
     logger: Logger,
 }
 
@@ -41,6 +43,8 @@ impl<E: ConsensusEnclaveProxy, L: Ledger + Clone> ClientApiService<E, L> {
         ledger: L,
         tx_manager: TxManager<E, L>,
         is_serving_fn: Arc<(dyn Fn() -> bool + Sync + Send)>,
+        tx_selection_policy: TxSelectionPolicy,
+        admission_control: AdmissionControl,
         logger: Logger,
     ) -> Self {
         Self {
@@ -49,6 +53,8 @@ impl<E: ConsensusEnclaveProxy, L: Ledger + Clone> ClientApiService<E, L> {
             tx_manager,
             ledger,
             is_serving_fn,
+            tx_selection_policy,
+            admission_control,
             logger,
         }
     }
@@ -56,18 +62,42 @@ impl<E: ConsensusEnclaveProxy, L: Ledger + Clone> ClientApiService<E, L> {
     fn real_client_tx_propose(
         &mut self,
         request: Message,
+        source_ip: Option<IpAddr>,
         logger: &Logger,
     ) -> Result<ProposeTxResponse, ConsensusGrpcError> {
         counters::ADD_TX_INITIATED.inc();
 
-        if counters::CUR_NUM_PENDING_VALUES.get() > PENDING_LIMIT {
+        let session = ClientSession::from(request.get_channel_id());
+        if let Err(rejection) = self.admission_control.check(
+            counters::CUR_NUM_PENDING_VALUES.get(),
+            &session,
+            source_ip,
+        ) {
             self.enclave.client_discard_message(request.into())?;
 
             log::trace!(
                 logger,
-                "Ignoring add transaction call, node is over capacity."
+                "Ignoring add transaction call, not admitted: {:?}",
+                rejection
             );
-            return Err(ConsensusGrpcError::OverCapacity);
+            return Err(match rejection {
+                AdmissionRejection::Paused => {
+                    counters::ADD_TX_PAUSED.inc();
+                    ConsensusGrpcError::NotServing
+                }
+                AdmissionRejection::OverCapacity => {
+                    counters::ADD_TX_OVER_CAPACITY.inc();
+                    ConsensusGrpcError::OverCapacity
+                }
+                AdmissionRejection::ClientRateLimited => {
+                    counters::ADD_TX_CLIENT_RATE_LIMITED.inc();
+                    ConsensusGrpcError::ClientRateLimited
+                }
+                AdmissionRejection::SourceRateLimited => {
+                    counters::ADD_TX_SOURCE_RATE_LIMITED.inc();
+                    ConsensusGrpcError::SourceRateLimited
+                }
+            });
         }
 
         // Check if node is accepting requests.
@@ -84,7 +114,17 @@ impl<E: ConsensusEnclaveProxy, L: Ledger + Clone> ClientApiService<E, L> {
         let tx_context = self.enclave.client_tx_propose(request.into())?;
         let tx_hash = tx_context.tx_hash;
 
-        match self.tx_manager.insert_proposed_tx(tx_context) {
+        // When the network is congested and transactions are selected by fee, reject transactions
+        // that would not make it into the next block.
+        let min_fee = match self.tx_selection_policy {
+            TxSelectionPolicy::HashOrder => 0,
+            TxSelectionPolicy::FeePriority => self.tx_manager.admission_fee_floor(),
+        };
+
+        match self
+            .tx_manager
+            .insert_proposed_tx_with_min_fee(tx_context, min_fee)
+        {
             Ok(tx_context) => {
                 // Submit for consideration in next SCP slot.
                 (*self.scp_client_value_sender)(*tx_context.tx_hash(), None, None);
@@ -118,6 +158,27 @@ impl<E: ConsensusEnclaveProxy, L: Ledger + Clone> ClientApiService<E, L> {
                 Err(err.into())
             }
 
+            Err(TxManagerError::FeeTooLowForCurrentLoad(min_fee)) => {
+                log::trace!(
+                    logger,
+                    "Rejecting transaction {tx_hash}, fee is below {min_fee}",
+                    tx_hash = tx_hash.to_string(),
+                    min_fee = min_fee
+                );
+                counters::ADD_TX_FEE_TOO_LOW.inc();
+                Err(ConsensusGrpcError::FeeTooLowForCurrentLoad(min_fee))
+            }
+
+            Err(TxManagerError::CacheFull) => {
+                log::trace!(
+                    logger,
+                    "Rejecting transaction {tx_hash}, tx cache is full",
+                    tx_hash = tx_hash.to_string(),
+                );
+                counters::ADD_TX_CACHE_FULL.inc();
+                Err(ConsensusGrpcError::TxCacheFull)
+            }
+
             Err(err) => {
                 log::info!(
                     logger,
@@ -139,11 +200,12 @@ impl<E: ConsensusEnclaveProxy, L: Ledger + Clone> ConsensusClientApi for ClientA
         sink: UnarySink<ProposeTxResponse>,
     ) {
         let _timer = SVC_COUNTERS.req(&ctx);
+        let source_ip = source_ip_from_grpc_peer(&ctx.peer());
         common::logger::scoped_global_logger(&rpc_logger(&ctx, &self.logger), |logger| {
             send_result(
                 ctx,
                 sink,
-                self.real_client_tx_propose(request, &logger)
+                self.real_client_tx_propose(request, source_ip, &logger)
                     .or_else(ConsensusGrpcError::into)
                     .and_then(|mut resp| {
                         resp.set_num_blocks(
@@ -156,3 +218,141 @@ impl<E: ConsensusEnclaveProxy, L: Ledger + Clone> ConsensusClientApi for ClientA
         });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validators::DefaultTxManagerUntrustedInterfaces;
+    use common::logger::test_with_logger;
+    use consensus_enclave_mock::ConsensusServiceMockEnclave;
+    use ledger_db::LedgerDB;
+    use rand::{rngs::StdRng, SeedableRng};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use transaction::{account_keys::AccountKey, tx::Tx};
+    use transaction_test_utils::{create_ledger, create_transaction, initialize_ledger};
+
+    fn create_client_api_service(
+        ledger: &LedgerDB,
+        tx_manager: &TxManager<ConsensusServiceMockEnclave, LedgerDB>,
+        tx_selection_policy: TxSelectionPolicy,
+        num_proposed: Arc<AtomicUsize>,
+        logger: &Logger,
+    ) -> ClientApiService<ConsensusServiceMockEnclave, LedgerDB> {
+        ClientApiService::new(
+            ConsensusServiceMockEnclave::default(),
+            Arc::new(move |_tx_hash, _node_id, _responder_id| {
+                num_proposed.fetch_add(1, Ordering::SeqCst);
+            }),
+            ledger.clone(),
+            tx_manager.clone(),
+            Arc::new(|| true),
+            tx_selection_policy,
+            AdmissionControl::new(i64::max_value(), None, None),
+            logger.clone(),
+        )
+    }
+
+    fn propose_tx_request(tx: &Tx) -> Message {
+        // The mock enclave expects a plaintext serialized Tx.
+        let mut request = Message::default();
+        request.set_data(mcserial::encode(tx));
+        request
+    }
+
+    #[test_with_logger]
+    // Under fee-priority selection, transactions paying less than the admission fee floor should
+    // be rejected.
+    fn test_client_tx_propose_rejects_fee_below_floor(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let sender = AccountKey::random(&mut rng);
+        let mut ledger = create_ledger();
+        let n_blocks = 3;
+        initialize_ledger(&mut ledger, n_blocks, &sender, &mut rng);
+
+        let tx_out = ledger.get_transactions_by_block(0).unwrap()[0].outputs[1].clone();
+        let recipient = AccountKey::random(&mut rng);
+        let tx = create_transaction(
+            &mut ledger,
+            &tx_out,
+            &sender,
+            &recipient.default_subaddress(),
+            n_blocks + 1,
+            &mut rng,
+        );
+
+        let tx_manager = TxManager::new(
+            ConsensusServiceMockEnclave::default(),
+            ledger.clone(),
+            DefaultTxManagerUntrustedInterfaces::new(ledger.clone()),
+            logger.clone(),
+        );
+        tx_manager.set_admission_fee_floor(tx.prefix.fee + 1);
+
+        let num_proposed = Arc::new(AtomicUsize::new(0));
+        let mut fee_priority_service = create_client_api_service(
+            &ledger,
+            &tx_manager,
+            TxSelectionPolicy::FeePriority,
+            num_proposed.clone(),
+            &logger,
+        );
+
+        match fee_priority_service.real_client_tx_propose(propose_tx_request(&tx), None, &logger) {
+            Err(ConsensusGrpcError::FeeTooLowForCurrentLoad(min_fee)) => {
+                assert_eq!(min_fee, tx.prefix.fee + 1)
+            }
+            result => panic!("Unexpected result {:?}", result),
+        }
+        assert_eq!(num_proposed.load(Ordering::SeqCst), 0);
+
+        // Once the floor drops to its fee, the same transaction is accepted.
+        tx_manager.set_admission_fee_floor(tx.prefix.fee);
+        assert!(fee_priority_service
+            .real_client_tx_propose(propose_tx_request(&tx), None, &logger)
+            .is_ok());
+        assert_eq!(num_proposed.load(Ordering::SeqCst), 1);
+    }
+
+    #[test_with_logger]
+    // Under hash-order selection, the admission fee floor should not apply.
+    fn test_client_tx_propose_ignores_fee_floor_in_hash_order(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([2u8; 32]);
+        let sender = AccountKey::random(&mut rng);
+        let mut ledger = create_ledger();
+        let n_blocks = 3;
+        initialize_ledger(&mut ledger, n_blocks, &sender, &mut rng);
+
+        let tx_out = ledger.get_transactions_by_block(0).unwrap()[0].outputs[1].clone();
+        let recipient = AccountKey::random(&mut rng);
+        let tx = create_transaction(
+            &mut ledger,
+            &tx_out,
+            &sender,
+            &recipient.default_subaddress(),
+            n_blocks + 1,
+            &mut rng,
+        );
+
+        let tx_manager = TxManager::new(
+            ConsensusServiceMockEnclave::default(),
+            ledger.clone(),
+            DefaultTxManagerUntrustedInterfaces::new(ledger.clone()),
+            logger.clone(),
+        );
+        tx_manager.set_admission_fee_floor(tx.prefix.fee + 1);
+
+        let num_proposed = Arc::new(AtomicUsize::new(0));
+        let mut hash_order_service = create_client_api_service(
+            &ledger,
+            &tx_manager,
+            TxSelectionPolicy::HashOrder,
+            num_proposed.clone(),
+            &logger,
+        );
+
+        assert!(hash_order_service
+            .real_client_tx_propose(propose_tx_request(&tx), None, &logger)
+            .is_ok());
+        assert_eq!(num_proposed.load(Ordering::SeqCst), 1);
+    }
+}
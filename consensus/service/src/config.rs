@@ -2,16 +2,31 @@
 
 //! Configuration parameters for the Consensus Service application.
 
+use crate::{
+    admission_control::{AdmissionControl, RateLimit},
+    validators::TxSelectionPolicy,
+};
 use attest::ProviderId;
 use base64;
 use common::{HashMap, HashSet, NodeID, ResponderId};
+use failure::Fail;
 use keys::{DistinguishedEncoding, Ed25519Pair, Ed25519Private};
 use mcuri::{ConnectionUri, ConsensusClientUri as ClientUri, ConsensusPeerUri as PeerUri};
+use peers::{
+    ThreadedBroadcasterCircuitBreakerRetryPolicy, ThreadedBroadcasterDynamicRetryPolicy,
+    ThreadedBroadcasterExponentialBackoffRetryPolicy, ThreadedBroadcasterFibonacciRetryPolicy,
+};
 use scp::{QuorumSet, QuorumSetMember};
 use serde::{Deserialize, Serialize};
-use std::{fmt::Debug, fs, iter::FromIterator, path::PathBuf, string::String, sync::Arc};
+use std::{fmt::Debug, fs, path::PathBuf, str::FromStr, string::String, sync::Arc, time::Duration};
 use structopt::StructOpt;
 
+#[derive(Debug, Fail)]
+pub enum NetworkConfigError {
+    #[fail(display = "Invalid network configuration: {}", _0)]
+    Invalid(String),
+}
+
 #[derive(Clone, Debug, StructOpt)]
 #[structopt(
     name = "consensus_service",
@@ -63,6 +78,11 @@ pub struct Config {
     #[structopt(long)]
     pub management_listen_addr: Option<String>,
 
+    /// Bearer token required by the management server's admin endpoints. The admin endpoints are
+    /// disabled when this is not set.
+    #[structopt(long)]
+    pub admin_token: Option<String>,
+
     /// The location to write the externalized blocks for the ledger.
     #[structopt(long, parse(from_os_str))]
     pub ledger_path: PathBuf,
@@ -78,6 +98,94 @@ pub struct Config {
     /// Path to the sealed block signing key
     #[structopt(long, parse(from_os_str))]
     pub sealed_block_signing_key: PathBuf,
+
+    /// Directory in which to persist pending transactions, so that they survive a restart.
+    /// Transactions are sealed to the enclave before being written.
+    #[structopt(long, parse(from_os_str))]
+    pub tx_cache_path: Option<PathBuf>,
+
+    /// How transactions are selected when there are more than fit in a block (hash-order or
+    /// fee-priority). All nodes in the network must use the same value.
+    #[structopt(long, default_value = "hash-order")]
+    pub tx_selection_policy: TxSelectionPolicy,
+
+    /// Maximum number of values pending consensus before client transactions are rejected.
+    #[structopt(long, default_value = "500")]
+    pub pending_limit: i64,
+
+    /// Transactions per second a single client session may submit (0 for unlimited).
+    #[structopt(long, default_value = "10")]
+    pub client_tx_rate_limit: u32,
+
+    /// Transactions a single client session may submit in a burst.
+    #[structopt(long, default_value = "20")]
+    pub client_tx_burst: u32,
+
+    /// Transactions per second a single source IP address may submit (0 for unlimited).
+    #[structopt(long, default_value = "50")]
+    pub source_ip_tx_rate_limit: u32,
+
+    /// Transactions a single source IP address may submit in a burst.
+    #[structopt(long, default_value = "100")]
+    pub source_ip_tx_burst: u32,
+
+    /// Maximum number of proposed transactions held in memory. When full, the transactions
+    /// paying the lowest fee are evicted first.
+    #[structopt(long, default_value = "10000")]
+    pub max_tx_cache_entries: usize,
+
+    /// Learn about other validators from our peers, and connect to them.
+    #[structopt(long)]
+    pub peer_discovery: bool,
+
+    /// The URI other validators should use to connect to this node. It is shared with peers during
+    /// peer discovery, and must match the peer responder id and msg-signer-key.
+    #[structopt(long)]
+    pub advertise_peer_uri: Option<PeerUri>,
+
+    /// How failed broadcasts to a peer are retried (fibonacci, exponential-backoff or
+    /// circuit-breaker). The circuit breaker retries like exponential-backoff, but stops trying a
+    /// peer for a while after repeated failures.
+    #[structopt(long, default_value = "fibonacci")]
+    pub broadcast_retry_policy: BroadcastRetryPolicy,
+
+    /// Number of consecutive failed broadcasts after which the circuit breaker stops trying a peer.
+    #[structopt(long, default_value = "5")]
+    pub circuit_breaker_failure_threshold: u32,
+
+    /// Seconds the circuit breaker waits before trying a failing peer again.
+    #[structopt(long, default_value = "30")]
+    pub circuit_breaker_cooldown_secs: u64,
+}
+
+/// Retry policies that can be selected for broadcasting messages to peers.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BroadcastRetryPolicy {
+    Fibonacci,
+    ExponentialBackoff,
+    CircuitBreaker,
+}
+
+impl Default for BroadcastRetryPolicy {
+    fn default() -> Self {
+        Self::Fibonacci
+    }
+}
+
+impl FromStr for BroadcastRetryPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fibonacci" => Ok(Self::Fibonacci),
+            "exponential-backoff" => Ok(Self::ExponentialBackoff),
+            "circuit-breaker" => Ok(Self::CircuitBreaker),
+            _ => Err(
+                "Unknown value, valid values are fibonacci/exponential-backoff/circuit-breaker"
+                    .into(),
+            ),
+        }
+    }
 }
 
 fn keypair_from_base64(src: &str) -> Result<Arc<Ed25519Pair>, String> {
@@ -105,28 +213,28 @@ pub struct NetworkConfig {
 }
 
 impl NetworkConfig {
+    /// Panics if the quorum set refers to unknown peers.
     pub fn quorum_set(&self) -> QuorumSet {
-        let mut peer_map: HashMap<ResponderId, NodeID> =
-            HashMap::from_iter(self.broadcast_peers.iter().cloned().map(|uri| {
-                (
-                    uri.responder_id().unwrap_or_else(|e| {
-                        panic!("unable to get responder_id for {}: {:?}", uri, e)
-                    }),
-                    uri.node_id()
-                        .unwrap_or_else(|e| panic!("unable to get node_id for {}: {:?}", uri, e)),
-                )
-            }));
+        self.try_quorum_set()
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Resolve the quorum set's responder ids into node ids using the configured peers.
+    pub fn try_quorum_set(&self) -> Result<QuorumSet, NetworkConfigError> {
+        let mut peer_map: HashMap<ResponderId, NodeID> = HashMap::default();
+        for uri in self.broadcast_peers.iter() {
+            let (responder_id, node_id) = Self::peer_ids(uri)?;
+            peer_map.insert(responder_id, node_id);
+        }
 
         if let Some(known_peers) = self.known_peers.as_ref() {
             for uri in known_peers.iter() {
-                let responder_id = uri
-                    .responder_id()
-                    .unwrap_or_else(|e| panic!("unable to get responder_id for {}: {:?}", uri, e));
-                let node_id = uri
-                    .node_id()
-                    .unwrap_or_else(|e| panic!("unable to get node_id for {}: {:?}", uri, e));
+                let (responder_id, node_id) = Self::peer_ids(uri)?;
                 if peer_map.get(&responder_id).unwrap_or(&node_id) != &node_id {
-                    panic!("node id mismatch for {}", responder_id);
+                    return Err(NetworkConfigError::Invalid(format!(
+                        "node id mismatch for {}",
+                        responder_id
+                    )));
                 } else {
                     peer_map.insert(responder_id, node_id);
                 }
@@ -140,34 +248,74 @@ impl NetworkConfig {
         self.broadcast_peers.clone()
     }
 
+    fn peer_ids(uri: &PeerUri) -> Result<(ResponderId, NodeID), NetworkConfigError> {
+        let responder_id = uri.responder_id().map_err(|e| {
+            NetworkConfigError::Invalid(format!("unable to get responder_id for {}: {:?}", uri, e))
+        })?;
+        let node_id = uri.node_id().map_err(|e| {
+            NetworkConfigError::Invalid(format!("unable to get node_id for {}: {:?}", uri, e))
+        })?;
+        Ok((responder_id, node_id))
+    }
+
     // Convert a QuorumSet<ResponderId> -> QuorumSet<NodeID> based on a
     // ResponderID -> NodeID map.
     fn resolve_quorum_set(
         src: &QuorumSet<ResponderId>,
         peer_map: &HashMap<ResponderId, NodeID>,
-    ) -> QuorumSet<NodeID> {
+    ) -> Result<QuorumSet<NodeID>, NetworkConfigError> {
         let mut new_members = Vec::with_capacity(src.members.len());
         for member in src.members.iter() {
             let new_member = match member {
                 QuorumSetMember::Node(responder_id) => QuorumSetMember::Node(
-                    peer_map
-                        .get(&responder_id)
-                        .unwrap_or_else(|| {
-                            panic!("Unknown responder_id {} in quorum set", responder_id)
-                        })
-                        .clone(),
+                    peer_map.get(&responder_id).cloned().ok_or_else(|| {
+                        NetworkConfigError::Invalid(format!(
+                            "Unknown responder_id {} in quorum set",
+                            responder_id
+                        ))
+                    })?,
                 ),
                 QuorumSetMember::InnerSet(qs_config) => {
-                    QuorumSetMember::InnerSet(Self::resolve_quorum_set(&qs_config, peer_map))
+                    QuorumSetMember::InnerSet(Self::resolve_quorum_set(&qs_config, peer_map)?)
                 }
             };
             new_members.push(new_member);
         }
-        QuorumSet::new(src.threshold, new_members)
+        Ok(QuorumSet::new(src.threshold, new_members))
     }
 }
 
 impl Config {
+    /// Admission control for client-submitted transactions.
+    pub fn admission_control(&self) -> AdmissionControl {
+        AdmissionControl::new(
+            self.pending_limit,
+            RateLimit::new(self.client_tx_rate_limit, self.client_tx_burst),
+            RateLimit::new(self.source_ip_tx_rate_limit, self.source_ip_tx_burst),
+        )
+    }
+
+    /// The retry policy used when broadcasting messages to peers.
+    pub fn retry_policy(&self) -> ThreadedBroadcasterDynamicRetryPolicy {
+        match self.broadcast_retry_policy {
+            BroadcastRetryPolicy::Fibonacci => {
+                ThreadedBroadcasterFibonacciRetryPolicy::default().into()
+            }
+            BroadcastRetryPolicy::ExponentialBackoff => {
+                ThreadedBroadcasterExponentialBackoffRetryPolicy::default().into()
+            }
+            BroadcastRetryPolicy::CircuitBreaker => {
+                let mut policy = ThreadedBroadcasterCircuitBreakerRetryPolicy::new(
+                    ThreadedBroadcasterExponentialBackoffRetryPolicy::default(),
+                );
+                policy
+                    .failure_threshold(self.circuit_breaker_failure_threshold)
+                    .cooldown(Duration::from_secs(self.circuit_breaker_cooldown_secs));
+                policy.into()
+            }
+        }
+    }
+
     /// Get NodeID for this consensus validator.
     pub fn node_id(&self) -> NodeID {
         NodeID {
@@ -176,31 +324,74 @@ impl Config {
         }
     }
 
+    /// The URI to share with peers during peer discovery, if any. Panics if it does not refer to
+    /// this node.
+    pub fn advertise_peer_uri(&self) -> Option<PeerUri> {
+        let uri = self.advertise_peer_uri.clone()?;
+        let node_id = uri
+            .node_id()
+            .unwrap_or_else(|err| panic!("Invalid advertise-peer-uri {}: {:?}", uri, err));
+        if node_id != self.node_id() {
+            panic!(
+                "advertise-peer-uri {} does not match peer-responder-id and msg-signer-key",
+                uri
+            );
+        }
+        Some(uri)
+    }
+
     /// Get the network configuration by loading the network.toml/json file.
+    /// Panics if the configuration cannot be loaded or is invalid.
     pub fn network(&self) -> NetworkConfig {
+        self.load_network().unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Load and validate the network configuration from the network.toml/json file.
+    pub fn load_network(&self) -> Result<NetworkConfig, NetworkConfigError> {
         // Read configuration file.
-        let data = fs::read_to_string(&self.network_path)
-            .unwrap_or_else(|err| panic!("failed reading {:?}: {:?}", self.network_path, err));
+        let data = fs::read_to_string(&self.network_path).map_err(|err| {
+            NetworkConfigError::Invalid(format!(
+                "failed reading {:?}: {:?}",
+                self.network_path, err
+            ))
+        })?;
 
         // Parse configuration file.
         let network: NetworkConfig =
             match self.network_path.extension().and_then(|ext| ext.to_str()) {
-                None => panic!(
-                    "failed figuring out file extension for path {:?}",
-                    self.network_path
-                ),
-                Some("toml") => toml::from_str(&data).unwrap_or_else(|err| {
-                    panic!("failed TOML parsing {:?}: {:?}", self.network_path, err)
-                }),
-                Some("json") => serde_json::from_str(&data).unwrap_or_else(|err| {
-                    panic!("failed JSON parsing {:?}: {:?}", self.network_path, err)
-                }),
-                Some(ext) => panic!(
-                    "Unrecognized extension in path {:?}: {:?}",
-                    self.network_path, ext
-                ),
+                None => {
+                    return Err(NetworkConfigError::Invalid(format!(
+                        "failed figuring out file extension for path {:?}",
+                        self.network_path
+                    )))
+                }
+                Some("toml") => toml::from_str(&data).map_err(|err| {
+                    NetworkConfigError::Invalid(format!(
+                        "failed TOML parsing {:?}: {:?}",
+                        self.network_path, err
+                    ))
+                })?,
+                Some("json") => serde_json::from_str(&data).map_err(|err| {
+                    NetworkConfigError::Invalid(format!(
+                        "failed JSON parsing {:?}: {:?}",
+                        self.network_path, err
+                    ))
+                })?,
+                Some(ext) => {
+                    return Err(NetworkConfigError::Invalid(format!(
+                        "Unrecognized extension in path {:?}: {:?}",
+                        self.network_path, ext
+                    )))
+                }
             };
 
+        self.validate_network(&network)?;
+
+        // Success.
+        Ok(network)
+    }
+
+    fn validate_network(&self, network: &NetworkConfig) -> Result<(), NetworkConfigError> {
         // Sanity tests:
         // - Our responder ID should not appear in `broadcast_peers` or `known_peers`.
         //   This also ensures it is not part of the quorum set.
@@ -211,33 +402,40 @@ impl Config {
             .chain(network.known_peers.iter().flatten());
         let mut spotted_responder_ids = HashSet::default();
         for peer_uri in peer_uris {
-            let responder_id = peer_uri.responder_id().unwrap_or_else(|e| {
-                panic!("failed getting responder id for {:?}: {:?}", peer_uri, e)
-            });
+            let responder_id = peer_uri.responder_id().map_err(|e| {
+                NetworkConfigError::Invalid(format!(
+                    "failed getting responder id for {:?}: {:?}",
+                    peer_uri, e
+                ))
+            })?;
 
             if self.peer_responder_id == responder_id {
-                panic!(
+                return Err(NetworkConfigError::Invalid(format!(
                     "Our peer responder id ({}) should not appear in broadcast_peers or known_peers!",
                     responder_id
-                );
+                )));
             }
 
             if !spotted_responder_ids.insert(responder_id.clone()) {
-                panic!(
+                return Err(NetworkConfigError::Invalid(format!(
                     "Duplicate responder_id {} found in network configuration",
                     responder_id
-                );
+                )));
             }
         }
 
         // Sanity test: We should have at least one source of transactions, if we have any peers
         // configured.
         if !network.broadcast_peers.is_empty() && network.tx_source_urls.is_empty() {
-            panic!("Network configuration is missing tx_source_urls");
+            return Err(NetworkConfigError::Invalid(
+                "Network configuration is missing tx_source_urls".to_string(),
+            ));
         }
 
-        // Success.
-        network
+        // Sanity test: Every quorum set member should be a known peer.
+        network.try_quorum_set()?;
+
+        Ok(())
     }
 }
 
@@ -381,6 +579,27 @@ mod tests {
         }
     }
 
+    #[test]
+    // A quorum set member that is not a configured peer is reported as an error.
+    fn test_try_quorum_set_unknown_member() {
+        let input_toml: &str = r#"
+            broadcast_peers = [
+                "insecure-mcp://0.0.0.0:8082?consensus-msg-key=MCowBQYDK2VwAyEA_ii3rCch5qhMbLZ2vVgpQr1iTrq1BBN2-i0mMPuAJhQ=",
+            ]
+            tx_source_urls = ["file:///tmp/dump"]
+            quorum_set = { threshold = 2, members = [
+                { type = "Node", args = "0.0.0.0:8082" },
+                { type = "Node", args = "0.0.0.0:8083" },
+            ] }
+        "#;
+        let network: NetworkConfig = toml::from_str(input_toml).expect("failed parsing toml");
+
+        match network.try_quorum_set() {
+            Err(NetworkConfigError::Invalid(msg)) => assert!(msg.contains("0.0.0.0:8083")),
+            Ok(quorum_set) => panic!("unexpected quorum set {:?}", quorum_set),
+        }
+    }
+
     #[test]
     fn test_local_uris_with_pubkey() {
         let config = Config {
@@ -396,10 +615,24 @@ mod tests {
             peer_listen_uri: PeerUri::from_str("insecure-mcp://0.0.0.0:8081/").unwrap(),
             client_listen_uri: ClientUri::from_str("insecure-mc://0.0.0.0:3223/").unwrap(),
             management_listen_addr: None,
+            admin_token: None,
             ledger_path: PathBuf::default(),
             scp_debug_dump: None,
             origin_block_path: None,
             sealed_block_signing_key: PathBuf::default(),
+            tx_cache_path: None,
+            tx_selection_policy: TxSelectionPolicy::default(),
+            pending_limit: 500,
+            client_tx_rate_limit: 10,
+            client_tx_burst: 20,
+            source_ip_tx_rate_limit: 50,
+            source_ip_tx_burst: 100,
+            max_tx_cache_entries: 10000,
+            peer_discovery: false,
+            advertise_peer_uri: None,
+            broadcast_retry_policy: BroadcastRetryPolicy::default(),
+            circuit_breaker_failure_threshold: 5,
+            circuit_breaker_cooldown_secs: 30,
         };
 
         assert_eq!(
@@ -445,10 +678,24 @@ mod tests {
             peer_listen_uri: PeerUri::from_str("mcp://0.0.0.0:8443/?tls-chain=./public/attest/test_certs/selfsigned_mobilecoin.crt&tls-key=./public/attest/test_certs/selfsigned_mobilecoin.key").unwrap(),
             client_listen_uri: ClientUri::from_str("insecure-mc://0.0.0.0:3223/").unwrap(),
             management_listen_addr: None,
+            admin_token: None,
             ledger_path: PathBuf::default(),
             scp_debug_dump: None,
             origin_block_path: None,
             sealed_block_signing_key: PathBuf::default(),
+            tx_cache_path: None,
+            tx_selection_policy: TxSelectionPolicy::default(),
+            pending_limit: 500,
+            client_tx_rate_limit: 10,
+            client_tx_burst: 20,
+            source_ip_tx_rate_limit: 50,
+            source_ip_tx_burst: 100,
+            max_tx_cache_entries: 10000,
+            peer_discovery: false,
+            advertise_peer_uri: None,
+            broadcast_retry_policy: BroadcastRetryPolicy::default(),
+            circuit_breaker_failure_threshold: 5,
+            circuit_breaker_cooldown_secs: 30,
         };
 
         assert_eq!(
@@ -3,10 +3,22 @@
 //! The MobileCoin consensus service.
 
 use crate::{
-    attested_api_service::AttestedApiService, background_work_queue::BackgroundWorkQueue,
-    blockchain_api_service, byzantine_ledger::ByzantineLedger, client_api_service, config::Config,
-    counters, management::ManagementServer, peer_api_service, peer_keepalive::PeerKeepalive,
-    tx_manager::TxManager, validators::DefaultTxManagerUntrustedInterfaces,
+    admission_control::AdmissionControl,
+    attested_api_service::AttestedApiService,
+    background_work_queue::BackgroundWorkQueue,
+    blockchain_api_service,
+    byzantine_ledger::ByzantineLedger,
+    client_api_service,
+    config::{Config, NetworkConfigError},
+    counters,
+    management::{AdminApi, ManagementServer},
+    network_reloader::NetworkReloader,
+    peer_api_service,
+    peer_discovery::{AddPeersFn, PeerDiscovery, PeerExchange},
+    peer_keepalive::PeerKeepalive,
+    tx_cache_store::TxCacheStore,
+    tx_manager::TxManager,
+    validators::DefaultTxManagerUntrustedInterfaces,
 };
 use attest::{
     IasQuoteError, PibError, QuoteError, QuoteSignType, TargetInfoError, VerificationReport,
@@ -33,10 +45,14 @@ use peers::{PeerConnection, ThreadedBroadcaster, VerifiedConsensusMsg};
 use retry::{delay::Fibonacci, retry, Error as RetryError, OperationResult};
 use std::{
     sync::{Arc, Mutex},
-    time::Instant,
+    thread,
+    time::{Duration, Instant},
 };
 use transaction::tx::TxHash;
 
+/// How often `wait_for_drain` checks whether the node has drained.
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
 #[derive(Debug, Fail)]
 pub enum ConsensusServiceError {
     #[fail(display = "Error getting quoting enclave target info: {}", _0)]
@@ -57,6 +73,8 @@ pub enum ConsensusServiceError {
     BackgroundWorkQueueStart(String),
     #[fail(display = "Failed to stop background work queue: {}", _0)]
     BackgroundWorkQueueStop(String),
+    #[fail(display = "Failed to reload network configuration: {}", _0)]
+    NetworkConfig(NetworkConfigError),
 }
 
 impl From<EnclaveError> for ConsensusServiceError {
@@ -65,6 +83,12 @@ impl From<EnclaveError> for ConsensusServiceError {
     }
 }
 
+impl From<NetworkConfigError> for ConsensusServiceError {
+    fn from(src: NetworkConfigError) -> Self {
+        ConsensusServiceError::NetworkConfig(src)
+    }
+}
+
 impl From<PibError> for ConsensusServiceError {
     fn from(src: PibError) -> Self {
         ConsensusServiceError::TcbUpdate(src)
@@ -126,6 +150,10 @@ pub struct ConsensusService<E: ConsensusEnclaveProxy, R: RaClient + Send + Sync
     broadcaster: Arc<Mutex<ThreadedBroadcaster>>,
     tx_manager: TxManager<E, LedgerDB>,
     peer_keepalive: Arc<Mutex<PeerKeepalive>>,
+    admission_control: AdmissionControl,
+    network_reloader: NetworkReloader<E>,
+    peer_exchange: PeerExchange<PeerConnection<E>>,
+    peer_discovery: Option<PeerDiscovery>,
 
     consensus_rpc_server: Option<grpcio::Server>,
     user_rpc_server: Option<grpcio::Server>,
@@ -174,28 +202,91 @@ impl<E: ConsensusEnclaveProxy, R: RaClient + Send + Sync + 'static> ConsensusSer
         // Broadcaster
         let broadcaster = Arc::new(Mutex::new(ThreadedBroadcaster::new(
             &peer_manager,
-            &peers::ThreadedBroadcasterFibonacciRetryPolicy::default(),
+            &config.retry_policy(),
             logger.clone(),
         )));
 
         // Tx Manager
-        let tx_manager = TxManager::new(
+        let mut tx_manager = TxManager::new(
             enclave.clone(),
             ledger_db.clone(),
-            DefaultTxManagerUntrustedInterfaces::new(ledger_db.clone()),
+            DefaultTxManagerUntrustedInterfaces::new_with_selection_policy(
+                ledger_db.clone(),
+                config.tx_selection_policy,
+            ),
             logger.clone(),
-        );
+        )
+        .with_max_entries(config.max_tx_cache_entries);
+        if let Some(tx_cache_path) = &config.tx_cache_path {
+            let store = TxCacheStore::open(tx_cache_path).unwrap_or_else(|err| {
+                panic!(
+                    "Could not open tx cache store at {:?}: {}",
+                    tx_cache_path, err
+                )
+            });
+            tx_manager = tx_manager.with_store(store);
+        }
 
         // Peer Keepalive
         let peer_keepalive = Arc::new(Mutex::new(PeerKeepalive::start(
             peer_manager.clone(),
             consensus_msgs_from_network.get_sender_fn(),
+            broadcaster.lock().expect("mutex poisoned").health_scores(),
             logger.clone(),
         )));
 
+        // Client transaction admission, also controlled through the management server.
+        let admission_control = config.admission_control();
+
+        let byzantine_ledger = Arc::new(Mutex::new(None));
+
+        // Network configuration reloading, triggered through the management server.
+        let network_reloader = NetworkReloader::new(
+            config.clone(),
+            enclave.clone(),
+            local_node_id.clone(),
+            env.clone(),
+            peer_manager.clone(),
+            broadcaster.clone(),
+            peer_keepalive.clone(),
+            byzantine_ledger.clone(),
+            logger.clone(),
+        );
+
+        // Peer discovery. We always share our peer list, but only connect to the peers other nodes
+        // tell us about when discovery is enabled.
+        let add_peers_fn: Option<AddPeersFn> = if config.peer_discovery {
+            let network_reloader = network_reloader.clone();
+            Some(Arc::new(move |peer_uris| {
+                network_reloader.add_discovered_peers(peer_uris)
+            }))
+        } else {
+            None
+        };
+        let peer_exchange = PeerExchange::new(
+            peer_manager.clone(),
+            local_node_id.clone(),
+            config.msg_signer_key.clone(),
+            config.advertise_peer_uri(),
+            add_peers_fn,
+            logger.clone(),
+        );
+
         // Management Server
         let management_server = if config.management_listen_addr.is_some() {
-            Some(ManagementServer::new(config.clone(), logger.clone()))
+            let admin_api = Self::create_admin_api(
+                &admission_control,
+                &tx_manager,
+                &byzantine_ledger,
+                &peer_keepalive,
+                &network_reloader,
+                &logger,
+            );
+            Some(ManagementServer::new(
+                config.clone(),
+                admin_api,
+                logger.clone(),
+            ))
         } else {
             None
         };
@@ -217,10 +308,14 @@ impl<E: ConsensusEnclaveProxy, R: RaClient + Send + Sync + 'static> ConsensusSer
             broadcaster,
             tx_manager,
             peer_keepalive,
+            admission_control,
+            network_reloader,
+            peer_exchange,
+            peer_discovery: None,
 
             consensus_rpc_server: None,
             user_rpc_server: None,
-            byzantine_ledger: Arc::new(Mutex::new(None)),
+            byzantine_ledger,
         }
     }
 
@@ -229,22 +324,36 @@ impl<E: ConsensusEnclaveProxy, R: RaClient + Send + Sync + 'static> ConsensusSer
     }
 
     pub fn start(&mut self) -> Result<(), ConsensusServiceError> {
-        let ret = {
-            self.update_enclave_report_cache()?;
-            self.start_consensus_rpc_server()?;
-            self.start_user_rpc_server()?;
-            self.start_byzantine_ledger_service()?;
-            self.start_management_server()?;
-
-            // Success.
-            Ok(())
-        };
+        let ret = self
+            .update_enclave_report_cache()
+            .and_then(|_| self.start_services());
         if ret.is_err() {
             let _ = self.stop();
         }
         ret
     }
 
+    /// Start the service without first obtaining an IAS report for the enclave. This is only
+    /// useful with enclaves that do not need one to attest, such as the mock enclave used by tests.
+    pub fn start_unattested(&mut self) -> Result<(), ConsensusServiceError> {
+        let ret = self.start_services();
+        if ret.is_err() {
+            let _ = self.stop();
+        }
+        ret
+    }
+
+    fn start_services(&mut self) -> Result<(), ConsensusServiceError> {
+        self.start_consensus_rpc_server()?;
+        self.start_user_rpc_server()?;
+        self.start_byzantine_ledger_service()?;
+        self.start_management_server()?;
+        self.start_peer_discovery()?;
+
+        // Success.
+        Ok(())
+    }
+
     pub fn stop(&mut self) -> Result<(), ConsensusServiceError> {
         log::debug!(self.logger, "Attempting to stop node...");
 
@@ -254,6 +363,10 @@ impl<E: ConsensusEnclaveProxy, R: RaClient + Send + Sync + 'static> ConsensusSer
 
         self.peer_keepalive.lock().expect("mutex poisoned").stop();
 
+        if let Some(ref mut peer_discovery) = self.peer_discovery.take() {
+            peer_discovery.stop();
+        }
+
         if let Some(ref mut server) = self.user_rpc_server.take() {
             server.shutdown().wait().or_else(|_| {
                 Err(ConsensusServiceError::RpcShutdown(
@@ -282,9 +395,46 @@ impl<E: ConsensusEnclaveProxy, R: RaClient + Send + Sync + 'static> ConsensusSer
             byzantine_ledger.stop();
         }
 
+        // Persist transactions received since the last flush by ByzantineLedger.
+        if let Err(err) = self.tx_manager.flush_to_store() {
+            log::error!(
+                self.logger,
+                "Failed persisting cached transactions: {}",
+                err
+            );
+        }
+
         Ok(())
     }
 
+    /// Stop accepting client transactions and stop nominating. The node finishes the slot that is
+    /// in progress, after which `is_drained` returns true.
+    pub fn drain(&self) {
+        drain(
+            &self.admission_control,
+            &self.byzantine_ledger,
+            &self.logger,
+        );
+    }
+
+    /// Check if the node has finished draining.
+    pub fn is_drained(&self) -> bool {
+        is_drained(&self.byzantine_ledger)
+    }
+
+    /// Re-read the network configuration file and apply it: peers are reconnected right away, and
+    /// the new quorum set is used from the next slot.
+    pub fn reload_network(&self) -> Result<(), ConsensusServiceError> {
+        Ok(self.network_reloader.reload()?)
+    }
+
+    /// Block until the node has been drained, e.g. through the management server.
+    pub fn wait_for_drain(&self) {
+        while !self.is_drained() {
+            thread::sleep(DRAIN_POLL_INTERVAL);
+        }
+    }
+
     #[allow(dead_code)]
     pub fn wait_for_all_threads(&mut self) -> Result<(), ConsensusServiceError> {
         log::debug!(
@@ -420,6 +570,8 @@ impl<E: ConsensusEnclaveProxy, R: RaClient + Send + Sync + 'static> ConsensusSer
                 self.ledger_db.clone(),
                 self.tx_manager.clone(),
                 self.create_is_serving_user_requests_fn(),
+                self.config.tx_selection_policy,
+                self.admission_control.clone(),
                 self.logger.clone(),
             ),
         );
@@ -492,6 +644,13 @@ impl<E: ConsensusEnclaveProxy, R: RaClient + Send + Sync + 'static> ConsensusSer
                 .and_then(|byzantine_ledger| byzantine_ledger.get_highest_scp_message())
         });
 
+        // Peers can change when the network configuration is reloaded.
+        let peer_manager = self.peer_manager.clone();
+        let known_responder_ids_fn = Arc::new(move || peer_manager.responder_ids());
+
+        let peer_exchange = self.peer_exchange.clone();
+        let peer_exchange_fn = Arc::new(move |msg| peer_exchange.handle_msg(msg));
+
         let blockchain_service = blockchain_grpc::create_blockchain_api(
             blockchain_api_service::BlockchainApiService::new(
                 self.ledger_db.clone(),
@@ -507,7 +666,8 @@ impl<E: ConsensusEnclaveProxy, R: RaClient + Send + Sync + 'static> ConsensusSer
                 self.ledger_db.clone(),
                 self.tx_manager.clone(),
                 get_highest_scp_message_fn,
-                self.peer_manager.responder_ids(),
+                known_responder_ids_fn,
+                peer_exchange_fn,
                 self.logger.clone(),
             ));
 
@@ -555,6 +715,20 @@ impl<E: ConsensusEnclaveProxy, R: RaClient + Send + Sync + 'static> ConsensusSer
             self.logger.clone(),
         ));
 
+        // Re-propose transactions that were pending before a restart.
+        match self.tx_manager.restore_from_store() {
+            Ok(restored_hashes) => {
+                if !restored_hashes.is_empty() {
+                    if let Some(byzantine_ledger) = byzantine_ledger.as_ref() {
+                        byzantine_ledger.push_values(restored_hashes, None);
+                    }
+                }
+            }
+            Err(err) => {
+                log::error!(self.logger, "Failed restoring stored transactions: {}", err);
+            }
+        }
+
         // Handling of incoming SCP messages.
         let byzantine_ledger_1 = self.byzantine_ledger.clone();
         let peer_keepalive = self.peer_keepalive.clone();
@@ -596,6 +770,64 @@ impl<E: ConsensusEnclaveProxy, R: RaClient + Send + Sync + 'static> ConsensusSer
         Ok(())
     }
 
+    fn start_peer_discovery(&mut self) -> Result<(), ConsensusServiceError> {
+        if self.config.peer_discovery {
+            log::info!(self.logger, "Starting peer discovery.");
+            self.peer_discovery = Some(PeerDiscovery::start(
+                self.peer_exchange.clone(),
+                self.logger.clone(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Creates the hooks used by the management server's admin endpoints.
+    fn create_admin_api(
+        admission_control: &AdmissionControl,
+        tx_manager: &TxManager<E, LedgerDB>,
+        byzantine_ledger: &Arc<Mutex<Option<ByzantineLedger>>>,
+        peer_keepalive: &Arc<Mutex<PeerKeepalive>>,
+        network_reloader: &NetworkReloader<E>,
+        logger: &Logger,
+    ) -> AdminApi {
+        let tx_manager = tx_manager.clone();
+
+        let scp_byzantine_ledger = byzantine_ledger.clone();
+
+        let peer_keepalive = peer_keepalive.clone();
+
+        let drain_admission_control = admission_control.clone();
+        let drain_byzantine_ledger = byzantine_ledger.clone();
+        let drain_logger = logger.clone();
+
+        let is_drained_byzantine_ledger = byzantine_ledger.clone();
+
+        let network_reloader = network_reloader.clone();
+
+        AdminApi {
+            admission_control: admission_control.clone(),
+            get_tx_contexts_fn: Arc::new(move || tx_manager.tx_contexts()),
+            get_highest_scp_message_fn: Arc::new(move || {
+                let byzantine_ledger = scp_byzantine_ledger.lock().expect("mutex poisoned");
+                byzantine_ledger
+                    .as_ref()
+                    .and_then(|byzantine_ledger| byzantine_ledger.get_highest_scp_message())
+            }),
+            get_peer_health_fn: Arc::new(move || {
+                peer_keepalive.lock().expect("mutex poisoned").peer_health()
+            }),
+            drain_fn: Arc::new(move || {
+                drain(
+                    &drain_admission_control,
+                    &drain_byzantine_ledger,
+                    &drain_logger,
+                )
+            }),
+            is_drained_fn: Arc::new(move || is_drained(&is_drained_byzantine_ledger)),
+            reload_network_fn: Arc::new(move || network_reloader.reload()),
+        }
+    }
+
     /// Creates a function that returns true if the node is currently serving user requests.
     fn create_is_serving_user_requests_fn(&self) -> Arc<dyn Fn() -> bool + Sync + Send> {
         let byzantine_ledger = self.byzantine_ledger.clone();
@@ -617,35 +849,16 @@ impl<E: ConsensusEnclaveProxy, R: RaClient + Send + Sync + 'static> ConsensusSer
         let local_node_id = self.local_node_id.clone();
         let broadcaster = self.broadcaster.clone();
 
-        // Figure out which node IDs we are going to be relaying received transactions from.
-        // See comment below ("Broadcast to peers") for more details.
-        let relay_from_nodes: Vec<ResponderId> = self
-            .peer_manager
-            .conns()
-            .iter()
-            .filter_map(|conn| {
-                let uri = conn.uri();
-                if uri.consensus_relay_incoming_txs() {
-                    match uri.responder_id() {
-                        Ok(responder_id) => Some(responder_id),
-                        Err(_e) => {
-                            log::warn!(
-                                self.logger,
-                                "Could not get responder_id from {:?}",
-                                uri.to_string()
-                            );
-                            None
-                        }
-                    }
-                } else {
-                    None
-                }
-            })
-            .collect();
+        let peer_manager = self.peer_manager.clone();
+        let logger = self.logger.clone();
 
         Arc::new(move |tx_hash, origin_node, relayed_from| {
             let origin_node = origin_node.unwrap_or(&local_node_id);
 
+            // Figure out which node IDs we are going to be relaying received transactions from.
+            // See comment below ("Broadcast to peers") for more details.
+            let relay_from_nodes = relay_from_nodes(&peer_manager, &logger);
+
             // Broadcast to peers.
             //
             // Nodes always relay transactions sent to them by clients to all their peers. As such, in
@@ -696,3 +909,54 @@ impl<E: ConsensusEnclaveProxy, R: RaClient + Send + Sync + 'static> Drop
         let _ = self.stop();
     }
 }
+
+/// Responder IDs of the peers whose transactions we relay to our other peers.
+fn relay_from_nodes<E: ConsensusEnclaveProxy>(
+    peer_manager: &ConnectionManager<PeerConnection<E>>,
+    logger: &Logger,
+) -> Vec<ResponderId> {
+    peer_manager
+        .conns()
+        .iter()
+        .filter_map(|conn| {
+            let uri = conn.uri();
+            if uri.consensus_relay_incoming_txs() {
+                match uri.responder_id() {
+                    Ok(responder_id) => Some(responder_id),
+                    Err(_e) => {
+                        log::warn!(
+                            logger,
+                            "Could not get responder_id from {:?}",
+                            uri.to_string()
+                        );
+                        None
+                    }
+                }
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Stops client intake, and asks ByzantineLedger to stop nominating.
+fn drain(
+    admission_control: &AdmissionControl,
+    byzantine_ledger: &Arc<Mutex<Option<ByzantineLedger>>>,
+    logger: &Logger,
+) {
+    log::info!(logger, "Draining node.");
+    admission_control.pause();
+    if let Some(byzantine_ledger) = &*byzantine_ledger.lock().expect("lock poisoned") {
+        byzantine_ledger.drain();
+    }
+}
+
+fn is_drained(byzantine_ledger: &Arc<Mutex<Option<ByzantineLedger>>>) -> bool {
+    byzantine_ledger
+        .lock()
+        .expect("lock poisoned")
+        .as_ref()
+        .map(|byzantine_ledger| byzantine_ledger.is_drained())
+        .unwrap_or(false)
+}
@@ -43,6 +43,11 @@ lazy_static::lazy_static! {
     // under a set limit.
     pub static ref CUR_NUM_PENDING_VALUES: IntGauge = OP_COUNTERS.gauge("cur_num_pending_values");
 
+    // Lowest fee that still fits in a block given the current pending values. NOTE: This gauge is
+    // also used to reject add_transaction requests that pay too little when fee-priority
+    // transaction selection is enabled.
+    pub static ref CUR_PENDING_VALUES_FEE_FLOOR: IntGauge = OP_COUNTERS.gauge("cur_pending_values_fee_floor");
+
     // Current slot number.
     pub static ref CUR_SLOT_NUM: IntGauge = OP_COUNTERS.gauge("cur_slot_num");
 
@@ -94,6 +99,9 @@ lazy_static::lazy_static! {
     // Number of entries in the transactions cache.
     pub static ref TX_CACHE_NUM_ENTRIES: IntGauge = OP_COUNTERS.gauge("tx_cache_num_entries");
 
+    // Number of transactions evicted from the full transactions cache.
+    pub static ref TX_CACHE_EVICTIONS: IntCounter = OP_COUNTERS.counter("tx_cache_evictions");
+
     // Number of consensus messages dropped due to referencing an invalid previous block id.
     pub static ref SCP_MESSAGES_DROPPED_DUE_TO_INVALID_PREV_BLOCK_ID: IntCounter = OP_COUNTERS.counter("scp_messages_dropped_due_to_invalid_prev_block_id");
 
@@ -109,6 +117,24 @@ lazy_static::lazy_static! {
     // Number of times a transaction is added to the user_api_service
     pub static ref ADD_TX: IntCounter = OP_COUNTERS.counter("add_tx");
 
+    // Number of times a transaction was rejected for paying too low a fee under the current load
+    pub static ref ADD_TX_FEE_TOO_LOW: IntCounter = OP_COUNTERS.counter("add_tx_fee_too_low");
+
+    // Number of times a transaction was rejected because the client session exceeded its rate limit
+    pub static ref ADD_TX_CLIENT_RATE_LIMITED: IntCounter = OP_COUNTERS.counter("add_tx_client_rate_limited");
+
+    // Number of times a transaction was rejected because its source IP exceeded its rate limit
+    pub static ref ADD_TX_SOURCE_RATE_LIMITED: IntCounter = OP_COUNTERS.counter("add_tx_source_rate_limited");
+
+    // Number of times a transaction was rejected because client intake was paused
+    pub static ref ADD_TX_PAUSED: IntCounter = OP_COUNTERS.counter("add_tx_paused");
+
+    // Number of times a transaction was rejected because the node had too many pending values
+    pub static ref ADD_TX_OVER_CAPACITY: IntCounter = OP_COUNTERS.counter("add_tx_over_capacity");
+
+    // Number of times a transaction was rejected because the tx cache was full of better-paying txs
+    pub static ref ADD_TX_CACHE_FULL: IntCounter = OP_COUNTERS.counter("add_tx_cache_full");
+
     // Time it takes to perform the well-formed check
     pub static ref WELL_FORMED_CHECK_TIME: Histogram = OP_COUNTERS.histogram("well_formed_check_time");
 
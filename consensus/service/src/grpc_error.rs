@@ -23,6 +23,22 @@ pub enum ConsensusGrpcError {
     #[fail(display = "Over capacity")]
     OverCapacity,
 
+    /// The client session is sending transactions too quickly.
+    #[fail(display = "Client rate limited")]
+    ClientRateLimited,
+
+    /// The client's source address is sending transactions too quickly.
+    #[fail(display = "Source rate limited")]
+    SourceRateLimited,
+
+    /// The transaction cache is full of transactions that are worth more than this one.
+    #[fail(display = "Transaction cache full")]
+    TxCacheFull,
+
+    /// The transaction's fee is too low to be included given the current load.
+    #[fail(display = "Fee too low for current load, minimum fee is {}", _0)]
+    FeeTooLowForCurrentLoad(u64),
+
     /// Service is currently not serving requests.
     #[fail(display = "Temporarily not serving requests")]
     NotServing,
@@ -77,6 +93,10 @@ impl From<TxManagerError> for ConsensusGrpcError {
             TxManagerError::Enclave(err) => Self::from(err),
             TxManagerError::TransactionValidation(err) => Self::from(err),
             TxManagerError::LedgerDb(err) => Self::from(err),
+            TxManagerError::FeeTooLowForCurrentLoad(min_fee) => {
+                Self::FeeTooLowForCurrentLoad(min_fee)
+            }
+            TxManagerError::CacheFull => Self::TxCacheFull,
             _ => Self::Other(format!("tx manager error: {}", src)),
         }
     }
@@ -90,10 +110,6 @@ impl From<ConsensusGrpcError> for RpcStatus {
                 RpcStatusCode::INTERNAL,
                 Some(format!("Ledger error: {}", err)),
             ),
-            ConsensusGrpcError::OverCapacity => RpcStatus::new(
-                RpcStatusCode::UNAVAILABLE,
-                Some("Temporarily over capacity".into()),
-            ),
             ConsensusGrpcError::NotServing => RpcStatus::new(
                 RpcStatusCode::UNAVAILABLE,
                 Some("Temporarily not serving requests".into()),
@@ -131,6 +147,32 @@ impl Into<Result<ProposeTxResponse, RpcStatus>> for ConsensusGrpcError {
                 resp.set_result(ProposeTxResult::from(err));
                 Ok(resp)
             }
+            Self::FeeTooLowForCurrentLoad(min_fee) => {
+                let mut resp = ProposeTxResponse::new();
+                resp.set_result(ProposeTxResult::FeeTooLowForCurrentLoad);
+                resp.set_minimum_fee(min_fee);
+                Ok(resp)
+            }
+            Self::OverCapacity => {
+                let mut resp = ProposeTxResponse::new();
+                resp.set_result(ProposeTxResult::OverCapacity);
+                Ok(resp)
+            }
+            Self::ClientRateLimited => {
+                let mut resp = ProposeTxResponse::new();
+                resp.set_result(ProposeTxResult::ClientRateLimited);
+                Ok(resp)
+            }
+            Self::SourceRateLimited => {
+                let mut resp = ProposeTxResponse::new();
+                resp.set_result(ProposeTxResult::SourceRateLimited);
+                Ok(resp)
+            }
+            Self::TxCacheFull => {
+                let mut resp = ProposeTxResponse::new();
+                resp.set_result(ProposeTxResult::TxCacheFull);
+                Ok(resp)
+            }
             _ => Err(RpcStatus::from(self)),
         }
     }
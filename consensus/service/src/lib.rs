@@ -9,9 +9,11 @@ extern crate test;
 
 pub mod config;
 pub mod consensus_service;
+pub mod tx_cache_store;
 pub mod tx_manager;
 pub mod validators; // Public so that it can be benchmarked by the `benchmarks` crate.
 
+mod admission_control;
 mod attested_api_service;
 mod background_work_queue;
 mod blockchain_api_service;
@@ -20,7 +22,9 @@ mod client_api_service;
 mod counters;
 mod grpc_error;
 mod management;
+mod network_reloader;
 mod peer_api_service;
+mod peer_discovery;
 mod peer_keepalive;
 
 #[cfg(test)]
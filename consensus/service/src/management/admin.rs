@@ -0,0 +1,186 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Admin endpoints of the management server, for inspecting and controlling a running node.
+//!
+//! All admin endpoints require an `Authorization: Bearer <token>` header matching the configured
+//! `--admin-token`. Without a configured token they are disabled.
+
+use crate::{
+    admission_control::AdmissionControl,
+    config::{Config, NetworkConfigError},
+    peer_keepalive::PeerHealth,
+};
+use common::logger::{log, Logger};
+use consensus_enclave::WellFormedTxContext;
+use peers::ConsensusMsg;
+use rouille::{router, Request, Response};
+use serde_json::json;
+use std::sync::Arc;
+use subtle::ConstantTimeEq;
+
+/// Hooks into the running service, used by the admin endpoints.
+#[derive(Clone)]
+pub struct AdminApi {
+    /// Controls client transaction intake.
+    pub admission_control: AdmissionControl,
+
+    /// Returns the contexts of all transactions in the `TxManager` cache.
+    pub get_tx_contexts_fn: Arc<dyn Fn() -> Vec<WellFormedTxContext> + Sync + Send>,
+
+    /// Returns the highest SCP message this node has issued.
+    pub get_highest_scp_message_fn: Arc<dyn Fn() -> Option<ConsensusMsg> + Sync + Send>,
+
+    /// Returns how recently each peer was heard from.
+    pub get_peer_health_fn: Arc<dyn Fn() -> Vec<PeerHealth> + Sync + Send>,
+
+    /// Stops client intake and nomination. The node exits once the current slot is complete.
+    pub drain_fn: Arc<dyn Fn() + Sync + Send>,
+
+    /// Returns true once the node has finished draining.
+    pub is_drained_fn: Arc<dyn Fn() -> bool + Sync + Send>,
+
+    /// Re-reads and applies the network configuration file.
+    pub reload_network_fn: Arc<dyn Fn() -> Result<(), NetworkConfigError> + Sync + Send>,
+}
+
+pub fn handle_request(
+    request: &Request,
+    config: &Config,
+    admin_api: &AdminApi,
+    logger: &Logger,
+) -> Response {
+    let expected_token = match &config.admin_token {
+        Some(token) => token,
+        None => return Response::empty_404(),
+    };
+    if !is_authorized(request, expected_token) {
+        log::warn!(logger, "Unauthorized admin request");
+        return Response::text("Unauthorized").with_status_code(401);
+    }
+
+    router!(request,
+        (GET) (/admin/status) => {
+            status(admin_api)
+        },
+        (GET) (/admin/tx-cache) => {
+            let tx_contexts = (admin_api.get_tx_contexts_fn)();
+            let txs: Vec<serde_json::Value> = tx_contexts
+                .iter()
+                .map(|context| json!({
+                    "tx_hash": context.tx_hash().to_string(),
+                    "fee": context.fee(),
+                    "tombstone_block": context.tombstone_block(),
+                }))
+                .collect();
+            Response::json(&json!({
+                "num_entries": txs.len(),
+                "max_entries": config.max_tx_cache_entries,
+                "txs": txs,
+            }))
+        },
+        (GET) (/admin/scp) => {
+            match (admin_api.get_highest_scp_message_fn)() {
+                Some(consensus_msg) => Response::json(&json!({
+                    "slot_index": consensus_msg.scp_msg.slot_index,
+                    "scp_msg": consensus_msg.scp_msg.to_display(),
+                })),
+                None => Response::json(&json!(null)),
+            }
+        },
+        (GET) (/admin/peers) => {
+            let peers: Vec<serde_json::Value> = (admin_api.get_peer_health_fn)()
+                .iter()
+                .map(|peer| json!({
+                    "responder_id": peer.responder_id.to_string(),
+                    "secs_since_last_heard": peer.since_last_heard.as_secs_f64(),
+                    "is_silent": peer.is_silent,
+                    "score": peer.score,
+                }))
+                .collect();
+            Response::json(&peers)
+        },
+        (POST) (/admin/pause-client-txs) => {
+            log::info!(logger, "Pausing client transaction intake");
+            admin_api.admission_control.pause();
+            status(admin_api)
+        },
+        (POST) (/admin/resume-client-txs) => {
+            log::info!(logger, "Resuming client transaction intake");
+            admin_api.admission_control.resume();
+            status(admin_api)
+        },
+        (POST) (/admin/drain) => {
+            log::info!(logger, "Draining node");
+            (admin_api.drain_fn)();
+            status(admin_api)
+        },
+        (POST) (/admin/reload-network) => {
+            log::info!(logger, "Reloading network configuration");
+            match (admin_api.reload_network_fn)() {
+                Ok(()) => status(admin_api),
+                Err(err) => {
+                    log::error!(logger, "Failed reloading network configuration: {}", err);
+                    Response::text(err.to_string()).with_status_code(400)
+                }
+            }
+        },
+        _ => Response::empty_404()
+    )
+}
+
+fn status(admin_api: &AdminApi) -> Response {
+    Response::json(&json!({
+        "client_txs_paused": admin_api.admission_control.is_paused(),
+        "drained": (admin_api.is_drained_fn)(),
+    }))
+}
+
+/// Checks the request's bearer token, in constant time.
+fn is_authorized(request: &Request, expected_token: &str) -> bool {
+    request
+        .header("Authorization")
+        .and_then(|value| {
+            if value.starts_with("Bearer ") {
+                Some(&value["Bearer ".len()..])
+            } else {
+                None
+            }
+        })
+        .map(|token| bool::from(token.as_bytes().ct_eq(expected_token.as_bytes())))
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request_with_authorization(value: &str) -> Request {
+        Request::fake_http(
+            "GET",
+            "/admin/status",
+            vec![("Authorization".to_string(), value.to_string())],
+            vec![],
+        )
+    }
+
+    #[test]
+    // Only the exact bearer token is accepted.
+    fn test_is_authorized() {
+        assert!(is_authorized(
+            &request_with_authorization("Bearer s3cret"),
+            "s3cret"
+        ));
+        assert!(!is_authorized(
+            &request_with_authorization("Bearer s3cre"),
+            "s3cret"
+        ));
+        assert!(!is_authorized(
+            &request_with_authorization("s3cret"),
+            "s3cret"
+        ));
+        assert!(!is_authorized(
+            &Request::fake_http("GET", "/admin/status", vec![], vec![]),
+            "s3cret"
+        ));
+    }
+}
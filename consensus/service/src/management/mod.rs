@@ -1,5 +1,9 @@
 // Copyright (c) 2018-2020 MobileCoin Inc.
 
+mod admin;
+
+pub use self::admin::AdminApi;
+
 use crate::config::Config;
 use build_info;
 use common::logger::{log, o, Logger};
@@ -27,23 +31,26 @@ pub struct ManagementServer {
     stop_requested: Arc<AtomicBool>,
     thread_handle: Option<thread::JoinHandle<()>>,
     config: Config,
+    admin_api: AdminApi,
     logger: Logger,
 }
 
 impl ManagementServer {
-    pub fn new(config: Config, logger: Logger) -> Self {
+    pub fn new(config: Config, admin_api: AdminApi, logger: Logger) -> Self {
         let stop_requested = Arc::new(AtomicBool::new(false));
 
         Self {
             stop_requested,
             thread_handle: None,
             config,
+            admin_api,
             logger,
         }
     }
 
     pub fn start(&mut self) {
         let server_config = self.config.clone();
+        let server_admin_api = self.admin_api.clone();
         let server_logger = self.logger.clone();
         let listen_addr =
             self.config.management_listen_addr.clone().expect(
@@ -71,12 +78,13 @@ impl ManagementServer {
             let req_method = request.method().to_string();
             let req_url = request.raw_url().to_string();
             let handler_config = server_config.clone();
+            let handler_admin_api = server_admin_api.clone();
             let handler_logger =
                 server_logger.new(o!("req_method" => req_method, "req_url" => req_url));
 
             rouille::log_custom(request, log_ok, log_err, || {
                 OP_COUNTER.inc("requests");
-                Self::handle_request(request, handler_config, handler_logger)
+                Self::handle_request(request, handler_config, handler_admin_api, handler_logger)
             })
         })
         .expect("failed creating management web server");
@@ -112,7 +120,16 @@ impl ManagementServer {
         }
     }
 
-    fn handle_request(request: &Request, config: Config, logger: Logger) -> Response {
+    fn handle_request(
+        request: &Request,
+        config: Config,
+        admin_api: AdminApi,
+        logger: Logger,
+    ) -> Response {
+        if request.url().starts_with("/admin/") {
+            return admin::handle_request(request, &config, &admin_api, &logger);
+        }
+
         let reg = Handlebars::new();
 
         router!(request,
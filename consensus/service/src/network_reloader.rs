@@ -0,0 +1,191 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Applies changes to the network configuration file to a running node.
+//!
+//! The peer set is updated right away, while the new quorum set takes effect from the next slot.
+//! Peers found through peer discovery are kept across reloads.
+
+use crate::{
+    byzantine_ledger::ByzantineLedger,
+    config::{Config, NetworkConfigError},
+    peer_keepalive::PeerKeepalive,
+};
+use common::{
+    logger::{log, Logger},
+    NodeID,
+};
+use consensus_enclave::ConsensusEnclaveProxy;
+use grpcio;
+use mcconnection::{Connection, ConnectionManager};
+use mcuri::{ConnectionUri, ConsensusPeerUri as PeerUri};
+use peers::{PeerConnection, ThreadedBroadcaster};
+use std::sync::{Arc, Mutex};
+
+/// Maximum number of peers found through peer discovery that we connect to.
+const MAX_DISCOVERED_PEERS: usize = 64;
+
+#[derive(Clone)]
+pub struct NetworkReloader<E: ConsensusEnclaveProxy> {
+    config: Config,
+    enclave: E,
+    local_node_id: NodeID,
+    env: Arc<grpcio::Environment>,
+    peer_manager: ConnectionManager<PeerConnection<E>>,
+    broadcaster: Arc<Mutex<ThreadedBroadcaster>>,
+    peer_keepalive: Arc<Mutex<PeerKeepalive>>,
+    byzantine_ledger: Arc<Mutex<Option<ByzantineLedger>>>,
+
+    // Peers listed in the network configuration.
+    configured_peers: Arc<Mutex<Vec<PeerUri>>>,
+
+    // Peers learned about from other peers.
+    discovered_peers: Arc<Mutex<Vec<PeerUri>>>,
+
+    // Held while reloading, so that concurrent reloads are applied one after the other.
+    reload_lock: Arc<Mutex<()>>,
+
+    logger: Logger,
+}
+
+impl<E: ConsensusEnclaveProxy> NetworkReloader<E> {
+    pub fn new(
+        config: Config,
+        enclave: E,
+        local_node_id: NodeID,
+        env: Arc<grpcio::Environment>,
+        peer_manager: ConnectionManager<PeerConnection<E>>,
+        broadcaster: Arc<Mutex<ThreadedBroadcaster>>,
+        peer_keepalive: Arc<Mutex<PeerKeepalive>>,
+        byzantine_ledger: Arc<Mutex<Option<ByzantineLedger>>>,
+        logger: Logger,
+    ) -> Self {
+        let configured_peers = peer_manager.conns().iter().map(|conn| conn.uri()).collect();
+        Self {
+            config,
+            enclave,
+            local_node_id,
+            env,
+            peer_manager,
+            broadcaster,
+            peer_keepalive,
+            byzantine_ledger,
+            configured_peers: Arc::new(Mutex::new(configured_peers)),
+            discovered_peers: Arc::new(Mutex::new(Vec::new())),
+            reload_lock: Arc::new(Mutex::new(())),
+            logger,
+        }
+    }
+
+    /// Re-read the network configuration file and apply it. Nothing is changed if the new
+    /// configuration is invalid.
+    pub fn reload(&self) -> Result<(), NetworkConfigError> {
+        let _guard = self.reload_lock.lock().expect("mutex poisoned");
+
+        let network = self.config.load_network()?;
+        let quorum_set = network.try_quorum_set()?;
+
+        log::info!(
+            self.logger,
+            "Reloading network configuration from {:?}",
+            self.config.network_path
+        );
+
+        *self.configured_peers.lock().expect("mutex poisoned") = network.broadcast_peers();
+        self.apply_peers();
+
+        // Quorum set and transaction sources, applied by ByzantineLedger at the next slot.
+        if let Some(byzantine_ledger) = &*self.byzantine_ledger.lock().expect("lock poisoned") {
+            byzantine_ledger.update_network(quorum_set, network.tx_source_urls);
+        }
+
+        Ok(())
+    }
+
+    /// Connect to peers found through peer discovery. Peers we already know of are ignored, and so
+    /// are new peers once `MAX_DISCOVERED_PEERS` have been discovered.
+    pub fn add_discovered_peers(&self, peer_uris: Vec<PeerUri>) {
+        let _guard = self.reload_lock.lock().expect("mutex poisoned");
+
+        {
+            let mut discovered_peers = self.discovered_peers.lock().expect("mutex poisoned");
+            let known_responder_ids = self.peer_manager.responder_ids();
+            for peer_uri in peer_uris {
+                let responder_id = match peer_uri.responder_id() {
+                    Ok(responder_id) => responder_id,
+                    Err(_) => continue,
+                };
+                if known_responder_ids.contains(&responder_id)
+                    || discovered_peers
+                        .iter()
+                        .any(|uri| uri.responder_id().ok() == Some(responder_id.clone()))
+                {
+                    continue;
+                }
+                if discovered_peers.len() >= MAX_DISCOVERED_PEERS {
+                    log::warn!(
+                        self.logger,
+                        "Ignoring discovered peer {}: already discovered {} peers",
+                        peer_uri,
+                        discovered_peers.len()
+                    );
+                    continue;
+                }
+                log::info!(self.logger, "Discovered peer {}", peer_uri);
+                discovered_peers.push(peer_uri);
+            }
+        }
+
+        self.apply_peers();
+    }
+
+    // Connect to the configured and discovered peers. Connections to peers whose URI did not
+    // change are kept.
+    fn apply_peers(&self) {
+        let configured_peers = self
+            .configured_peers
+            .lock()
+            .expect("mutex poisoned")
+            .clone();
+        let discovered_peers = self
+            .discovered_peers
+            .lock()
+            .expect("mutex poisoned")
+            .clone();
+
+        // The configuration takes precedence over what other peers told us.
+        let configured_responder_ids: Vec<_> = configured_peers
+            .iter()
+            .filter_map(|uri| uri.responder_id().ok())
+            .collect();
+        let peer_uris = configured_peers
+            .into_iter()
+            .chain(discovered_peers.into_iter().filter(|uri| {
+                uri.responder_id()
+                    .map(|responder_id| !configured_responder_ids.contains(&responder_id))
+                    .unwrap_or(false)
+            }));
+
+        let peers: Vec<PeerConnection<E>> = peer_uris
+            .map(|peer_uri| {
+                PeerConnection::new(
+                    self.enclave.clone(),
+                    self.local_node_id.clone(),
+                    peer_uri,
+                    self.env.clone(),
+                    self.logger.clone(),
+                )
+            })
+            .collect();
+        self.peer_manager.update(peers, self.logger.clone());
+
+        self.broadcaster
+            .lock()
+            .expect("mutex poisoned")
+            .update_peers(&self.peer_manager);
+
+        self.peer_keepalive
+            .lock()
+            .expect("mutex poisoned")
+            .update_peers(&self.peer_manager.responder_ids());
+    }
+}
@@ -23,11 +23,14 @@ use mcserial::deserialize;
 use metrics::SVC_COUNTERS;
 use mobilecoin_api::{
     consensus_common::ProposeTxResponse,
-    consensus_peer::{ConsensusMsg as GrpcConsensusMsg, FetchLatestMsgResponse, FetchTxsRequest},
+    consensus_peer::{
+        ConsensusMsg as GrpcConsensusMsg, FetchLatestMsgResponse, FetchTxsRequest,
+        PeerExchangeMsg as GrpcPeerExchangeMsg,
+    },
     consensus_peer_grpc::ConsensusPeerApi,
     empty::Empty,
 };
-use peers::TxProposeAAD;
+use peers::{PeerExchangeMsg, PeerExchangeMsgError, TxProposeAAD};
 use std::{
     convert::{TryFrom, TryInto},
     sync::Arc,
@@ -38,6 +41,14 @@ use transaction::tx::TxHash;
 // implement the `fetch_latest_msg` RPC call.
 type FetchLatestMsgFn = Arc<dyn Fn() -> Option<peers::ConsensusMsg> + Sync + Send>;
 
+// Callback method for returning the responder IDs of the currently configured peers.
+type KnownResponderIdsFn = Arc<dyn Fn() -> Vec<ResponderId> + Sync + Send>;
+
+// Callback method for handling the peer list sent by a remote node and returning our own, used to
+// implement the `exchange_peers` RPC call.
+type PeerExchangeFn =
+    Arc<dyn Fn(PeerExchangeMsg) -> Result<PeerExchangeMsg, PeerExchangeMsgError> + Sync + Send>;
+
 #[derive(Clone)]
 pub struct PeerApiService<E: ConsensusEnclaveProxy, L: Ledger> {
     /// Enclave instance.
@@ -58,11 +69,14 @@ pub struct PeerApiService<E: ConsensusEnclaveProxy, L: Ledger> {
     /// Callback function for getting the latest SCP statement the local node has issued.
     fetch_latest_msg_fn: FetchLatestMsgFn,
 
-    /// List of recognized responder IDs to accept messages from.
+    /// Returns the list of recognized responder IDs to accept messages from.
     /// We only want to accept messages from peers we can initiate outgoing requests to. That is
     /// necessary for resolving TxHashes into Txs. If we received a consensus message from a peer
     /// not on this list, we won't be able to reach out to it to ask for the transaction contents.
-    known_responder_ids: Vec<ResponderId>,
+    known_responder_ids_fn: KnownResponderIdsFn,
+
+    /// Callback function for exchanging peer lists with a remote node.
+    peer_exchange_fn: PeerExchangeFn,
 
     /// Logger.
     logger: Logger,
@@ -76,7 +90,8 @@ impl<E: ConsensusEnclaveProxy, L: Ledger> PeerApiService<E, L> {
         ledger: L,
         tx_manager: TxManager<E, L>,
         fetch_latest_msg_fn: FetchLatestMsgFn,
-        known_responder_ids: Vec<ResponderId>,
+        known_responder_ids_fn: KnownResponderIdsFn,
+        peer_exchange_fn: PeerExchangeFn,
         logger: Logger,
     ) -> Self {
         Self {
@@ -86,7 +101,8 @@ impl<E: ConsensusEnclaveProxy, L: Ledger> PeerApiService<E, L> {
             ledger,
             tx_manager,
             fetch_latest_msg_fn,
-            known_responder_ids,
+            known_responder_ids_fn,
+            peer_exchange_fn,
             logger,
         }
     }
@@ -252,7 +268,7 @@ impl<E: ConsensusEnclaveProxy, L: Ledger> ConsensusPeerApi for PeerApiService<E,
             };
 
             // See if we recognize this peer.
-            if !self.known_responder_ids.contains(&from_responder_id) {
+            if !(self.known_responder_ids_fn)().contains(&from_responder_id) {
                 send_result(
                     ctx,
                     sink,
@@ -347,4 +363,42 @@ impl<E: ConsensusEnclaveProxy, L: Ledger> ConsensusPeerApi for PeerApiService<E,
             )
         });
     }
+
+    fn exchange_peers(
+        &mut self,
+        ctx: RpcContext,
+        request: GrpcPeerExchangeMsg,
+        sink: UnarySink<GrpcPeerExchangeMsg>,
+    ) {
+        let _timer = SVC_COUNTERS.req(&ctx);
+        common::logger::scoped_global_logger(&rpc_logger(&ctx, &self.logger), |logger| {
+            let remote_msg: PeerExchangeMsg = match deserialize(request.get_payload()) {
+                Ok(val) => val,
+                Err(err) => {
+                    send_result(
+                        ctx,
+                        sink,
+                        Err(rpc_invalid_arg_error(
+                            "exchange_peers",
+                            format!("Message deserialize error: {}", err),
+                            &logger,
+                        )),
+                        &logger,
+                    );
+                    return;
+                }
+            };
+
+            let result = (self.peer_exchange_fn)(remote_msg)
+                .map_err(|err| rpc_invalid_arg_error("exchange_peers", err, &logger))
+                .map(|local_msg| {
+                    let mut response = GrpcPeerExchangeMsg::new();
+                    response.set_payload(
+                        mcserial::serialize(&local_msg).expect("failed serializing peer list"),
+                    );
+                    response
+                });
+            send_result(ctx, sink, result, &logger);
+        });
+    }
 }
@@ -0,0 +1,374 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Peer discovery. Validators periodically send each other signed lists of the peers they know of,
+//! so that a new validator only needs to be added to the network configuration of one of its
+//! peers in order to be found by the rest of the network.
+
+use common::{
+    logger::{log, Logger},
+    NodeID,
+};
+use keys::Ed25519Pair;
+use mcconnection::{Connection, ConnectionManager};
+use mcuri::{ConnectionUri, ConsensusPeerUri as PeerUri};
+use peers::{
+    ConsensusConnection, PeerExchangeMsg, PeerExchangeMsgError, RetryableConsensusConnection,
+};
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Time between exchanging peer lists with each of our peers.
+const PEER_EXCHANGE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Callback for connecting to newly discovered peers.
+pub type AddPeersFn = Arc<dyn Fn(Vec<PeerUri>) + Sync + Send>;
+
+/// Builds the peer lists we share, and learns from the lists shared with us.
+pub struct PeerExchange<CC: ConsensusConnection> {
+    conn_manager: ConnectionManager<CC>,
+    local_node_id: NodeID,
+    msg_signer_key: Arc<Ed25519Pair>,
+
+    /// Our own URI, included in the lists we share so that peers can connect back to us.
+    advertise_uri: Option<PeerUri>,
+
+    /// Connects to peers we learn about. Lists shared with us are ignored when this is not set.
+    add_peers_fn: Option<AddPeersFn>,
+
+    logger: Logger,
+}
+
+impl<CC: ConsensusConnection> Clone for PeerExchange<CC> {
+    fn clone(&self) -> Self {
+        Self {
+            conn_manager: self.conn_manager.clone(),
+            local_node_id: self.local_node_id.clone(),
+            msg_signer_key: self.msg_signer_key.clone(),
+            advertise_uri: self.advertise_uri.clone(),
+            add_peers_fn: self.add_peers_fn.clone(),
+            logger: self.logger.clone(),
+        }
+    }
+}
+
+impl<CC: ConsensusConnection> PeerExchange<CC> {
+    pub fn new(
+        conn_manager: ConnectionManager<CC>,
+        local_node_id: NodeID,
+        msg_signer_key: Arc<Ed25519Pair>,
+        advertise_uri: Option<PeerUri>,
+        add_peers_fn: Option<AddPeersFn>,
+        logger: Logger,
+    ) -> Self {
+        Self {
+            conn_manager,
+            local_node_id,
+            msg_signer_key,
+            advertise_uri,
+            add_peers_fn,
+            logger,
+        }
+    }
+
+    /// The signed list of peers we know of.
+    pub fn local_msg(&self) -> Result<PeerExchangeMsg, PeerExchangeMsgError> {
+        let known_peers = self
+            .advertise_uri
+            .iter()
+            .cloned()
+            .chain(self.conn_manager.conns().iter().map(|conn| conn.uri()))
+            .collect();
+        PeerExchangeMsg::new(
+            self.local_node_id.clone(),
+            known_peers,
+            &self.msg_signer_key,
+        )
+    }
+
+    /// Handle a list of peers sent to us, and reply with our own.
+    pub fn handle_msg(
+        &self,
+        msg: PeerExchangeMsg,
+    ) -> Result<PeerExchangeMsg, PeerExchangeMsgError> {
+        msg.verify_signature()?;
+        self.learn(&msg);
+        self.local_msg()
+    }
+
+    /// Exchange peer lists with each of our peers.
+    pub fn exchange_with_peers(&self) {
+        let local_msg = match self.local_msg() {
+            Ok(msg) => msg,
+            Err(err) => {
+                log::error!(self.logger, "Failed creating peer exchange msg: {:?}", err);
+                return;
+            }
+        };
+
+        for conn in self.conn_manager.conns() {
+            let remote_msg = match conn.exchange_peers(&local_msg, std::iter::empty()) {
+                Ok(msg) => msg,
+                Err(err) => {
+                    log::debug!(
+                        self.logger,
+                        "Failed exchanging peers with {}: {:?}",
+                        conn,
+                        err
+                    );
+                    continue;
+                }
+            };
+
+            // The reply must come from the node we expect to be talking to.
+            if conn.uri().node_id().ok().as_ref() != Some(&remote_msg.sender_id) {
+                log::warn!(
+                    self.logger,
+                    "Ignoring peer list from {}: unexpected sender {}",
+                    conn,
+                    remote_msg.sender_id
+                );
+                continue;
+            }
+            if let Err(err) = remote_msg.verify_signature() {
+                log::warn!(
+                    self.logger,
+                    "Ignoring peer list from {}: signature verification failed: {:?}",
+                    conn,
+                    err
+                );
+                continue;
+            }
+
+            self.learn(&remote_msg);
+        }
+    }
+
+    // Connects to the peers in a verified message that we do not know of yet. Only peers we are
+    // already connected to (configured, or discovered through them) are trusted to tell us about
+    // other nodes: anyone can generate a key and sign a message.
+    fn learn(&self, msg: &PeerExchangeMsg) {
+        let add_peers_fn = match &self.add_peers_fn {
+            Some(add_peers_fn) => add_peers_fn,
+            None => return,
+        };
+
+        let sender_is_peer = self
+            .conn_manager
+            .conn(&msg.sender_id.responder_id)
+            .and_then(|conn| conn.uri().node_id().ok())
+            .map(|node_id| node_id == msg.sender_id)
+            .unwrap_or(false);
+        if !sender_is_peer {
+            log::debug!(
+                self.logger,
+                "Ignoring peer list from {}: not one of our peers",
+                msg.sender_id
+            );
+            return;
+        }
+
+        let known_responder_ids = self.conn_manager.responder_ids();
+        let new_peers: Vec<PeerUri> = msg
+            .known_peers
+            .iter()
+            .filter(|uri| match uri.node_id() {
+                Ok(node_id) => {
+                    node_id != self.local_node_id
+                        && !known_responder_ids.contains(&node_id.responder_id)
+                }
+                Err(_) => false,
+            })
+            .cloned()
+            .collect();
+
+        if !new_peers.is_empty() {
+            log::info!(
+                self.logger,
+                "Learned about {} new peers from {}",
+                new_peers.len(),
+                msg.sender_id
+            );
+            add_peers_fn(new_peers);
+        }
+    }
+}
+
+/// Periodically exchanges peer lists with our peers.
+pub struct PeerDiscovery {
+    join_handle: Option<thread::JoinHandle<()>>,
+    stop_requested: Arc<AtomicBool>,
+}
+
+impl PeerDiscovery {
+    pub fn start<CC: ConsensusConnection + 'static>(
+        peer_exchange: PeerExchange<CC>,
+        logger: Logger,
+    ) -> Self {
+        let stop_requested = Arc::new(AtomicBool::new(false));
+        let thread_stop_requested = stop_requested.clone();
+        let join_handle = Some(
+            thread::Builder::new()
+                .name("PeerDiscovery".into())
+                .spawn(move || {
+                    Self::thread_entrypoint(peer_exchange, thread_stop_requested, logger)
+                })
+                .expect("Failed spawning PeerDiscovery thread"),
+        );
+
+        Self {
+            join_handle,
+            stop_requested,
+        }
+    }
+
+    pub fn stop(&mut self) {
+        self.stop_requested.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.join_handle.take() {
+            thread.join().expect("PeerDiscovery thread join failed");
+        }
+    }
+
+    fn thread_entrypoint<CC: ConsensusConnection + 'static>(
+        peer_exchange: PeerExchange<CC>,
+        stop_requested: Arc<AtomicBool>,
+        logger: Logger,
+    ) {
+        log::debug!(logger, "PeerDiscovery thread has started.");
+
+        let mut last_exchange_at: Option<Instant> = None;
+
+        loop {
+            if stop_requested.load(Ordering::SeqCst) {
+                log::debug!(logger, "PeerDiscovery stop requested.");
+                break;
+            }
+
+            let now = Instant::now();
+            if last_exchange_at
+                .map(|last| now.saturating_duration_since(last) >= PEER_EXCHANGE_INTERVAL)
+                .unwrap_or(true)
+            {
+                peer_exchange.exchange_with_peers();
+                last_exchange_at = Some(now);
+            }
+
+            thread::sleep(Duration::from_secs(1));
+        }
+    }
+}
+
+impl Drop for PeerDiscovery {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::logger::test_with_logger;
+    use ledger_db::test_utils::get_mock_ledger;
+    use peers_tests::{test_node_id_and_signer, test_peer_uri, MockPeerConnection};
+    use std::sync::Mutex;
+
+    fn test_peer_exchange(
+        logger: Logger,
+    ) -> (PeerExchange<MockPeerConnection>, Arc<Mutex<Vec<PeerUri>>>) {
+        let (local_node_id, local_signer_key) = test_node_id_and_signer(1);
+        let ledger = get_mock_ledger(1);
+        let peer2 = MockPeerConnection::new(test_peer_uri(2), local_node_id.clone(), ledger, 0);
+        let conn_manager = ConnectionManager::new(vec![peer2], logger.clone());
+
+        let added = Arc::new(Mutex::new(Vec::new()));
+        let added_clone = added.clone();
+        let add_peers_fn: AddPeersFn = Arc::new(move |uris| {
+            added_clone.lock().unwrap().extend(uris);
+        });
+
+        let peer_exchange = PeerExchange::new(
+            conn_manager,
+            local_node_id,
+            Arc::new(local_signer_key),
+            None,
+            Some(add_peers_fn),
+            logger,
+        );
+        (peer_exchange, added)
+    }
+
+    #[test_with_logger]
+    // A connected peer may tell us about other peers.
+    fn test_learn_from_peer(logger: Logger) {
+        let (peer_exchange, added) = test_peer_exchange(logger);
+
+        let (node2_id, node2_signer) = test_node_id_and_signer(2);
+        let msg = PeerExchangeMsg::new(
+            node2_id,
+            vec![test_peer_uri(1), test_peer_uri(2), test_peer_uri(3)],
+            &node2_signer,
+        )
+        .unwrap();
+
+        let reply = peer_exchange.handle_msg(msg).unwrap();
+        assert!(reply.verify_signature().is_ok());
+        assert_eq!(reply.known_peers, vec![test_peer_uri(2)]);
+
+        // Only node 3 is new: node 1 is us and node 2 is already connected.
+        assert_eq!(*added.lock().unwrap(), vec![test_peer_uri(3)]);
+    }
+
+    #[test_with_logger]
+    // A node we are not connected to may not tell us about any node, not even itself, but still
+    // gets our list.
+    fn test_ignore_unknown_node(logger: Logger) {
+        let (peer_exchange, added) = test_peer_exchange(logger);
+
+        let (node4_id, node4_signer) = test_node_id_and_signer(4);
+        let msg = PeerExchangeMsg::new(
+            node4_id,
+            vec![test_peer_uri(3), test_peer_uri(4)],
+            &node4_signer,
+        )
+        .unwrap();
+
+        let reply = peer_exchange.handle_msg(msg).unwrap();
+        assert_eq!(reply.known_peers, vec![test_peer_uri(2)]);
+        assert!(added.lock().unwrap().is_empty());
+    }
+
+    #[test_with_logger]
+    // A node cannot pass as one of our peers by using the peer's responder id with its own key.
+    fn test_ignore_impersonated_peer(logger: Logger) {
+        let (peer_exchange, added) = test_peer_exchange(logger);
+
+        let (node2_id, _) = test_node_id_and_signer(2);
+        let (node5_id, node5_signer) = test_node_id_and_signer(5);
+        let sender_id = NodeID {
+            responder_id: node2_id.responder_id,
+            public_key: node5_id.public_key,
+        };
+        let msg = PeerExchangeMsg::new(sender_id, vec![test_peer_uri(3)], &node5_signer).unwrap();
+
+        peer_exchange.handle_msg(msg).unwrap();
+        assert!(added.lock().unwrap().is_empty());
+    }
+
+    #[test_with_logger]
+    // Messages with an invalid signature are rejected.
+    fn test_reject_invalid_signature(logger: Logger) {
+        let (peer_exchange, added) = test_peer_exchange(logger);
+
+        let (node4_id, _) = test_node_id_and_signer(4);
+        let (_, other_signer) = test_node_id_and_signer(5);
+        let msg = PeerExchangeMsg::new(node4_id, vec![test_peer_uri(4)], &other_signer).unwrap();
+
+        assert!(peer_exchange.handle_msg(msg).is_err());
+        assert!(added.lock().unwrap().is_empty());
+    }
+}
@@ -12,7 +12,7 @@ use common::{
     HashMap, ResponderId,
 };
 use mcconnection::ConnectionManager;
-use peers::{ConsensusConnection, RetryableConsensusConnection};
+use peers::{ConsensusConnection, PeerHealthScores, RetryableConsensusConnection};
 use retry::Error as RetryError;
 use std::{
     convert::TryInto,
@@ -30,16 +30,33 @@ const START_KEEPALIVE_CHECKS_AFTER: Duration = Duration::from_secs(30);
 /// Time between keepalive checks for silent nodes.
 const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(10);
 
+/// How recently a peer was heard from.
+#[derive(Clone, Debug)]
+pub struct PeerHealth {
+    pub responder_id: ResponderId,
+
+    /// Time since the peer last sent us a consensus message or answered a keepalive.
+    pub since_last_heard: Duration,
+
+    /// Whether the peer has been quiet long enough to be pinged.
+    pub is_silent: bool,
+
+    /// Health score, from 0.0 (recent requests to the peer failed) to 1.0.
+    pub score: f64,
+}
+
 pub struct PeerKeepalive {
     join_handle: Option<thread::JoinHandle<()>>,
     stop_requested: Arc<AtomicBool>,
     responder_id_to_last_heard: Arc<Mutex<HashMap<ResponderId, Instant>>>,
+    health_scores: PeerHealthScores,
 }
 
 impl PeerKeepalive {
     pub fn start<CC: ConsensusConnection + 'static>(
         conn_manager: ConnectionManager<CC>,
         incoming_consensus_msgs_sender: BackgroundWorkQueueSenderFn<IncomingConsensusMsg>,
+        health_scores: PeerHealthScores,
         logger: Logger,
     ) -> Self {
         // Start by asssuming we heard from all of our peers.
@@ -55,6 +72,7 @@ impl PeerKeepalive {
         let stop_requested = Arc::new(AtomicBool::new(false));
         let thread_stop_requested = stop_requested.clone();
         let thread_responder_id_to_last_heard = responder_id_to_last_heard.clone();
+        let thread_health_scores = health_scores.clone();
         let join_handle = Some(
             thread::Builder::new()
                 .name("PeerKeepAlive".into())
@@ -64,6 +82,7 @@ impl PeerKeepalive {
                         thread_stop_requested,
                         incoming_consensus_msgs_sender,
                         thread_responder_id_to_last_heard,
+                        thread_health_scores,
                         logger,
                     )
                 })
@@ -74,6 +93,7 @@ impl PeerKeepalive {
             join_handle,
             stop_requested,
             responder_id_to_last_heard,
+            health_scores,
         }
     }
 
@@ -92,11 +112,51 @@ impl PeerKeepalive {
         responder_id_to_last_heard.insert(responder_id, Instant::now());
     }
 
+    /// Replace the set of peers being tracked. Newly added peers are considered to have just been
+    /// heard from.
+    pub fn update_peers(&self, responder_ids: &[ResponderId]) {
+        let mut responder_id_to_last_heard = self
+            .responder_id_to_last_heard
+            .lock()
+            .expect("mutex poisoned");
+        responder_id_to_last_heard.retain(|responder_id, _| responder_ids.contains(responder_id));
+        let now = Instant::now();
+        for responder_id in responder_ids {
+            responder_id_to_last_heard
+                .entry(responder_id.clone())
+                .or_insert(now);
+        }
+    }
+
+    /// Health of every peer, sorted by responder id.
+    pub fn peer_health(&self) -> Vec<PeerHealth> {
+        let responder_id_to_last_heard = self
+            .responder_id_to_last_heard
+            .lock()
+            .expect("mutex poisoned");
+        let now = Instant::now();
+        let mut peer_health: Vec<PeerHealth> = responder_id_to_last_heard
+            .iter()
+            .map(|(responder_id, last_heard)| {
+                let since_last_heard = now.saturating_duration_since(*last_heard);
+                PeerHealth {
+                    responder_id: responder_id.clone(),
+                    since_last_heard,
+                    is_silent: since_last_heard > START_KEEPALIVE_CHECKS_AFTER,
+                    score: self.health_scores.score(responder_id),
+                }
+            })
+            .collect();
+        peer_health.sort_by(|a, b| a.responder_id.cmp(&b.responder_id));
+        peer_health
+    }
+
     fn thread_entrypoint<CC: ConsensusConnection>(
         conn_manager: ConnectionManager<CC>,
         stop_requested: Arc<AtomicBool>,
         incoming_consensus_msgs_sender: BackgroundWorkQueueSenderFn<IncomingConsensusMsg>,
         responder_id_to_last_heard: Arc<Mutex<HashMap<ResponderId, Instant>>>,
+        health_scores: PeerHealthScores,
         logger: Logger,
     ) {
         log::debug!(logger, "PeerKeepAlive thread has started.");
@@ -144,6 +204,8 @@ impl PeerKeepalive {
                         .and_then(|conn| conn.fetch_latest_msg(std::iter::empty()))
                     {
                         Ok(None) => {
+                            health_scores.record_success(&responder_id);
+
                             let mut responder_id_to_last_heard =
                                 responder_id_to_last_heard.lock().expect("mutex poisoned");
                             responder_id_to_last_heard.insert(responder_id.clone(), Instant::now());
@@ -155,6 +217,8 @@ impl PeerKeepalive {
                             );
                         }
                         Ok(Some(unverified_consensus_msg)) => {
+                            health_scores.record_success(&responder_id);
+
                             // Validate message signature
                             // FIXME: Additional verification for quorum set members that public key matches expected
                             match unverified_consensus_msg.clone().try_into() {
@@ -185,6 +249,8 @@ impl PeerKeepalive {
                             };
                         }
                         Err(err) => {
+                            health_scores.record_failure(&responder_id);
+
                             log::info!(
                                 logger,
                                 "failed checking with silent node {}: {:?}",
@@ -0,0 +1,154 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! On-disk storage for the transactions held by `TxManager`, allowing pending transactions to
+//! survive a restart of the node.
+//!
+//! Only enclave-sealed transactions are stored, so the contents of the transactions are never
+//! visible outside of the enclave.
+
+use consensus_enclave::SealedWellFormedTx;
+use failure::Fail;
+use lmdb::{Cursor, Database, DatabaseFlags, Environment, Transaction, WriteFlags};
+use std::{convert::TryFrom, fs, path::Path, sync::Arc};
+use transaction::tx::TxHash;
+
+/// Max size of the LMDB file.
+const MAX_LMDB_FILE_SIZE: usize = 1_073_741_824; // 1 GB
+
+/// LMDB database name.
+pub const SEALED_TXS_DB_NAME: &str = "tx_cache_store:sealed_txs";
+
+#[derive(Clone, Debug, Fail)]
+pub enum TxCacheStoreError {
+    #[fail(display = "LMDB error: {}", _0)]
+    Lmdb(lmdb::Error),
+
+    #[fail(display = "IO error: {}", _0)]
+    IO(String),
+
+    #[fail(display = "Invalid tx hash stored")]
+    InvalidTxHash,
+}
+
+impl From<lmdb::Error> for TxCacheStoreError {
+    fn from(src: lmdb::Error) -> Self {
+        Self::Lmdb(src)
+    }
+}
+
+impl From<std::io::Error> for TxCacheStoreError {
+    fn from(src: std::io::Error) -> Self {
+        Self::IO(src.to_string())
+    }
+}
+
+/// Sealed transactions, by hash.
+#[derive(Clone)]
+pub struct TxCacheStore {
+    env: Arc<Environment>,
+
+    /// `tx hash -> SealedWellFormedTx`
+    sealed_txs: Database,
+}
+
+impl TxCacheStore {
+    /// Opens the store at `path`, creating it if it does not exist.
+    pub fn open(path: &Path) -> Result<Self, TxCacheStoreError> {
+        fs::create_dir_all(path)?;
+
+        let env = Environment::new()
+            .set_max_dbs(1)
+            .set_map_size(MAX_LMDB_FILE_SIZE)
+            .open(path)?;
+        let sealed_txs = env.create_db(Some(SEALED_TXS_DB_NAME), DatabaseFlags::empty())?;
+
+        Ok(Self {
+            env: Arc::new(env),
+            sealed_txs,
+        })
+    }
+
+    /// Stores sealed transactions and removes others, in a single write transaction. Hashes to
+    /// remove that are not in the store are ignored.
+    pub fn update<'a>(
+        &self,
+        inserted: impl IntoIterator<Item = (&'a TxHash, &'a SealedWellFormedTx)>,
+        removed: impl IntoIterator<Item = &'a TxHash>,
+    ) -> Result<(), TxCacheStoreError> {
+        let mut db_txn = self.env.begin_rw_txn()?;
+        for (tx_hash, sealed_tx) in inserted {
+            db_txn.put(
+                self.sealed_txs,
+                tx_hash.as_bytes(),
+                &sealed_tx.0,
+                WriteFlags::empty(),
+            )?;
+        }
+        for tx_hash in removed {
+            match db_txn.del(self.sealed_txs, tx_hash.as_bytes(), None) {
+                Ok(()) | Err(lmdb::Error::NotFound) => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+        db_txn.commit()?;
+        Ok(())
+    }
+
+    /// Removes transactions from the store. Hashes that are not in the store are ignored.
+    pub fn remove<'a>(
+        &self,
+        tx_hashes: impl IntoIterator<Item = &'a TxHash>,
+    ) -> Result<(), TxCacheStoreError> {
+        self.update(Vec::new(), tx_hashes)
+    }
+
+    /// Returns all stored transactions.
+    pub fn get_all(&self) -> Result<Vec<(TxHash, SealedWellFormedTx)>, TxCacheStoreError> {
+        let db_txn = self.env.begin_ro_txn()?;
+        let mut cursor = db_txn.open_ro_cursor(self.sealed_txs)?;
+        cursor
+            .iter_start()
+            .map(|(key_bytes, value_bytes)| {
+                let tx_hash =
+                    TxHash::try_from(key_bytes).map_err(|_| TxCacheStoreError::InvalidTxHash)?;
+                Ok((tx_hash, SealedWellFormedTx(value_bytes.to_vec())))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    #[test]
+    // Stored transactions should be returned by `get_all` until removed.
+    fn test_insert_get_all_remove() {
+        let temp_dir = TempDir::new("tx_cache_store").unwrap();
+        let store = TxCacheStore::open(temp_dir.path()).unwrap();
+        assert_eq!(store.get_all().unwrap(), vec![]);
+
+        let hash_a = TxHash([1u8; 32]);
+        let hash_b = TxHash([2u8; 32]);
+        let sealed_a = SealedWellFormedTx(vec![1, 2, 3]);
+        let sealed_b = SealedWellFormedTx(vec![4, 5, 6]);
+        store
+            .update(vec![(&hash_a, &sealed_a), (&hash_b, &sealed_b)], Vec::new())
+            .unwrap();
+
+        assert_eq!(
+            store.get_all().unwrap(),
+            vec![(hash_a, sealed_a.clone()), (hash_b, sealed_b)]
+        );
+
+        // Removing a hash that is not stored is not an error.
+        store.remove(&[hash_b, TxHash([3u8; 32])]).unwrap();
+        assert_eq!(store.get_all().unwrap(), vec![(hash_a, sealed_a.clone())]);
+
+        // Contents survive reopening the store.
+        drop(store);
+        let store = TxCacheStore::open(temp_dir.path()).unwrap();
+        assert_eq!(store.get_all().unwrap(), vec![(hash_a, sealed_a)]);
+    }
+}
@@ -2,7 +2,10 @@
 
 //! The entity that manages cached transactions on the untrusted side.
 
-use crate::counters;
+use crate::{
+    counters,
+    tx_cache_store::{TxCacheStore, TxCacheStoreError},
+};
 use attest_enclave_api::{EnclaveMessage, PeerSession};
 use common::{
     logger::{log, Logger},
@@ -17,7 +20,10 @@ use ledger_db::{Error as LedgerDbError, Ledger};
 use std::{
     collections::BTreeSet,
     iter::FromIterator,
-    sync::{Arc, Mutex, MutexGuard},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
 };
 use transaction::{
     constants::MAX_TRANSACTIONS_PER_BLOCK,
@@ -43,6 +49,15 @@ pub enum TxManagerError {
 
     #[fail(display = "Ledger error: {}", _0)]
     LedgerDb(LedgerDbError),
+
+    #[fail(display = "Fee too low for current load, minimum fee is {}", _0)]
+    FeeTooLowForCurrentLoad(u64),
+
+    #[fail(display = "Tx cache store error: {}", _0)]
+    Store(TxCacheStoreError),
+
+    #[fail(display = "Tx cache is full")]
+    CacheFull,
 }
 
 impl From<ConsensusEnclaveError> for TxManagerError {
@@ -67,6 +82,12 @@ impl From<LedgerDbError> for TxManagerError {
     }
 }
 
+impl From<TxCacheStoreError> for TxManagerError {
+    fn from(err: TxCacheStoreError) -> Self {
+        Self::Store(err)
+    }
+}
+
 pub type TxManagerResult<T> = Result<T, TxManagerError>;
 
 struct CacheEntry {
@@ -85,6 +106,22 @@ impl CacheEntry {
     }
 }
 
+/// Changes to the cache that have not been written to the store yet.
+#[derive(Default)]
+struct UnstoredChanges {
+    /// Transactions inserted into the cache.
+    inserted: Vec<(TxHash, WellFormedEncryptedTx)>,
+
+    /// Transactions evicted from the cache.
+    evicted: HashSet<TxHash>,
+}
+
+/// Orders transactions by how little they are worth keeping in a full cache: lowest fee first, then
+/// soonest tombstone block, with the hash as a tie-breaker.
+fn eviction_order(tx_hash: &TxHash, context: &WellFormedTxContext) -> (u64, u64, TxHash) {
+    (context.fee(), context.tombstone_block(), *tx_hash)
+}
+
 /// A trait for representing the untrusted part of validation/combining. This is presented as a
 /// trait to make testing easier.
 pub trait UntrustedInterfaces: Clone {
@@ -137,6 +174,25 @@ pub struct TxManager<
 
     /// Map of tx hashes to data we hold for each tx.
     cache: Arc<Mutex<HashMap<TxHash, CacheEntry>>>,
+
+    /// Optional on-disk copy of the cache, holding enclave-sealed transactions.
+    store: Option<TxCacheStore>,
+
+    /// Changes to the cache waiting to be written to `store` by `flush_to_store`.
+    unstored_changes: Arc<Mutex<UnstoredChanges>>,
+
+    /// Maximum number of proposed transactions held in the cache. When full, the transactions
+    /// paying the lowest fee, and then those closest to their tombstone block, are evicted first.
+    /// Transactions held for consensus are never evicted.
+    max_entries: usize,
+
+    /// Minimum fee of newly proposed transactions under fee-priority selection. Shared by all
+    /// clones.
+    admission_fee_floor: Arc<AtomicU64>,
+
+    /// Hashes that consensus is working with: values pending nomination, and values of the current
+    /// slot. Never evicted, since the slot may externalize them.
+    in_consensus: Arc<Mutex<HashSet<TxHash>>>,
 }
 
 impl<E: ConsensusEnclaveProxy, L: Ledger, UI: UntrustedInterfaces> TxManager<E, L, UI> {
@@ -148,7 +204,131 @@ impl<E: ConsensusEnclaveProxy, L: Ledger, UI: UntrustedInterfaces> TxManager<E,
             untrusted,
             logger,
             cache: Arc::new(Mutex::new(HashMap::default())),
+            store: None,
+            unstored_changes: Arc::new(Mutex::new(UnstoredChanges::default())),
+            max_entries: usize::MAX,
+            admission_fee_floor: Arc::new(AtomicU64::new(0)),
+            in_consensus: Arc::new(Mutex::new(HashSet::default())),
+        }
+    }
+
+    /// Limit the number of proposed transactions held in the cache.
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries;
+        self
+    }
+
+    /// Persist cached transactions to `store`, so that they can be restored by
+    /// `restore_from_store` after a restart. Newly inserted transactions are written by
+    /// `flush_to_store`.
+    pub fn with_store(mut self, store: TxCacheStore) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// Reload the transactions held in the store into the cache.
+    /// Transactions that can no longer be unsealed (e.g. sealed by a previous enclave build) or
+    /// that are no longer valid (e.g. past their tombstone block, or already spent) are dropped
+    /// from the store. Returns the hashes of the restored transactions.
+    pub fn restore_from_store(&self) -> TxManagerResult<Vec<TxHash>> {
+        let store = match &self.store {
+            Some(store) => store,
+            None => return Ok(Vec::new()),
+        };
+
+        let stored_txs = store.get_all()?;
+        let num_stored_txs = stored_txs.len();
+
+        // Unseal each transaction on its own, so that one bad entry does not prevent restoring
+        // the others.
+        let mut restored_hashes = Vec::new();
+        let mut dropped_hashes = Vec::new();
+        for (tx_hash, sealed_tx) in stored_txs {
+            let (encrypted_tx, context) = match self.enclave.unseal_txs(vec![sealed_tx]) {
+                Ok(mut unsealed_txs) if unsealed_txs.len() == 1 => unsealed_txs.remove(0),
+                Ok(unsealed_txs) => {
+                    log::warn!(
+                        self.logger,
+                        "Dropping stored tx {}: unsealed into {} txs",
+                        tx_hash,
+                        unsealed_txs.len()
+                    );
+                    dropped_hashes.push(tx_hash);
+                    continue;
+                }
+                Err(err) => {
+                    log::warn!(
+                        self.logger,
+                        "Dropping stored tx {}: failed unsealing: {}",
+                        tx_hash,
+                        err
+                    );
+                    dropped_hashes.push(tx_hash);
+                    continue;
+                }
+            };
+
+            if let Err(err) = self.untrusted.is_valid(&context) {
+                log::debug!(self.logger, "Dropping stored tx {}: {:?}", tx_hash, err);
+                dropped_hashes.push(tx_hash);
+                continue;
+            }
+
+            restored_hashes.push(*context.tx_hash());
+            self.lock_cache().insert(
+                *context.tx_hash(),
+                CacheEntry {
+                    encrypted_tx,
+                    context,
+                },
+            );
         }
+        counters::TX_CACHE_NUM_ENTRIES.set(self.num_entries() as i64);
+
+        store.remove(&dropped_hashes)?;
+
+        log::info!(
+            self.logger,
+            "Restored {} of {} stored transactions",
+            restored_hashes.len(),
+            num_stored_txs,
+        );
+        Ok(restored_hashes)
+    }
+
+    /// Write the transactions inserted into the cache since the last call to the store, sealed
+    /// in a single batch, and remove the ones evicted from it.
+    pub fn flush_to_store(&self) -> TxManagerResult<()> {
+        let store = match &self.store {
+            Some(store) => store,
+            None => return Ok(()),
+        };
+
+        let changes = std::mem::take(&mut *self.lock_unstored_changes());
+        if changes.inserted.is_empty() && changes.evicted.is_empty() {
+            return Ok(());
+        }
+
+        // Transactions that left the cache in the meantime need not be stored.
+        let (tx_hashes, encrypted_txs): (Vec<TxHash>, Vec<WellFormedEncryptedTx>) = {
+            let cache = self.lock_cache();
+            changes
+                .inserted
+                .into_iter()
+                .filter(|(tx_hash, _)| cache.contains_key(tx_hash))
+                .unzip()
+        };
+        let sealed_txs = if encrypted_txs.is_empty() {
+            Vec::new()
+        } else {
+            self.enclave.seal_txs(&encrypted_txs)?
+        };
+
+        store.update(
+            tx_hashes.iter().zip(sealed_txs.iter()),
+            changes.evicted.iter(),
+        )?;
+        Ok(())
     }
 
     /// Insert a new transaction into the cache.
@@ -158,6 +338,35 @@ impl<E: ConsensusEnclaveProxy, L: Ledger, UI: UntrustedInterfaces> TxManager<E,
         tx_context: TxContext,
         // _origin_node: Option<NodeID>
         // _relayed_to: Option<NodeID>,
+    ) -> TxManagerResult<WellFormedTxContext> {
+        self.insert_tx(tx_context, 0, true)
+    }
+
+    /// Insert a new transaction into the cache, provided it pays at least `min_fee`.
+    /// This enforces that the transaction is well-formed.
+    pub fn insert_proposed_tx_with_min_fee(
+        &self,
+        tx_context: TxContext,
+        min_fee: u64,
+    ) -> TxManagerResult<WellFormedTxContext> {
+        self.insert_tx(tx_context, min_fee, true)
+    }
+
+    /// Insert a transaction referenced by a consensus message into the cache.
+    /// This enforces that the transaction is well-formed, but not the cache size limit, since
+    /// consensus cannot make progress without the transactions it references.
+    pub fn insert_referenced_tx(
+        &self,
+        tx_context: TxContext,
+    ) -> TxManagerResult<WellFormedTxContext> {
+        self.insert_tx(tx_context, 0, false)
+    }
+
+    fn insert_tx(
+        &self,
+        tx_context: TxContext,
+        min_fee: u64,
+        enforce_max_entries: bool,
     ) -> TxManagerResult<WellFormedTxContext> {
         // If already in cache then we're done.
         {
@@ -186,23 +395,73 @@ impl<E: ConsensusEnclaveProxy, L: Ledger, UI: UntrustedInterfaces> TxManager<E,
 
         drop(timer);
 
-        log::trace!(
-            self.logger,
-            "Inserted well-formed transaction request {hash} into cache",
-            hash = well_formed_tx_context.tx_hash().to_string(),
-        );
+        // The fee is only visible once the enclave has checked the transaction.
+        if well_formed_tx_context.fee() < min_fee {
+            return Err(TxManagerError::FeeTooLowForCurrentLoad(min_fee));
+        }
 
-        // Store in our cache.
-        {
+        // Store in our cache, making room for it if necessary.
+        let evicted_hash = {
             let mut cache = self.lock_cache();
+            let evicted_hash = if enforce_max_entries && cache.len() >= self.max_entries {
+                let in_consensus = self.lock_in_consensus();
+                let evicted_hash = cache
+                    .iter()
+                    .filter(|(tx_hash, _entry)| !in_consensus.contains(*tx_hash))
+                    .min_by_key(|(tx_hash, entry)| eviction_order(tx_hash, entry.context()))
+                    .filter(|(tx_hash, entry)| {
+                        eviction_order(tx_hash, entry.context())
+                            < eviction_order(
+                                well_formed_tx_context.tx_hash(),
+                                &well_formed_tx_context,
+                            )
+                    })
+                    .map(|(tx_hash, _entry)| *tx_hash)
+                    .ok_or(TxManagerError::CacheFull)?;
+                cache.remove(&evicted_hash);
+                Some(evicted_hash)
+            } else {
+                None
+            };
+
             cache.insert(
                 *well_formed_tx_context.tx_hash(),
                 CacheEntry {
-                    encrypted_tx: well_formed_encrypted_tx,
+                    encrypted_tx: well_formed_encrypted_tx.clone(),
                     context: well_formed_tx_context.clone(),
                 },
             );
             counters::TX_CACHE_NUM_ENTRIES.set(cache.len() as i64);
+            evicted_hash
+        };
+
+        log::trace!(
+            self.logger,
+            "Inserted well-formed transaction request {hash} into cache",
+            hash = well_formed_tx_context.tx_hash().to_string(),
+        );
+
+        if let Some(evicted_hash) = evicted_hash {
+            log::debug!(
+                self.logger,
+                "Evicted {} from the full tx cache to make room for {}",
+                evicted_hash,
+                well_formed_tx_context.tx_hash(),
+            );
+            counters::TX_CACHE_EVICTIONS.inc();
+        }
+
+        // Queue the changes for the store. Sealing and writing them is left to `flush_to_store`,
+        // so that it happens in batches and off the path of client requests.
+        if self.store.is_some() {
+            let mut changes = self.lock_unstored_changes();
+            if let Some(evicted_hash) = evicted_hash {
+                changes.evicted.insert(evicted_hash);
+            }
+            changes.evicted.remove(well_formed_tx_context.tx_hash());
+            changes
+                .inserted
+                .push((*well_formed_tx_context.tx_hash(), well_formed_encrypted_tx));
         }
 
         // Success!
@@ -234,6 +493,16 @@ impl<E: ConsensusEnclaveProxy, L: Ledger, UI: UntrustedInterfaces> TxManager<E,
 
         counters::TX_CACHE_NUM_ENTRIES.set(cache.len() as i64);
 
+        if let Some(store) = &self.store {
+            if let Err(err) = store.remove(&purged_hashes) {
+                log::error!(
+                    self.logger,
+                    "Failed removing expired txs from store: {}",
+                    err
+                );
+            }
+        }
+
         purged_hashes
     }
 
@@ -290,6 +559,49 @@ impl<E: ConsensusEnclaveProxy, L: Ledger, UI: UntrustedInterfaces> TxManager<E,
             .combine(&tx_contexts, MAX_TRANSACTIONS_PER_BLOCK)
     }
 
+    /// The lowest fee among the `max_elements` highest-paying transactions in `tx_hashes`, or 0 if
+    /// fewer than `max_elements` of them are in the cache. Under fee-priority selection, a new
+    /// transaction paying less than this would not make it into the next block.
+    pub fn fee_floor(&self, tx_hashes: &[TxHash], max_elements: usize) -> u64 {
+        let cache = self.lock_cache();
+        let mut fees: Vec<u64> = tx_hashes
+            .iter()
+            .filter_map(|tx_hash| cache.get(tx_hash))
+            .map(|entry| entry.context().fee())
+            .collect();
+        if max_elements == 0 || fees.len() < max_elements {
+            return 0;
+        }
+
+        fees.sort_unstable_by(|a, b| b.cmp(a));
+        fees[max_elements - 1]
+    }
+
+    /// Set the minimum fee of newly proposed transactions, typically to the `fee_floor` of the
+    /// values pending consensus.
+    pub fn set_admission_fee_floor(&self, fee_floor: u64) {
+        self.admission_fee_floor.store(fee_floor, Ordering::SeqCst);
+    }
+
+    /// The minimum fee of newly proposed transactions, as last set by `set_admission_fee_floor`.
+    pub fn admission_fee_floor(&self) -> u64 {
+        self.admission_fee_floor.load(Ordering::SeqCst)
+    }
+
+    /// Protect the given hashes from eviction, because consensus is working with them. Hashes that
+    /// are not (yet) in the cache may be given as well.
+    pub fn hold_for_consensus(&self, tx_hashes: impl IntoIterator<Item = TxHash>) {
+        self.lock_in_consensus().extend(tx_hashes);
+    }
+
+    /// Replace the hashes protected from eviction, typically with the values still pending once a
+    /// slot is over.
+    pub fn set_held_for_consensus(&self, tx_hashes: impl IntoIterator<Item = TxHash>) {
+        let mut in_consensus = self.lock_in_consensus();
+        in_consensus.clear();
+        in_consensus.extend(tx_hashes);
+    }
+
     /// A "shim" that converts the output of consensus into something that can be written to the ledger.
     pub fn tx_hashes_to_block(
         &self,
@@ -354,9 +666,32 @@ impl<E: ConsensusEnclaveProxy, L: Ledger, UI: UntrustedInterfaces> TxManager<E,
         self.lock_cache().len()
     }
 
+    pub fn max_entries(&self) -> usize {
+        self.max_entries
+    }
+
+    /// The contexts of all cached transactions, lowest fee first.
+    pub fn tx_contexts(&self) -> Vec<WellFormedTxContext> {
+        let cache = self.lock_cache();
+        let mut contexts: Vec<WellFormedTxContext> = cache
+            .values()
+            .map(|entry| entry.context().clone())
+            .collect();
+        contexts.sort_by_key(|context| eviction_order(context.tx_hash(), context));
+        contexts
+    }
+
     fn lock_cache(&self) -> MutexGuard<HashMap<TxHash, CacheEntry>> {
         self.cache.lock().expect("lock poisoned")
     }
+
+    fn lock_in_consensus(&self) -> MutexGuard<HashSet<TxHash>> {
+        self.in_consensus.lock().expect("lock poisoned")
+    }
+
+    fn lock_unstored_changes(&self) -> MutexGuard<UnstoredChanges> {
+        self.unstored_changes.lock().expect("lock poisoned")
+    }
 }
 
 #[cfg(test)]
@@ -364,10 +699,15 @@ mod tests {
     use super::*;
     use crate::validators::DefaultTxManagerUntrustedInterfaces;
     use common::logger::test_with_logger;
+    use consensus_enclave::SealedWellFormedTx;
     use consensus_enclave_mock::ConsensusServiceMockEnclave;
     use rand::{rngs::StdRng, SeedableRng};
-    use transaction::account_keys::AccountKey;
-    use transaction_test_utils::{create_ledger, create_transaction, initialize_ledger};
+    use tempdir::TempDir;
+    use transaction::{account_keys::AccountKey, constants::BASE_FEE};
+    use transaction_test_utils::{
+        create_ledger, create_transaction, create_transaction_with_amount, initialize_ledger,
+        INITIALIZE_LEDGER_AMOUNT,
+    };
 
     #[test_with_logger]
     fn test_hashes_to_block(logger: Logger) {
@@ -503,4 +843,257 @@ mod tests {
         // The ledger was previously initialized with 3 blocks.
         assert_eq!(block.index, 3);
     }
+
+    #[test_with_logger]
+    // Transactions persisted by one TxManager should be restored by another one sharing the same
+    // store, except for those that are no longer valid.
+    fn test_restore_from_store(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([77u8; 32]);
+        let sender = AccountKey::random(&mut rng);
+        let mut ledger = create_ledger();
+        let n_blocks = 3;
+        initialize_ledger(&mut ledger, n_blocks, &sender, &mut rng);
+
+        let temp_dir = TempDir::new("tx_cache_store").unwrap();
+        let store = TxCacheStore::open(temp_dir.path()).unwrap();
+        let tx_manager = TxManager::new(
+            ConsensusServiceMockEnclave::default(),
+            ledger.clone(),
+            DefaultTxManagerUntrustedInterfaces::new(ledger.clone()),
+            logger.clone(),
+        )
+        .with_store(store.clone());
+
+        let tx_out = ledger
+            .get_transactions_by_block(n_blocks - 1)
+            .unwrap()
+            .pop()
+            .unwrap()
+            .outputs[0]
+            .clone();
+
+        // A transaction that expires once the ledger has one more block.
+        let recipient = AccountKey::random(&mut rng);
+        let expiring_tx = create_transaction(
+            &mut ledger,
+            &tx_out,
+            &sender,
+            &recipient.default_subaddress(),
+            n_blocks + 1,
+            &mut rng,
+        );
+
+        // A transaction that remains valid.
+        let recipient = AccountKey::random(&mut rng);
+        let valid_tx = create_transaction(
+            &mut ledger,
+            &tx_out,
+            &sender,
+            &recipient.default_subaddress(),
+            n_blocks + 2,
+            &mut rng,
+        );
+
+        for tx in &[&expiring_tx, &valid_tx] {
+            tx_manager
+                .insert_proposed_tx(ConsensusServiceMockEnclave::tx_to_tx_context(tx))
+                .unwrap();
+        }
+
+        // Inserted transactions are only written once flushed.
+        assert_eq!(store.get_all().unwrap().len(), 0);
+        tx_manager.flush_to_store().unwrap();
+        assert_eq!(store.get_all().unwrap().len(), 2);
+
+        // An entry that cannot be unsealed, e.g. because it was sealed by another enclave.
+        let unsealable_hash = TxHash([9u8; 32]);
+        store
+            .update(
+                vec![(&unsealable_hash, &SealedWellFormedTx(vec![1, 2, 3]))],
+                Vec::new(),
+            )
+            .unwrap();
+
+        // Restart against a ledger that has moved on by one block.
+        let mut advanced_ledger = create_ledger();
+        initialize_ledger(&mut advanced_ledger, n_blocks + 1, &sender, &mut rng);
+        let restarted_tx_manager = TxManager::new(
+            ConsensusServiceMockEnclave::default(),
+            advanced_ledger.clone(),
+            DefaultTxManagerUntrustedInterfaces::new(advanced_ledger),
+            logger,
+        )
+        .with_store(store.clone());
+        assert_eq!(restarted_tx_manager.num_entries(), 0);
+
+        let restored_hashes = restarted_tx_manager.restore_from_store().unwrap();
+        assert_eq!(restored_hashes, vec![valid_tx.tx_hash()]);
+        assert_eq!(restarted_tx_manager.num_entries(), 1);
+        assert!(restarted_tx_manager
+            .get_encrypted_tx_by_hash(&valid_tx.tx_hash())
+            .is_some());
+
+        // The expired transaction and the entry that could not be unsealed are no longer stored.
+        let stored_hashes: Vec<TxHash> = store
+            .get_all()
+            .unwrap()
+            .into_iter()
+            .map(|(tx_hash, _)| tx_hash)
+            .collect();
+        assert_eq!(stored_hashes, vec![valid_tx.tx_hash()]);
+    }
+
+    #[test_with_logger]
+    // A full cache should evict the transactions paying the lowest fee, and then those closest to
+    // their tombstone block, and reject transactions that are worth less than everything it holds.
+    fn test_max_entries_eviction(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([77u8; 32]);
+        let sender = AccountKey::random(&mut rng);
+        let mut ledger = create_ledger();
+        let n_blocks = 3;
+        initialize_ledger(&mut ledger, n_blocks, &sender, &mut rng);
+
+        let tx_manager = TxManager::new(
+            ConsensusServiceMockEnclave::default(),
+            ledger.clone(),
+            DefaultTxManagerUntrustedInterfaces::new(ledger.clone()),
+            logger,
+        )
+        .with_max_entries(2);
+
+        // An unspent output of the origin block.
+        let tx_out = ledger.get_transactions_by_block(0).unwrap()[0].outputs[1].clone();
+
+        let mut create_tx = |fee: u64, tombstone_block: u64| {
+            let recipient = AccountKey::random(&mut rng);
+            create_transaction_with_amount(
+                &mut ledger,
+                &tx_out,
+                &sender,
+                &recipient.default_subaddress(),
+                INITIALIZE_LEDGER_AMOUNT - fee,
+                fee,
+                tombstone_block,
+                &mut rng,
+            )
+        };
+        let expires_soon = create_tx(BASE_FEE, n_blocks + 1);
+        let expires_later = create_tx(BASE_FEE, n_blocks + 2);
+        let pays_more = create_tx(BASE_FEE * 2, n_blocks + 1);
+        let pays_least = create_tx(BASE_FEE, n_blocks + 1);
+
+        for tx in &[&expires_soon, &expires_later] {
+            tx_manager
+                .insert_proposed_tx(ConsensusServiceMockEnclave::tx_to_tx_context(tx))
+                .unwrap();
+        }
+
+        // The transaction paying more replaces the one closest to its tombstone block.
+        tx_manager
+            .insert_proposed_tx(ConsensusServiceMockEnclave::tx_to_tx_context(&pays_more))
+            .unwrap();
+        assert_eq!(tx_manager.num_entries(), 2);
+        assert!(tx_manager
+            .get_encrypted_tx_by_hash(&expires_soon.tx_hash())
+            .is_none());
+
+        // A transaction that is not worth more than anything in the cache is rejected.
+        match tx_manager
+            .insert_proposed_tx(ConsensusServiceMockEnclave::tx_to_tx_context(&pays_least))
+        {
+            Err(TxManagerError::CacheFull) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        // Unless consensus needs it.
+        tx_manager
+            .insert_referenced_tx(ConsensusServiceMockEnclave::tx_to_tx_context(&pays_least))
+            .unwrap();
+        assert_eq!(tx_manager.num_entries(), 3);
+    }
+
+    #[test_with_logger]
+    // Transactions that consensus is working with must not be evicted, even when a full cache
+    // receives transactions that pay more, since the slot may still externalize them.
+    fn test_max_entries_eviction_skips_txs_in_consensus(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([78u8; 32]);
+        let sender = AccountKey::random(&mut rng);
+        let mut ledger = create_ledger();
+        let n_blocks = 3;
+        initialize_ledger(&mut ledger, n_blocks, &sender, &mut rng);
+
+        let tx_manager = TxManager::new(
+            ConsensusServiceMockEnclave::default(),
+            ledger.clone(),
+            DefaultTxManagerUntrustedInterfaces::new(ledger.clone()),
+            logger,
+        )
+        .with_max_entries(2);
+
+        // An unspent output of the origin block.
+        let tx_out = ledger.get_transactions_by_block(0).unwrap()[0].outputs[1].clone();
+
+        let mut create_tx = |fee: u64| {
+            let recipient = AccountKey::random(&mut rng);
+            create_transaction_with_amount(
+                &mut ledger,
+                &tx_out,
+                &sender,
+                &recipient.default_subaddress(),
+                INITIALIZE_LEDGER_AMOUNT - fee,
+                fee,
+                n_blocks + 1,
+                &mut rng,
+            )
+        };
+        let pending = create_tx(BASE_FEE);
+        let nominated = create_tx(BASE_FEE);
+        let pays_more = create_tx(BASE_FEE * 2);
+        let pays_most = create_tx(BASE_FEE * 3);
+
+        // A slot is in progress with one value pending and one nominated by a peer.
+        tx_manager
+            .insert_proposed_tx(ConsensusServiceMockEnclave::tx_to_tx_context(&pending))
+            .unwrap();
+        tx_manager.hold_for_consensus(vec![pending.tx_hash(), nominated.tx_hash()]);
+        tx_manager
+            .insert_referenced_tx(ConsensusServiceMockEnclave::tx_to_tx_context(&nominated))
+            .unwrap();
+
+        // Everything that pays less is held, so the cache has no room.
+        match tx_manager
+            .insert_proposed_tx(ConsensusServiceMockEnclave::tx_to_tx_context(&pays_more))
+        {
+            Err(TxManagerError::CacheFull) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+        assert_eq!(
+            tx_manager.missing_hashes(&BTreeSet::from_iter(vec![
+                pending.tx_hash(),
+                nominated.tx_hash()
+            ])),
+            Vec::<TxHash>::new()
+        );
+
+        // Once the slot is over, only the values still pending are held.
+        tx_manager.set_held_for_consensus(vec![pending.tx_hash()]);
+        tx_manager
+            .insert_proposed_tx(ConsensusServiceMockEnclave::tx_to_tx_context(&pays_more))
+            .unwrap();
+        assert!(tx_manager
+            .get_encrypted_tx_by_hash(&nominated.tx_hash())
+            .is_none());
+
+        // The lowest-fee entry that is not held is evicted first.
+        tx_manager
+            .insert_proposed_tx(ConsensusServiceMockEnclave::tx_to_tx_context(&pays_most))
+            .unwrap();
+        assert_eq!(tx_manager.num_entries(), 2);
+        assert!(tx_manager
+            .get_encrypted_tx_by_hash(&pending.tx_hash())
+            .is_some());
+        assert!(tx_manager
+            .get_encrypted_tx_by_hash(&pays_more.tx_hash())
+            .is_none());
+    }
 }
@@ -18,21 +18,59 @@ use crate::tx_manager::UntrustedInterfaces as TxManagerUntrustedInterfaces;
 use common::HashSet;
 use consensus_enclave::WellFormedTxContext;
 use ledger_db::Ledger;
-use std::collections::BTreeSet;
+use std::{cmp::Reverse, collections::BTreeSet, str::FromStr};
 use transaction::{
     ring_signature::KeyImage,
     tx::{TxHash, TxOutMembershipProof},
     validation::{validate_tombstone, TransactionValidationError, TransactionValidationResult},
 };
 
+/// Determines which transactions `combine` keeps when there are more candidates than fit in a
+/// block. Every node in the network must use the same policy, otherwise nodes would disagree on
+/// the composite value.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TxSelectionPolicy {
+    /// Admit transactions in order of their hashes.
+    HashOrder,
+
+    /// Admit transactions with higher fees first, breaking ties by hash.
+    FeePriority,
+}
+
+impl Default for TxSelectionPolicy {
+    fn default() -> Self {
+        Self::HashOrder
+    }
+}
+
+impl FromStr for TxSelectionPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hash-order" => Ok(Self::HashOrder),
+            "fee-priority" => Ok(Self::FeePriority),
+            _ => Err("Unknown value, valid values are hash-order/fee-priority".into()),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct DefaultTxManagerUntrustedInterfaces<L: Ledger> {
     ledger: L,
+    selection_policy: TxSelectionPolicy,
 }
 
 impl<L: Ledger> DefaultTxManagerUntrustedInterfaces<L> {
     pub fn new(ledger: L) -> Self {
-        Self { ledger }
+        Self::new_with_selection_policy(ledger, TxSelectionPolicy::default())
+    }
+
+    pub fn new_with_selection_policy(ledger: L, selection_policy: TxSelectionPolicy) -> Self {
+        Self {
+            ledger,
+            selection_policy,
+        }
     }
 }
 
@@ -115,11 +153,17 @@ impl<L: Ledger> TxManagerUntrustedInterfaces for DefaultTxManagerUntrustedInterf
         tx_contexts: &[&WellFormedTxContext],
         max_elements: usize,
     ) -> BTreeSet<TxHash> {
+        // Decide the order in which candidates are considered.
+        let mut candidates = tx_contexts.to_vec();
+        if self.selection_policy == TxSelectionPolicy::FeePriority {
+            candidates.sort_by_key(|tx_context| (Reverse(tx_context.fee()), *tx_context.tx_hash()));
+        }
+
         // Allow transactions that do not introduce key image double-spends.
         let mut allowed_hashes = BTreeSet::new();
         let mut used_key_images = HashSet::default();
 
-        for tx_context in tx_contexts.iter() {
+        for tx_context in candidates.iter() {
             if allowed_hashes.len() >= max_elements {
                 // Enforce maximum size.
                 break;
@@ -676,7 +720,7 @@ mod combine_tests {
         transaction_builder.add_input(input_credentials);
         transaction_builder.set_fee(0);
         transaction_builder
-            .add_output(123, &bob.default_subaddress(), None, &mut rng)
+            .add_output(123, &bob.default_subaddress(), None, None, &mut rng)
             .unwrap();
 
         let tx = transaction_builder.build(&mut rng).unwrap();
@@ -748,7 +792,7 @@ mod combine_tests {
                 transaction_builder.add_input(input_credentials);
                 transaction_builder.set_fee(0);
                 transaction_builder
-                    .add_output(88, &bob.default_subaddress(), None, &mut rng)
+                    .add_output(88, &bob.default_subaddress(), None, None, &mut rng)
                     .unwrap();
 
                 let tx = transaction_builder.build(&mut rng).unwrap();
@@ -814,7 +858,7 @@ mod combine_tests {
             transaction_builder.add_input(input_credentials);
             transaction_builder.set_fee(0);
             transaction_builder
-                .add_output(123, &bob.default_subaddress(), None, &mut rng)
+                .add_output(123, &bob.default_subaddress(), None, None, &mut rng)
                 .unwrap();
 
             let tx = transaction_builder.build(&mut rng).unwrap();
@@ -847,7 +891,13 @@ mod combine_tests {
             transaction_builder.add_input(input_credentials);
             transaction_builder.set_fee(0);
             transaction_builder
-                .add_output(123, &recipient_account.default_subaddress(), None, &mut rng)
+                .add_output(
+                    123,
+                    &recipient_account.default_subaddress(),
+                    None,
+                    None,
+                    &mut rng,
+                )
                 .unwrap();
 
             let tx = transaction_builder.build(&mut rng).unwrap();
@@ -902,7 +952,13 @@ mod combine_tests {
             transaction_builder.add_input(input_credentials);
             transaction_builder.set_fee(0);
             transaction_builder
-                .add_output(123, &recipient_account.default_subaddress(), None, &mut rng)
+                .add_output(
+                    123,
+                    &recipient_account.default_subaddress(),
+                    None,
+                    None,
+                    &mut rng,
+                )
                 .unwrap();
 
             let tx = transaction_builder.build(&mut rng).unwrap();
@@ -917,4 +973,81 @@ mod combine_tests {
         assert_eq!(combined_transactions.len(), 2);
         assert!(combined_transactions.contains(third_client_tx.tx_hash()));
     }
+
+    #[test]
+    // In fee-priority mode, `combine` should prefer the transactions paying the highest fees.
+    fn combine_fee_priority() {
+        let mut rng = Hc128Rng::from_seed([1u8; 32]);
+
+        let alice = AccountKey::random(&mut rng);
+        let bob = AccountKey::random(&mut rng);
+
+        let fees = vec![1, 5, 3, 5, 2];
+        let tx_contexts: Vec<WellFormedTxContext> = fees
+            .iter()
+            .map(|fee| {
+                // Create a TxOut that was sent to Alice.
+                let tx_out = TxOut::new(
+                    100,
+                    &alice.default_subaddress(),
+                    &RistrettoPrivate::from_random(&mut rng),
+                    Default::default(),
+                    &mut rng,
+                )
+                .unwrap();
+
+                // Alice spends it, sending everything but the fee to Bob.
+                let onetime_private_key = recover_onetime_private_key(
+                    &RistrettoPublic::try_from(&tx_out.public_key).unwrap(),
+                    alice.view_private_key(),
+                    &alice.default_subaddress_spend_key(),
+                );
+
+                let ring: Vec<TxOut> = vec![tx_out];
+                let membership_proofs: Vec<TxOutMembershipProof> = ring
+                    .iter()
+                    .map(|_tx_out| {
+                        // TODO: provide valid proofs for each tx_out.
+                        TxOutMembershipProof::new(0, 0, HashMap::default())
+                    })
+                    .collect();
+
+                let input_credentials = InputCredentials::new(
+                    ring,
+                    membership_proofs,
+                    0,
+                    onetime_private_key,
+                    *alice.view_private_key(),
+                    &mut rng,
+                )
+                .unwrap();
+
+                let mut transaction_builder = TransactionBuilder::new();
+                transaction_builder.add_input(input_credentials);
+                transaction_builder.set_fee(*fee);
+                transaction_builder
+                    .add_output(100 - fee, &bob.default_subaddress(), None, None, &mut rng)
+                    .unwrap();
+
+                let tx = transaction_builder.build(&mut rng).unwrap();
+                WellFormedTxContext::from(&tx)
+            })
+            .collect();
+
+        let ledger = get_mock_ledger(10);
+        let untrusted = DefaultTxManagerUntrustedInterfaces::new_with_selection_policy(
+            ledger,
+            TxSelectionPolicy::FeePriority,
+        );
+        let ref_tx_contexts: Vec<&WellFormedTxContext> = tx_contexts.iter().collect();
+        let combined_transactions = untrusted.combine(&ref_tx_contexts[..], 3);
+
+        // The two transactions paying 5 and the one paying 3 should be selected.
+        let expected: BTreeSet<TxHash> = tx_contexts
+            .iter()
+            .filter(|tx_context| tx_context.fee() >= 3)
+            .map(|tx_context| *tx_context.tx_hash())
+            .collect();
+        assert_eq!(combined_transactions, expected);
+    }
 }
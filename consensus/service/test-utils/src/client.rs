@@ -0,0 +1,116 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! A client for nodes running the mock enclave.
+//!
+//! `ThickClient` needs a node that can complete a real attestation handshake, which the mock
+//! enclave cannot do. The mock enclave instead reads proposed transactions as plaintext, so this
+//! client sends them unencrypted over the same gRPC API. This is a deliberate limit of the test
+//! network: it does not exercise attestation or the encryption of proposed transactions.
+
+use attest_api::attest::Message;
+use common::logger::{log, o, Logger};
+use grpcio::{ChannelBuilder, EnvBuilder};
+use mcconnection::{Connection, ConnectionUriGrpcioChannel, Error, Result, UserTxConnection};
+use mcuri::{ConnectionUri, ConsensusClientUri as ClientUri};
+use mobilecoin_api::{
+    consensus_client_grpc::ConsensusClientApiClient, consensus_common::ProposeTxResult,
+};
+use std::{
+    cmp::Ordering,
+    fmt::{Display, Formatter, Result as FmtResult},
+    hash::{Hash, Hasher},
+    sync::Arc,
+};
+use transaction::{tx::Tx, BlockIndex};
+
+/// Submits transactions to a node running `ConsensusServiceMockEnclave`.
+pub struct TestClient {
+    uri: ClientUri,
+    logger: Logger,
+    consensus_client_api_client: ConsensusClientApiClient,
+}
+
+impl TestClient {
+    pub fn new(uri: ClientUri, logger: Logger) -> Self {
+        let logger = logger.new(o!("mc.cxn" => uri.to_string()));
+
+        let env = Arc::new(
+            EnvBuilder::new()
+                .name_prefix("TestClient-RPC".to_string())
+                .build(),
+        );
+        let ch = ChannelBuilder::default_channel_builder(env).connect_to_uri(&uri, &logger);
+        let consensus_client_api_client = ConsensusClientApiClient::new(ch);
+
+        Self {
+            uri,
+            logger,
+            consensus_client_api_client,
+        }
+    }
+}
+
+impl Connection for TestClient {
+    type Uri = ClientUri;
+
+    fn uri(&self) -> Self::Uri {
+        self.uri.clone()
+    }
+}
+
+impl UserTxConnection for TestClient {
+    fn propose_tx(&mut self, tx: &Tx) -> Result<BlockIndex> {
+        log::trace!(self.logger, "Proposing tx {}", tx.tx_hash());
+
+        let mut msg = Message::new();
+        msg.set_data(mcserial::encode(tx));
+        let resp = self
+            .consensus_client_api_client
+            .client_tx_propose(&msg)
+            .map_err(Error::from)?;
+
+        match resp.get_result() {
+            ProposeTxResult::Ok => Ok(resp.get_num_blocks()),
+            ProposeTxResult::FeeTooLowForCurrentLoad => {
+                Err(Error::FeeTooLowForCurrentLoad(resp.get_minimum_fee()))
+            }
+            result @ ProposeTxResult::OverCapacity
+            | result @ ProposeTxResult::ClientRateLimited
+            | result @ ProposeTxResult::SourceRateLimited
+            | result @ ProposeTxResult::TxCacheFull => Err(Error::NotAdmitted(result)),
+            result => Err(result.into()),
+        }
+    }
+}
+
+impl Display for TestClient {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{}", self.uri)
+    }
+}
+
+impl Eq for TestClient {}
+
+impl Hash for TestClient {
+    fn hash<H: Hasher>(&self, hasher: &mut H) {
+        self.uri.addr().hash(hasher);
+    }
+}
+
+impl PartialEq for TestClient {
+    fn eq(&self, other: &Self) -> bool {
+        self.uri.addr() == other.uri.addr()
+    }
+}
+
+impl Ord for TestClient {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.uri.addr().cmp(&other.uri.addr())
+    }
+}
+
+impl PartialOrd for TestClient {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.uri.addr().partial_cmp(&other.uri.addr())
+    }
+}
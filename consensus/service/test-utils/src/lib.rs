@@ -0,0 +1,386 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! An in-process network of consensus validators, for end-to-end tests that run without SGX.
+//!
+//! Every node is a full `ConsensusService` backed by `ConsensusServiceMockEnclave`, its own
+//! tempdir `LedgerDB`, and gRPC servers listening on loopback ports.
+//!
+//! # Scope
+//!
+//! The network covers consensus and ledger convergence, not attestation or encryption:
+//!
+//! * Nodes are started with `ConsensusService::start_unattested`, which skips the IAS report
+//!   step, so the remote attestation client (`SimClient` under `SGX_MODE=SW`) is never called.
+//! * The mock enclave cannot complete an attestation handshake, so `ThickClient` cannot connect
+//!   to the nodes. Transactions are submitted with `TestClient` instead, which sends them
+//!   unencrypted over the same gRPC API, and the mock enclave reads them as plaintext.
+//! * Nodes exchange transactions with each other unencrypted as well.
+//!
+//! Regressions in the attested client path, i.e. `ThickClient`, the AKE and report
+//! verification, are not caught by tests built on this network.
+
+mod client;
+
+pub use self::client::TestClient;
+
+use attest_net::{Client as RaClientImpl, RaClient};
+use common::logger::{log, o, Logger};
+use consensus_enclave_mock::ConsensusServiceMockEnclave;
+use consensus_service::{config::Config, consensus_service::ConsensusService};
+use keys::{DistinguishedEncoding, Ed25519Pair, Ed25519Private, Ed25519Public, FromRandom};
+use ledger_db::{Ledger, LedgerDB};
+use mcuri::ConsensusClientUri as ClientUri;
+use rand::{rngs::StdRng, SeedableRng};
+use std::{
+    fs,
+    net::TcpListener,
+    thread,
+    time::{Duration, Instant},
+};
+use structopt::StructOpt;
+use tempdir::TempDir;
+use transaction::{account_keys::AccountKey, tx::TxOut, Block};
+use transaction_test_utils::{create_ledger, initialize_ledger};
+
+/// How often `wait_until` polls the nodes' ledgers.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A validator in a `TestNetwork`.
+pub struct TestNode {
+    service: ConsensusService<ConsensusServiceMockEnclave, RaClientImpl>,
+    ledger: LedgerDB,
+    client_uri: ClientUri,
+}
+
+impl TestNode {
+    /// The node's ledger.
+    pub fn ledger(&self) -> &LedgerDB {
+        &self.ledger
+    }
+
+    /// The URI clients use to reach this node.
+    pub fn client_uri(&self) -> ClientUri {
+        self.client_uri.clone()
+    }
+
+    /// A client connected to this node.
+    pub fn client(&self, logger: Logger) -> TestClient {
+        TestClient::new(self.client_uri.clone(), logger)
+    }
+}
+
+/// Static configuration of a node, decided before any node is started.
+struct NodeSetup {
+    /// DER encoding of the node's message signing key.
+    signer_key_der: Vec<u8>,
+    signer_public_key: Ed25519Public,
+    peer_port: u16,
+    client_port: u16,
+}
+
+impl NodeSetup {
+    fn responder_id(&self) -> String {
+        format!("127.0.0.1:{}", self.peer_port)
+    }
+
+    fn peer_uri(&self) -> String {
+        format!(
+            "insecure-mcp://127.0.0.1:{}/?consensus-msg-key={}",
+            self.peer_port,
+            hex::encode(&self.signer_public_key)
+        )
+    }
+}
+
+/// A network of validators running in this process.
+pub struct TestNetwork {
+    nodes: Vec<TestNode>,
+
+    /// Owner of the outputs in the initial ledger.
+    account_key: AccountKey,
+
+    /// Holds the nodes' network configuration files.
+    _config_dir: TempDir,
+
+    logger: Logger,
+}
+
+impl TestNetwork {
+    /// Start `num_nodes` validators sharing an initial ledger of `num_blocks` blocks.
+    /// Each node trusts all other nodes, and needs all of them to agree.
+    pub fn start(num_nodes: usize, num_blocks: u64, logger: Logger) -> Self {
+        assert!(num_nodes > 0, "A network needs at least one node");
+        assert!(num_blocks > 0, "The initial ledger needs an origin block");
+
+        let mut rng: StdRng = SeedableRng::from_seed([7u8; 32]);
+        let account_key = AccountKey::random(&mut rng);
+
+        // Build the initial ledger once, and give every node a copy of it.
+        let mut initial_ledger = create_ledger();
+        initialize_ledger(&mut initial_ledger, num_blocks, &account_key, &mut rng);
+
+        let setups: Vec<NodeSetup> = (0..num_nodes)
+            .map(|_| {
+                let signer_key = Ed25519Private::from_random(&mut rng);
+                NodeSetup {
+                    signer_key_der: signer_key.to_der(),
+                    signer_public_key: Ed25519Pair::from(signer_key).public_key(),
+                    peer_port: free_port(),
+                    client_port: free_port(),
+                }
+            })
+            .collect();
+
+        let config_dir = TempDir::new("consensus_test_network").expect("Could not create tempdir");
+
+        let nodes = setups
+            .iter()
+            .enumerate()
+            .map(|(i, setup)| {
+                let peers: Vec<&NodeSetup> = setups
+                    .iter()
+                    .enumerate()
+                    .filter(|(j, _)| *j != i)
+                    .map(|(_, peer)| peer)
+                    .collect();
+
+                let network_path = config_dir.path().join(format!("network-{}.toml", i));
+                fs::write(&network_path, network_toml(&peers))
+                    .expect("Could not write network configuration");
+
+                let args = vec![
+                    "consensus-service".to_string(),
+                    "--peer-responder-id".to_string(),
+                    setup.responder_id(),
+                    "--client-responder-id".to_string(),
+                    format!("127.0.0.1:{}", setup.client_port),
+                    "--msg-signer-key".to_string(),
+                    base64::encode(&setup.signer_key_der),
+                    "--network".to_string(),
+                    network_path.to_string_lossy().to_string(),
+                    "--ias-api-key".to_string(),
+                    String::new(),
+                    "--ias-spid".to_string(),
+                    "22222222222222222222222222222222".to_string(),
+                    "--peer-listen-uri".to_string(),
+                    format!("insecure-mcp://127.0.0.1:{}/", setup.peer_port),
+                    "--client-listen-uri".to_string(),
+                    format!("insecure-mc://127.0.0.1:{}/", setup.client_port),
+                    "--ledger-path".to_string(),
+                    config_dir
+                        .path()
+                        .join(format!("ledger-{}", i))
+                        .to_string_lossy()
+                        .to_string(),
+                    "--sealed-block-signing-key".to_string(),
+                    config_dir
+                        .path()
+                        .join(format!("sealed-key-{}", i))
+                        .to_string_lossy()
+                        .to_string(),
+                    // Tests submit transactions in bursts from a single address.
+                    "--client-tx-rate-limit".to_string(),
+                    "0".to_string(),
+                    "--source-ip-tx-rate-limit".to_string(),
+                    "0".to_string(),
+                ];
+                let config = Config::from_iter(args);
+
+                let ledger = copy_ledger(&initial_ledger);
+                let client_uri = config.client_listen_uri.clone();
+                let node_logger = logger.new(o!("mc.test_node" => setup.responder_id()));
+                let mut service = ConsensusService::new(
+                    config,
+                    ConsensusServiceMockEnclave::default(),
+                    ledger.clone(),
+                    RaClientImpl::new("").expect("Could not create remote attestation client"),
+                    node_logger,
+                );
+                service
+                    .start_unattested()
+                    .expect("Failed starting consensus service");
+
+                TestNode {
+                    service,
+                    ledger,
+                    client_uri,
+                }
+            })
+            .collect();
+
+        log::info!(logger, "Started a test network of {} nodes", num_nodes);
+
+        Self {
+            nodes,
+            account_key,
+            _config_dir: config_dir,
+            logger,
+        }
+    }
+
+    /// The nodes in the network.
+    pub fn nodes(&self) -> &[TestNode] {
+        &self.nodes
+    }
+
+    /// The owner of every output in the initial ledger.
+    pub fn account_key(&self) -> &AccountKey {
+        &self.account_key
+    }
+
+    /// Outputs in the initial ledger that have not been spent, all owned by `account_key`.
+    pub fn spendable_tx_outs(&self) -> Vec<TxOut> {
+        // Outputs of the origin block other than the first one are never spent when initializing
+        // the ledger.
+        let ledger = &self.nodes[0].ledger;
+        ledger
+            .get_transactions_by_block(0)
+            .expect("Could not get origin block transactions")[0]
+            .outputs
+            .iter()
+            .skip(1)
+            .cloned()
+            .collect()
+    }
+
+    /// Wait until every node's ledger holds at least `num_blocks` blocks.
+    /// Returns false if that did not happen within `timeout`.
+    pub fn wait_for_num_blocks(&self, num_blocks: u64, timeout: Duration) -> bool {
+        self.wait_until(timeout, |ledger| {
+            ledger
+                .num_blocks()
+                .map(|n| n >= num_blocks)
+                .unwrap_or(false)
+        })
+    }
+
+    /// Wait until `condition` holds for every node's ledger.
+    /// Returns false if that did not happen within `timeout`.
+    pub fn wait_until(&self, timeout: Duration, condition: impl Fn(&LedgerDB) -> bool) -> bool {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if self.nodes.iter().all(|node| condition(&node.ledger)) {
+                return true;
+            }
+            if Instant::now() >= deadline {
+                return false;
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    /// Whether every node's ledger holds the same blocks.
+    pub fn ledgers_converged(&self) -> bool {
+        let blocks: Vec<Vec<Block>> = self
+            .nodes
+            .iter()
+            .map(|node| all_blocks(&node.ledger))
+            .collect();
+        blocks.windows(2).all(|pair| pair[0] == pair[1])
+    }
+}
+
+impl Drop for TestNetwork {
+    fn drop(&mut self) {
+        for node in self.nodes.iter_mut() {
+            if let Err(err) = node.service.stop() {
+                log::error!(self.logger, "Failed stopping node: {}", err);
+            }
+        }
+    }
+}
+
+/// A port that was free when this was called.
+fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .expect("Could not find a free port")
+        .port()
+}
+
+/// A network.toml trusting all of `peers`.
+fn network_toml(peers: &[&NodeSetup]) -> String {
+    let quote = |s: String| format!("\"{}\"", s);
+    let broadcast_peers: Vec<String> = peers.iter().map(|peer| quote(peer.peer_uri())).collect();
+    let members: Vec<String> = peers
+        .iter()
+        .map(|peer| format!("{{ type = \"Node\", args = \"{}\" }}", peer.responder_id()))
+        .collect();
+
+    format!(
+        r#"
+            broadcast_peers = [{}]
+            tx_source_urls = ["file:///dev/null"]
+            quorum_set = {{ threshold = {}, members = [{}] }}
+        "#,
+        broadcast_peers.join(", "),
+        peers.len(),
+        members.join(", "),
+    )
+}
+
+/// A new ledger holding the same blocks as `ledger`.
+fn copy_ledger(ledger: &LedgerDB) -> LedgerDB {
+    let mut copy = create_ledger();
+    for block_index in 0..ledger.num_blocks().expect("Could not get num blocks") {
+        let block = ledger.get_block(block_index).expect("Could not get block");
+        let transactions = ledger
+            .get_transactions_by_block(block_index)
+            .expect("Could not get transactions");
+        copy.append_block(&block, &transactions, None)
+            .expect("Could not append block");
+    }
+    copy
+}
+
+fn all_blocks(ledger: &LedgerDB) -> Vec<Block> {
+    (0..ledger.num_blocks().unwrap_or(0))
+        .filter_map(|block_index| ledger.get_block(block_index).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::logger::test_with_logger;
+    use mcconnection::UserTxConnection;
+    use transaction_test_utils::create_transaction;
+
+    const BLOCK_TIMEOUT: Duration = Duration::from_secs(60);
+
+    #[test_with_logger]
+    // Transactions submitted to different nodes should end up in the same blocks on every node.
+    fn test_ledgers_converge(logger: Logger) {
+        let num_blocks = 2;
+        let network = TestNetwork::start(3, num_blocks, logger.clone());
+        let mut rng: StdRng = SeedableRng::from_seed([8u8; 32]);
+
+        let tx_outs = network.spendable_tx_outs();
+        assert!(tx_outs.len() >= network.nodes().len());
+
+        for (node, tx_out) in network.nodes().iter().zip(tx_outs.iter()) {
+            let mut ledger = node.ledger().clone();
+            let recipient = AccountKey::random(&mut rng);
+            let tx = create_transaction(
+                &mut ledger,
+                tx_out,
+                network.account_key(),
+                &recipient.default_subaddress(),
+                num_blocks + 10,
+                &mut rng,
+            );
+            node.client(logger.clone())
+                .propose_tx(&tx)
+                .expect("Failed proposing tx");
+        }
+
+        // The initial ledger holds one transaction per block.
+        let expected_num_txs = num_blocks + network.nodes().len() as u64;
+        assert!(
+            network.wait_until(BLOCK_TIMEOUT, |ledger| ledger.num_txs().unwrap()
+                == expected_num_txs),
+            "Proposed transactions did not reach every ledger"
+        );
+        assert!(network.ledgers_converged());
+    }
+}
@@ -5,7 +5,7 @@ use common::Hash;
 use transaction::{
     ring_signature::KeyImage,
     tx::{TxOut, TxOutMembershipProof},
-    Block, BlockSignature, RedactedTx,
+    Block, BlockID, BlockSignature, BlockSignatureSet, RedactedTx,
 };
 
 pub trait Ledger: Clone + Send {
@@ -23,9 +23,24 @@ pub trait Ledger: Clone + Send {
     /// Gets a Block by its index in the blockchain.
     fn get_block(&self, block_number: u64) -> Result<Block, Error>;
 
+    /// Gets the index of the block with the given ID.
+    fn get_block_index_by_block_id(&self, block_id: &BlockID) -> Result<u64, Error>;
+
     /// Gets a block signature by its index in the blockchain.
     fn get_block_signature(&self, block_number: u64) -> Result<BlockSignature, Error>;
 
+    /// Gets all known signatures for a block, including the one it was appended with.
+    fn get_block_signature_set(&self, block_number: u64) -> Result<BlockSignatureSet, Error>;
+
+    /// Adds a signature over an existing block to the block's signature set.
+    ///
+    /// Returns Ok(false) if the set already contains a signature from the same signer.
+    fn add_block_signature(
+        &mut self,
+        block_number: u64,
+        signature: &BlockSignature,
+    ) -> Result<bool, Error>;
+
     /// Get the total number of TxOuts in the ledger.
     fn num_txos(&self) -> Result<u64, Error>;
 
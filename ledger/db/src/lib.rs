@@ -14,7 +14,10 @@ use lmdb::{
 };
 use mcserial::{deserialize, serialize};
 use std::{path::PathBuf, sync::Arc};
-use transaction::{hash_block_contents, Block, BlockID, BlockSignature, RedactedTx, BLOCK_VERSION};
+use transaction::{
+    hash_block_contents, Block, BlockID, BlockSignature, BlockSignatureSet, RedactedTx,
+    BLOCK_VERSION,
+};
 
 mod error;
 mod ledger_trait;
@@ -36,7 +39,9 @@ const MAX_LMDB_FILE_SIZE: usize = 1_099_511_627_776; // 1 TB
 // LMDB Database names.
 pub const COUNTS_DB_NAME: &str = "ledger_db:counts";
 pub const BLOCKS_DB_NAME: &str = "ledger_db:blocks";
+pub const BLOCK_NUMBER_BY_ID_DB_NAME: &str = "ledger_db:block_number_by_id";
 pub const BLOCK_SIGNATURES_DB_NAME: &str = "ledger_db:block_signatures";
+pub const BLOCK_SIGNATURE_SETS_DB_NAME: &str = "ledger_db:block_signature_sets";
 pub const KEY_IMAGES_DB_NAME: &str = "ledger_db:key_images";
 pub const KEY_IMAGES_BY_BLOCK_DB_NAME: &str = "ledger_db:key_images_by_block";
 pub const TRANSACTIONS_BY_BLOCK_DB_NAME: &str = "ledger_db:transactions_by_block";
@@ -57,9 +62,15 @@ pub struct LedgerDB {
     /// Blocks by block number. `block number -> Block`
     blocks: Database,
 
+    /// Block numbers by block ID. `block id -> block number`
+    block_number_by_id: Database,
+
     /// Block signatures by number. `block number -> BlockSignature`
     block_signatures: Database,
 
+    /// Signatures collected from the network, by block number. `block number -> BlockSignatureSet`
+    block_signature_sets: Database,
+
     /// Transactions by block. `block number -> Vec<TxStored>`
     transactions_by_block: Database,
 
@@ -153,6 +164,13 @@ impl Ledger for LedgerDB {
         Ok(block)
     }
 
+    /// Gets the index of the block with the given ID.
+    fn get_block_index_by_block_id(&self, block_id: &BlockID) -> Result<u64, Error> {
+        let db_transaction = self.env.begin_ro_txn()?;
+        let index_bytes = db_transaction.get(self.block_number_by_id, &block_id.0)?;
+        Ok(key_bytes_to_u64(index_bytes))
+    }
+
     /// Gets a block signature by its index in the blockchain.
     fn get_block_signature(&self, block_number: u64) -> Result<BlockSignature, Error> {
         let db_transaction = self.env.begin_ro_txn()?;
@@ -162,6 +180,48 @@ impl Ledger for LedgerDB {
         Ok(signature)
     }
 
+    /// Gets all known signatures for a block, including the one it was appended with.
+    fn get_block_signature_set(&self, block_number: u64) -> Result<BlockSignatureSet, Error> {
+        let db_transaction = self.env.begin_ro_txn()?;
+        self.read_block_signature_set(block_number, &db_transaction)
+    }
+
+    /// Adds a signature over an existing block to the block's signature set.
+    fn add_block_signature(
+        &mut self,
+        block_number: u64,
+        signature: &BlockSignature,
+    ) -> Result<bool, Error> {
+        // Signatures are relayed by every peer, so most are already known. Check for that before
+        // verifying the signature and opening a write transaction.
+        let block: Block = {
+            let db_transaction = self.env.begin_ro_txn()?;
+            let signature_set = self.read_block_signature_set(block_number, &db_transaction)?;
+            if signature_set.contains_signer(signature.signer()) {
+                return Ok(false);
+            }
+            deserialize(db_transaction.get(self.blocks, &u64_to_key_bytes(block_number))?)?
+        };
+        signature
+            .verify(&block)
+            .map_err(|_| Error::InvalidBlockSignature)?;
+
+        let mut db_transaction = self.env.begin_rw_txn()?;
+        let mut signature_set = self.read_block_signature_set(block_number, &db_transaction)?;
+        if !signature_set.insert(signature.clone()) {
+            return Ok(false);
+        }
+
+        db_transaction.put(
+            self.block_signature_sets,
+            &u64_to_key_bytes(block_number),
+            &serialize(&signature_set)?,
+            WriteFlags::empty(),
+        )?;
+        db_transaction.commit()?;
+        Ok(true)
+    }
+
     /// Returns the index of the TxOut with the given hash.
     fn get_tx_out_index_by_hash(&self, tx_out_hash: &[u8; 32]) -> Result<u64, Error> {
         let db_transaction: RoTransaction = self.env.begin_ro_txn()?;
@@ -237,24 +297,35 @@ impl LedgerDB {
 
         let counts = env.open_db(Some(COUNTS_DB_NAME))?;
         let blocks = env.open_db(Some(BLOCKS_DB_NAME))?;
+        // Ledgers created before blocks were indexed by ID do not have this database yet. It is
+        // populated below.
+        let block_number_by_id =
+            env.create_db(Some(BLOCK_NUMBER_BY_ID_DB_NAME), DatabaseFlags::empty())?;
         let block_signatures = env.open_db(Some(BLOCK_SIGNATURES_DB_NAME))?;
+        // Ledgers created before signature sets were introduced do not have this database yet.
+        let block_signature_sets =
+            env.create_db(Some(BLOCK_SIGNATURE_SETS_DB_NAME), DatabaseFlags::empty())?;
         let key_images = env.open_db(Some(KEY_IMAGES_DB_NAME))?;
         let key_images_by_block = env.open_db(Some(KEY_IMAGES_BY_BLOCK_DB_NAME))?;
         let transactions_by_block = env.open_db(Some(TRANSACTIONS_BY_BLOCK_DB_NAME))?;
 
         let tx_out_store = TxOutStore::new(&env)?;
 
-        Ok(LedgerDB {
+        let ledger_db = LedgerDB {
             env: Arc::new(env),
             path,
             counts,
             blocks,
+            block_number_by_id,
             block_signatures,
+            block_signature_sets,
             key_images,
             key_images_by_block,
             transactions_by_block,
             tx_out_store,
-        })
+        };
+        ledger_db.index_block_ids()?;
+        Ok(ledger_db)
     }
 
     /// Creates a fresh Ledger Database in the given path.
@@ -272,7 +343,9 @@ impl LedgerDB {
 
         let counts = env.create_db(Some(COUNTS_DB_NAME), DatabaseFlags::empty())?;
         env.create_db(Some(BLOCKS_DB_NAME), DatabaseFlags::empty())?;
+        env.create_db(Some(BLOCK_NUMBER_BY_ID_DB_NAME), DatabaseFlags::empty())?;
         env.create_db(Some(BLOCK_SIGNATURES_DB_NAME), DatabaseFlags::empty())?;
+        env.create_db(Some(BLOCK_SIGNATURE_SETS_DB_NAME), DatabaseFlags::empty())?;
         env.create_db(Some(KEY_IMAGES_DB_NAME), DatabaseFlags::empty())?;
         env.create_db(Some(KEY_IMAGES_BY_BLOCK_DB_NAME), DatabaseFlags::empty())?;
         env.create_db(Some(TRANSACTIONS_BY_BLOCK_DB_NAME), DatabaseFlags::empty())?;
@@ -328,6 +401,13 @@ impl LedgerDB {
             WriteFlags::empty(),
         )?;
 
+        db_transaction.put(
+            self.block_number_by_id,
+            &block.id.0,
+            &u64_to_key_bytes(block.index),
+            WriteFlags::empty(),
+        )?;
+
         if let Some(signature) = signature {
             db_transaction.put(
                 self.block_signatures,
@@ -337,11 +417,72 @@ impl LedgerDB {
                 }),
                 WriteFlags::empty(),
             )?;
+
+            db_transaction.put(
+                self.block_signature_sets,
+                &u64_to_key_bytes(block.index),
+                &serialize(&BlockSignatureSet::new(vec![signature.clone()])).unwrap_or_else(|_| {
+                    panic!("Could not serialize block signature {:?}", signature)
+                }),
+                WriteFlags::empty(),
+            )?;
         }
 
         Ok(())
     }
 
+    /// Index the IDs of blocks appended before blocks were indexed by ID. Nothing is written if
+    /// the last block is already indexed.
+    fn index_block_ids(&self) -> Result<(), Error> {
+        let num_blocks = self.num_blocks()?;
+        if num_blocks == 0 {
+            return Ok(());
+        }
+        let last_block = self.get_block(num_blocks - 1)?;
+        match self.get_block_index_by_block_id(&last_block.id) {
+            Ok(_) => return Ok(()),
+            Err(Error::NotFound) => {}
+            Err(e) => return Err(e),
+        }
+
+        let mut db_transaction = self.env.begin_rw_txn()?;
+        for block_number in 0..num_blocks {
+            let block: Block =
+                deserialize(db_transaction.get(self.blocks, &u64_to_key_bytes(block_number))?)?;
+            db_transaction.put(
+                self.block_number_by_id,
+                &block.id.0,
+                &u64_to_key_bytes(block_number),
+                WriteFlags::empty(),
+            )?;
+        }
+        db_transaction.commit()?;
+        Ok(())
+    }
+
+    /// Read the signature set of a block. Blocks without a stored set fall back to the signature
+    /// they were appended with, if any.
+    fn read_block_signature_set(
+        &self,
+        block_number: u64,
+        db_transaction: &impl Transaction,
+    ) -> Result<BlockSignatureSet, Error> {
+        let key = u64_to_key_bytes(block_number);
+        match db_transaction.get(self.block_signature_sets, &key) {
+            Ok(bytes) => Ok(deserialize(bytes)?),
+            Err(lmdb::Error::NotFound) => {
+                // Make sure the block exists.
+                db_transaction.get(self.blocks, &key)?;
+                match db_transaction.get(self.block_signatures, &key) {
+                    Ok(bytes) => Ok(BlockSignatureSet::new(vec![deserialize(bytes)?])),
+                    Err(lmdb::Error::NotFound) => Ok(BlockSignatureSet::default()),
+                    Err(e) => Err(e.into()),
+                }
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
     fn write_key_images(
         &self,
         block_index: u64,
@@ -456,7 +597,7 @@ mod ledger_db_test {
     use super::*;
     use common::HashMap;
     use core::convert::TryFrom;
-    use keys::{FromRandom, RistrettoPrivate, RistrettoPublic};
+    use keys::{Ed25519Pair, FromRandom, RistrettoPrivate, RistrettoPublic};
     use rand::{rngs::StdRng, SeedableRng};
     use rand_core::RngCore;
     use tempdir::TempDir;
@@ -545,6 +686,46 @@ mod ledger_db_test {
         assert_eq!(ledger_db.num_txos().unwrap(), 0);
     }
 
+    #[test]
+    // Signatures added to a block should accumulate in its signature set.
+    fn test_add_block_signature() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let mut ledger_db = create_db();
+        let blocks = populate_db(&mut ledger_db, 2, 1);
+
+        // Blocks appended without a signature have an empty signature set.
+        assert!(ledger_db.get_block_signature_set(1).unwrap().is_empty());
+
+        let signer_a = Ed25519Pair::from_random(&mut rng);
+        let signer_b = Ed25519Pair::from_random(&mut rng);
+        let sig_a = BlockSignature::from_block_and_keypair(&blocks[1], &signer_a).unwrap();
+        let sig_b = BlockSignature::from_block_and_keypair(&blocks[1], &signer_b).unwrap();
+
+        assert_eq!(ledger_db.add_block_signature(1, &sig_a), Ok(true));
+        assert_eq!(ledger_db.add_block_signature(1, &sig_a), Ok(false));
+        assert_eq!(ledger_db.add_block_signature(1, &sig_b), Ok(true));
+
+        // Once a signer is recorded, its further signatures are ignored without being verified.
+        let sig_a_over_block_0 =
+            BlockSignature::from_block_and_keypair(&blocks[0], &signer_a).unwrap();
+        assert_eq!(
+            ledger_db.add_block_signature(1, &sig_a_over_block_0),
+            Ok(false)
+        );
+
+        let signature_set = ledger_db.get_block_signature_set(1).unwrap();
+        assert_eq!(signature_set.signatures(), &[sig_a.clone(), sig_b][..]);
+
+        // A signature over a different block is rejected.
+        assert_eq!(
+            ledger_db.add_block_signature(0, &sig_a),
+            Err(Error::InvalidBlockSignature)
+        );
+
+        // Blocks that do not exist have no signature set.
+        assert_eq!(ledger_db.get_block_signature_set(2), Err(Error::NotFound));
+    }
+
     #[test]
     // Appending a block should correctly update each LMDB database.
     fn test_append_block() {
@@ -642,16 +823,16 @@ mod ledger_db_test {
 
         // Add outputs that sum to 1000.
         transaction_builder
-            .add_output(100, &recipient_a.default_subaddress(), None, &mut rng)
+            .add_output(100, &recipient_a.default_subaddress(), None, None, &mut rng)
             .unwrap();
         transaction_builder
-            .add_output(200, &recipient_b.default_subaddress(), None, &mut rng)
+            .add_output(200, &recipient_b.default_subaddress(), None, None, &mut rng)
             .unwrap();
         transaction_builder
-            .add_output(300, &recipient_c.default_subaddress(), None, &mut rng)
+            .add_output(300, &recipient_c.default_subaddress(), None, None, &mut rng)
             .unwrap();
         transaction_builder
-            .add_output(400, &recipient_d.default_subaddress(), None, &mut rng)
+            .add_output(400, &recipient_d.default_subaddress(), None, None, &mut rng)
             .unwrap();
 
         let tx = transaction_builder.build(&mut rng).unwrap();
@@ -760,6 +941,29 @@ mod ledger_db_test {
         }
     }
 
+    #[test]
+    // Getting a block index by block ID should return the index of that block, if it exists.
+    fn test_get_block_index_by_block_id() {
+        let mut ledger_db = create_db();
+        let n_blocks = 43;
+        let expected_blocks = populate_db(&mut ledger_db, n_blocks, 1);
+
+        for expected_block in &expected_blocks {
+            let block_index = ledger_db
+                .get_block_index_by_block_id(&expected_block.id)
+                .unwrap();
+            assert_eq!(block_index, expected_block.index);
+        }
+
+        match ledger_db.get_block_index_by_block_id(&BlockID::default()) {
+            Ok(_index) => panic!("Should not return an index."),
+            Err(Error::NotFound) => {
+                // This is expected.
+            }
+            Err(e) => panic!("Unexpected error {:?}", e),
+        }
+    }
+
     #[test]
     // Getting a block by its index should return an error if the block doesn't exist.
     fn test_get_block_by_index_doesnt_exist() {
@@ -926,6 +1130,7 @@ mod ledger_db_test {
                 output_value,
                 &recipient_account.default_subaddress(),
                 None,
+                None,
                 &mut rng,
             )
             .unwrap();
@@ -1246,6 +1451,7 @@ mod ledger_db_test {
                     value,
                     &recipient_account.default_subaddress(),
                     None,
+                    None,
                     &mut rng,
                 )
                 .unwrap();
@@ -1300,6 +1506,7 @@ mod ledger_db_test {
                     value,
                     &recipient_account.default_subaddress(),
                     None,
+                    None,
                     &mut rng,
                 )
                 .unwrap();
@@ -1355,6 +1562,7 @@ mod ledger_db_test {
                     value,
                     &recipient_account.default_subaddress(),
                     None,
+                    None,
                     &mut rng,
                 )
                 .unwrap();
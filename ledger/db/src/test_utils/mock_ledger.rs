@@ -13,7 +13,7 @@ use transaction::{
     account_keys::AccountKey,
     ring_signature::KeyImage,
     tx::{TxHash, TxOut, TxOutMembershipElement, TxOutMembershipProof},
-    Block, BlockID, BlockSignature, RedactedTx, BLOCK_VERSION,
+    Block, BlockID, BlockSignature, BlockSignatureSet, RedactedTx, BLOCK_VERSION,
 };
 
 pub struct MockLedgerInner {
@@ -25,6 +25,7 @@ pub struct MockLedgerInner {
     pub membership_proofs: HashMap<u64, TxOutMembershipProof>,
     pub key_images_by_block_number: HashMap<u64, Vec<KeyImage>>,
     pub key_images: HashMap<KeyImage, u64>,
+    pub block_signature_sets: HashMap<u64, BlockSignatureSet>,
 }
 
 #[derive(Clone)]
@@ -44,6 +45,7 @@ impl Default for MockLedger {
                 membership_proofs: HashMap::default(),
                 key_images_by_block_number: HashMap::default(),
                 key_images: HashMap::default(),
+                block_signature_sets: HashMap::default(),
             })),
         }
     }
@@ -124,10 +126,48 @@ impl Ledger for MockLedger {
             .ok_or(Error::NotFound)
     }
 
+    fn get_block_index_by_block_id(&self, block_id: &BlockID) -> Result<u64, Error> {
+        self.lock()
+            .blocks_by_block_id
+            .get(block_id)
+            .map(|block| block.index)
+            .ok_or(Error::NotFound)
+    }
+
     fn get_block_signature(&self, _block_number: u64) -> Result<BlockSignature, Error> {
         Err(Error::NotFound)
     }
 
+    fn get_block_signature_set(&self, block_number: u64) -> Result<BlockSignatureSet, Error> {
+        let inner = self.lock();
+        if !inner.blocks_by_block_number.contains_key(&block_number) {
+            return Err(Error::NotFound);
+        }
+        Ok(inner
+            .block_signature_sets
+            .get(&block_number)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    fn add_block_signature(
+        &mut self,
+        block_number: u64,
+        signature: &BlockSignature,
+    ) -> Result<bool, Error> {
+        let block = self.get_block(block_number)?;
+        signature
+            .verify(&block)
+            .map_err(|_| Error::InvalidBlockSignature)?;
+
+        Ok(self
+            .lock()
+            .block_signature_sets
+            .entry(block_number)
+            .or_default()
+            .insert(signature.clone()))
+    }
+
     fn get_tx_out_index_by_hash(&self, _tx_out_hash: &[u8; 32]) -> Result<u64, Error> {
         // Unused for these tests.
         unimplemented!()
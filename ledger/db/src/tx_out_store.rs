@@ -776,6 +776,7 @@ pub mod tx_out_store_tests {
                 target_key: target_key.into(),
                 public_key: public_key.into(),
                 e_account_hint: EncryptedFogHint::new(&[7u8; 128]),
+                e_memo: None,
             };
             tx_outs.push(tx_out);
         }
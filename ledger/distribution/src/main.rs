@@ -13,9 +13,14 @@ use protobuf::Message;
 use rusoto_core::{Region, RusotoError};
 use rusoto_s3::{PutObjectError, PutObjectRequest, S3Client, S3};
 use serde::{Deserialize, Serialize};
-use std::{fs, path::PathBuf, str::FromStr};
+use std::{
+    fs,
+    path::PathBuf,
+    str::FromStr,
+    time::{Duration, Instant},
+};
 use structopt::StructOpt;
-use transaction::{Block, BlockIndex, BlockSignature, RedactedTx};
+use transaction::{Block, BlockIndex, BlockSignature, BlockSignatureSet, RedactedTx};
 
 pub trait BlockHandler {
     fn handle_block(
@@ -23,6 +28,7 @@ pub trait BlockHandler {
         block: &Block,
         transactions: &[RedactedTx],
         signature: &Option<BlockSignature>,
+        signature_set: &BlockSignatureSet,
     );
 }
 
@@ -73,6 +79,20 @@ pub struct Config {
     /// State file, defaults to ~/.mc-ledger-distribution-state
     #[structopt(long)]
     pub state_file: Option<PathBuf>,
+
+    /// Number of validator signatures to wait for before distributing a block. Signatures from
+    /// other validators trickle in after the block has been appended to the local ledger.
+    #[structopt(long, default_value = "1")]
+    pub min_block_signatures: usize,
+
+    /// Maximum number of seconds to wait for `min_block_signatures` before distributing a block
+    /// with the signatures that are available.
+    #[structopt(long, default_value = "10", parse(try_from_str = parse_duration_in_seconds))]
+    pub block_signatures_timeout: Duration,
+}
+
+fn parse_duration_in_seconds(src: &str) -> Result<Duration, std::num::ParseIntError> {
+    Ok(Duration::from_secs(u64::from_str(src)?))
 }
 
 /// State file contents.
@@ -147,6 +167,7 @@ impl BlockHandler for S3BlockWriter {
         block: &Block,
         transactions: &[RedactedTx],
         signature: &Option<BlockSignature>,
+        signature_set: &BlockSignatureSet,
     ) {
         log::info!(self.logger, "S3: Handling block {}", block.index);
 
@@ -160,6 +181,9 @@ impl BlockHandler for S3BlockWriter {
             let bc_signature = blockchain::BlockSignature::from(signature);
             s3_block.set_signature(bc_signature);
         }
+        if !signature_set.is_empty() {
+            s3_block.set_signature_set(blockchain::BlockSignatureSet::from(signature_set));
+        }
 
         let dest = self
             .path
@@ -199,6 +223,7 @@ impl BlockHandler for LocalBlockWriter {
         block: &Block,
         transactions: &[RedactedTx],
         signature: &Option<BlockSignature>,
+        signature_set: &BlockSignatureSet,
     ) {
         log::info!(self.logger, "S3: Handling block {}", block.index);
 
@@ -212,6 +237,9 @@ impl BlockHandler for LocalBlockWriter {
             let bc_signature = blockchain::BlockSignature::from(signature);
             s3_block.set_signature(bc_signature);
         }
+        if !signature_set.is_empty() {
+            s3_block.set_signature_set(blockchain::BlockSignatureSet::from(signature_set));
+        }
 
         let bytes = s3_block
             .write_to_bytes()
@@ -294,6 +322,7 @@ fn main() {
         first_desired_block
     );
     let mut next_block_num = first_desired_block;
+    let mut waiting_for_signatures_since: Option<Instant> = None;
     loop {
         while let (Ok(transactions), Ok(block)) = (
             ledger_db.get_transactions_by_block(next_block_num),
@@ -315,7 +344,36 @@ fn main() {
                 }
             };
 
-            block_handler.handle_block(&block, &transactions, &signature);
+            let signature_set = match ledger_db.get_block_signature_set(next_block_num) {
+                Ok(signature_set) => signature_set,
+                Err(err) => {
+                    log::error!(
+                        logger,
+                        "Failed getting signature set for block #{}: {:?}",
+                        next_block_num,
+                        err
+                    );
+                    BlockSignatureSet::default()
+                }
+            };
+
+            // Give the other validators' signatures a chance to arrive.
+            if signature_set.len() < config.min_block_signatures {
+                let since = *waiting_for_signatures_since.get_or_insert_with(Instant::now);
+                if since.elapsed() < config.block_signatures_timeout {
+                    break;
+                }
+                log::warn!(
+                    logger,
+                    "Distributing block #{} with {} signatures, wanted {}",
+                    next_block_num,
+                    signature_set.len(),
+                    config.min_block_signatures
+                );
+            }
+            waiting_for_signatures_since = None;
+
+            block_handler.handle_block(&block, &transactions, &signature, &signature_set);
             next_block_num += 1;
 
             let state = StateData {
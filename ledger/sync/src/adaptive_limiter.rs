@@ -0,0 +1,211 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Adaptive limits on the number of concurrent requests made to each of a set of sources.
+//!
+//! Each source starts out with a small limit. The limit grows by one with every request that
+//! completes in a timely manner, and is halved with every request that fails or is unusually slow
+//! (additive increase, multiplicative decrease). Requests thus gravitate towards the sources that
+//! keep up with them, without overloading any of them.
+
+use std::{
+    cmp::Ordering,
+    sync::{
+        atomic::{AtomicUsize, Ordering as AtomicOrdering},
+        Condvar, Mutex,
+    },
+    time::Duration,
+};
+
+/// A request is considered slow if it takes this many times longer than the source's average.
+const SLOW_REQUEST_FACTOR: u32 = 4;
+
+/// Weight given to the latency of a new request when updating a source's average, as a fraction
+/// `1 / LATENCY_SMOOTHING`.
+const LATENCY_SMOOTHING: u32 = 8;
+
+#[derive(Clone, Debug)]
+struct SourceState {
+    /// Number of requests currently made to this source.
+    in_flight: usize,
+
+    /// Maximum number of concurrent requests this source is currently trusted with.
+    limit: usize,
+
+    /// Exponentially weighted average latency of successful requests.
+    average_latency: Option<Duration>,
+}
+
+impl SourceState {
+    /// Orders sources by the fraction of their limit that is in use.
+    fn cmp_load(&self, other: &Self) -> Ordering {
+        (self.in_flight * other.limit).cmp(&(other.in_flight * self.limit))
+    }
+}
+
+pub struct AdaptiveLimiter {
+    sources: Mutex<Vec<SourceState>>,
+    condvar: Condvar,
+
+    /// Upper bound on each source's limit.
+    max_limit: usize,
+
+    /// Where to start looking for a source, so that ties are broken in round-robin order.
+    next_source: AtomicUsize,
+}
+
+impl AdaptiveLimiter {
+    /// Creates a limiter for `num_sources` sources, each allowed `initial_limit` concurrent
+    /// requests to begin with, and never more than `max_limit`.
+    pub fn new(num_sources: usize, initial_limit: usize, max_limit: usize) -> Self {
+        let max_limit = max_limit.max(1);
+        let source = SourceState {
+            in_flight: 0,
+            limit: initial_limit.max(1).min(max_limit),
+            average_latency: None,
+        };
+
+        Self {
+            sources: Mutex::new(vec![source; num_sources]),
+            condvar: Condvar::new(),
+            max_limit,
+            next_source: AtomicUsize::new(0),
+        }
+    }
+
+    /// Waits until a source can take another request, and returns its index. The source with the
+    /// smallest fraction of its limit in use is picked.
+    ///
+    /// Every call must be followed by a call to either `record_success` or `record_failure`.
+    pub fn acquire(&self) -> usize {
+        let mut sources = self.sources.lock().expect("mutex poisoned");
+        assert!(!sources.is_empty(), "No sources to acquire");
+
+        loop {
+            let num_sources = sources.len();
+            let start = self.next_source.fetch_add(1, AtomicOrdering::SeqCst) % num_sources;
+            let source_index = (0..num_sources)
+                .map(|offset| (start + offset) % num_sources)
+                .filter(|index| sources[*index].in_flight < sources[*index].limit)
+                .min_by(|a, b| sources[*a].cmp_load(&sources[*b]));
+
+            if let Some(source_index) = source_index {
+                sources[source_index].in_flight += 1;
+                return source_index;
+            }
+
+            sources = self.condvar.wait(sources).expect("condvar wait failed");
+        }
+    }
+
+    /// Records that a request to `source_index` succeeded after `latency`.
+    pub fn record_success(&self, source_index: usize, latency: Duration) {
+        let mut sources = self.sources.lock().expect("mutex poisoned");
+        let source = &mut sources[source_index];
+        source.in_flight -= 1;
+
+        match source.average_latency {
+            Some(average_latency) if latency > average_latency * SLOW_REQUEST_FACTOR => {
+                source.limit = (source.limit / 2).max(1);
+            }
+            _ => {
+                source.limit = (source.limit + 1).min(self.max_limit);
+            }
+        }
+        source.average_latency = Some(match source.average_latency {
+            Some(average_latency) => {
+                (average_latency * (LATENCY_SMOOTHING - 1) + latency) / LATENCY_SMOOTHING
+            }
+            None => latency,
+        });
+
+        self.condvar.notify_all();
+    }
+
+    /// Records that a request to `source_index` failed.
+    pub fn record_failure(&self, source_index: usize) {
+        let mut sources = self.sources.lock().expect("mutex poisoned");
+        let source = &mut sources[source_index];
+        source.in_flight -= 1;
+        source.limit = (source.limit / 2).max(1);
+
+        self.condvar.notify_all();
+    }
+
+    /// The current limit of each source.
+    #[cfg(test)]
+    pub fn limits(&self) -> Vec<usize> {
+        self.sources
+            .lock()
+            .expect("mutex poisoned")
+            .iter()
+            .map(|source| source.limit)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{sync::Arc, thread};
+
+    #[test]
+    // Requests should be spread across sources according to their spare capacity.
+    fn test_acquire_least_loaded() {
+        let limiter = AdaptiveLimiter::new(2, 2, 8);
+
+        let first = limiter.acquire();
+        let second = limiter.acquire();
+        assert_ne!(first, second);
+
+        let third = limiter.acquire();
+        let fourth = limiter.acquire();
+        assert_ne!(third, fourth);
+    }
+
+    #[test]
+    // Limits should grow on success, and shrink on failure and on slow requests.
+    fn test_limits_adapt() {
+        let limiter = AdaptiveLimiter::new(1, 2, 3);
+
+        // Timely requests grow the limit, up to the maximum.
+        for _ in 0..2 {
+            let source_index = limiter.acquire();
+            limiter.record_success(source_index, Duration::from_millis(10));
+        }
+        assert_eq!(limiter.limits(), vec![3]);
+
+        // A request much slower than the average halves the limit.
+        let source_index = limiter.acquire();
+        limiter.record_success(source_index, Duration::from_secs(1));
+        assert_eq!(limiter.limits(), vec![1]);
+
+        // A failed request halves the limit, which never drops below one.
+        let source_index = limiter.acquire();
+        limiter.record_failure(source_index);
+        assert_eq!(limiter.limits(), vec![1]);
+
+        for _ in 0..2 {
+            let source_index = limiter.acquire();
+            limiter.record_success(source_index, Duration::from_millis(10));
+        }
+        assert_eq!(limiter.limits(), vec![3]);
+
+        let source_index = limiter.acquire();
+        limiter.record_failure(source_index);
+        assert_eq!(limiter.limits(), vec![1]);
+    }
+
+    #[test]
+    // `acquire` should block while every source is at its limit.
+    fn test_acquire_waits_for_capacity() {
+        let limiter = Arc::new(AdaptiveLimiter::new(1, 1, 1));
+        assert_eq!(limiter.acquire(), 0);
+
+        let thread_limiter = limiter.clone();
+        let handle = thread::spawn(move || thread_limiter.acquire());
+
+        thread::sleep(Duration::from_millis(50));
+        limiter.record_success(0, Duration::from_millis(10));
+        assert_eq!(handle.join().unwrap(), 0);
+    }
+}
@@ -0,0 +1,532 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Implementation of the `TransactionsFetcher` trait that wraps another fetcher, and keeps the
+//! blocks it fetched in a size-bounded on-disk cache. Repeat requests, e.g. from sync attempts
+//! that are retried, restarted processes, or several processes on one host sharing the cache
+//! directory, are served locally instead of hitting the archive again.
+//!
+//! Only verified blocks are cached: blocks whose transactions were requested for a block already
+//! agreed upon by a quorum of peers, and that match its contents hash. Blocks fetched by index are
+//! served from the cache when possible, but not cached themselves, since the caller has yet to
+//! verify them. Cached transactions are checked against their block's contents hash on every
+//! read, and blocks that fail the check are evicted and fetched again.
+
+use crate::{counters, transactions_fetcher_trait::TransactionsFetcher};
+use common::{
+    logger::{log, Logger},
+    ResponderId,
+};
+use filetime::FileTime;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+use transaction::{hash_block_contents, Block, BlockIndex, RedactedTx};
+
+/// Extension of the files blocks are cached in.
+const CACHE_FILE_EXTENSION: &str = "block";
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+struct CachedBlock {
+    block: Block,
+    transactions: Vec<RedactedTx>,
+}
+
+/// A block cached in the directory.
+struct CacheFile {
+    /// When the block was last used, i.e. the modification time of its file.
+    last_use: SystemTime,
+    block_index: BlockIndex,
+    size: u64,
+}
+
+/// The cache directory is the only record of what is cached, so that processes sharing it see
+/// each other's blocks. Each use of a block sets its file's modification time, and eviction scans
+/// the directory, so that the size limit and recency apply to the directory as a whole.
+pub struct CachingTransactionsFetcher<TF: TransactionsFetcher> {
+    /// Fetcher used on cache misses.
+    inner: TF,
+
+    /// Directory the blocks are cached in.
+    cache_dir: PathBuf,
+
+    /// Blocks are evicted, least recently used first, to keep the cache below this many bytes.
+    max_size: u64,
+
+    logger: Logger,
+}
+
+impl<TF: TransactionsFetcher> CachingTransactionsFetcher<TF> {
+    /// Wraps `inner` with a cache of at most `max_size` bytes in `cache_dir`. Blocks already in
+    /// `cache_dir` are reused.
+    pub fn new(
+        inner: TF,
+        cache_dir: &Path,
+        max_size: u64,
+        logger: Logger,
+    ) -> Result<Self, io::Error> {
+        fs::create_dir_all(cache_dir)?;
+
+        let caching_transactions_fetcher = Self {
+            inner,
+            cache_dir: cache_dir.to_path_buf(),
+            max_size,
+            logger,
+        };
+
+        let cache_files = caching_transactions_fetcher.cache_files()?;
+        log::info!(
+            caching_transactions_fetcher.logger,
+            "Transactions cache {:?} holds {} blocks ({} bytes)",
+            cache_dir,
+            cache_files.len(),
+            cache_files.iter().map(|file| file.size).sum::<u64>()
+        );
+
+        caching_transactions_fetcher.evict();
+        Ok(caching_transactions_fetcher)
+    }
+
+    fn path(&self, block_index: BlockIndex) -> PathBuf {
+        self.cache_dir
+            .join(format!("{:020}.{}", block_index, CACHE_FILE_EXTENSION))
+    }
+
+    /// The blocks currently cached in the directory, by any process sharing it.
+    fn cache_files(&self) -> Result<Vec<CacheFile>, io::Error> {
+        let mut cache_files = Vec::new();
+        for entry in fs::read_dir(&self.cache_dir)? {
+            let path = entry?.path();
+            let block_index = match block_index_from_path(&path) {
+                Some(block_index) => block_index,
+                None => continue,
+            };
+            // The file may have been evicted by another process since the directory was read.
+            let metadata = match fs::metadata(&path) {
+                Ok(metadata) => metadata,
+                Err(ref err) if err.kind() == io::ErrorKind::NotFound => continue,
+                Err(err) => return Err(err),
+            };
+            cache_files.push(CacheFile {
+                last_use: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+                block_index,
+                size: metadata.len(),
+            });
+        }
+        Ok(cache_files)
+    }
+
+    /// Reads a block from the cache, if it is there and its transactions match its contents hash.
+    /// Cached blocks that do not are evicted.
+    fn get_cached(&self, block_index: BlockIndex) -> Option<CachedBlock> {
+        let path = self.path(block_index);
+        let bytes = match fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(err) => {
+                if err.kind() != io::ErrorKind::NotFound {
+                    log::warn!(
+                        self.logger,
+                        "Failed reading cached block {:?}: {}",
+                        path,
+                        err
+                    );
+                }
+                return None;
+            }
+        };
+
+        // Cache files are not trusted, since anything with access to the directory could have
+        // corrupted them.
+        let cached_block = mcserial::deserialize::<CachedBlock>(&bytes)
+            .map_err(|err| err.to_string())
+            .and_then(|cached_block| {
+                if cached_block.block.index != block_index {
+                    Err("block index mismatch".to_string())
+                } else if hash_block_contents(&cached_block.transactions)
+                    != cached_block.block.contents_hash
+                {
+                    Err("contents hash mismatch".to_string())
+                } else {
+                    Ok(cached_block)
+                }
+            });
+
+        match cached_block {
+            Ok(cached_block) => {
+                self.touch(&path);
+                Some(cached_block)
+            }
+            Err(err) => {
+                log::warn!(
+                    self.logger,
+                    "Evicting invalid cached block {:?}: {}",
+                    path,
+                    err
+                );
+                self.remove_file(block_index);
+                None
+            }
+        }
+    }
+
+    /// Writes a verified block to the cache, evicting other blocks if needed.
+    fn put_cached(&self, cached_block: &CachedBlock) {
+        let block_index = cached_block.block.index;
+        let path = self.path(block_index);
+
+        let result = mcserial::serialize(cached_block)
+            .map_err(|err| err.to_string())
+            .and_then(|bytes| {
+                // Write to a temporary file first, so that other processes sharing the directory
+                // never read a half written block.
+                let tmp_path = path.with_extension(format!("{}.tmp", std::process::id()));
+                fs::write(&tmp_path, &bytes).map_err(|err| err.to_string())?;
+                fs::rename(&tmp_path, &path).map_err(|err| err.to_string())
+            });
+
+        match result {
+            Ok(()) => {
+                self.touch(&path);
+                self.evict();
+            }
+            Err(err) => {
+                log::warn!(self.logger, "Failed caching block {:?}: {}", path, err);
+            }
+        }
+    }
+
+    /// Records that the block cached at `path` was just used.
+    fn touch(&self, path: &Path) {
+        // Set explicitly, since the modification time of a write is too coarse to order blocks
+        // cached in quick succession.
+        let now = FileTime::from_system_time(SystemTime::now());
+        if let Err(err) = filetime::set_file_mtime(path, now) {
+            log::debug!(self.logger, "Failed touching {:?}: {}", path, err);
+        }
+    }
+
+    /// Evicts the least recently used blocks until the cache directory fits in `max_size`.
+    fn evict(&self) {
+        let mut cache_files = match self.cache_files() {
+            Ok(cache_files) => cache_files,
+            Err(err) => {
+                log::warn!(
+                    self.logger,
+                    "Failed listing transactions cache {:?}: {}",
+                    self.cache_dir,
+                    err
+                );
+                return;
+            }
+        };
+        cache_files.sort_by_key(|file| (file.last_use, file.block_index));
+
+        let mut total_size: u64 = cache_files.iter().map(|file| file.size).sum();
+        for file in cache_files {
+            if total_size <= self.max_size {
+                break;
+            }
+            self.remove_file(file.block_index);
+            total_size -= file.size;
+        }
+    }
+
+    fn remove_file(&self, block_index: BlockIndex) {
+        let path = self.path(block_index);
+        if let Err(err) = fs::remove_file(&path) {
+            // Another process sharing the directory may have evicted it first.
+            if err.kind() != io::ErrorKind::NotFound {
+                log::warn!(self.logger, "Failed evicting {:?}: {}", path, err);
+            }
+        }
+    }
+}
+
+/// The index of the block cached at `path`, if `path` is a cache file.
+fn block_index_from_path(path: &Path) -> Option<BlockIndex> {
+    if path.extension()? != CACHE_FILE_EXTENSION {
+        return None;
+    }
+    path.file_stem()?.to_str()?.parse().ok()
+}
+
+impl<TF: TransactionsFetcher> TransactionsFetcher for CachingTransactionsFetcher<TF> {
+    type Error = TF::Error;
+
+    fn get_transactions_by_block(
+        &self,
+        safe_responder_ids: &[ResponderId],
+        block: &Block,
+    ) -> Result<Vec<RedactedTx>, Self::Error> {
+        if let Some(cached_block) = self.get_cached(block.index) {
+            if cached_block.block == *block {
+                counters::TRANSACTIONS_CACHE_HIT_COUNT.inc();
+                return Ok(cached_block.transactions);
+            }
+        }
+        counters::TRANSACTIONS_CACHE_MISS_COUNT.inc();
+
+        let transactions = self
+            .inner
+            .get_transactions_by_block(safe_responder_ids, block)?;
+
+        // `block` is known good, so the transactions are too if they match its contents hash.
+        if hash_block_contents(&transactions) == block.contents_hash {
+            self.put_cached(&CachedBlock {
+                block: block.clone(),
+                transactions: transactions.clone(),
+            });
+        }
+
+        Ok(transactions)
+    }
+
+    fn get_block_by_index(
+        &self,
+        block_index: BlockIndex,
+    ) -> Result<(Block, Vec<RedactedTx>), Self::Error> {
+        if let Some(cached_block) = self.get_cached(block_index) {
+            counters::TRANSACTIONS_CACHE_HIT_COUNT.inc();
+            return Ok((cached_block.block, cached_block.transactions));
+        }
+        counters::TRANSACTIONS_CACHE_MISS_COUNT.inc();
+
+        self.inner.get_block_by_index(block_index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::MockTransactionsFetcher;
+    use common::logger::test_with_logger;
+    use ledger_db::{
+        test_utils::{get_mock_ledger, MockLedger},
+        Ledger,
+    };
+    use tempdir::TempDir;
+
+    /// Size of the cache file of `block_index`.
+    fn cached_size(cache_dir: &Path, block_index: BlockIndex) -> u64 {
+        fs::metadata(cache_dir.join(format!("{:020}.{}", block_index, CACHE_FILE_EXTENSION)))
+            .unwrap()
+            .len()
+    }
+
+    #[test_with_logger]
+    // Verified blocks should be served from the cache, across instances.
+    fn test_get_transactions_by_block_cached(logger: Logger) {
+        let dir = TempDir::new("transactions_cache").unwrap();
+        let ledger = get_mock_ledger(10);
+        let block = ledger.get_block(5).unwrap();
+        let transactions = ledger.get_transactions_by_block(5).unwrap();
+
+        let fetcher = CachingTransactionsFetcher::new(
+            MockTransactionsFetcher::new(ledger.clone()),
+            dir.path(),
+            u64::max_value(),
+            logger.clone(),
+        )
+        .unwrap();
+        assert_eq!(
+            fetcher.get_transactions_by_block(&[], &block).unwrap(),
+            transactions
+        );
+
+        // A fetcher whose source has no blocks can only serve cached ones.
+        let fetcher = CachingTransactionsFetcher::new(
+            MockTransactionsFetcher::new(MockLedger::default()),
+            dir.path(),
+            u64::max_value(),
+            logger,
+        )
+        .unwrap();
+        assert_eq!(
+            fetcher.get_transactions_by_block(&[], &block).unwrap(),
+            transactions
+        );
+        assert_eq!(
+            fetcher.get_block_by_index(5).unwrap(),
+            (block, transactions)
+        );
+        assert!(fetcher
+            .get_transactions_by_block(&[], &ledger.get_block(6).unwrap())
+            .is_err());
+        assert!(fetcher.get_block_by_index(6).is_err());
+    }
+
+    #[test_with_logger]
+    // Blocks fetched by index have yet to be verified, and should not be cached.
+    fn test_get_block_by_index_not_cached(logger: Logger) {
+        let dir = TempDir::new("transactions_cache").unwrap();
+        let ledger = get_mock_ledger(10);
+
+        let fetcher = CachingTransactionsFetcher::new(
+            MockTransactionsFetcher::new(ledger.clone()),
+            dir.path(),
+            u64::max_value(),
+            logger,
+        )
+        .unwrap();
+        assert_eq!(
+            fetcher.get_block_by_index(5).unwrap(),
+            (
+                ledger.get_block(5).unwrap(),
+                ledger.get_transactions_by_block(5).unwrap()
+            )
+        );
+        assert!(fetcher.get_cached(5).is_none());
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[test_with_logger]
+    // The least recently used blocks should be evicted to keep the cache within its size.
+    fn test_eviction(logger: Logger) {
+        let dir = TempDir::new("transactions_cache").unwrap();
+        let ledger = get_mock_ledger(10);
+
+        // Find out how much room blocks take.
+        let fetcher = CachingTransactionsFetcher::new(
+            MockTransactionsFetcher::new(ledger.clone()),
+            dir.path(),
+            u64::max_value(),
+            logger.clone(),
+        )
+        .unwrap();
+        for block_index in 1..4 {
+            let block = ledger.get_block(block_index).unwrap();
+            fetcher.get_transactions_by_block(&[], &block).unwrap();
+        }
+        let max_size: u64 = (2..4)
+            .map(|block_index| cached_size(dir.path(), block_index))
+            .sum();
+
+        // Reopening the cache with room for two blocks evicts the oldest one.
+        let fetcher = CachingTransactionsFetcher::new(
+            MockTransactionsFetcher::new(ledger.clone()),
+            dir.path(),
+            max_size,
+            logger,
+        )
+        .unwrap();
+        assert!(fetcher.get_cached(1).is_none());
+        assert!(fetcher.get_cached(2).is_some());
+
+        // Block 3 is now the least recently used.
+        let block = ledger.get_block(1).unwrap();
+        fetcher.get_transactions_by_block(&[], &block).unwrap();
+        assert!(fetcher.get_cached(3).is_none());
+        assert!(fetcher.get_cached(1).is_some());
+        assert!(fetcher.get_cached(2).is_some());
+    }
+
+    #[test_with_logger]
+    // Fetchers sharing a directory should serve each other's blocks, and keep the directory as a
+    // whole within its size.
+    fn test_shared_cache_dir(logger: Logger) {
+        let dir = TempDir::new("transactions_cache").unwrap();
+        let ledger = get_mock_ledger(10);
+        let max_size: u64 = (1..3)
+            .map(|block_index| {
+                let cached_block = CachedBlock {
+                    block: ledger.get_block(block_index).unwrap(),
+                    transactions: ledger.get_transactions_by_block(block_index).unwrap(),
+                };
+                mcserial::serialize(&cached_block).unwrap().len() as u64
+            })
+            .sum();
+
+        let fetcher1 = CachingTransactionsFetcher::new(
+            MockTransactionsFetcher::new(ledger.clone()),
+            dir.path(),
+            max_size,
+            logger.clone(),
+        )
+        .unwrap();
+        // Created before `fetcher1` caches anything, with a source that has no blocks.
+        let fetcher2 = CachingTransactionsFetcher::new(
+            MockTransactionsFetcher::new(MockLedger::default()),
+            dir.path(),
+            max_size,
+            logger.clone(),
+        )
+        .unwrap();
+
+        let block = ledger.get_block(1).unwrap();
+        let transactions = ledger.get_transactions_by_block(1).unwrap();
+        fetcher1.get_transactions_by_block(&[], &block).unwrap();
+        assert_eq!(
+            fetcher2.get_transactions_by_block(&[], &block).unwrap(),
+            transactions
+        );
+        assert_eq!(
+            fetcher2.get_block_by_index(1).unwrap(),
+            (block, transactions)
+        );
+
+        // Blocks cached by one fetcher count towards the size limit of the others.
+        let fetcher3 = CachingTransactionsFetcher::new(
+            MockTransactionsFetcher::new(ledger.clone()),
+            dir.path(),
+            max_size,
+            logger,
+        )
+        .unwrap();
+        fetcher3
+            .get_transactions_by_block(&[], &ledger.get_block(2).unwrap())
+            .unwrap();
+        fetcher1
+            .get_transactions_by_block(&[], &ledger.get_block(3).unwrap())
+            .unwrap();
+        let total_size: u64 = fetcher1
+            .cache_files()
+            .unwrap()
+            .iter()
+            .map(|file| file.size)
+            .sum();
+        assert!(total_size <= max_size);
+        assert!(fetcher2.get_cached(3).is_some());
+    }
+
+    #[test_with_logger]
+    // Cached blocks whose transactions do not match the contents hash should be evicted and
+    // fetched again.
+    fn test_tampered_block_refetched(logger: Logger) {
+        let dir = TempDir::new("transactions_cache").unwrap();
+        let ledger = get_mock_ledger(10);
+        let block = ledger.get_block(5).unwrap();
+        let transactions = ledger.get_transactions_by_block(5).unwrap();
+
+        let fetcher = CachingTransactionsFetcher::new(
+            MockTransactionsFetcher::new(ledger.clone()),
+            dir.path(),
+            u64::max_value(),
+            logger,
+        )
+        .unwrap();
+        fetcher.get_transactions_by_block(&[], &block).unwrap();
+
+        // Swap in another block's transactions.
+        let path = fetcher.path(5);
+        let tampered_block = CachedBlock {
+            block: block.clone(),
+            transactions: ledger.get_transactions_by_block(6).unwrap(),
+        };
+        fs::write(&path, mcserial::serialize(&tampered_block).unwrap()).unwrap();
+        assert!(fetcher.get_cached(5).is_none());
+        assert!(!path.exists());
+
+        fs::write(&path, mcserial::serialize(&tampered_block).unwrap()).unwrap();
+        assert_eq!(
+            fetcher.get_transactions_by_block(&[], &block).unwrap(),
+            transactions
+        );
+        assert_eq!(
+            fetcher.get_block_by_index(5).unwrap(),
+            (block, transactions)
+        );
+        assert!(fetcher.get_cached(5).is_some());
+    }
+}
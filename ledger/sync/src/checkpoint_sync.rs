@@ -0,0 +1,634 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Fast initial sync up to a trusted checkpoint.
+//!
+//! Rather than waiting for a quorum of peers to agree on every block, blocks up to the checkpoint
+//! are downloaded in bulk from the `TransactionsFetcher` and trusted because they hash-chain to
+//! the checkpoint's block ID:
+//!
+//! 1. Blocks are downloaded from the checkpoint down to the local ledger, checking that each
+//!    block's ID agrees with its contents and is the parent ID of the block above it. Only the IDs
+//!    of the verified blocks are kept in memory, so that a ledger far behind the checkpoint does
+//!    not hold all of the blocks in between.
+//! 2. The verified blocks are downloaded again from the local ledger up, and appended once their
+//!    IDs and contents match the verified chain.
+
+use crate::{ledger_sync_error::LedgerSyncError, transactions_fetcher_trait::TransactionsFetcher};
+use common::logger::{log, Logger};
+use ledger_db::Ledger;
+use std::{
+    cmp,
+    collections::BTreeMap,
+    convert::TryFrom,
+    ops::Range,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+use transaction::{compute_block_id, hash_block_contents, Block, BlockID, BlockIndex, RedactedTx};
+
+/// Maximal number of blocks downloaded at the same time.
+const MAX_FETCH_WORKERS: u64 = 10;
+
+/// A block trusted without the agreement of a quorum of peers, e.g. one published by the network's
+/// operators.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Checkpoint {
+    pub block_index: BlockIndex,
+    pub block_id: BlockID,
+}
+
+impl FromStr for Checkpoint {
+    type Err = String;
+
+    /// Parses a checkpoint given as the block index and hex-encoded block ID, e.g. 1234:7a3f...
+    fn from_str(src: &str) -> Result<Self, Self::Err> {
+        let mut parts = src.splitn(2, ':');
+        let block_index = parts
+            .next()
+            .and_then(|index| BlockIndex::from_str(index).ok())
+            .ok_or_else(|| format!("Invalid checkpoint block index in {}", src))?;
+        let block_id_bytes = parts
+            .next()
+            .and_then(|id| hex::decode(id).ok())
+            .ok_or_else(|| format!("Invalid checkpoint block ID in {}", src))?;
+        let block_id = BlockID::try_from(&block_id_bytes[..])
+            .map_err(|err| format!("Invalid checkpoint block ID in {}: {:?}", src, err))?;
+        Ok(Self {
+            block_index,
+            block_id,
+        })
+    }
+}
+
+/// Progress of syncing up to a checkpoint.
+pub struct CheckpointSync {
+    checkpoint: Checkpoint,
+
+    /// Indexes and IDs of the blocks verified against the checkpoint, from the checkpoint down.
+    /// Blocks are popped off the end as they are appended.
+    verified_block_ids: Vec<(BlockIndex, BlockID)>,
+
+    /// The ID the highest unverified block must have.
+    expected_block_id: BlockID,
+
+    /// Whether the verified blocks reach down to the local ledger.
+    chain_complete: bool,
+}
+
+impl CheckpointSync {
+    pub fn new(checkpoint: Checkpoint) -> Self {
+        let expected_block_id = checkpoint.block_id.clone();
+        Self {
+            checkpoint,
+            verified_block_ids: Vec::new(),
+            expected_block_id,
+            chain_complete: false,
+        }
+    }
+
+    pub fn checkpoint(&self) -> &Checkpoint {
+        &self.checkpoint
+    }
+
+    /// Whether the ledger has reached the checkpoint.
+    ///
+    /// Returns an error if the ledger already contains a different block at the checkpoint's
+    /// index.
+    pub fn is_reached<L: Ledger>(&self, ledger: &L) -> Result<bool, LedgerSyncError> {
+        if ledger.num_blocks()? <= self.checkpoint.block_index {
+            return Ok(false);
+        }
+        if ledger.get_block(self.checkpoint.block_index)?.id != self.checkpoint.block_id {
+            return Err(LedgerSyncError::CheckpointMismatch);
+        }
+        Ok(true)
+    }
+
+    /// Make progress towards the checkpoint: verify the next `batch_size` blocks of the chain
+    /// down from the checkpoint, or, once the chain reaches the ledger, append the next
+    /// `batch_size` blocks to it.
+    ///
+    /// Returns the verified blocks to append next, in order. There are none while the chain is
+    /// still being verified.
+    pub fn sync_next_batch<L: Ledger, TF: TransactionsFetcher + 'static>(
+        &mut self,
+        ledger: &L,
+        transactions_fetcher: Arc<TF>,
+        batch_size: u64,
+        timeout: Duration,
+        logger: &Logger,
+    ) -> Result<Vec<(Block, Vec<RedactedTx>)>, LedgerSyncError> {
+        if self.chain_complete {
+            self.get_blocks_to_append(ledger, transactions_fetcher, batch_size, timeout, logger)
+        } else {
+            self.verify_next_blocks(ledger, transactions_fetcher, batch_size, timeout, logger)?;
+            Ok(Vec::new())
+        }
+    }
+
+    // Verify the next blocks down from the checkpoint. Nothing is recorded unless all of them
+    // are valid, so that a bad transactions source does not corrupt the verified chain.
+    fn verify_next_blocks<L: Ledger, TF: TransactionsFetcher + 'static>(
+        &mut self,
+        ledger: &L,
+        transactions_fetcher: Arc<TF>,
+        batch_size: u64,
+        timeout: Duration,
+        logger: &Logger,
+    ) -> Result<(), LedgerSyncError> {
+        let num_blocks = ledger.num_blocks()?;
+        let top = self.checkpoint.block_index + 1 - self.verified_block_ids.len() as u64;
+        let bottom = cmp::max(num_blocks, top.saturating_sub(batch_size));
+        log::debug!(
+            logger,
+            "Verifying blocks [{}, {}) against checkpoint at block {}",
+            bottom,
+            top,
+            self.checkpoint.block_index
+        );
+
+        let blocks = get_blocks_by_index(transactions_fetcher, bottom..top, timeout, logger)?;
+
+        let mut expected_block_id = self.expected_block_id.clone();
+        for (block, transactions) in blocks.iter().rev() {
+            if block.id != expected_block_id || !is_block_valid(block, transactions) {
+                log::error!(
+                    logger,
+                    "Block {} does not chain to checkpoint {:?}",
+                    block.index,
+                    self.checkpoint
+                );
+                return Err(LedgerSyncError::CheckpointMismatch);
+            }
+            expected_block_id = block.parent_id.clone();
+        }
+
+        if bottom == num_blocks {
+            let last_block = ledger.get_block(num_blocks - 1)?;
+            if last_block.id != expected_block_id {
+                log::error!(
+                    logger,
+                    "Checkpoint {:?} does not chain to local block {}",
+                    self.checkpoint,
+                    last_block.index
+                );
+                return Err(LedgerSyncError::CheckpointMismatch);
+            }
+            log::info!(
+                logger,
+                "Verified {} blocks down from checkpoint at block {}",
+                self.verified_block_ids.len() + blocks.len(),
+                self.checkpoint.block_index
+            );
+            self.chain_complete = true;
+        }
+
+        self.verified_block_ids.extend(
+            blocks
+                .into_iter()
+                .rev()
+                .map(|(block, _transactions)| (block.index, block.id)),
+        );
+        self.expected_block_id = expected_block_id;
+        Ok(())
+    }
+
+    // Download the next verified blocks, in order, to append to the ledger. Blocks the ledger
+    // already has, e.g. appended by another sync, are skipped. If the ledger is missing blocks that
+    // were taken before, e.g. because appending them failed, the chain is verified again.
+    fn get_blocks_to_append<L: Ledger, TF: TransactionsFetcher + 'static>(
+        &mut self,
+        ledger: &L,
+        transactions_fetcher: Arc<TF>,
+        batch_size: u64,
+        timeout: Duration,
+        logger: &Logger,
+    ) -> Result<Vec<(Block, Vec<RedactedTx>)>, LedgerSyncError> {
+        let num_blocks = ledger.num_blocks()?;
+        while let Some((block_index, _block_id)) = self.verified_block_ids.last() {
+            if *block_index >= num_blocks {
+                break;
+            }
+            self.verified_block_ids.pop();
+        }
+
+        let chain_intact = match self.verified_block_ids.last() {
+            Some((block_index, _block_id)) => *block_index == num_blocks,
+            None => num_blocks > self.checkpoint.block_index,
+        };
+        if !chain_intact {
+            *self = Self::new(self.checkpoint.clone());
+            return Ok(Vec::new());
+        }
+        if self.verified_block_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let num_kept = self
+            .verified_block_ids
+            .len()
+            .saturating_sub(batch_size as usize);
+        let top = self.verified_block_ids[num_kept].0 + 1;
+        let blocks = get_blocks_by_index(transactions_fetcher, num_blocks..top, timeout, logger)?;
+
+        // The verified IDs commit to the blocks' contents, so the downloaded blocks are the
+        // verified ones if they match them.
+        let block_ids = self.verified_block_ids[num_kept..].iter().rev();
+        for ((block, transactions), (_block_index, block_id)) in blocks.iter().zip(block_ids) {
+            if block.id != *block_id {
+                log::error!(
+                    logger,
+                    "Block {} does not match the chain verified against checkpoint {:?}",
+                    block.index,
+                    self.checkpoint
+                );
+                return Err(LedgerSyncError::InvalidBlockId);
+            }
+            if !is_block_valid(block, transactions) {
+                log::error!(
+                    logger,
+                    "Transactions of block {} do not match its contents hash",
+                    block.index
+                );
+                return Err(LedgerSyncError::TransactionsAndBlockMismatch);
+            }
+        }
+
+        self.verified_block_ids.truncate(num_kept);
+        Ok(blocks)
+    }
+}
+
+/// Whether the block's ID agrees with the block and its transactions.
+fn is_block_valid(block: &Block, transactions: &[RedactedTx]) -> bool {
+    let contents_hash = hash_block_contents(transactions);
+    let derived_block_id = compute_block_id(
+        block.version,
+        &block.parent_id,
+        block.index,
+        &block.root_element,
+        &contents_hash,
+    );
+    block.id == derived_block_id
+}
+
+/// Gets the blocks in `range` and their transactions, in order.
+///
+/// Blocks are fetched concurrently, and fetches are retried until `timeout` elapses. Returns an
+/// error if any block could not be fetched by then.
+fn get_blocks_by_index<TF: TransactionsFetcher + 'static>(
+    transactions_fetcher: Arc<TF>,
+    range: Range<BlockIndex>,
+    timeout: Duration,
+    logger: &Logger,
+) -> Result<Vec<(Block, Vec<RedactedTx>)>, LedgerSyncError> {
+    type ResultsMap = BTreeMap<BlockIndex, (Block, Vec<RedactedTx>)>;
+
+    // Workers exit once the queue is drained.
+    let (sender, receiver) = crossbeam_channel::unbounded();
+    for block_index in range.clone() {
+        sender.send(block_index).expect("failed sending to channel");
+    }
+    drop(sender);
+
+    let results = Arc::new(Mutex::new(ResultsMap::new()));
+    let deadline = Instant::now() + timeout;
+
+    let num_workers = cmp::min(MAX_FETCH_WORKERS, range.end - range.start);
+    let thread_handles: Vec<_> = (0..num_workers)
+        .map(|worker_num| {
+            let thread_results = results.clone();
+            let thread_receiver = receiver.clone();
+            let thread_logger = logger.clone();
+            let thread_transactions_fetcher = transactions_fetcher.clone();
+
+            thread::Builder::new()
+                .name(format!("GetBlocksByIndex:{}", worker_num))
+                .spawn(move || {
+                    for block_index in thread_receiver.iter() {
+                        loop {
+                            if Instant::now() > deadline {
+                                log::error!(
+                                    thread_logger,
+                                    "Worker {} giving up on block {}: deadline exceeded",
+                                    worker_num,
+                                    block_index,
+                                );
+                                return;
+                            }
+
+                            match thread_transactions_fetcher.get_block_by_index(block_index) {
+                                Ok((block, transactions)) if block.index == block_index => {
+                                    thread_results
+                                        .lock()
+                                        .expect("mutex poisoned")
+                                        .insert(block_index, (block, transactions));
+                                    break;
+                                }
+                                Ok((block, _transactions)) => log::error!(
+                                    thread_logger,
+                                    "Worker {} asked for block {}, got block {}",
+                                    worker_num,
+                                    block_index,
+                                    block.index
+                                ),
+                                Err(err) => log::error!(
+                                    thread_logger,
+                                    "Worker {} failed getting block {}: {:?}",
+                                    worker_num,
+                                    block_index,
+                                    err
+                                ),
+                            }
+                        }
+                    }
+                })
+                .expect("Failed spawning GetBlocksByIndex thread!")
+        })
+        .collect();
+
+    for thread_handle in thread_handles.into_iter() {
+        if let Err(err) = thread_handle.join() {
+            log::error!(
+                logger,
+                "Failed joining get_blocks_by_index worker thread: {:?}",
+                err
+            );
+        }
+    }
+
+    let results = std::mem::take(&mut *results.lock().expect("mutex poisoned"));
+    if results.len() as u64 != range.end - range.start {
+        return Err(LedgerSyncError::BlocksUnavailable);
+    }
+    Ok(results.into_iter().map(|(_index, block)| block).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::MockTransactionsFetcher;
+    use common::{logger::test_with_logger, ResponderId};
+    use ledger_db::test_utils::get_mock_ledger;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+    const TIMEOUT: Duration = Duration::from_secs(10);
+
+    // Counts the blocks fetched through it.
+    struct CountingTransactionsFetcher<L: Ledger + Sync> {
+        inner: MockTransactionsFetcher<L>,
+        num_blocks_fetched: AtomicUsize,
+    }
+
+    impl<L: Ledger + Sync> TransactionsFetcher for CountingTransactionsFetcher<L> {
+        type Error = String;
+
+        fn get_transactions_by_block(
+            &self,
+            safe_responder_ids: &[ResponderId],
+            block: &Block,
+        ) -> Result<Vec<RedactedTx>, Self::Error> {
+            self.inner
+                .get_transactions_by_block(safe_responder_ids, block)
+        }
+
+        fn get_block_by_index(
+            &self,
+            block_index: BlockIndex,
+        ) -> Result<(Block, Vec<RedactedTx>), Self::Error> {
+            self.num_blocks_fetched.fetch_add(1, Ordering::SeqCst);
+            self.inner.get_block_by_index(block_index)
+        }
+    }
+
+    #[test_with_logger]
+    // Blocks up to the checkpoint are verified, then appended, a batch at a time. Each block is
+    // downloaded once to verify it, and once more to append it.
+    fn test_sync_to_checkpoint(logger: Logger) {
+        let source_ledger = get_mock_ledger(25);
+        let transactions_fetcher = Arc::new(CountingTransactionsFetcher {
+            inner: MockTransactionsFetcher::new(source_ledger.clone()),
+            num_blocks_fetched: AtomicUsize::new(0),
+        });
+        let mut local_ledger = get_mock_ledger(3);
+
+        let checkpoint = Checkpoint {
+            block_index: 20,
+            block_id: source_ledger.get_block(20).unwrap().id,
+        };
+        let mut checkpoint_sync = CheckpointSync::new(checkpoint);
+
+        while !checkpoint_sync.is_reached(&local_ledger).unwrap() {
+            let blocks = checkpoint_sync
+                .sync_next_batch(
+                    &local_ledger,
+                    transactions_fetcher.clone(),
+                    4,
+                    TIMEOUT,
+                    &logger,
+                )
+                .unwrap();
+            assert!(blocks.len() <= 4);
+            for (block, transactions) in blocks {
+                local_ledger
+                    .append_block(&block, &transactions, None)
+                    .unwrap();
+            }
+        }
+
+        // Nothing past the checkpoint is appended.
+        assert_eq!(local_ledger.num_blocks().unwrap(), 21);
+        assert_eq!(
+            transactions_fetcher
+                .num_blocks_fetched
+                .load(Ordering::SeqCst),
+            2 * 18
+        );
+        for block_index in 0..21 {
+            assert_eq!(
+                local_ledger.get_block(block_index).unwrap(),
+                source_ledger.get_block(block_index).unwrap()
+            );
+        }
+    }
+
+    #[test_with_logger]
+    // Blocks that were taken but not appended are verified again.
+    fn test_reverify_after_failed_append(logger: Logger) {
+        let source_ledger = get_mock_ledger(10);
+        let transactions_fetcher = Arc::new(MockTransactionsFetcher::new(source_ledger.clone()));
+        let local_ledger = get_mock_ledger(3);
+
+        let checkpoint = Checkpoint {
+            block_index: 8,
+            block_id: source_ledger.get_block(8).unwrap().id,
+        };
+        let mut checkpoint_sync = CheckpointSync::new(checkpoint);
+
+        let sync_next_batch = |checkpoint_sync: &mut CheckpointSync| {
+            checkpoint_sync
+                .sync_next_batch(
+                    &local_ledger,
+                    transactions_fetcher.clone(),
+                    100,
+                    TIMEOUT,
+                    &logger,
+                )
+                .unwrap()
+        };
+
+        assert!(sync_next_batch(&mut checkpoint_sync).is_empty());
+        assert!(checkpoint_sync.chain_complete);
+        assert_eq!(sync_next_batch(&mut checkpoint_sync).len(), 6);
+
+        // The blocks were not appended.
+        assert!(sync_next_batch(&mut checkpoint_sync).is_empty());
+        assert!(!checkpoint_sync.chain_complete);
+        assert!(sync_next_batch(&mut checkpoint_sync).is_empty());
+        assert_eq!(sync_next_batch(&mut checkpoint_sync).len(), 6);
+    }
+
+    // Serves the transactions of the previous block while `tamper` is set.
+    struct TamperingTransactionsFetcher<L: Ledger + Sync> {
+        inner: MockTransactionsFetcher<L>,
+        tamper: AtomicBool,
+    }
+
+    impl<L: Ledger + Sync> TransactionsFetcher for TamperingTransactionsFetcher<L> {
+        type Error = String;
+
+        fn get_transactions_by_block(
+            &self,
+            safe_responder_ids: &[ResponderId],
+            block: &Block,
+        ) -> Result<Vec<RedactedTx>, Self::Error> {
+            self.inner
+                .get_transactions_by_block(safe_responder_ids, block)
+        }
+
+        fn get_block_by_index(
+            &self,
+            block_index: BlockIndex,
+        ) -> Result<(Block, Vec<RedactedTx>), Self::Error> {
+            let (block, transactions) = self.inner.get_block_by_index(block_index)?;
+            if self.tamper.load(Ordering::SeqCst) {
+                let (_block, other_transactions) =
+                    self.inner.get_block_by_index(block_index - 1)?;
+                return Ok((block, other_transactions));
+            }
+            Ok((block, transactions))
+        }
+    }
+
+    #[test_with_logger]
+    // Blocks downloaded again to be appended must match the verified chain.
+    fn test_tampered_blocks_not_appended(logger: Logger) {
+        let source_ledger = get_mock_ledger(10);
+        let transactions_fetcher = Arc::new(TamperingTransactionsFetcher {
+            inner: MockTransactionsFetcher::new(source_ledger.clone()),
+            tamper: AtomicBool::new(false),
+        });
+        let local_ledger = get_mock_ledger(3);
+
+        let checkpoint = Checkpoint {
+            block_index: 8,
+            block_id: source_ledger.get_block(8).unwrap().id,
+        };
+        let mut checkpoint_sync = CheckpointSync::new(checkpoint);
+
+        let sync_next_batch = |checkpoint_sync: &mut CheckpointSync| {
+            checkpoint_sync.sync_next_batch(
+                &local_ledger,
+                transactions_fetcher.clone(),
+                100,
+                TIMEOUT,
+                &logger,
+            )
+        };
+
+        assert!(sync_next_batch(&mut checkpoint_sync).unwrap().is_empty());
+        assert!(checkpoint_sync.chain_complete);
+        assert_eq!(checkpoint_sync.verified_block_ids.len(), 6);
+
+        transactions_fetcher.tamper.store(true, Ordering::SeqCst);
+        match sync_next_batch(&mut checkpoint_sync) {
+            Err(LedgerSyncError::TransactionsAndBlockMismatch) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+        assert_eq!(checkpoint_sync.verified_block_ids.len(), 6);
+
+        transactions_fetcher.tamper.store(false, Ordering::SeqCst);
+        let blocks = sync_next_batch(&mut checkpoint_sync).unwrap();
+        assert_eq!(blocks.len(), 6);
+        for (block, transactions) in blocks {
+            assert_eq!(block, source_ledger.get_block(block.index).unwrap());
+            assert_eq!(
+                transactions,
+                source_ledger
+                    .get_transactions_by_block(block.index)
+                    .unwrap()
+            );
+        }
+    }
+
+    #[test_with_logger]
+    // Blocks that do not chain to the checkpoint are never appended.
+    fn test_sync_to_wrong_checkpoint(logger: Logger) {
+        let source_ledger = get_mock_ledger(25);
+        let transactions_fetcher = Arc::new(MockTransactionsFetcher::new(source_ledger.clone()));
+        let local_ledger = get_mock_ledger(3);
+
+        let checkpoint = Checkpoint {
+            block_index: 20,
+            block_id: source_ledger.get_block(19).unwrap().id,
+        };
+        let mut checkpoint_sync = CheckpointSync::new(checkpoint);
+
+        match checkpoint_sync.sync_next_batch(
+            &local_ledger,
+            transactions_fetcher,
+            100,
+            TIMEOUT,
+            &logger,
+        ) {
+            Err(LedgerSyncError::CheckpointMismatch) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+        assert!(!checkpoint_sync.chain_complete);
+        assert!(checkpoint_sync.verified_block_ids.is_empty());
+    }
+
+    #[test]
+    // A ledger that already contains a different block at the checkpoint's index is rejected.
+    fn test_ledger_past_wrong_checkpoint() {
+        let ledger = get_mock_ledger(25);
+        let checkpoint_sync = CheckpointSync::new(Checkpoint {
+            block_index: 20,
+            block_id: ledger.get_block(19).unwrap().id,
+        });
+
+        match checkpoint_sync.is_reached(&ledger) {
+            Err(LedgerSyncError::CheckpointMismatch) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_checkpoint_from_str() {
+        let block_id = get_mock_ledger(1).get_block(0).unwrap().id;
+        let checkpoint = Checkpoint::from_str(&format!("1234:{}", hex::encode(&block_id))).unwrap();
+        assert_eq!(
+            checkpoint,
+            Checkpoint {
+                block_index: 1234,
+                block_id,
+            }
+        );
+
+        assert!(Checkpoint::from_str("1234").is_err());
+        assert!(Checkpoint::from_str("x:00").is_err());
+        assert!(Checkpoint::from_str("1234:00").is_err());
+    }
+}
@@ -0,0 +1,218 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! NetworkState implementation that is not part of consensus, and follows the SCP messages the
+//! validators themselves emit. Each poll fetches the latest signed consensus message from every
+//! validator's peer API, and feeds the ones signed by that validator into SCPNetworkState.
+
+use crate::{network_state_trait::NetworkState, scp_network_state::SCPNetworkState};
+use common::{
+    logger::{log, Logger},
+    HashSet, NodeID, ResponderId,
+};
+use grpcio::{ChannelBuilder, Environment};
+use mcconnection::ConnectionUriGrpcioChannel;
+use mcuri::{ConnectionUri, ConsensusPeerUri};
+use mobilecoin_api::{consensus_peer_grpc::ConsensusPeerApiClient, empty::Empty};
+use peers::{ConsensusMsg, VerifiedConsensusMsg};
+use scp::{Msg, QuorumSet};
+use std::{convert::TryFrom, str::FromStr, sync::Arc};
+use transaction::BlockIndex;
+
+// See `PollingNetworkState`.
+const FAKE_NODE_ID: &str = "fake:7777";
+
+pub struct ConsensusMsgNetworkState {
+    /// Peer API clients of the validators we follow, with the node ID of each validator.
+    peers: Vec<(NodeID, ConsensusPeerApiClient)>,
+
+    /// SCPNetworkState instance that provides the actual blocking/quorum set check logic.
+    scp_network_state: SCPNetworkState<ResponderId>,
+
+    /// Logger.
+    logger: Logger,
+}
+
+impl ConsensusMsgNetworkState {
+    /// Follows the validators at `peer_uris`. Their responder IDs should be the ones the quorum set
+    /// and the sync connections use.
+    pub fn new(
+        quorum_set: QuorumSet<ResponderId>,
+        peer_uris: &[ConsensusPeerUri],
+        env: Arc<Environment>,
+        logger: Logger,
+    ) -> Self {
+        let peers = peer_uris
+            .iter()
+            .map(|uri| {
+                let node_id = uri.node_id().expect("Could not get node_id from URI");
+                let ch = ChannelBuilder::default_channel_builder(env.clone())
+                    .max_receive_message_len(std::i32::MAX)
+                    .connect_to_uri(uri, &logger);
+                (node_id, ConsensusPeerApiClient::new(ch))
+            })
+            .collect();
+        let local_node_id = ResponderId::from_str(FAKE_NODE_ID).unwrap();
+
+        Self {
+            peers,
+            scp_network_state: SCPNetworkState::new(local_node_id, quorum_set, logger.clone()),
+            logger,
+        }
+    }
+
+    /// Fetches the latest message of each validator.
+    fn poll_peers(&mut self) {
+        for (node_id, client) in &self.peers {
+            let response = match client.fetch_latest_msg(&Empty::new()) {
+                Ok(response) => response,
+                Err(err) => {
+                    log::error!(
+                        self.logger,
+                        "Failed fetching latest message from {}: {:?}",
+                        node_id,
+                        err
+                    );
+                    continue;
+                }
+            };
+            if response.get_payload().is_empty() {
+                continue;
+            }
+
+            match mcserial::deserialize::<ConsensusMsg>(response.get_payload()) {
+                Ok(msg) => {
+                    record_msg(&mut self.scp_network_state, node_id, msg, &self.logger);
+                }
+                Err(err) => {
+                    log::error!(
+                        self.logger,
+                        "Failed deserializing latest message from {}: {:?}",
+                        node_id,
+                        err
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Records `msg` as the latest message of the validator `node_id`, provided it was issued and
+/// signed by that validator. Returns true if it was recorded.
+fn record_msg(
+    scp_network_state: &mut SCPNetworkState<ResponderId>,
+    node_id: &NodeID,
+    msg: ConsensusMsg,
+    logger: &Logger,
+) -> bool {
+    if msg.issuer_node_id() != node_id {
+        log::warn!(
+            logger,
+            "Ignoring message from {} issued by {}",
+            node_id,
+            msg.issuer_node_id()
+        );
+        return false;
+    }
+    let msg = match VerifiedConsensusMsg::try_from(msg) {
+        Ok(msg) => msg,
+        Err(err) => {
+            log::warn!(
+                logger,
+                "Ignoring invalid message from {}: {:?}",
+                node_id,
+                err
+            );
+            return false;
+        }
+    };
+
+    let scp_msg = msg.scp_msg();
+    scp_network_state.push(Msg::new(
+        node_id.responder_id.clone(),
+        QuorumSet::empty(),
+        scp_msg.slot_index,
+        scp_msg.topic.clone(),
+    ));
+    true
+}
+
+impl NetworkState for ConsensusMsgNetworkState {
+    fn is_blocking_and_quorum(&self, conn_ids: &HashSet<ResponderId>) -> bool {
+        self.scp_network_state.is_blocking_and_quorum(conn_ids)
+    }
+
+    fn is_behind(&self, local_block_index: BlockIndex) -> bool {
+        self.scp_network_state.is_behind(local_block_index)
+    }
+
+    fn highest_block_index_on_network(&self) -> Option<BlockIndex> {
+        self.scp_network_state.highest_block_index_on_network()
+    }
+
+    /// Fetches the validators' latest messages.
+    fn poll(&mut self) {
+        self.poll_peers();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::logger::test_with_logger;
+    use ledger_db::test_utils::get_mock_ledger;
+    use peers_tests::test_node_id_and_signer;
+    use scp::{core_types::Ballot, msg::ExternalizePayload, Topic};
+    use transaction::tx::TxHash;
+
+    #[test_with_logger]
+    // Only messages issued and signed by the polled validator should be recorded.
+    fn test_record_msg(logger: Logger) {
+        let (node_id, signer) = test_node_id_and_signer(22);
+        let (other_node_id, _) = test_node_id_and_signer(33);
+        let mut scp_network_state = SCPNetworkState::new(
+            ResponderId::from_str(FAKE_NODE_ID).unwrap(),
+            QuorumSet::new_with_node_ids(1, vec![node_id.responder_id.clone()]),
+            logger.clone(),
+        );
+
+        let ledger = get_mock_ledger(10);
+        let msg = ConsensusMsg::from_scp_msg(
+            &ledger,
+            Msg::new(
+                node_id.clone(),
+                QuorumSet::empty(),
+                9,
+                Topic::Externalize(ExternalizePayload {
+                    C: Ballot::new(1, &[TxHash::default()]),
+                    HN: 1,
+                }),
+            ),
+            &signer,
+        )
+        .unwrap();
+
+        // Relayed by another validator.
+        assert!(!record_msg(
+            &mut scp_network_state,
+            &other_node_id,
+            msg.clone(),
+            &logger
+        ));
+
+        // Tampered with.
+        let mut tampered_msg = msg.clone();
+        tampered_msg.scp_msg.slot_index = 100;
+        assert!(!record_msg(
+            &mut scp_network_state,
+            &node_id,
+            tampered_msg,
+            &logger
+        ));
+        assert_eq!(scp_network_state.highest_block_index_on_network(), None);
+
+        assert!(record_msg(&mut scp_network_state, &node_id, msg, &logger));
+        assert_eq!(scp_network_state.highest_block_index_on_network(), Some(9));
+        assert!(scp_network_state.is_behind(8));
+        assert!(!scp_network_state.is_behind(9));
+    }
+}
@@ -23,6 +23,24 @@ lazy_static::lazy_static! {
     // Number of txouts in the ledger (by querying ledger)
     pub static ref TXO_IN_LEDGER: IntGauge = OP_COUNTERS.gauge("num_txos");
 
+    // Number of blocks the network is known to be ahead of the local ledger.
+    pub static ref BLOCKS_BEHIND: IntGauge = OP_COUNTERS.gauge("blocks_behind");
+
+    // Set to 1 once a fork of the network is detected, at which point ledger sync halts.
+    pub static ref FORK_DETECTED: IntGauge = OP_COUNTERS.gauge("fork_detected");
+
+    // Requests served from the transactions cache.
+    pub static ref TRANSACTIONS_CACHE_HIT_COUNT: IntCounter = OP_COUNTERS.counter("transactions_cache_hit_count");
+
+    // Requests the transactions cache had to forward to the archive.
+    pub static ref TRANSACTIONS_CACHE_MISS_COUNT: IntCounter = OP_COUNTERS.counter("transactions_cache_miss_count");
+
     // Time it takes to perform append_block
     pub static ref APPEND_BLOCK_TIME: Histogram = OP_COUNTERS.histogram("append_block");
+
+    // Time it takes to fetch blocks from a peer
+    pub static ref GET_BLOCKS_TIME: Histogram = OP_COUNTERS.histogram("get_blocks");
+
+    // Time it takes to fetch the transactions of a block
+    pub static ref GET_TRANSACTIONS_TIME: Histogram = OP_COUNTERS.histogram("get_transactions");
 }
@@ -0,0 +1,174 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Evidence that the network forked, i.e. that distinct blocks were found at the same index.
+//!
+//! Ledger sync stops syncing as soon as it records evidence of a fork: which branch is legitimate
+//! is for an operator to decide. The evidence can be persisted, so that syncing stays halted across
+//! restarts until the operator removes it.
+
+use crate::counters;
+use common::ResponderId;
+use failure::Fail;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+use transaction::{Block, BlockIndex, BlockSignatureSet};
+
+#[derive(Debug, Fail)]
+pub enum ForkEvidenceError {
+    #[fail(display = "IO error: {}", _0)]
+    IO(io::Error),
+
+    #[fail(display = "Failed encoding fork evidence: {}", _0)]
+    Encode(mcserial::encode::Error),
+
+    #[fail(display = "Failed decoding fork evidence: {}", _0)]
+    Decode(mcserial::decode::Error),
+}
+
+impl From<io::Error> for ForkEvidenceError {
+    fn from(src: io::Error) -> Self {
+        ForkEvidenceError::IO(src)
+    }
+}
+
+impl From<mcserial::encode::Error> for ForkEvidenceError {
+    fn from(src: mcserial::encode::Error) -> Self {
+        ForkEvidenceError::Encode(src)
+    }
+}
+
+impl From<mcserial::decode::Error> for ForkEvidenceError {
+    fn from(src: mcserial::decode::Error) -> Self {
+        ForkEvidenceError::Decode(src)
+    }
+}
+
+/// One of the versions of a block that were found at the same index.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ConflictingBlock {
+    pub block: Block,
+
+    /// Signatures over the block, as far as they could be obtained.
+    pub signature_set: BlockSignatureSet,
+
+    /// Peers that served this block.
+    pub responder_ids: Vec<ResponderId>,
+
+    /// Whether this block is in the local ledger.
+    pub in_local_ledger: bool,
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ForkEvidence {
+    /// Index the conflicting blocks were found at.
+    pub block_index: BlockIndex,
+
+    /// The distinct blocks found at `block_index`.
+    pub conflicting_blocks: Vec<ConflictingBlock>,
+}
+
+/// Holds the evidence of a fork once one is detected, and shares it between the ledger sync
+/// service and anyone else interested in it.
+#[derive(Clone, Default)]
+pub struct ForkEvidenceStore {
+    /// File the evidence is persisted to, if any.
+    path: Option<PathBuf>,
+
+    evidence: Arc<Mutex<Option<ForkEvidence>>>,
+}
+
+impl ForkEvidenceStore {
+    /// A store that persists evidence to `path`, starting out with the evidence already there, if
+    /// any.
+    pub fn open(path: &Path) -> Result<Self, ForkEvidenceError> {
+        let evidence = match fs::read(path) {
+            Ok(bytes) => Some(mcserial::deserialize::<ForkEvidence>(&bytes)?),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+            Err(err) => return Err(err.into()),
+        };
+        if evidence.is_some() {
+            counters::FORK_DETECTED.set(1);
+        }
+
+        Ok(Self {
+            path: Some(path.to_path_buf()),
+            evidence: Arc::new(Mutex::new(evidence)),
+        })
+    }
+
+    /// The evidence of a fork, if one was detected.
+    pub fn get(&self) -> Option<ForkEvidence> {
+        self.evidence.lock().expect("mutex poisoned").clone()
+    }
+
+    pub fn is_forked(&self) -> bool {
+        self.evidence.lock().expect("mutex poisoned").is_some()
+    }
+
+    /// Records evidence of a fork, unless evidence was already recorded. The evidence is kept in
+    /// memory even if persisting it fails.
+    pub fn record(&self, evidence: ForkEvidence) -> Result<(), ForkEvidenceError> {
+        let mut current_evidence = self.evidence.lock().expect("mutex poisoned");
+        if current_evidence.is_some() {
+            return Ok(());
+        }
+
+        counters::FORK_DETECTED.set(1);
+        let bytes = mcserial::serialize(&evidence)?;
+        *current_evidence = Some(evidence);
+
+        if let Some(path) = self.path.as_ref() {
+            // Write to a temporary file first, so that the evidence is never left half written.
+            let tmp_path = path.with_extension("tmp");
+            fs::write(&tmp_path, bytes)?;
+            fs::rename(&tmp_path, path)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ledger_db::test_utils::get_test_ledger_blocks;
+    use tempdir::TempDir;
+
+    fn test_fork_evidence() -> ForkEvidence {
+        let blocks_and_transactions = get_test_ledger_blocks(2);
+        ForkEvidence {
+            block_index: 1,
+            conflicting_blocks: vec![ConflictingBlock {
+                block: blocks_and_transactions[1].0.clone(),
+                signature_set: BlockSignatureSet::default(),
+                responder_ids: vec![ResponderId("node1.test.com:443".to_string())],
+                in_local_ledger: true,
+            }],
+        }
+    }
+
+    #[test]
+    // Evidence should survive reopening the store, and never be overwritten.
+    fn test_record_and_reopen() {
+        let dir = TempDir::new("fork_evidence").unwrap();
+        let path = dir.path().join("fork_evidence");
+
+        let store = ForkEvidenceStore::open(&path).unwrap();
+        assert!(!store.is_forked());
+
+        let evidence = test_fork_evidence();
+        store.record(evidence.clone()).unwrap();
+        assert_eq!(store.get(), Some(evidence.clone()));
+
+        let mut other_evidence = evidence.clone();
+        other_evidence.block_index = 2;
+        store.record(other_evidence).unwrap();
+        assert_eq!(store.get(), Some(evidence.clone()));
+
+        let reopened_store = ForkEvidenceStore::open(&path).unwrap();
+        assert_eq!(reopened_store.get(), Some(evidence));
+    }
+}
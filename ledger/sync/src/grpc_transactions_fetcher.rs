@@ -0,0 +1,246 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Implementation of the `TransactionsFetcher` trait that fetches transactions data directly from
+//! peers, using the blockchain API. Unlike `ReqwestTransactionsFetcher`, it does not require the
+//! network to publish its blocks to an object store such as S3.
+
+use crate::transactions_fetcher_trait::{TransactionFetcherError, TransactionsFetcher};
+use common::{
+    logger::{log, Logger},
+    ResponderId,
+};
+use failure::Fail;
+use mcconnection::{
+    BlockContents, BlockchainConnection, ConnectionManager, Error as ConnectionError, RetryError,
+    RetryableBlockchainConnection, SyncConnection,
+};
+use retry::delay::Fibonacci;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use transaction::{hash_block_contents, Block, BlockIndex, RedactedTx};
+
+#[derive(Debug, Fail)]
+pub enum GrpcTransactionsFetcherError {
+    #[fail(display = "No connection to any of the requested responders")]
+    NoConnections,
+
+    #[fail(display = "Connection error on {}: {:?}", _0, _1)]
+    Connection(ResponderId, RetryError<ConnectionError>),
+
+    #[fail(display = "Received an invalid block from {}: {}", _0, _1)]
+    InvalidBlockReceived(ResponderId, String),
+}
+
+impl TransactionFetcherError for GrpcTransactionsFetcherError {}
+
+pub struct GrpcTransactionsFetcher<BC: BlockchainConnection> {
+    /// Connections to the peers transactions data is fetched from.
+    manager: ConnectionManager<BC>,
+
+    /// Used to spread requests across peers, in round-robin order.
+    source_index_counter: AtomicUsize,
+
+    logger: Logger,
+}
+
+impl<BC: BlockchainConnection> GrpcTransactionsFetcher<BC> {
+    pub fn new(manager: ConnectionManager<BC>, logger: Logger) -> Self {
+        Self {
+            manager,
+            source_index_counter: AtomicUsize::new(0),
+            logger,
+        }
+    }
+
+    /// Tries each of `responder_ids` in turn, starting from the next one in round-robin order,
+    /// until `fetch` succeeds. Returns the error of the last attempt if none do.
+    fn fetch_from_any(
+        &self,
+        responder_ids: &[ResponderId],
+        fetch: impl Fn(
+            &ResponderId,
+            &SyncConnection<BC>,
+        ) -> Result<BlockContents, GrpcTransactionsFetcherError>,
+    ) -> Result<BlockContents, GrpcTransactionsFetcherError> {
+        let mut responder_ids = responder_ids.to_vec();
+        if !responder_ids.is_empty() {
+            let source_index_counter = self.source_index_counter.fetch_add(1, Ordering::SeqCst);
+            let len = responder_ids.len();
+            responder_ids.rotate_left(source_index_counter % len);
+        }
+
+        let mut last_error = GrpcTransactionsFetcherError::NoConnections;
+        for responder_id in responder_ids.iter() {
+            let conn = match self.manager.conn(responder_id) {
+                Some(conn) => conn,
+                None => continue,
+            };
+
+            match fetch(responder_id, &conn) {
+                Ok(block_contents) => return Ok(block_contents),
+                Err(err) => {
+                    log::debug!(
+                        self.logger,
+                        "Failed fetching block contents from {}: {}",
+                        responder_id,
+                        err
+                    );
+                    last_error = err;
+                }
+            }
+        }
+
+        Err(last_error)
+    }
+}
+
+/// Checks that the transactions are the ones committed to by the block's `contents_hash`.
+fn verify_contents_hash(
+    responder_id: &ResponderId,
+    block_contents: &BlockContents,
+) -> Result<(), GrpcTransactionsFetcherError> {
+    if hash_block_contents(&block_contents.transactions) != block_contents.block.contents_hash {
+        return Err(GrpcTransactionsFetcherError::InvalidBlockReceived(
+            responder_id.clone(),
+            "contents hash mismatch".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+impl<BC: BlockchainConnection> TransactionsFetcher for GrpcTransactionsFetcher<BC> {
+    type Error = GrpcTransactionsFetcherError;
+
+    fn get_transactions_by_block(
+        &self,
+        safe_responder_ids: &[ResponderId],
+        block: &Block,
+    ) -> Result<Vec<RedactedTx>, Self::Error> {
+        let block_contents = self.fetch_from_any(safe_responder_ids, |responder_id, conn| {
+            log::debug!(
+                self.logger,
+                "Attempting to fetch block {} from {}",
+                block.index,
+                responder_id
+            );
+
+            let block_contents = conn
+                .fetch_block_by_id(&block.id, Fibonacci::from_millis(10).take(5))
+                .map_err(|err| {
+                    GrpcTransactionsFetcherError::Connection(responder_id.clone(), err)
+                })?;
+
+            // Check that we received data for the block we actually asked about.
+            if block_contents.block != *block {
+                return Err(GrpcTransactionsFetcherError::InvalidBlockReceived(
+                    responder_id.clone(),
+                    "block data mismatch".to_string(),
+                ));
+            }
+            verify_contents_hash(responder_id, &block_contents)?;
+
+            Ok(block_contents)
+        })?;
+
+        Ok(block_contents.transactions)
+    }
+
+    fn get_block_by_index(
+        &self,
+        block_index: BlockIndex,
+    ) -> Result<(Block, Vec<RedactedTx>), Self::Error> {
+        let responder_ids = self.manager.responder_ids();
+        let block_contents = self.fetch_from_any(&responder_ids, |responder_id, conn| {
+            let blocks = conn
+                .fetch_blocks_with_transactions(
+                    block_index..block_index + 1,
+                    Fibonacci::from_millis(10).take(5),
+                )
+                .map_err(|err| {
+                    GrpcTransactionsFetcherError::Connection(responder_id.clone(), err)
+                })?;
+            let block_contents = blocks
+                .into_iter()
+                .find(|block_contents| block_contents.block.index == block_index)
+                .ok_or_else(|| {
+                    GrpcTransactionsFetcherError::InvalidBlockReceived(
+                        responder_id.clone(),
+                        format!("block {} missing from response", block_index),
+                    )
+                })?;
+            verify_contents_hash(responder_id, &block_contents)?;
+
+            Ok(block_contents)
+        })?;
+
+        Ok((block_contents.block, block_contents.transactions))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::logger::test_with_logger;
+    use ledger_db::{test_utils::get_mock_ledger, Ledger};
+    use mcuri::ConnectionUri;
+    use peers_tests::{test_node_id, test_peer_uri, MockPeerConnection};
+
+    fn mock_peer(id: u32, num_blocks: usize) -> MockPeerConnection {
+        MockPeerConnection::new(
+            test_peer_uri(id),
+            test_node_id(1),
+            get_mock_ledger(num_blocks),
+            0,
+        )
+    }
+
+    #[test_with_logger]
+    // Transactions should be fetched from whichever responder has the block.
+    fn test_get_transactions_by_block(logger: Logger) {
+        let peer_a = mock_peer(2, 3);
+        let peer_b = mock_peer(3, 10);
+        let ledger = peer_b.ledger.clone();
+        let responder_ids = vec![
+            peer_a.uri.responder_id().unwrap(),
+            peer_b.uri.responder_id().unwrap(),
+        ];
+        let manager = ConnectionManager::new(vec![peer_a, peer_b], logger.clone());
+        let transactions_fetcher = GrpcTransactionsFetcher::new(manager, logger);
+
+        // Repeat to start from each of the responders at least once.
+        for _ in 0..2 {
+            let block = ledger.get_block(5).unwrap();
+            let transactions = transactions_fetcher
+                .get_transactions_by_block(&responder_ids, &block)
+                .unwrap();
+            assert_eq!(transactions, ledger.get_transactions_by_block(5).unwrap());
+        }
+    }
+
+    #[test_with_logger]
+    // Responders the fetcher has no connection to should be ignored.
+    fn test_get_transactions_by_block_no_connections(logger: Logger) {
+        let manager = ConnectionManager::new(vec![mock_peer(2, 10)], logger.clone());
+        let transactions_fetcher = GrpcTransactionsFetcher::new(manager, logger);
+
+        let block = get_mock_ledger(10).get_block(5).unwrap();
+        let responder_ids = vec![test_peer_uri(3).responder_id().unwrap()];
+        match transactions_fetcher.get_transactions_by_block(&responder_ids, &block) {
+            Err(GrpcTransactionsFetcherError::NoConnections) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    #[test_with_logger]
+    fn test_get_block_by_index(logger: Logger) {
+        let peer = mock_peer(2, 10);
+        let ledger = peer.ledger.clone();
+        let manager = ConnectionManager::new(vec![peer], logger.clone());
+        let transactions_fetcher = GrpcTransactionsFetcher::new(manager, logger);
+
+        let (block, transactions) = transactions_fetcher.get_block_by_index(7).unwrap();
+        assert_eq!(block, ledger.get_block(7).unwrap());
+        assert_eq!(transactions, ledger.get_transactions_by_block(7).unwrap());
+
+        assert!(transactions_fetcher.get_block_by_index(10).is_err());
+    }
+}
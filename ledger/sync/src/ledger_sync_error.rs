@@ -34,6 +34,15 @@ pub enum LedgerSyncError {
 
     #[fail(display = "Invalid block ID.")]
     InvalidBlockId,
+
+    #[fail(display = "Blocks do not chain to the checkpoint.")]
+    CheckpointMismatch,
+
+    #[fail(display = "Blocks could not be fetched in time.")]
+    BlocksUnavailable,
+
+    #[fail(display = "The network forked, syncing is halted.")]
+    ForkDetected,
 }
 
 impl<TFE: TransactionFetcherError + 'static> From<TFE> for LedgerSyncError {
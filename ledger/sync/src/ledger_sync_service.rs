@@ -5,25 +5,33 @@
 //! (`TransactionsFetcher`) for fetching actual transaction data.
 
 use crate::{
-    counters, ledger_sync_error::LedgerSyncError, network_state_trait::NetworkState,
+    checkpoint_sync::{Checkpoint, CheckpointSync},
+    counters,
+    fork_evidence::{ConflictingBlock, ForkEvidence, ForkEvidenceStore},
+    ledger_sync_error::LedgerSyncError,
+    network_state_trait::NetworkState,
     transactions_fetcher_trait::TransactionsFetcher,
+    transactions_pipeline::TransactionsPipeline,
 };
 use common::{
     logger::{log, Logger},
     HashMap, HashSet, ResponderId,
 };
+use keys::Ed25519Public;
 use ledger_db::Ledger;
 use mcconnection::{
     BlockchainConnection, Connection, ConnectionManager, RetryableBlockchainConnection,
+    SyncConnection,
 };
 use mcuri::ConnectionUri;
 use retry::delay::Fibonacci;
 use std::{
     collections::BTreeMap,
     iter::FromIterator,
+    mem,
     sync::{Arc, Condvar, Mutex},
     thread,
-    time::{Duration, Instant},
+    time::Duration,
 };
 use transaction::{
     compute_block_id, hash_block_contents, ring_signature::KeyImage, Block, BlockID, BlockIndex,
@@ -34,6 +42,15 @@ use transaction::{
 const DEFAULT_GET_BLOCKS_TIMEOUT: Duration = Duration::from_secs(30);
 const DEFAULT_GET_TRANSACTIONS_TIMEOUT: Duration = Duration::from_secs(30);
 
+/// Maximal number of blocks whose transactions are fetched ahead of the next block to append.
+const DEFAULT_TRANSACTIONS_WINDOW: usize = 32;
+
+/// Number of blocks verified or appended at a time while syncing up to a checkpoint.
+const DEFAULT_CHECKPOINT_SYNC_BATCH_SIZE: u64 = 1000;
+
+/// A callback invoked with the blocks ledger sync appended to the ledger, once they are appended.
+pub type PostAppendHook = Arc<dyn Fn(&[Block]) + Sync + Send>;
+
 pub struct LedgerSyncService<L: Ledger, BC: BlockchainConnection, TF: TransactionsFetcher> {
     /// Local ledger database.
     ledger: L,
@@ -48,6 +65,27 @@ pub struct LedgerSyncService<L: Ledger, BC: BlockchainConnection, TF: Transactio
     get_blocks_timeout: Duration,
     get_transactions_timeout: Duration,
 
+    /// Maximal number of blocks whose transactions are fetched ahead of the next block to append.
+    transactions_window: usize,
+
+    /// Transactions fetched during a previous attempt but not appended yet, by block ID.
+    prefetched_transactions: HashMap<BlockID, Vec<RedactedTx>>,
+
+    /// Progress of syncing up to a trusted checkpoint, until the ledger reaches it.
+    checkpoint_sync: Option<CheckpointSync>,
+    checkpoint_sync_batch_size: u64,
+
+    /// Evidence of a fork of the network. Syncing halts once there is some.
+    fork_evidence_store: ForkEvidenceStore,
+
+    /// Signers whose signatures establish a block as part of the network's history, and the
+    /// number of their valid signatures a block needs. Used to confirm forks.
+    trusted_block_signers: Vec<Ed25519Public>,
+    required_block_signatures: usize,
+
+    /// Called after blocks are appended to the ledger.
+    post_append_hook: Option<PostAppendHook>,
+
     /// Logger.
     logger: Logger,
 }
@@ -75,10 +113,62 @@ impl<L: Ledger, BC: BlockchainConnection + 'static, TF: TransactionsFetcher + 's
             transactions_fetcher: Arc::new(transactions_fetcher),
             get_blocks_timeout: DEFAULT_GET_BLOCKS_TIMEOUT,
             get_transactions_timeout: DEFAULT_GET_TRANSACTIONS_TIMEOUT,
+            transactions_window: DEFAULT_TRANSACTIONS_WINDOW,
+            prefetched_transactions: HashMap::default(),
+            checkpoint_sync: None,
+            checkpoint_sync_batch_size: DEFAULT_CHECKPOINT_SYNC_BATCH_SIZE,
+            fork_evidence_store: ForkEvidenceStore::default(),
+            trusted_block_signers: Vec::new(),
+            required_block_signatures: 0,
+            post_append_hook: None,
             logger,
         }
     }
 
+    /// Fetch the transactions of at most `window` blocks ahead of the next block to append.
+    pub fn with_transactions_window(mut self, window: usize) -> Self {
+        self.transactions_window = window;
+        self
+    }
+
+    /// Sync up to `checkpoint` from the transactions fetcher, verifying blocks against the
+    /// checkpoint instead of waiting for a quorum of peers to agree on each of them.
+    pub fn with_checkpoint(mut self, checkpoint: Checkpoint) -> Self {
+        self.checkpoint_sync = Some(CheckpointSync::new(checkpoint));
+        self
+    }
+
+    /// Record evidence of forks in `fork_evidence_store`. If it already holds evidence, syncing is
+    /// halted from the start.
+    pub fn with_fork_evidence_store(mut self, fork_evidence_store: ForkEvidenceStore) -> Self {
+        self.fork_evidence_store = fork_evidence_store;
+        self
+    }
+
+    /// Also consider a block established, when confirming a fork, if it carries at least
+    /// `threshold` valid signatures made by `trusted_signers`. Otherwise only blocks in the local
+    /// ledger and blocks served by a blocking set that is also a quorum are.
+    pub fn with_trusted_block_signers(
+        mut self,
+        trusted_signers: Vec<Ed25519Public>,
+        threshold: usize,
+    ) -> Self {
+        assert!(threshold > 0, "threshold must be at least 1");
+        self.trusted_block_signers = trusted_signers;
+        self.required_block_signatures = threshold;
+        self
+    }
+
+    /// Call `post_append_hook` after appending blocks to the ledger.
+    pub fn with_post_append_hook(mut self, post_append_hook: PostAppendHook) -> Self {
+        self.post_append_hook = Some(post_append_hook);
+        self
+    }
+
+    pub fn fork_evidence_store(&self) -> &ForkEvidenceStore {
+        &self.fork_evidence_store
+    }
+
     /// Check if our ledger is currently behind.
     pub fn is_behind<NS: NetworkState>(&self, network_state: &NS) -> bool {
         let num_blocks: u64 = self
@@ -88,6 +178,14 @@ impl<L: Ledger, BC: BlockchainConnection + 'static, TF: TransactionsFetcher + 's
 
         if num_blocks == 0 {
             true
+        } else if self
+            .checkpoint_sync
+            .as_ref()
+            .map_or(false, |checkpoint_sync| {
+                num_blocks <= checkpoint_sync.checkpoint().block_index
+            })
+        {
+            true
         } else {
             network_state.is_behind(num_blocks - 1)
         }
@@ -105,6 +203,12 @@ impl<L: Ledger, BC: BlockchainConnection + 'static, TF: TransactionsFetcher + 's
     ///     * None of the key images in the block have appeared before.
     /// 5. Append safe blocks to the ledger.
     ///
+    /// While the ledger is behind a trusted checkpoint, blocks up to it are instead fetched in
+    /// batches from the transactions fetcher and verified against the checkpoint.
+    ///
+    /// If peers disagree on the block at some index, with each other or with the local ledger, the
+    /// evidence is recorded and syncing halts for good.
+    ///
     /// # Arguments
     /// * `network_state` - Current state of the network, used to determine if we're behind.
     /// * `limit` - Maximum number of blocks to add to the ledger.
@@ -113,67 +217,104 @@ impl<L: Ledger, BC: BlockchainConnection + 'static, TF: TransactionsFetcher + 's
         network_state: &impl NetworkState,
         limit: u32,
     ) -> Result<(), LedgerSyncError> {
-        let (responder_ids, _, potentially_safe_blocks) = self
-            .get_potentially_safe_blocks(network_state, limit)
-            .ok_or(LedgerSyncError::NoSafeBlocks)?;
+        if self.fork_evidence_store.is_forked() {
+            return Err(LedgerSyncError::ForkDetected);
+        }
+
+        if self.checkpoint_sync.is_some() {
+            return self.attempt_checkpoint_sync();
+        }
+
+        let potentially_safe_blocks = self.get_potentially_safe_blocks(network_state, limit);
+        if self.fork_evidence_store.is_forked() {
+            return Err(LedgerSyncError::ForkDetected);
+        }
+        let (responder_ids, _, potentially_safe_blocks) =
+            potentially_safe_blocks.ok_or(LedgerSyncError::NoSafeBlocks)?;
 
         if potentially_safe_blocks.is_empty() {
             return Err(LedgerSyncError::EmptyBlockVec);
         }
 
-        // Get transactions.
-        let block_index_to_opt_transactions: BTreeMap<BlockIndex, Option<Vec<RedactedTx>>> =
-            get_transactions(
-                self.transactions_fetcher.clone(),
-                &responder_ids,
-                &potentially_safe_blocks,
-                self.get_transactions_timeout,
+        // Get transactions, and append blocks as soon as a contiguous prefix of them is verified.
+        let mut transactions_pipeline = TransactionsPipeline::new(
+            self.transactions_fetcher.clone(),
+            &responder_ids,
+            potentially_safe_blocks,
+            mem::take(&mut self.prefetched_transactions),
+            self.transactions_window,
+            self.get_transactions_timeout,
+            &self.logger,
+        );
+
+        let mut result = Ok(());
+        for blocks_with_transactions in transactions_pipeline.by_ref() {
+            // Process safe blocks.
+            log::trace!(
                 &self.logger,
+                "Identifying safe blocks out of {} blocks",
+                blocks_with_transactions.len()
             );
+            let safe_blocks =
+                identify_safe_blocks(&self.ledger, &blocks_with_transactions, &self.logger)
+                    .unwrap_or_default();
+            if safe_blocks.is_empty() {
+                log::info!(self.logger, "No safe blocks.");
+                break;
+            }
 
-        let mut blocks_with_transactions: Vec<(Block, Vec<RedactedTx>)> = Vec::new();
+            let all_safe = safe_blocks.len() == blocks_with_transactions.len();
+            result = self.append_safe_blocks(&safe_blocks);
+            if result.is_err() || !all_safe {
+                break;
+            }
+        }
 
-        {
-            // Populate `blocks_with_transactions`. This just returns all (block, transactions) until
-            // it reaches a None.
-            let mut block_index_to_transactions: BTreeMap<BlockIndex, Vec<RedactedTx>> =
-                block_index_to_opt_transactions
-                    .into_iter()
-                    .take_while(|(_block_index, transactions_opt)| transactions_opt.is_some())
-                    .map(|(block_index, transactions_opt)| (block_index, transactions_opt.unwrap()))
-                    .collect();
+        // Keep transactions fetched ahead of a failure, so the next attempt need not fetch them
+        // again.
+        self.prefetched_transactions = transactions_pipeline.finish();
 
-            let block_index_to_block: BTreeMap<BlockIndex, Block> = potentially_safe_blocks
-                .into_iter()
-                .map(|block| (block.index, block))
-                .collect();
+        result
+    }
 
-            // Join blocks and transactions, allowing for the possibility that transactions
-            // may not be available for some blocks due to failed network requests for transactions.
-            for (block_index, block) in block_index_to_block {
-                if let Some(transactions) = block_index_to_transactions.remove(&block_index) {
-                    blocks_with_transactions.push((block, transactions));
-                } else {
-                    log::error!(self.logger, "No transactions for block {:?}", block);
-                    break;
-                }
+    /// Makes progress towards the checkpoint, and stops checkpoint sync once the ledger reaches
+    /// it.
+    fn attempt_checkpoint_sync(&mut self) -> Result<(), LedgerSyncError> {
+        let checkpoint_sync = match self.checkpoint_sync.as_mut() {
+            Some(checkpoint_sync) => checkpoint_sync,
+            None => return Ok(()),
+        };
+
+        let logger = &self.logger;
+        let is_reached = checkpoint_sync.is_reached(&self.ledger).map_err(|err| {
+            log::error!(
+                logger,
+                "Ledger disagrees with checkpoint {:?}: {}",
+                checkpoint_sync.checkpoint(),
+                err
+            );
+            err
+        })?;
+        if !is_reached {
+            let safe_blocks = checkpoint_sync.sync_next_batch(
+                &self.ledger,
+                self.transactions_fetcher.clone(),
+                self.checkpoint_sync_batch_size,
+                self.get_transactions_timeout,
+                &self.logger,
+            )?;
+            if !safe_blocks.is_empty() {
+                self.append_safe_blocks(&safe_blocks)?;
             }
+            return Ok(());
         }
 
-        // Process safe blocks.
-        log::trace!(
-            &self.logger,
-            "Identifying safe blocks out of {} blocks",
-            blocks_with_transactions.len()
+        log::info!(
+            self.logger,
+            "Reached checkpoint at block {}, syncing with peers from now on",
+            checkpoint_sync.checkpoint().block_index
         );
-        if let Ok(safe_blocks) =
-            identify_safe_blocks(&self.ledger, &blocks_with_transactions, &self.logger)
-        {
-            self.append_safe_blocks(&safe_blocks)?;
-        } else {
-            log::info!(self.logger, "No safe blocks.");
-        }
-
+        self.checkpoint_sync = None;
         Ok(())
     }
 
@@ -207,14 +348,38 @@ impl<L: Ledger, BC: BlockchainConnection + 'static, TF: TransactionsFetcher + 's
             next_block_index + BlockIndex::from(limit)
         );
 
-        let node_to_blocks: HashMap<ResponderId, Vec<Block>> = get_blocks(
+        let (node_to_blocks, diverging_blocks): (
+            HashMap<ResponderId, Vec<Block>>,
+            HashMap<ResponderId, Block>,
+        ) = get_blocks(
             &self.manager,
-            last_block,
+            last_block.clone(),
             limit,
             self.get_blocks_timeout,
             &self.logger,
         );
 
+        // Peers that disagree with the local ledger. Their blocks do not chain to the local ledger,
+        // so they are not in `node_to_blocks`.
+        if !diverging_blocks.is_empty() {
+            let mut block_id_to_nodes: HashMap<BlockID, HashSet<ResponderId>> = HashMap::default();
+            for (responder_id, block) in diverging_blocks.iter() {
+                block_id_to_nodes
+                    .entry(block.id.clone())
+                    .or_insert_with(HashSet::default)
+                    .insert(responder_id.clone());
+            }
+            let peer_blocks: Vec<Block> = diverging_blocks.values().cloned().collect();
+            if self.record_fork(
+                last_block.index,
+                &block_id_to_nodes,
+                &peer_blocks,
+                network_state,
+            ) {
+                return None;
+            }
+        }
+
         for (responder_id, blocks) in &node_to_blocks {
             log::debug!(
                 self.logger,
@@ -227,6 +392,17 @@ impl<L: Ledger, BC: BlockchainConnection + 'static, TF: TransactionsFetcher + 's
         let grouping: BTreeMap<BlockIndex, HashMap<BlockID, HashSet<ResponderId>>> =
             group_by_block(&node_to_blocks);
 
+        // Peers that disagree with each other. The lowest such index is where the network forked.
+        if let Some((block_index, block_id_to_nodes)) = grouping
+            .iter()
+            .find(|(_block_index, block_id_to_nodes)| block_id_to_nodes.len() > 1)
+        {
+            let peer_blocks: Vec<Block> = node_to_blocks.values().flatten().cloned().collect();
+            if self.record_fork(*block_index, block_id_to_nodes, &peer_blocks, network_state) {
+                return None;
+            }
+        }
+
         // If sync_target is Some, it indicates that the local ledger should attempt to be synced
         // from the given nodes, up to and including the Block with the given BlockID in BlockIndex.
         let mut sync_target: Option<(BlockIndex, BlockID, Vec<ResponderId>)> = None;
@@ -277,6 +453,128 @@ impl<L: Ledger, BC: BlockchainConnection + 'static, TF: TransactionsFetcher + 's
         }
     }
 
+    /// Records evidence that distinct blocks exist at `block_index`, which halts syncing, if at
+    /// least two of them are established (see `is_established`). Otherwise a single faulty peer
+    /// could halt syncing. Returns true if the fork was recorded.
+    ///
+    /// # Arguments
+    /// * `block_index` - Index of the conflicting blocks.
+    /// * `block_id_to_nodes` - The peers that have each of the conflicting blocks.
+    /// * `peer_blocks` - Blocks received from peers, including the conflicting blocks.
+    /// * `network_state` - Current state of the network.
+    fn record_fork(
+        &self,
+        block_index: BlockIndex,
+        block_id_to_nodes: &HashMap<BlockID, HashSet<ResponderId>>,
+        peer_blocks: &[Block],
+        network_state: &impl NetworkState,
+    ) -> bool {
+        let mut conflicting_blocks: Vec<ConflictingBlock> = Vec::new();
+
+        // The local ledger's version of the block, if it has one.
+        if let Ok(block) = self.ledger.get_block(block_index) {
+            let signature_set = self
+                .ledger
+                .get_block_signature_set(block_index)
+                .unwrap_or_default();
+            conflicting_blocks.push(ConflictingBlock {
+                block,
+                signature_set,
+                responder_ids: Vec::new(),
+                in_local_ledger: true,
+            });
+        }
+
+        for (block_id, responder_ids) in block_id_to_nodes.iter() {
+            let mut responder_ids: Vec<ResponderId> = responder_ids.iter().cloned().collect();
+            responder_ids.sort();
+
+            if let Some(conflicting_block) = conflicting_blocks
+                .iter_mut()
+                .find(|conflicting_block| conflicting_block.block.id == *block_id)
+            {
+                conflicting_block.responder_ids = responder_ids;
+                continue;
+            }
+
+            let block = match peer_blocks.iter().find(|block| block.id == *block_id) {
+                Some(block) => block.clone(),
+                None => continue,
+            };
+
+            // Ask the peers that have the block for the signatures over it.
+            let signature_set = responder_ids
+                .iter()
+                .filter_map(|responder_id| self.manager.conn(responder_id))
+                .find_map(|conn| {
+                    conn.fetch_block_by_id(block_id, Fibonacci::from_millis(10).take(5))
+                        .ok()
+                })
+                .map(|block_contents| block_contents.signature_set)
+                .unwrap_or_default();
+
+            conflicting_blocks.push(ConflictingBlock {
+                block,
+                signature_set,
+                responder_ids,
+                in_local_ledger: false,
+            });
+        }
+
+        let num_established = conflicting_blocks
+            .iter()
+            .filter(|conflicting_block| self.is_established(conflicting_block, network_state))
+            .count();
+        if num_established < 2 {
+            log::warn!(
+                self.logger,
+                "Ignoring unconfirmed conflicting blocks at block {}: {:?}",
+                block_index,
+                conflicting_blocks
+            );
+            return false;
+        }
+
+        let fork_evidence = ForkEvidence {
+            block_index,
+            conflicting_blocks,
+        };
+        log::crit!(
+            self.logger,
+            "Network fork detected at block {}, halting ledger sync: {:?}",
+            block_index,
+            fork_evidence
+        );
+        if let Err(err) = self.fork_evidence_store.record(fork_evidence) {
+            log::error!(self.logger, "Failed persisting fork evidence: {}", err);
+        }
+        true
+    }
+
+    /// Whether a block is established as part of the network's history: it is in the local
+    /// ledger, it is served by a blocking set that is also a quorum, or it carries enough valid
+    /// signatures from trusted signers.
+    fn is_established(
+        &self,
+        conflicting_block: &ConflictingBlock,
+        network_state: &impl NetworkState,
+    ) -> bool {
+        if conflicting_block.in_local_ledger {
+            return true;
+        }
+
+        let responder_ids = HashSet::from_iter(conflicting_block.responder_ids.iter().cloned());
+        if network_state.is_blocking_and_quorum(&responder_ids) {
+            return true;
+        }
+
+        self.required_block_signatures > 0
+            && conflicting_block
+                .signature_set
+                .num_valid_trusted_signatures(&conflicting_block.block, &self.trusted_block_signers)
+                >= self.required_block_signatures
+    }
+
     /// Append safe blocks to the local ledger.
     fn append_safe_blocks(
         &mut self,
@@ -310,6 +608,14 @@ impl<L: Ledger, BC: BlockchainConnection + 'static, TF: TransactionsFetcher + 's
             counters::TXO_IN_LEDGER.set(self.ledger.num_txos()? as i64);
         }
 
+        if let Some(post_append_hook) = self.post_append_hook.as_ref() {
+            let blocks: Vec<Block> = blocks_and_transactions
+                .iter()
+                .map(|(block, _transactions)| block.clone())
+                .collect();
+            post_append_hook(&blocks);
+        }
+
         Ok(())
     }
 }
@@ -323,16 +629,21 @@ impl<L: Ledger, BC: BlockchainConnection + 'static, TF: TransactionsFetcher + 's
 /// * `timeout` - Overall request timeout.
 ///
 /// Peers are queried concurrently, and any successful responses collected before a timeout occurs are returned.
+/// Also returns each peer's version of `append_after_block`, for peers whose blocks do not chain
+/// to it.
 fn get_blocks<BC: BlockchainConnection + 'static>(
     manager: &ConnectionManager<BC>,
     append_after_block: Block,
     limit: u32,
     timeout: Duration,
     logger: &Logger,
-) -> HashMap<ResponderId, Vec<Block>> {
+) -> (
+    HashMap<ResponderId, Vec<Block>>,
+    HashMap<ResponderId, Block>,
+) {
     // Query each peer in a separate worker thread. A separate thread performs a timeout.
     // Any responses obtained before the timeout are returned.
-    type ResultsMap = HashMap<ResponderId, Vec<Block>>;
+    type ResultsMap = HashMap<ResponderId, (Vec<Block>, Option<Block>)>;
     let results_and_condvar = Arc::new((Mutex::new(ResultsMap::default()), Condvar::new()));
 
     let append_after_block = Arc::new(append_after_block);
@@ -350,6 +661,7 @@ fn get_blocks<BC: BlockchainConnection + 'static>(
                 let start = thread_append_after_block.index + 1;
                 let end = start + u64::from(limit);
                 let mut blocks_result = Vec::new();
+                let mut diverging_block = None;
                 let responder_id = match conn.uri().responder_id() {
                     Ok(responder_id) => responder_id,
                     Err(e) => {
@@ -362,10 +674,23 @@ fn get_blocks<BC: BlockchainConnection + 'static>(
                         panic!("No node id");
                     }
                 };
-                match conn
-                    .fetch_blocks(start..end, Fibonacci::from_millis(10).take(5))
+                let fetch_result = {
+                    let _timer = counters::GET_BLOCKS_TIME.start_timer();
+                    conn.fetch_blocks(start..end, Fibonacci::from_millis(10).take(5))
+                };
+                match fetch_result
                     .map_err(LedgerSyncError::Consensus)
-                    .and_then(|blocks| verify_block_ids(blocks, &thread_append_after_block))
+                    .and_then(|blocks| {
+                        // Blocks that do not chain to our highest block mean the peer may disagree
+                        // with our ledger.
+                        if blocks.first().map_or(false, |block| {
+                            block.parent_id != thread_append_after_block.id
+                        }) {
+                            diverging_block =
+                                get_diverging_block(&conn, &thread_append_after_block, &logger);
+                        }
+                        verify_block_ids(blocks, &thread_append_after_block)
+                    })
                 {
                     Ok(mut blocks) => {
                         log::debug!(logger, "Received {} blocks from {}", blocks.len(), conn);
@@ -383,7 +708,7 @@ fn get_blocks<BC: BlockchainConnection + 'static>(
                 // would be represented as an empty vector and would be filtered out before
                 // returning from this function.
                 let mut results = lock.lock().expect("mutex poisoned");
-                results.insert(responder_id, blocks_result);
+                results.insert(responder_id, (blocks_result, diverging_block));
                 condvar.notify_one();
             })
             .expect("Failed spawning GetBlocks thread!");
@@ -397,13 +722,51 @@ fn get_blocks<BC: BlockchainConnection + 'static>(
         })
         .expect("waiting on condvar failed");
 
+    let diverging_blocks = HashMap::from_iter(worker_results.iter().filter_map(
+        |(responder_id, (_blocks, diverging_block))| {
+            diverging_block
+                .clone()
+                .map(|block| (responder_id.clone(), block))
+        },
+    ));
+
     // Filter out results with no blocks
-    HashMap::from_iter(
+    let node_to_blocks = HashMap::from_iter(
         worker_results
             .clone()
             .into_iter()
+            .map(|(responder_id, (blocks, _diverging_block))| (responder_id, blocks))
             .filter(|(_responder_id, blocks)| !blocks.is_empty()),
-    )
+    );
+
+    (node_to_blocks, diverging_blocks)
+}
+
+/// Gets the peer's version of `block`, if it differs from `block`.
+fn get_diverging_block<BC: BlockchainConnection>(
+    conn: &SyncConnection<BC>,
+    block: &Block,
+    logger: &Logger,
+) -> Option<Block> {
+    match conn.fetch_blocks(
+        block.index..block.index + 1,
+        Fibonacci::from_millis(10).take(5),
+    ) {
+        Ok(blocks) => blocks
+            .into_iter()
+            .find(|peer_block| peer_block.index == block.index)
+            .filter(|peer_block| peer_block.id != block.id && peer_block.is_block_id_valid()),
+        Err(err) => {
+            log::warn!(
+                logger,
+                "Failed to retrieve block {} from {}: {:?}",
+                block.index,
+                conn,
+                err
+            );
+            None
+        }
+    }
 }
 
 fn verify_block_ids(
@@ -460,198 +823,6 @@ fn group_by_block(
     block_index_to_grouping
 }
 
-/// Gets all transactions for each block in a list of Blocks.
-///
-/// It is assumed that all peers have identical Block IDs for the given blocks, so it is
-/// sufficient to obtain each transaction from a single peer. Specifically, this is expected to
-/// be used in conjunction with `group_by_block` which identifies peers who have identical blocks.
-///
-/// # Arguments
-/// * `transactions_fetcher` - The mechanism used for fetching transaction contents for a given
-/// block.
-/// * `safe_responder_ids` - ResponderIds that have been identified as agreeing with eachother on the
-///                     `blocks` we want to fetch.
-/// * `blocks` - List of blocks to fetch transactions for.
-/// * `timeout` - Overall request timeout.
-///
-/// Peers are queried concurrently. Currently, this method will run indefinitely until all
-/// transactions have been retrieved.
-fn get_transactions<TF: TransactionsFetcher + 'static>(
-    transactions_fetcher: Arc<TF>,
-    safe_responder_ids: &[ResponderId],
-    blocks: &[Block],
-    timeout: Duration,
-    logger: &Logger,
-) -> BTreeMap<BlockIndex, Option<Vec<RedactedTx>>> {
-    type ResultsMap = BTreeMap<BlockIndex, Option<Vec<RedactedTx>>>;
-
-    enum Msg {
-        ProcessBlock(Block),
-        Stop,
-    }
-
-    // The channel is going to hold the list of pending blocks we still need to get transactions
-    // for.
-    let (sender, receiver) = crossbeam_channel::bounded(blocks.len());
-    for block in blocks.iter().cloned() {
-        sender
-            .send(Msg::ProcessBlock(block))
-            .expect("failed sending to channel");
-    }
-
-    let results_and_condvar = Arc::new((Mutex::new(ResultsMap::new()), Condvar::new()));
-    let deadline = Instant::now() + timeout;
-
-    // Spawn worker threads.
-    let mut thread_handles = Vec::new();
-
-    // TODO: hardcoded limit
-    let num_workers = std::cmp::min(5, blocks.len());
-    for worker_num in 0..num_workers {
-        let thread_results_and_condvar = results_and_condvar.clone();
-        let thread_sender = sender.clone();
-        let thread_receiver = receiver.clone();
-        let thread_logger = logger.clone();
-        let thread_transactions_fetcher = transactions_fetcher.clone();
-        let thread_safe_responder_ids = safe_responder_ids.to_owned();
-
-        let thread_handle = thread::Builder::new()
-            .name(format!("GetTxs:{}", worker_num))
-            .spawn(move || {
-                let &(ref lock, ref condvar) = &*thread_results_and_condvar;
-
-                for msg in thread_receiver.iter() {
-                    match msg {
-                        Msg::ProcessBlock(block) => {
-                            // Check for timeout.
-                            if Instant::now() > deadline {
-                                log::error!(
-                                    thread_logger,
-                                    "Worker {} giving up on block {}: deadline exceeded",
-                                    worker_num,
-                                    block.index,
-                                );
-
-                                let mut results = lock.lock().expect("mutex poisoned");
-                                results.insert(block.index, None);
-                                condvar.notify_one();
-                                continue;
-                            }
-
-                            // Try and get transactions for this block.
-                            log::trace!(
-                                thread_logger,
-                                "Worker {} attempting block {}",
-                                worker_num,
-                                block.index
-                            );
-
-                            match thread_transactions_fetcher
-                                .get_transactions_by_block(
-                                    thread_safe_responder_ids.as_slice(),
-                                    &block,
-                                )
-                                .map_err(LedgerSyncError::from)
-                                .and_then(|transactions| {
-                                    let contents_hash = hash_block_contents(&transactions);
-                                    if contents_hash != block.contents_hash {
-                                        log::debug!(
-                                            thread_logger,
-                                            "Transaction and block mismatch: {:02x?} vs {:02x?}",
-                                            contents_hash,
-                                            block.contents_hash,
-                                        );
-                                        Err(LedgerSyncError::TransactionsAndBlockMismatch)
-                                    } else {
-                                        Ok(transactions)
-                                    }
-                                }) {
-                                Ok(transactions) => {
-                                    // Log
-                                    log::trace!(
-                                        thread_logger,
-                                        "Worker {} got {} transactions for block {}",
-                                        worker_num,
-                                        transactions.len(),
-                                        block.index
-                                    );
-
-                                    // passing the actual block and not just a block index.
-                                    let mut results = lock.lock().expect("mutex poisoned");
-                                    let old_result =
-                                        results.insert(block.index, Some(transactions));
-
-                                    // We should encounter each block index only once.
-                                    assert!(old_result.is_none());
-
-                                    // Signal condition variable to check if maybe we're done.
-                                    condvar.notify_one();
-                                }
-
-                                Err(err) => {
-                                    // Log
-                                    log::error!(
-                                        thread_logger,
-                                        "Worker {} failed getting transactions for block {}: {}",
-                                        worker_num,
-                                        block.index,
-                                        err
-                                    );
-
-                                    // Put back to queue for a retry
-                                    // TODO sleep?
-                                    thread_sender
-                                        .send(Msg::ProcessBlock(block))
-                                        .expect("failed sending to channel");
-                                }
-                            }
-                        }
-                        Msg::Stop => {
-                            return;
-                        }
-                    }
-                }
-            })
-            .expect("Failed spawning GetBlocks thread!");
-
-        thread_handles.push(thread_handle);
-    }
-
-    // Wait until we get all results, or we timeout. Note that timeout checking is handled inside
-    // the worker threads.
-    log::trace!(logger, "Waiting on {} results", blocks.len());
-    let &(ref lock, ref condvar) = &*results_and_condvar;
-    let results = condvar
-        .wait_until(lock.lock().unwrap(), |ref mut results| {
-            results.len() == blocks.len()
-        })
-        .expect("waiting on condvar failed");
-
-    // Sanity - we will only get here when results.len() == blocks.len(), which only happens when
-    // everything in the queue was proceesed.
-    assert!(receiver.is_empty());
-
-    // Tell all threads to stop.
-    for _ in 0..blocks.len() {
-        sender.send(Msg::Stop).expect("failed sending to channel");
-    }
-
-    // Wait for all threads to stop
-    log::trace!(logger, "Joining worker threads...");
-    for thread_handle in thread_handles.into_iter() {
-        if let Err(err) = thread_handle.join() {
-            log::error!(
-                logger,
-                "Failed joining get_transactions worker thread: {:?}",
-                err
-            );
-        }
-    }
-
-    // Return results.
-    results.clone()
-}
-
 /// Identify a sequence of blocks that are safe to append to the local node's ledger.
 ///
 /// A "safe" block satisfies:
@@ -772,10 +943,11 @@ mod tests {
     use super::*;
     use crate::{test_utils::MockTransactionsFetcher, SCPNetworkState};
     use common::{logger::test_with_logger, NodeID};
-    use ledger_db::test_utils::{get_mock_ledger, get_test_ledger_blocks};
-    use peers_tests::{test_node_id, test_peer_uri, MockPeerConnection};
+    use ledger_db::test_utils::{get_mock_ledger, get_test_ledger_blocks, MockLedger};
+    use peers_tests::{test_node_id, test_node_id_and_signer, test_peer_uri, MockPeerConnection};
     use scp::{core_types::Ballot, msg::*, *};
     use std::convert::TryFrom;
+    use transaction::{BlockSignature, BLOCK_VERSION};
     use RedactedTx;
 
     #[test_with_logger]
@@ -853,6 +1025,123 @@ mod tests {
         assert_eq!(sync_service.is_behind(&network_state), true);
     }
 
+    #[test_with_logger]
+    // With a checkpoint, blocks up to it are synced from the transactions fetcher alone, and the
+    // ledger is behind until it reaches the checkpoint.
+    fn test_attempt_ledger_sync_to_checkpoint(logger: Logger) {
+        let network_state = SCPNetworkState::new(
+            test_node_id(11).responder_id,
+            QuorumSet::empty(),
+            logger.clone(),
+        );
+        let source_ledger = get_mock_ledger(25);
+        let ledger = get_mock_ledger(1);
+        let conn_manager = ConnectionManager::<MockPeerConnection>::new(vec![], logger.clone());
+        let transactions_fetcher = MockTransactionsFetcher::new(source_ledger.clone());
+        let checkpoint = Checkpoint {
+            block_index: 20,
+            block_id: source_ledger.get_block(20).unwrap().id,
+        };
+        let mut sync_service = LedgerSyncService::new(
+            ledger.clone(),
+            conn_manager,
+            transactions_fetcher,
+            logger.clone(),
+        )
+        .with_checkpoint(checkpoint);
+
+        while sync_service.is_behind(&network_state) {
+            sync_service
+                .attempt_ledger_sync(&network_state, 10)
+                .unwrap();
+        }
+        assert_eq!(ledger.num_blocks().unwrap(), 21);
+        assert_eq!(
+            ledger.get_block(20).unwrap(),
+            source_ledger.get_block(20).unwrap()
+        );
+
+        // The next attempt switches to syncing with peers.
+        sync_service
+            .attempt_ledger_sync(&network_state, 10)
+            .unwrap();
+        assert!(sync_service.checkpoint_sync.is_none());
+    }
+
+    #[test_with_logger]
+    // `attempt_ledger_sync` should append every block the peers agree on, even when there are
+    // more blocks than fit in the transactions window.
+    fn test_attempt_ledger_sync(logger: Logger) {
+        let node_a = test_node_id(22);
+        let node_b = test_node_id(33);
+        let local_node_id = test_node_id(11);
+        let local_quorum_set: QuorumSet<ResponderId> = QuorumSet::new_with_node_ids(
+            2,
+            vec![node_a.responder_id.clone(), node_b.responder_id.clone()],
+        );
+
+        let num_blocks = 17;
+        let mock_ledger = get_mock_ledger(num_blocks);
+        let mut network_state = SCPNetworkState::<ResponderId>::new(
+            local_node_id.responder_id.clone(),
+            local_quorum_set,
+            logger.clone(),
+        );
+        let mut peer_conns = Vec::<MockPeerConnection>::new();
+        for (peer_uri, node_id) in vec![(test_peer_uri(22), node_a), (test_peer_uri(33), node_b)] {
+            peer_conns.push(MockPeerConnection::new(
+                peer_uri,
+                local_node_id.clone(),
+                mock_ledger.clone(),
+                10,
+            ));
+            network_state.push(Msg::new(
+                node_id.responder_id,
+                QuorumSet::empty(),
+                mock_ledger.num_blocks().unwrap() - 1,
+                Topic::Externalize(ExternalizePayload {
+                    C: Ballot::new(100, &["foo"]),
+                    HN: 4,
+                }),
+            ));
+        }
+
+        let ledger = get_mock_ledger(1);
+        let conn_manager = ConnectionManager::new(peer_conns, logger.clone());
+        let transactions_fetcher = MockTransactionsFetcher::new(mock_ledger.clone());
+        let appended_blocks = Arc::new(Mutex::new(Vec::new()));
+        let hook_appended_blocks = appended_blocks.clone();
+        let mut sync_service = LedgerSyncService::new(
+            ledger.clone(),
+            conn_manager,
+            transactions_fetcher,
+            logger.clone(),
+        )
+        .with_transactions_window(3)
+        .with_post_append_hook(Arc::new(move |blocks: &[Block]| {
+            hook_appended_blocks
+                .lock()
+                .unwrap()
+                .extend_from_slice(blocks)
+        }));
+
+        sync_service
+            .attempt_ledger_sync(&network_state, 100)
+            .unwrap();
+        assert_eq!(ledger.num_blocks().unwrap(), num_blocks as u64);
+        assert_eq!(
+            ledger.get_block(num_blocks as u64 - 1).unwrap(),
+            mock_ledger.get_block(num_blocks as u64 - 1).unwrap()
+        );
+        assert!(sync_service.prefetched_transactions.is_empty());
+
+        // The hook should have been called with every appended block, in order.
+        let expected_blocks: Vec<Block> = (1..num_blocks as u64)
+            .map(|block_index| mock_ledger.get_block(block_index).unwrap())
+            .collect();
+        assert_eq!(*appended_blocks.lock().unwrap(), expected_blocks);
+    }
+
     #[test_with_logger]
     // `get_blocks` should gracefully handle peers who don't respond before the timeout.
     fn test_get_blocks_with_timeout(logger: Logger) {
@@ -873,7 +1162,9 @@ mod tests {
         let conn_manager = ConnectionManager::new(vec![fast_peer, slow_peer], logger.clone());
 
         let limit: u32 = 10; // Number of blocks to get.
-        let responses = get_blocks(&conn_manager, first_block.clone(), limit, timeout, &logger);
+        let (responses, diverging_blocks) =
+            get_blocks(&conn_manager, first_block.clone(), limit, timeout, &logger);
+        assert!(diverging_blocks.is_empty());
 
         // Only node 1 should be in the responses.
         assert!(responses.contains_key(&test_peer_uri(1).responder_id().unwrap()));
@@ -890,157 +1181,6 @@ mod tests {
         }
     }
 
-    #[test_with_logger]
-    // `get_transactions` should get correct transactions for the indicated blocks.
-    fn test_get_transactions(logger: Logger) {
-        let local_node_id = test_node_id(123);
-
-        let num_blocks = 25;
-        let mock_ledger = get_mock_ledger(num_blocks);
-
-        let conn_manager = ConnectionManager::new(
-            vec![
-                MockPeerConnection::new(
-                    test_peer_uri(1),
-                    local_node_id.clone(),
-                    mock_ledger.clone(),
-                    10,
-                ),
-                MockPeerConnection::new(
-                    test_peer_uri(2),
-                    local_node_id.clone(),
-                    mock_ledger.clone(),
-                    10,
-                ),
-                MockPeerConnection::new(test_peer_uri(3), local_node_id, mock_ledger.clone(), 10),
-            ],
-            logger.clone(),
-        );
-
-        let transactions_fetcher = Arc::new(MockTransactionsFetcher::new(mock_ledger.clone()));
-
-        let responder_ids: Vec<ResponderId> = conn_manager.responder_ids();
-
-        let blocks: Vec<Block> = (0..10)
-            .map(|idx| mock_ledger.get_block(idx).unwrap())
-            .collect();
-
-        let transactions_by_block = get_transactions(
-            transactions_fetcher,
-            &responder_ids.as_slice(),
-            &blocks,
-            Duration::from_secs(1),
-            &logger,
-        );
-
-        log::trace!(
-            logger,
-            "get_transactions returned: {:?}",
-            transactions_by_block
-        );
-
-        // The correct number of results should be returned.
-        assert_eq!(transactions_by_block.len(), 10);
-
-        for (block_index, transactions_opt) in transactions_by_block {
-            match transactions_opt {
-                Some(transactions) => {
-                    let expected_transactions = mock_ledger
-                        .lock()
-                        .transactions_by_block_number
-                        .get(&block_index)
-                        .unwrap()
-                        .clone();
-                    // The transactions should be correct for each block.
-                    assert_eq!(transactions, expected_transactions);
-                }
-                None => {
-                    panic!("All results should be Some");
-                }
-            }
-        }
-    }
-
-    #[test_with_logger]
-    // `get_transactions` should verify the transactions returned matched the block requested.
-    fn test_get_transactions_validates_block(logger: Logger) {
-        let local_node_id = test_node_id(123);
-
-        let num_blocks = 25;
-        let mock_ledger = get_mock_ledger(num_blocks);
-
-        let conn_manager = ConnectionManager::new(
-            vec![
-                MockPeerConnection::new(
-                    test_peer_uri(1),
-                    local_node_id.clone(),
-                    mock_ledger.clone(),
-                    10,
-                ),
-                MockPeerConnection::new(
-                    test_peer_uri(2),
-                    local_node_id.clone(),
-                    mock_ledger.clone(),
-                    10,
-                ),
-                MockPeerConnection::new(test_peer_uri(3), local_node_id, mock_ledger.clone(), 10),
-            ],
-            logger.clone(),
-        );
-
-        let transactions_fetcher = Arc::new(MockTransactionsFetcher::new(mock_ledger.clone()));
-
-        let responder_ids: Vec<ResponderId> = conn_manager.responder_ids();
-
-        let mut blocks: Vec<Block> = (0..10)
-            .map(|idx| mock_ledger.get_block(idx).unwrap())
-            .collect();
-
-        // Alter the contents hash of one of the blocks. This should cause
-        // `get_transactions` to error. Block index 3 is chosen arbitrarily.
-        const BAD_BLOCK_INDEX: u64 = 3;
-
-        blocks[BAD_BLOCK_INDEX as usize].contents_hash.0[0] =
-            !blocks[BAD_BLOCK_INDEX as usize].contents_hash.0[0];
-
-        let transactions_by_block = get_transactions(
-            transactions_fetcher,
-            &responder_ids.as_slice(),
-            &blocks,
-            Duration::from_secs(1),
-            &logger,
-        );
-
-        log::trace!(
-            logger,
-            "get_transactions returned: {:?}",
-            transactions_by_block
-        );
-
-        // The correct number of results should be returned.
-        assert_eq!(transactions_by_block.len(), 10);
-
-        for (block_index, transactions_opt) in transactions_by_block {
-            match transactions_opt {
-                Some(transactions) => {
-                    assert_ne!(block_index, BAD_BLOCK_INDEX);
-
-                    let expected_transactions = mock_ledger
-                        .lock()
-                        .transactions_by_block_number
-                        .get(&block_index)
-                        .unwrap()
-                        .clone();
-                    // The transactions should be correct for each block.
-                    assert_eq!(transactions, expected_transactions);
-                }
-                None => {
-                    assert_eq!(block_index, BAD_BLOCK_INDEX);
-                }
-            }
-        }
-    }
-
     #[test]
     #[ignore]
     fn test_get_transactions_for_nonexistent_blocks() {
@@ -1262,12 +1402,204 @@ mod tests {
         }
     }
 
-    #[test]
-    #[ignore]
-    fn test_get_potentially_safe_blocks_network_fork() {
-        // TODO: `get_potentially_safe_blocks` should do the right thing if the network is forked.
-        // This may mean returning None, returning the highest block before the fork, returning
-        // blocks along one fork if it is the only fork with quorum.
+    /// A mock ledger with `n_blocks` blocks, whose last block differs from `get_mock_ledger`'s.
+    fn get_forked_mock_ledger(n_blocks: usize) -> MockLedger {
+        let mut ledger = get_mock_ledger(n_blocks - 1);
+        let parent = ledger.get_block(n_blocks as u64 - 2).unwrap();
+        let block = Block::new(
+            BLOCK_VERSION,
+            &parent.id,
+            parent.index + 1,
+            &Default::default(),
+            &[],
+        );
+        ledger.set_block(&block, &[]);
+        ledger
+    }
+
+    #[test_with_logger]
+    // If peers disagree on a block and each version is signed by a trusted signer, the fork should
+    // be recorded and no blocks should be returned.
+    fn test_get_potentially_safe_blocks_network_fork(logger: Logger) {
+        let trivial_quorum_set = QuorumSet::<NodeID>::empty();
+
+        let node_a_uri = test_peer_uri(22);
+        let node_a = (test_node_id(22), trivial_quorum_set.clone());
+
+        let node_b_uri = test_peer_uri(33);
+        let node_b = (test_node_id(33), trivial_quorum_set);
+
+        let local_node_id = test_node_id(11);
+        let local_quorum_set: QuorumSet<ResponderId> = QuorumSet::new_with_node_ids(
+            1,
+            vec![node_a.0.responder_id.clone(), node_b.0.responder_id],
+        );
+
+        let network_state = SCPNetworkState::<ResponderId>::new(
+            local_node_id.responder_id.clone(),
+            local_quorum_set,
+            logger.clone(),
+        );
+
+        // Peers A and B agree on blocks [0, 5), and disagree on block 5.
+        let mut ledger_a = get_forked_mock_ledger(6);
+        let mut ledger_b = get_mock_ledger(8);
+        let peer_conns = vec![
+            MockPeerConnection::new(node_a_uri, local_node_id.clone(), ledger_a.clone(), 50),
+            MockPeerConnection::new(node_b_uri, local_node_id, ledger_b.clone(), 50),
+        ];
+
+        let ledger = get_mock_ledger(5);
+        let conn_manager = ConnectionManager::new(peer_conns, logger.clone());
+        let transactions_fetcher = MockTransactionsFetcher::new(ledger_b.clone());
+        let sync_service =
+            LedgerSyncService::new(ledger, conn_manager, transactions_fetcher, logger.clone());
+
+        // Without signatures, neither version of block 5 is established, so syncing goes on.
+        let (_, signer_a) = test_node_id_and_signer(22);
+        let (_, signer_b) = test_node_id_and_signer(33);
+        let mut sync_service = sync_service
+            .with_trusted_block_signers(vec![signer_a.public_key(), signer_b.public_key()], 1);
+        assert!(sync_service
+            .get_potentially_safe_blocks(&network_state, 100)
+            .is_none());
+        assert!(!sync_service.fork_evidence_store().is_forked());
+
+        for (peer_ledger, signer) in vec![(&mut ledger_a, &signer_a), (&mut ledger_b, &signer_b)] {
+            let block = peer_ledger.get_block(5).unwrap();
+            let signature = BlockSignature::from_block_and_keypair(&block, signer).unwrap();
+            peer_ledger.add_block_signature(5, &signature).unwrap();
+        }
+        assert!(sync_service
+            .get_potentially_safe_blocks(&network_state, 100)
+            .is_none());
+
+        let fork_evidence = sync_service.fork_evidence_store().get().unwrap();
+        assert_eq!(fork_evidence.block_index, 5);
+        assert_eq!(fork_evidence.conflicting_blocks.len(), 2);
+        for conflicting_block in fork_evidence.conflicting_blocks.iter() {
+            assert!(!conflicting_block.in_local_ledger);
+            assert_eq!(conflicting_block.responder_ids.len(), 1);
+            let peer_ledger = if conflicting_block.responder_ids[0] == node_a.0.responder_id {
+                &ledger_a
+            } else {
+                &ledger_b
+            };
+            assert_eq!(conflicting_block.block, peer_ledger.get_block(5).unwrap());
+        }
+
+        // Syncing should be halted.
+        match sync_service.attempt_ledger_sync(&network_state, 100) {
+            Err(LedgerSyncError::ForkDetected) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    #[test_with_logger]
+    // If peers disagree with the local ledger's highest block, the fork should be recorded.
+    fn test_get_potentially_safe_blocks_local_ledger_diverged(logger: Logger) {
+        let trivial_quorum_set = QuorumSet::<NodeID>::empty();
+
+        let node_a_uri = test_peer_uri(22);
+        let node_a = (test_node_id(22), trivial_quorum_set.clone());
+
+        let node_b_uri = test_peer_uri(33);
+        let node_b = (test_node_id(33), trivial_quorum_set);
+
+        let local_node_id = test_node_id(11);
+        let local_quorum_set: QuorumSet<ResponderId> =
+            QuorumSet::new_with_node_ids(2, vec![node_a.0.responder_id, node_b.0.responder_id]);
+
+        let network_state = SCPNetworkState::<ResponderId>::new(
+            local_node_id.responder_id.clone(),
+            local_quorum_set,
+            logger.clone(),
+        );
+
+        let peer_ledger = get_mock_ledger(10);
+        let peer_conns = vec![
+            MockPeerConnection::new(node_a_uri, local_node_id.clone(), peer_ledger.clone(), 50),
+            MockPeerConnection::new(node_b_uri, local_node_id, peer_ledger.clone(), 50),
+        ];
+
+        // The local ledger's block 5 is not the network's.
+        let ledger = get_forked_mock_ledger(6);
+        let conn_manager = ConnectionManager::new(peer_conns, logger.clone());
+        let transactions_fetcher = MockTransactionsFetcher::new(peer_ledger.clone());
+        let mut sync_service = LedgerSyncService::new(
+            ledger.clone(),
+            conn_manager,
+            transactions_fetcher,
+            logger.clone(),
+        );
+
+        assert!(sync_service
+            .get_potentially_safe_blocks(&network_state, 100)
+            .is_none());
+
+        let fork_evidence = sync_service.fork_evidence_store().get().unwrap();
+        assert_eq!(fork_evidence.block_index, 5);
+        assert_eq!(fork_evidence.conflicting_blocks.len(), 2);
+
+        let local_block = &fork_evidence.conflicting_blocks[0];
+        assert!(local_block.in_local_ledger);
+        assert_eq!(local_block.block, ledger.get_block(5).unwrap());
+
+        let peer_block = &fork_evidence.conflicting_blocks[1];
+        assert!(!peer_block.in_local_ledger);
+        assert_eq!(peer_block.block, peer_ledger.get_block(5).unwrap());
+        assert_eq!(peer_block.responder_ids.len(), 2);
+    }
+
+    #[test_with_logger]
+    // A single peer that disagrees with the local ledger should neither halt syncing nor keep the
+    // other peers' blocks from being returned.
+    fn test_get_potentially_safe_blocks_one_peer_diverged(logger: Logger) {
+        let node_ids: Vec<NodeID> = (22..25).map(test_node_id).collect();
+        let local_node_id = test_node_id(11);
+        let local_quorum_set: QuorumSet<ResponderId> = QuorumSet::new_with_node_ids(
+            2,
+            node_ids
+                .iter()
+                .map(|node_id| node_id.responder_id.clone())
+                .collect(),
+        );
+        let network_state = SCPNetworkState::<ResponderId>::new(
+            local_node_id.responder_id.clone(),
+            local_quorum_set,
+            logger.clone(),
+        );
+
+        // Peer 22's block 4 is not the one in the local ledger.
+        let mut forked_ledger = get_forked_mock_ledger(5);
+        let parent = forked_ledger.get_block(4).unwrap();
+        let block = Block::new(BLOCK_VERSION, &parent.id, 5, &Default::default(), &[]);
+        forked_ledger.set_block(&block, &[]);
+        let peer_ledger = get_mock_ledger(10);
+        let peer_conns = vec![
+            MockPeerConnection::new(test_peer_uri(22), local_node_id.clone(), forked_ledger, 50),
+            MockPeerConnection::new(
+                test_peer_uri(23),
+                local_node_id.clone(),
+                peer_ledger.clone(),
+                50,
+            ),
+            MockPeerConnection::new(test_peer_uri(24), local_node_id, peer_ledger.clone(), 50),
+        ];
+
+        let ledger = get_mock_ledger(5);
+        let conn_manager = ConnectionManager::new(peer_conns, logger.clone());
+        let transactions_fetcher = MockTransactionsFetcher::new(peer_ledger.clone());
+        let mut sync_service =
+            LedgerSyncService::new(ledger, conn_manager, transactions_fetcher, logger.clone());
+
+        let (responder_ids, block_index, blocks) = sync_service
+            .get_potentially_safe_blocks(&network_state, 100)
+            .unwrap();
+        assert!(!sync_service.fork_evidence_store().is_forked());
+        assert_eq!(responder_ids.len(), 2);
+        assert_eq!(block_index, 9);
+        assert_eq!(blocks.len(), 5);
     }
 
     #[test_with_logger]
@@ -1,12 +1,14 @@
 // Copyright (c) 2018-2020 MobileCoin Inc.
 
-//! An integration between `PollingNetworkState` and `LedgerSyncService` that performs the sync in
-//! a background thread.
+//! An integration between a `NetworkState`, such as `PollingNetworkState`, and `LedgerSyncService`
+//! that performs the sync in a background thread.
 
-use crate::{LedgerSyncService, PollingNetworkState, TransactionsFetcher};
+use crate::{
+    counters, ForkEvidence, ForkEvidenceStore, LedgerSyncService, NetworkState, TransactionsFetcher,
+};
 use common::logger::{log, Logger};
 use ledger_db::Ledger;
-use mcconnection::{BlockchainConnection, ConnectionManager};
+use mcconnection::BlockchainConnection;
 use std::{
     sync::{
         atomic::{AtomicBool, Ordering},
@@ -23,6 +25,7 @@ pub struct LedgerSyncServiceThread {
     join_handle: Option<thread::JoinHandle<()>>,
     currently_behind: Arc<AtomicBool>,
     stop_requested: Arc<AtomicBool>,
+    fork_evidence_store: ForkEvidenceStore,
 }
 
 impl LedgerSyncServiceThread {
@@ -30,21 +33,15 @@ impl LedgerSyncServiceThread {
         L: Ledger + 'static,
         BC: BlockchainConnection + 'static,
         TF: TransactionsFetcher + 'static,
+        NS: NetworkState + 'static,
     >(
         ledger: L,
-        manager: ConnectionManager<BC>,
-        network_state: PollingNetworkState<BC>,
-        transactions_fetcher: TF,
+        ledger_sync_service: LedgerSyncService<L, BC, TF>,
+        network_state: NS,
         poll_interval: Duration,
         logger: Logger,
     ) -> Self {
-        let ledger_sync_service = LedgerSyncService::new(
-            ledger.clone(),
-            manager,
-            transactions_fetcher,
-            logger.clone(),
-        );
-
+        let fork_evidence_store = ledger_sync_service.fork_evidence_store().clone();
         let currently_behind = Arc::new(AtomicBool::new(false));
         let stop_requested = Arc::new(AtomicBool::new(false));
 
@@ -71,6 +68,7 @@ impl LedgerSyncServiceThread {
             join_handle,
             currently_behind,
             stop_requested,
+            fork_evidence_store,
         }
     }
 
@@ -85,14 +83,20 @@ impl LedgerSyncServiceThread {
         self.currently_behind.load(Ordering::SeqCst)
     }
 
+    /// Evidence of a fork of the network, if one was detected. Syncing is halted once it is.
+    pub fn fork_evidence(&self) -> Option<ForkEvidence> {
+        self.fork_evidence_store.get()
+    }
+
     fn thread_entrypoint<
         L: Ledger,
         BC: BlockchainConnection + 'static,
         TF: TransactionsFetcher + 'static,
+        NS: NetworkState + 'static,
     >(
         ledger: L,
         mut ledger_sync_service: LedgerSyncService<L, BC, TF>,
-        mut network_state: PollingNetworkState<BC>,
+        mut network_state: NS,
         poll_interval: Duration,
         currently_behind: Arc<AtomicBool>,
         stop_requested: Arc<AtomicBool>,
@@ -106,6 +110,12 @@ impl LedgerSyncServiceThread {
                 break;
             }
 
+            // Syncing stays halted once the network forked, until an operator intervenes.
+            if ledger_sync_service.fork_evidence_store().is_forked() {
+                std::thread::sleep(poll_interval);
+                continue;
+            }
+
             // See if we're currently behind. If we're not, poll to be sure.
             let mut is_behind = ledger_sync_service.is_behind(&network_state);
             if !is_behind {
@@ -115,12 +125,17 @@ impl LedgerSyncServiceThread {
 
             // Store current state and log.
             currently_behind.store(is_behind, Ordering::SeqCst);
+            let num_blocks = ledger.num_blocks().unwrap();
+            if let Some(highest_block_index) = network_state.highest_block_index_on_network() {
+                counters::BLOCKS_BEHIND
+                    .set((highest_block_index + 1).saturating_sub(num_blocks) as i64);
+            }
             if is_behind {
                 log::debug!(
                     logger,
                     "ledger sync service is_behind: {:?} num blocks {:?}",
                     is_behind,
-                    ledger.num_blocks().unwrap()
+                    num_blocks
                 );
             }
 
@@ -3,7 +3,13 @@
 #![feature(wait_timeout_until)]
 #![feature(wait_until)]
 
+mod adaptive_limiter;
+mod caching_transactions_fetcher;
+mod checkpoint_sync;
+mod consensus_msg_network_state;
 mod counters;
+mod fork_evidence;
+mod grpc_transactions_fetcher;
 mod ledger_sync_error;
 mod ledger_sync_service;
 mod ledger_sync_service_thread;
@@ -11,15 +17,23 @@ mod network_state_trait;
 mod polling_network_state;
 mod reqwest_transactions_fetcher;
 mod scp_network_state;
+mod subscription_network_state;
 mod transactions_fetcher_trait;
+mod transactions_pipeline;
 
+pub use caching_transactions_fetcher::CachingTransactionsFetcher;
+pub use checkpoint_sync::Checkpoint;
+pub use consensus_msg_network_state::ConsensusMsgNetworkState;
+pub use fork_evidence::{ConflictingBlock, ForkEvidence, ForkEvidenceError, ForkEvidenceStore};
+pub use grpc_transactions_fetcher::{GrpcTransactionsFetcher, GrpcTransactionsFetcherError};
 pub use ledger_sync_error::LedgerSyncError;
-pub use ledger_sync_service::LedgerSyncService;
+pub use ledger_sync_service::{LedgerSyncService, PostAppendHook};
 pub use ledger_sync_service_thread::LedgerSyncServiceThread;
 pub use network_state_trait::NetworkState;
 pub use polling_network_state::PollingNetworkState;
 pub use reqwest_transactions_fetcher::ReqwestTransactionsFetcher;
 pub use scp_network_state::SCPNetworkState;
+pub use subscription_network_state::SubscriptionNetworkState;
 pub use transactions_fetcher_trait::{TransactionFetcherError, TransactionsFetcher};
 
 #[cfg(any(test, feature = "test_utils"))]
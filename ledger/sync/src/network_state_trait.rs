@@ -19,4 +19,11 @@ pub trait NetworkState: Send {
     /// # Arguments
     /// * `local_block_index` - The highest block externalized by this node.
     fn is_behind(&self, local_block_index: BlockIndex) -> bool;
+
+    /// Returns the highest block index any peer is known to have externalized, if any.
+    fn highest_block_index_on_network(&self) -> Option<BlockIndex>;
+
+    /// Refreshes our view of the network. Does nothing for implementations that are kept up to
+    /// date by other means, e.g. by receiving consensus messages.
+    fn poll(&mut self) {}
 }
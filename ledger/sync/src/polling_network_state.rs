@@ -56,7 +56,7 @@ impl<BC: BlockchainConnection + 'static> PollingNetworkState<BC> {
     }
 
     /// Polls peers to find out the current state of the network.
-    pub fn poll(&mut self) {
+    fn poll_peers(&mut self) {
         type ResultsMap = HashMap<ResponderId, Option<BlockIndex>>;
         let results_and_condvar = Arc::new((Mutex::new(ResultsMap::default()), Condvar::new()));
 
@@ -141,7 +141,7 @@ impl<BC: BlockchainConnection + 'static> PollingNetworkState<BC> {
     }
 }
 
-impl<BC: BlockchainConnection> NetworkState for PollingNetworkState<BC> {
+impl<BC: BlockchainConnection + 'static> NetworkState for PollingNetworkState<BC> {
     /// Returns true if `connections` forms a blocking set for this node and, if the local node is
     /// included, a quorum.
     ///
@@ -158,4 +158,14 @@ impl<BC: BlockchainConnection> NetworkState for PollingNetworkState<BC> {
     fn is_behind(&self, local_block_index: BlockIndex) -> bool {
         self.scp_network_state.is_behind(local_block_index)
     }
+
+    /// Returns the highest block index any peer reported when last polled, if any.
+    fn highest_block_index_on_network(&self) -> Option<BlockIndex> {
+        self.scp_network_state.highest_block_index_on_network()
+    }
+
+    /// Polls peers to find out the current state of the network.
+    fn poll(&mut self) {
+        self.poll_peers();
+    }
 }
@@ -3,21 +3,21 @@
 //! Implementation of the `TransactionsFetcher` trait that fetches transactions data over http(s)
 //! using the `reqwest` library. It can be used, for example, to get transaction data from S3.
 
-use crate::transactions_fetcher_trait::{TransactionFetcherError, TransactionsFetcher};
+use crate::{
+    adaptive_limiter::AdaptiveLimiter,
+    transactions_fetcher_trait::{TransactionFetcherError, TransactionsFetcher},
+};
 use common::{
     logger::{log, Logger},
     ResponderId,
 };
 use failure::Fail;
+use keys::Ed25519Public;
 use mobilecoin_api::{blockchain, conversions::block_num_to_s3block_path};
 use reqwest::Error as ReqwestError;
 use serde::{Deserialize, Serialize};
-use std::{
-    convert::TryFrom,
-    fs,
-    sync::atomic::{AtomicU64, Ordering},
-};
-use transaction::{Block, BlockSignature, RedactedTx};
+use std::{convert::TryFrom, fs, time::Instant};
+use transaction::{Block, BlockIndex, BlockSignature, BlockSignatureSet, RedactedTx};
 use url::Url;
 
 #[derive(Debug, Fail)]
@@ -46,18 +46,33 @@ impl From<ReqwestError> for ReqwestTransactionsFetcherError {
 
 impl TransactionFetcherError for ReqwestTransactionsFetcherError {}
 
+/// Number of concurrent requests each source starts out being trusted with.
+const INITIAL_REQUESTS_PER_SOURCE: usize = 2;
+
+/// Default upper bound on the number of concurrent requests made to each source.
+const DEFAULT_MAX_REQUESTS_PER_SOURCE: usize = 16;
+
 #[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct S3BlockData {
     pub block: Block,
     pub transactions: Vec<RedactedTx>,
     pub signature: Option<BlockSignature>,
+    pub signature_set: BlockSignatureSet,
 }
 
 pub struct ReqwestTransactionsFetcher {
     pub source_urls: Vec<Url>,
     client: reqwest::Client,
     logger: Logger,
-    source_index_counter: AtomicU64,
+
+    /// Spreads requests across sources, adapting how many each takes to its latency and errors.
+    source_limiter: AdaptiveLimiter,
+
+    /// Signers whose block signatures count towards `required_block_signatures`.
+    trusted_block_signers: Vec<Ed25519Public>,
+
+    /// Minimum number of valid signatures by trusted signers a block must carry to be accepted.
+    required_block_signatures: usize,
 }
 
 impl ReqwestTransactionsFetcher {
@@ -89,14 +104,45 @@ impl ReqwestTransactionsFetcher {
             })
             .collect();
 
+        let source_urls = source_urls?;
+        let source_limiter = AdaptiveLimiter::new(
+            source_urls.len(),
+            INITIAL_REQUESTS_PER_SOURCE,
+            DEFAULT_MAX_REQUESTS_PER_SOURCE,
+        );
+
         Ok(Self {
-            source_urls: source_urls?,
+            source_urls,
             client,
             logger,
-            source_index_counter: AtomicU64::new(0),
+            source_limiter,
+            trusted_block_signers: Vec::new(),
+            required_block_signatures: 0,
         })
     }
 
+    /// Only accept blocks that carry at least `threshold` valid signatures made by
+    /// `trusted_signers`.
+    pub fn with_required_block_signatures(
+        mut self,
+        trusted_signers: Vec<Ed25519Public>,
+        threshold: usize,
+    ) -> Self {
+        self.trusted_block_signers = trusted_signers;
+        self.required_block_signatures = threshold;
+        self
+    }
+
+    /// Never make more than `max_requests` concurrent requests to a single source.
+    pub fn with_max_requests_per_source(mut self, max_requests: usize) -> Self {
+        self.source_limiter = AdaptiveLimiter::new(
+            self.source_urls.len(),
+            INITIAL_REQUESTS_PER_SOURCE,
+            max_requests,
+        );
+        self
+    }
+
     pub fn block_from_url(
         &self,
         url: &Url,
@@ -162,10 +208,40 @@ impl ReqwestTransactionsFetcher {
             })?;
         }
 
+        let mut signature_set = bc_block
+            .signature_set
+            .into_option()
+            .as_ref()
+            .map(BlockSignatureSet::try_from)
+            .transpose()
+            .map_err(|err| {
+                ReqwestTransactionsFetcherError::InvalidBlockReceived(
+                    url.to_string(),
+                    format!("invalid block signature set: {:?}", err),
+                )
+            })?
+            .unwrap_or_default();
+        if let Some(signature) = signature.as_ref() {
+            signature_set.insert(signature.clone());
+        }
+
+        let num_trusted_signatures =
+            signature_set.num_valid_trusted_signatures(&lg_block, &self.trusted_block_signers);
+        if num_trusted_signatures < self.required_block_signatures {
+            return Err(ReqwestTransactionsFetcherError::InvalidBlockReceived(
+                url.to_string(),
+                format!(
+                    "block has {} valid trusted signatures, {} required",
+                    num_trusted_signatures, self.required_block_signatures
+                ),
+            ));
+        }
+
         let s3_block_data = S3BlockData {
             block: lg_block,
             transactions: tx_stored,
             signature,
+            signature_set,
         };
         Ok(s3_block_data)
     }
@@ -182,39 +258,61 @@ impl ReqwestTransactionsFetcher {
         let s3block = self.block_from_url(&url)?;
         Ok((s3block.block, s3block.transactions))
     }
-}
-
-impl TransactionsFetcher for ReqwestTransactionsFetcher {
-    type Error = ReqwestTransactionsFetcherError;
 
-    fn get_transactions_by_block(
+    /// Fetches a block from the source with the most spare capacity, waiting for one to have
+    /// some if necessary.
+    fn block_by_index(
         &self,
-        _safe_responder_ids: &[ResponderId],
-        block: &Block,
-    ) -> Result<Vec<RedactedTx>, Self::Error> {
-        // Get the source to fetch from.
-        let source_index_counter =
-            self.source_index_counter.fetch_add(1, Ordering::SeqCst) as usize;
-        let source_url = &self.source_urls[source_index_counter % self.source_urls.len()];
-
+        block_index: BlockIndex,
+    ) -> Result<(Url, S3BlockData), ReqwestTransactionsFetcherError> {
         // Construct URL for the block we are trying to fetch.
-        let filename = block_num_to_s3block_path(block.index)
+        let filename = block_num_to_s3block_path(block_index)
             .into_os_string()
             .into_string()
             .unwrap();
-        let url = source_url
-            .join(&filename)
-            .map_err(|e| ReqwestTransactionsFetcherError::UrlParse(filename, e))?;
+
+        // Get the source to fetch from.
+        let source_index = self.source_limiter.acquire();
+        let url = match self.source_urls[source_index].join(&filename) {
+            Ok(url) => url,
+            Err(err) => {
+                self.source_limiter.record_failure(source_index);
+                return Err(ReqwestTransactionsFetcherError::UrlParse(filename, err));
+            }
+        };
 
         // Try and get the block.
         log::debug!(
             self.logger,
             "Attempting to fetch block {} from {}",
-            block.index,
+            block_index,
             url
         );
 
-        let s3_block_data = self.block_from_url(&url)?;
+        let start = Instant::now();
+        match self.block_from_url(&url) {
+            Ok(s3_block_data) => {
+                self.source_limiter
+                    .record_success(source_index, start.elapsed());
+                Ok((url, s3_block_data))
+            }
+            Err(err) => {
+                self.source_limiter.record_failure(source_index);
+                Err(err)
+            }
+        }
+    }
+}
+
+impl TransactionsFetcher for ReqwestTransactionsFetcher {
+    type Error = ReqwestTransactionsFetcherError;
+
+    fn get_transactions_by_block(
+        &self,
+        _safe_responder_ids: &[ResponderId],
+        block: &Block,
+    ) -> Result<Vec<RedactedTx>, Self::Error> {
+        let (url, s3_block_data) = self.block_by_index(block.index)?;
 
         // Check that we received data for the block we actually asked about.
         if *block != s3_block_data.block {
@@ -227,4 +325,12 @@ impl TransactionsFetcher for ReqwestTransactionsFetcher {
         // Got what we wanted!
         Ok(s3_block_data.transactions)
     }
+
+    fn get_block_by_index(
+        &self,
+        block_index: BlockIndex,
+    ) -> Result<(Block, Vec<RedactedTx>), Self::Error> {
+        let (_url, s3_block_data) = self.block_by_index(block_index)?;
+        Ok((s3_block_data.block, s3_block_data.transactions))
+    }
 }
@@ -65,6 +65,11 @@ impl<ID: GenericNodeId + Clone + Eq + PartialEq + Hash> SCPNetworkState<ID> {
         }
     }
 
+    /// Replace the quorum set used to decide whether we are behind. Known peer slots are kept.
+    pub fn set_quorum_set(&mut self, local_quorum_set: QuorumSet<ID>) {
+        self.local_quorum_set = local_quorum_set;
+    }
+
     pub fn peer_to_current_slot(&self) -> &HashMap<ID, SlotIndex> {
         &self.id_to_current_slot
     }
@@ -137,6 +142,11 @@ impl<ID: GenericNodeId + Send + AsRef<ResponderId> + DeserializeOwned + Serializ
                 .map(|node_id| node_id.as_ref().clone()),
         ))
     }
+
+    /// Returns the highest block index any peer is known to have externalized, if any.
+    fn highest_block_index_on_network(&self) -> Option<BlockIndex> {
+        self.peer_to_current_slot().values().max().cloned()
+    }
 }
 
 #[cfg(test)]
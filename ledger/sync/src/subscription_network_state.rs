@@ -0,0 +1,240 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! NetworkState implementation that is not part of consensus, and learns about the blocks nodes
+//! externalize from their block subscriptions. Like `PollingNetworkState`, it fakes SCP messages
+//! and utilizes SCPNetworkState, but hears about new blocks as soon as they are appended instead of
+//! once per poll.
+
+use crate::{network_state_trait::NetworkState, scp_network_state::SCPNetworkState};
+use common::{
+    logger::{log, Logger},
+    HashSet, ResponderId,
+};
+use mcconnection::{
+    BlockchainConnection, Connection, ConnectionManager, RetryableBlockchainConnection,
+    SyncConnection,
+};
+use mcuri::ConnectionUri;
+use retry::delay::Fibonacci;
+use scp::{core_types::Ballot, msg::ExternalizePayload, Msg, QuorumSet, SlotIndex, Topic};
+use std::{
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+use transaction::BlockIndex;
+
+// See `PollingNetworkState`.
+const FAKE_NODE_ID: &str = "fake:7777";
+
+/// How long to wait before subscribing again, after a subscription ends or fails.
+const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(1);
+
+pub struct SubscriptionNetworkState {
+    /// SCPNetworkState instance that provides the actual blocking/quorum set check logic. Shared
+    /// with the subscriber threads.
+    scp_network_state: Arc<Mutex<SCPNetworkState<ResponderId>>>,
+
+    /// Tells the subscriber threads to stop.
+    stop_requested: Arc<AtomicBool>,
+}
+
+impl SubscriptionNetworkState {
+    /// Subscribes to blocks from each of the nodes in `manager`, in background threads.
+    pub fn new<BC: BlockchainConnection + 'static>(
+        quorum_set: QuorumSet<ResponderId>,
+        manager: ConnectionManager<BC>,
+        logger: Logger,
+    ) -> Self {
+        let local_node_id = ResponderId::from_str(FAKE_NODE_ID).unwrap();
+        let scp_network_state = Arc::new(Mutex::new(SCPNetworkState::new(
+            local_node_id,
+            quorum_set,
+            logger.clone(),
+        )));
+        let stop_requested = Arc::new(AtomicBool::new(false));
+
+        for conn in manager.conns() {
+            let responder_id = conn
+                .uri()
+                .responder_id()
+                .expect("Could not get responder_id from URI");
+
+            let thread_scp_network_state = scp_network_state.clone();
+            let thread_stop_requested = stop_requested.clone();
+            let thread_logger = logger.clone();
+            thread::Builder::new()
+                .name(format!("Subscribe:{}", responder_id))
+                .spawn(move || {
+                    follow_node(
+                        conn,
+                        responder_id,
+                        thread_scp_network_state,
+                        thread_stop_requested,
+                        thread_logger,
+                    )
+                })
+                .expect("Failed spawning subscriber thread!");
+        }
+
+        Self {
+            scp_network_state,
+            stop_requested,
+        }
+    }
+}
+
+impl Drop for SubscriptionNetworkState {
+    fn drop(&mut self) {
+        // Subscriber threads notice once their subscription yields or ends.
+        self.stop_requested.store(true, Ordering::SeqCst);
+    }
+}
+
+impl NetworkState for SubscriptionNetworkState {
+    fn is_blocking_and_quorum(&self, conn_ids: &HashSet<ResponderId>) -> bool {
+        self.scp_network_state
+            .lock()
+            .expect("mutex poisoned")
+            .is_blocking_and_quorum(conn_ids)
+    }
+
+    fn is_behind(&self, local_block_index: BlockIndex) -> bool {
+        self.scp_network_state
+            .lock()
+            .expect("mutex poisoned")
+            .is_behind(local_block_index)
+    }
+
+    fn highest_block_index_on_network(&self) -> Option<BlockIndex> {
+        self.scp_network_state
+            .lock()
+            .expect("mutex poisoned")
+            .highest_block_index_on_network()
+    }
+}
+
+/// Records every block `conn` externalizes into `scp_network_state`, subscribing again whenever a
+/// subscription ends, until a stop is requested.
+fn follow_node<BC: BlockchainConnection>(
+    conn: SyncConnection<BC>,
+    responder_id: ResponderId,
+    scp_network_state: Arc<Mutex<SCPNetworkState<ResponderId>>>,
+    stop_requested: Arc<AtomicBool>,
+    logger: Logger,
+) {
+    let record = |block_index: BlockIndex| {
+        scp_network_state
+            .lock()
+            .expect("mutex poisoned")
+            .push(Msg::<&str, ResponderId>::new(
+                responder_id.clone(),
+                QuorumSet::empty(),
+                block_index as SlotIndex,
+                Topic::Externalize(ExternalizePayload {
+                    C: Ballot::new(1, &["fake"]),
+                    HN: 1,
+                }),
+            ));
+    };
+
+    while !stop_requested.load(Ordering::SeqCst) {
+        // Start from the node's current block, rather than replaying its whole ledger.
+        let block_index = match conn.fetch_block_height(Fibonacci::from_millis(50).take(10)) {
+            Ok(block_index) => block_index,
+            Err(err) => {
+                log::warn!(logger, "Failed getting last block from {}: {:?}", conn, err);
+                thread::sleep(RESUBSCRIBE_DELAY);
+                continue;
+            }
+        };
+        record(block_index);
+
+        let subscription = conn.write().subscribe_blocks(block_index + 1);
+        match subscription {
+            Ok(subscription) => {
+                for result in subscription {
+                    if stop_requested.load(Ordering::SeqCst) {
+                        return;
+                    }
+                    match result {
+                        Ok(block_contents) => {
+                            log::trace!(
+                                logger,
+                                "Block {} appended by {}",
+                                block_contents.block.index,
+                                conn
+                            );
+                            record(block_contents.block.index);
+                        }
+                        Err(err) => {
+                            log::warn!(logger, "Block subscription to {} failed: {:?}", conn, err);
+                            break;
+                        }
+                    }
+                }
+            }
+            Err(err) => {
+                log::warn!(
+                    logger,
+                    "Failed subscribing to blocks from {}: {:?}",
+                    conn,
+                    err
+                );
+            }
+        }
+
+        thread::sleep(RESUBSCRIBE_DELAY);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::logger::test_with_logger;
+    use ledger_db::test_utils::get_mock_ledger;
+    use peers_tests::{test_node_id, test_peer_uri, MockPeerConnection};
+    use std::time::Instant;
+
+    #[test_with_logger]
+    // Blocks externalized by a quorum of nodes should be noticed.
+    fn test_is_behind(logger: Logger) {
+        let peer_a =
+            MockPeerConnection::new(test_peer_uri(2), test_node_id(1), get_mock_ledger(10), 0);
+        let peer_b =
+            MockPeerConnection::new(test_peer_uri(3), test_node_id(1), get_mock_ledger(7), 0);
+        let quorum_set = QuorumSet::new_with_node_ids(
+            2,
+            vec![
+                test_peer_uri(2).responder_id().unwrap(),
+                test_peer_uri(3).responder_id().unwrap(),
+            ],
+        );
+        let manager = ConnectionManager::new(vec![peer_a, peer_b], logger.clone());
+        let network_state = SubscriptionNetworkState::new(quorum_set, manager, logger);
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while network_state.highest_block_index_on_network() != Some(9) {
+            assert!(
+                Instant::now() < deadline,
+                "Timed out waiting for subscriptions"
+            );
+            thread::sleep(Duration::from_millis(10));
+        }
+        // Wait for the other node, too.
+        while !network_state.is_behind(5) {
+            assert!(
+                Instant::now() < deadline,
+                "Timed out waiting for subscriptions"
+            );
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        // Only one of the nodes is past block 6, which is not a quorum.
+        assert!(!network_state.is_behind(6));
+    }
+}
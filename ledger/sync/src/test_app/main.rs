@@ -6,7 +6,7 @@ use attest::{Measurement, MrSigner};
 use common::{logger::log, ResponderId};
 use consensus_enclave_measurement::sigstruct;
 use ledger_db::{Ledger, LedgerDB};
-use ledger_sync::{LedgerSyncService, PollingNetworkState};
+use ledger_sync::{LedgerSyncService, NetworkState, PollingNetworkState};
 use mcconnection::{ConnectionManager, ThickClient};
 use mcuri::ConsensusClientUri as ClientUri;
 use scp::{test_utils::test_node_id, QuorumSet};
@@ -3,7 +3,7 @@
 use crate::{TransactionFetcherError, TransactionsFetcher};
 use common::ResponderId;
 use ledger_db::Ledger;
-use transaction::{Block, RedactedTx};
+use transaction::{Block, BlockIndex, RedactedTx};
 
 impl TransactionFetcherError for String {}
 
@@ -35,4 +35,24 @@ impl<L: Ledger + Sync> TransactionsFetcher for MockTransactionsFetcher<L> {
                 )
             })
     }
+
+    fn get_block_by_index(
+        &self,
+        block_index: BlockIndex,
+    ) -> Result<(Block, Vec<RedactedTx>), Self::Error> {
+        let block = self
+            .ledger
+            .get_block(block_index)
+            .map_err(|e| format!("Error getting block #{}: {:?}", block_index, e))?;
+        let transactions = self
+            .ledger
+            .get_transactions_by_block(block_index)
+            .map_err(|e| {
+                format!(
+                    "Error getting transactions for block #{}: {:?}",
+                    block_index, e
+                )
+            })?;
+        Ok((block, transactions))
+    }
 }
@@ -5,7 +5,7 @@
 
 use common::ResponderId;
 use std::fmt::Debug;
-use transaction::{Block, RedactedTx};
+use transaction::{Block, BlockIndex, RedactedTx};
 
 pub trait TransactionFetcherError: Debug + Send + Sync {}
 
@@ -24,4 +24,14 @@ pub trait TransactionsFetcher: Sized + Sync + Send {
         safe_responder_ids: &[ResponderId],
         block: &Block,
     ) -> Result<Vec<RedactedTx>, Self::Error>;
+
+    /// Fetches a block and its transactions, without knowing the block in advance.
+    /// The caller is responsible for verifying the block, e.g. against a trusted checkpoint.
+    ///
+    /// # Arguments
+    /// * `block_index` - Index of the block we want to fetch.
+    fn get_block_by_index(
+        &self,
+        block_index: BlockIndex,
+    ) -> Result<(Block, Vec<RedactedTx>), Self::Error>;
 }
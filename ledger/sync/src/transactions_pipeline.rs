@@ -0,0 +1,424 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Fetches the transactions of a sequence of blocks with a pool of worker threads, and hands them
+//! out in order as soon as a contiguous prefix of the sequence is available.
+//!
+//! Workers only fetch blocks within a window ahead of the next block to hand out, which bounds
+//! both the number of requests in flight and the memory held by blocks that cannot be handed out
+//! yet. A block whose fetch fails is retried on its own, without re-fetching any other block.
+
+use crate::{
+    counters, ledger_sync_error::LedgerSyncError, transactions_fetcher_trait::TransactionsFetcher,
+};
+use common::{
+    logger::{log, Logger},
+    HashMap, ResponderId,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    mem,
+    sync::{Arc, Condvar, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+use transaction::{hash_block_contents, Block, BlockID, RedactedTx};
+
+/// How long a worker waits before retrying a block it failed to get transactions for.
+const RETRY_DELAY: Duration = Duration::from_millis(100);
+
+#[derive(Default)]
+struct PipelineState {
+    /// Positions in `blocks` of the blocks whose transactions still need to be fetched.
+    pending: BTreeSet<usize>,
+
+    /// Transactions that were fetched but not handed out yet, by position in `blocks`.
+    fetched: BTreeMap<usize, Vec<RedactedTx>>,
+
+    /// Position in `blocks` of the next block to hand out.
+    next: usize,
+
+    /// Set when the workers should exit.
+    stop: bool,
+}
+
+pub struct TransactionsPipeline {
+    blocks: Arc<Vec<Block>>,
+    state_and_condvar: Arc<(Mutex<PipelineState>, Condvar)>,
+    thread_handles: Vec<thread::JoinHandle<()>>,
+    deadline: Instant,
+    logger: Logger,
+}
+
+impl TransactionsPipeline {
+    /// Starts fetching the transactions of `blocks`.
+    ///
+    /// It is assumed that all peers have identical Block IDs for the given blocks, so it is
+    /// sufficient to obtain each transaction from a single peer.
+    ///
+    /// # Arguments
+    /// * `transactions_fetcher` - The mechanism used for fetching transaction contents for a given
+    /// block.
+    /// * `safe_responder_ids` - ResponderIds that have been identified as agreeing with eachother on
+    /// the `blocks` we want to fetch.
+    /// * `blocks` - Consecutive blocks to fetch transactions for.
+    /// * `prefetched` - Transactions already fetched for some of the blocks, by block ID.
+    /// * `window` - Maximal number of blocks to fetch ahead of the next block to hand out.
+    /// * `timeout` - Overall request timeout.
+    pub fn new<TF: TransactionsFetcher + 'static>(
+        transactions_fetcher: Arc<TF>,
+        safe_responder_ids: &[ResponderId],
+        blocks: Vec<Block>,
+        mut prefetched: HashMap<BlockID, Vec<RedactedTx>>,
+        window: usize,
+        timeout: Duration,
+        logger: &Logger,
+    ) -> Self {
+        let mut state = PipelineState::default();
+        for (position, block) in blocks.iter().enumerate() {
+            match prefetched.remove(&block.id) {
+                Some(transactions) => {
+                    state.fetched.insert(position, transactions);
+                }
+                None => {
+                    state.pending.insert(position);
+                }
+            }
+        }
+
+        let window = window.max(1);
+        let num_workers = std::cmp::min(window, state.pending.len());
+        let blocks = Arc::new(blocks);
+        let state_and_condvar = Arc::new((Mutex::new(state), Condvar::new()));
+        let deadline = Instant::now() + timeout;
+
+        let mut thread_handles = Vec::new();
+        for worker_num in 0..num_workers {
+            let thread_blocks = blocks.clone();
+            let thread_state_and_condvar = state_and_condvar.clone();
+            let thread_logger = logger.clone();
+            let thread_transactions_fetcher = transactions_fetcher.clone();
+            let thread_safe_responder_ids = safe_responder_ids.to_owned();
+
+            let thread_handle = thread::Builder::new()
+                .name(format!("GetTxs:{}", worker_num))
+                .spawn(move || loop {
+                    let &(ref lock, ref condvar) = &*thread_state_and_condvar;
+
+                    // Wait for a block within the window that needs fetching.
+                    let position = {
+                        let mut state = lock.lock().expect("mutex poisoned");
+                        loop {
+                            let now = Instant::now();
+                            if state.stop || now >= deadline {
+                                return;
+                            }
+
+                            let window_end = state.next + window;
+                            let position = state.pending.range(..window_end).next().cloned();
+                            if let Some(position) = position {
+                                state.pending.remove(&position);
+                                break position;
+                            }
+
+                            state = condvar
+                                .wait_timeout(state, deadline - now)
+                                .expect("waiting on condvar failed")
+                                .0;
+                        }
+                    };
+                    let block = &thread_blocks[position];
+
+                    // Try and get transactions for this block.
+                    log::trace!(
+                        thread_logger,
+                        "Worker {} attempting block {}",
+                        worker_num,
+                        block.index
+                    );
+
+                    let transactions_result = {
+                        let _timer = counters::GET_TRANSACTIONS_TIME.start_timer();
+                        thread_transactions_fetcher
+                            .get_transactions_by_block(thread_safe_responder_ids.as_slice(), block)
+                    };
+                    match transactions_result.map_err(LedgerSyncError::from).and_then(
+                        |transactions| {
+                            let contents_hash = hash_block_contents(&transactions);
+                            if contents_hash != block.contents_hash {
+                                log::debug!(
+                                    thread_logger,
+                                    "Transaction and block mismatch: {:02x?} vs {:02x?}",
+                                    contents_hash,
+                                    block.contents_hash,
+                                );
+                                Err(LedgerSyncError::TransactionsAndBlockMismatch)
+                            } else {
+                                Ok(transactions)
+                            }
+                        },
+                    ) {
+                        Ok(transactions) => {
+                            log::trace!(
+                                thread_logger,
+                                "Worker {} got {} transactions for block {}",
+                                worker_num,
+                                transactions.len(),
+                                block.index
+                            );
+
+                            let mut state = lock.lock().expect("mutex poisoned");
+                            let old_result = state.fetched.insert(position, transactions);
+
+                            // We should encounter each block only once.
+                            assert!(old_result.is_none());
+                        }
+
+                        Err(err) => {
+                            log::error!(
+                                thread_logger,
+                                "Worker {} failed getting transactions for block {}: {}",
+                                worker_num,
+                                block.index,
+                                err
+                            );
+
+                            // Put back to queue for a retry.
+                            thread::sleep(RETRY_DELAY);
+                            let mut state = lock.lock().expect("mutex poisoned");
+                            state.pending.insert(position);
+                        }
+                    }
+
+                    // Signal the condition variable, the next block may now be available.
+                    condvar.notify_all();
+                })
+                .expect("Failed spawning GetTxs thread!");
+
+            thread_handles.push(thread_handle);
+        }
+
+        Self {
+            blocks,
+            state_and_condvar,
+            thread_handles,
+            deadline,
+            logger: logger.clone(),
+        }
+    }
+
+    /// Stops fetching, and returns the transactions that were fetched for blocks that were not
+    /// handed out, by block ID.
+    pub fn finish(mut self) -> HashMap<BlockID, Vec<RedactedTx>> {
+        self.stop();
+
+        let &(ref lock, _) = &*self.state_and_condvar;
+        let fetched = mem::take(&mut lock.lock().expect("mutex poisoned").fetched);
+        fetched
+            .into_iter()
+            .map(|(position, transactions)| (self.blocks[position].id.clone(), transactions))
+            .collect()
+    }
+
+    /// Tells the worker threads to exit, and waits for them to.
+    fn stop(&mut self) {
+        {
+            let &(ref lock, ref condvar) = &*self.state_and_condvar;
+            lock.lock().expect("mutex poisoned").stop = true;
+            condvar.notify_all();
+        }
+
+        log::trace!(self.logger, "Joining worker threads...");
+        for thread_handle in self.thread_handles.drain(..) {
+            if let Err(err) = thread_handle.join() {
+                log::error!(
+                    self.logger,
+                    "Failed joining get_transactions worker thread: {:?}",
+                    err
+                );
+            }
+        }
+    }
+}
+
+impl Iterator for TransactionsPipeline {
+    type Item = Vec<(Block, Vec<RedactedTx>)>;
+
+    /// Waits for the transactions of the next block, and returns them along with those of the
+    /// following blocks that are already available. Returns None once all blocks were handed
+    /// out, or if the deadline passes first.
+    fn next(&mut self) -> Option<Self::Item> {
+        let &(ref lock, ref condvar) = &*self.state_and_condvar;
+        let num_blocks = self.blocks.len();
+        let timeout = self.deadline.saturating_duration_since(Instant::now());
+        let (mut state, _wait_timeout_result) = condvar
+            .wait_timeout_until(lock.lock().unwrap(), timeout, |ref mut state| {
+                state.next >= num_blocks || state.fetched.contains_key(&state.next)
+            })
+            .expect("waiting on condvar failed");
+
+        let mut blocks_with_transactions = Vec::new();
+        loop {
+            let next = state.next;
+            match state.fetched.remove(&next) {
+                Some(transactions) => {
+                    blocks_with_transactions.push((self.blocks[next].clone(), transactions));
+                    state.next += 1;
+                }
+                None => break,
+            }
+        }
+
+        if blocks_with_transactions.is_empty() {
+            if state.next < num_blocks {
+                log::error!(
+                    self.logger,
+                    "Giving up on transactions for block {}: deadline exceeded",
+                    self.blocks[state.next].index
+                );
+            }
+            return None;
+        }
+
+        // The window moved forward, so workers may fetch more blocks.
+        condvar.notify_all();
+        Some(blocks_with_transactions)
+    }
+}
+
+impl Drop for TransactionsPipeline {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::MockTransactionsFetcher;
+    use common::logger::test_with_logger;
+    use ledger_db::{
+        test_utils::{get_mock_ledger, MockLedger},
+        Ledger,
+    };
+    use mcuri::ConnectionUri;
+    use peers_tests::test_peer_uri;
+
+    fn test_responder_ids() -> Vec<ResponderId> {
+        (1..4)
+            .map(|id| test_peer_uri(id).responder_id().unwrap())
+            .collect()
+    }
+
+    #[test_with_logger]
+    // The pipeline should hand out the correct transactions for the indicated blocks, in order.
+    fn test_get_transactions(logger: Logger) {
+        let mock_ledger = get_mock_ledger(25);
+        let transactions_fetcher = Arc::new(MockTransactionsFetcher::new(mock_ledger.clone()));
+
+        let blocks: Vec<Block> = (0..10)
+            .map(|idx| mock_ledger.get_block(idx).unwrap())
+            .collect();
+
+        // A window smaller than the number of blocks, to exercise moving it.
+        let pipeline = TransactionsPipeline::new(
+            transactions_fetcher,
+            &test_responder_ids(),
+            blocks.clone(),
+            HashMap::default(),
+            3,
+            Duration::from_secs(1),
+            &logger,
+        );
+        let blocks_with_transactions: Vec<(Block, Vec<RedactedTx>)> = pipeline.flatten().collect();
+
+        // The correct number of results should be returned.
+        assert_eq!(blocks_with_transactions.len(), 10);
+
+        for (block, (returned_block, transactions)) in
+            blocks.iter().zip(blocks_with_transactions.into_iter())
+        {
+            assert_eq!(*block, returned_block);
+
+            // The transactions should be correct for each block.
+            let expected_transactions = mock_ledger.get_transactions_by_block(block.index).unwrap();
+            assert_eq!(transactions, expected_transactions);
+        }
+    }
+
+    #[test_with_logger]
+    // The pipeline should verify the transactions returned matched the block requested, and stop
+    // handing out blocks at the first one it cannot get valid transactions for.
+    fn test_get_transactions_validates_block(logger: Logger) {
+        let mock_ledger = get_mock_ledger(25);
+        let transactions_fetcher = Arc::new(MockTransactionsFetcher::new(mock_ledger.clone()));
+
+        let mut blocks: Vec<Block> = (0..10)
+            .map(|idx| mock_ledger.get_block(idx).unwrap())
+            .collect();
+
+        // Alter the contents hash of one of the blocks. This should cause fetching its
+        // transactions to fail. Block index 3 is chosen arbitrarily.
+        const BAD_BLOCK_INDEX: usize = 3;
+
+        blocks[BAD_BLOCK_INDEX].contents_hash.0[0] = !blocks[BAD_BLOCK_INDEX].contents_hash.0[0];
+
+        let mut pipeline = TransactionsPipeline::new(
+            transactions_fetcher,
+            &test_responder_ids(),
+            blocks.clone(),
+            HashMap::default(),
+            10,
+            Duration::from_secs(1),
+            &logger,
+        );
+        let handed_out: Vec<(Block, Vec<RedactedTx>)> = pipeline.by_ref().flatten().collect();
+
+        // Only the blocks before the bad block should be handed out.
+        assert_eq!(handed_out.len(), BAD_BLOCK_INDEX);
+        for (block, transactions) in handed_out {
+            let expected_transactions = mock_ledger.get_transactions_by_block(block.index).unwrap();
+            assert_eq!(transactions, expected_transactions);
+        }
+
+        // The blocks after it were fetched nonetheless, and are kept for later.
+        let leftovers = pipeline.finish();
+        assert_eq!(leftovers.len(), blocks.len() - BAD_BLOCK_INDEX - 1);
+        for block in blocks[BAD_BLOCK_INDEX + 1..].iter() {
+            let expected_transactions = mock_ledger.get_transactions_by_block(block.index).unwrap();
+            assert_eq!(leftovers.get(&block.id), Some(&expected_transactions));
+        }
+    }
+
+    #[test_with_logger]
+    // Prefetched transactions should be handed out without fetching them again.
+    fn test_get_transactions_prefetched(logger: Logger) {
+        let mock_ledger = get_mock_ledger(10);
+
+        // This fetcher fails for every block.
+        let transactions_fetcher = Arc::new(MockTransactionsFetcher::new(MockLedger::default()));
+
+        let blocks: Vec<Block> = (0..10)
+            .map(|idx| mock_ledger.get_block(idx).unwrap())
+            .collect();
+        let prefetched: HashMap<BlockID, Vec<RedactedTx>> = blocks
+            .iter()
+            .map(|block| {
+                (
+                    block.id.clone(),
+                    mock_ledger.get_transactions_by_block(block.index).unwrap(),
+                )
+            })
+            .collect();
+
+        let pipeline = TransactionsPipeline::new(
+            transactions_fetcher,
+            &test_responder_ids(),
+            blocks,
+            prefetched,
+            5,
+            Duration::from_secs(1),
+            &logger,
+        );
+        assert_eq!(pipeline.flatten().count(), 10);
+    }
+}
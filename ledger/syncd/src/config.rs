@@ -0,0 +1,165 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Configuration parameters for ledger-syncd
+
+use common::ResponderId;
+use keys::{DistinguishedEncoding, Ed25519Public};
+use ledger_sync::Checkpoint;
+use mcuri::{ConnectionUri, ConsensusClientUri, ConsensusPeerUri};
+use scp::QuorumSet;
+use std::{path::PathBuf, str::FromStr, time::Duration};
+use structopt::StructOpt;
+
+/// How the daemon finds out that the network has moved ahead of the local ledger.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum NetworkStateKind {
+    /// Periodically ask each peer for its last block.
+    Polling,
+
+    /// Follow each peer's block subscription, and feed the blocks it externalizes to SCP's quorum
+    /// logic as they are appended.
+    Subscription,
+
+    /// Periodically fetch each validator's latest signed SCP message from its peer API, and feed
+    /// those to SCP's quorum logic. Requires `--scp-peer`.
+    Scp,
+}
+
+impl FromStr for NetworkStateKind {
+    type Err = String;
+
+    fn from_str(src: &str) -> Result<Self, Self::Err> {
+        match src {
+            "polling" => Ok(NetworkStateKind::Polling),
+            "subscription" => Ok(NetworkStateKind::Subscription),
+            "scp" => Ok(NetworkStateKind::Scp),
+            _ => Err(format!("Unknown network state {}", src)),
+        }
+    }
+}
+
+#[derive(Debug, StructOpt)]
+#[structopt(
+    name = "ledger-syncd",
+    about = "Keeps a local ledger in sync with the MobileCoin network."
+)]
+pub struct Config {
+    /// Path to ledger db (lmdb). If it does not contain a ledger yet, the origin block is fetched
+    /// from the network.
+    #[structopt(long, default_value = "/tmp/ledgerdb", parse(from_os_str))]
+    pub ledger_db: PathBuf,
+
+    /// Validator nodes to sync from.
+    #[structopt(long = "peer", required = true, min_values = 1)]
+    pub peers: Vec<ConsensusClientUri>,
+
+    /// Quorum set for ledger syncing. By default, the quorum set would include all peers.
+    ///
+    /// The quorum set is represented in JSON. For example:
+    /// {"threshold":1,"members":[{"type":"Node","args":"node2.test.mobilecoin.com:443"},{"type":"Node","args":"node3.test.mobilecoin.com:443"}]}
+    #[structopt(long, parse(try_from_str=parse_quorum_set_from_json))]
+    quorum_set: Option<QuorumSet<ResponderId>>,
+
+    /// How to track the network's progress: "polling", "subscription" or "scp".
+    #[structopt(long, default_value = "polling")]
+    pub network_state: NetworkStateKind,
+
+    /// Peer URIs of the validators whose SCP messages are followed when the network state is
+    /// "scp". Their responder IDs must match the ones of `--peer`.
+    #[structopt(long = "scp-peer", required_if("network_state", "scp"))]
+    pub scp_peers: Vec<ConsensusPeerUri>,
+
+    /// URLs to use for transaction data. If none are given, transaction data is fetched from the
+    /// peers.
+    ///
+    /// For example: https://s3-us-west-1.amazonaws.com/mobilecoin.chain/node1.test.mobilecoin.com/
+    #[structopt(long = "tx-source-url")]
+    pub tx_source_urls: Vec<String>,
+
+    /// Base64 DER-encoded public key of a validator whose block signatures are trusted. May be
+    /// specified multiple times.
+    #[structopt(long = "trusted-block-signer", parse(try_from_str=public_key_from_base64))]
+    pub trusted_block_signers: Vec<Ed25519Public>,
+
+    /// Minimum number of valid signatures by trusted block signers a block fetched from a
+    /// transaction source must carry.
+    #[structopt(long, default_value = "0")]
+    pub required_block_signatures: usize,
+
+    /// Trusted block to quickly sync up to from the transaction sources, before syncing with
+    /// peers. Blocks up to it are trusted because they hash-chain to it.
+    ///
+    /// The checkpoint is given as the block index and hex-encoded block ID, e.g. 1234:7a3f...
+    #[structopt(long)]
+    pub checkpoint: Option<Checkpoint>,
+
+    /// Directory to cache blocks fetched from the transaction sources in. May be shared by several
+    /// processes on one host.
+    #[structopt(long, parse(from_os_str))]
+    pub tx_cache_dir: Option<PathBuf>,
+
+    /// Maximum size of the transactions cache, in megabytes. Processes sharing the cache directory
+    /// should use the same size, which bounds the directory as a whole.
+    #[structopt(long, default_value = "1024")]
+    pub tx_cache_size_mb: u64,
+
+    /// How many seconds to wait between polling.
+    #[structopt(long, default_value = "5", parse(try_from_str=parse_duration_in_seconds))]
+    pub poll_interval: Duration,
+
+    /// Port to serve the GRPC health check service from. Reports NOT_SERVING once the network
+    /// forked.
+    #[structopt(long)]
+    pub health_port: Option<u16>,
+
+    /// Address to serve Prometheus metrics from, at /metrics, e.g. 0.0.0.0:9090.
+    #[structopt(long)]
+    pub metrics_listen_addr: Option<String>,
+
+    /// Executable to run after blocks are appended to the ledger. It is passed the indexes of the
+    /// first and last appended blocks as arguments. Syncing waits for it to exit.
+    #[structopt(long, parse(from_os_str))]
+    pub post_append_hook: Option<PathBuf>,
+}
+
+fn parse_duration_in_seconds(src: &str) -> Result<Duration, std::num::ParseIntError> {
+    Ok(Duration::from_secs(u64::from_str(src)?))
+}
+
+fn public_key_from_base64(src: &str) -> Result<Ed25519Public, String> {
+    let pubkey_bytes = base64::decode_config(src, base64::STANDARD)
+        .map_err(|err| format!("Could not decode public key from base64 {:?}", err))?;
+
+    Ed25519Public::try_from_der(pubkey_bytes.as_slice())
+        .map_err(|err| format!("Could not get Ed25519Public from der {:?}", err))
+}
+
+fn parse_quorum_set_from_json(src: &str) -> Result<QuorumSet<ResponderId>, String> {
+    Ok(serde_json::from_str(src)
+        .map_err(|err| format!("Error parsing quorum set {}: {:?}", src, err))?)
+}
+
+impl Config {
+    pub fn quorum_set(&self) -> QuorumSet<ResponderId> {
+        // If we have an explicit quorum set, use that.
+        if let Some(quorum_set) = &self.quorum_set {
+            return quorum_set.clone();
+        }
+
+        // Otherwise create a quorum set that includes all of the peers we know about.
+        let node_ids = self
+            .peers
+            .iter()
+            .map(|p| {
+                p.responder_id().unwrap_or_else(|e| {
+                    panic!(
+                        "Could not get responder_id from uri {}: {:?}",
+                        p.to_string(),
+                        e
+                    )
+                })
+            })
+            .collect::<Vec<ResponderId>>();
+        QuorumSet::new_with_node_ids(node_ids.len() as u32, node_ids)
+    }
+}
@@ -0,0 +1,290 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! ledger-syncd daemon entry point. Keeps a local ledger in sync with the network, for services
+//! that need a synced ledger but none of mobilecoind's wallet functionality.
+
+mod config;
+
+use attest::{Measurement, MrSigner};
+use common::logger::{create_app_logger, log, o, Logger};
+use config::{Config, NetworkStateKind};
+use consensus_enclave_measurement::sigstruct;
+use grpc_util::{HealthCheckStatus, HealthService};
+use ledger_db::{Ledger, LedgerDB};
+use ledger_sync::{
+    CachingTransactionsFetcher, ConsensusMsgNetworkState, ForkEvidenceStore,
+    GrpcTransactionsFetcher, LedgerSyncService, LedgerSyncServiceThread, PollingNetworkState,
+    PostAppendHook, ReqwestTransactionsFetcher, SubscriptionNetworkState, TransactionsFetcher,
+};
+use mcconnection::{ConnectionManager, ThickClient};
+use prometheus::Encoder;
+use rouille::{router, Response, Server};
+use std::{convert::TryFrom, path::PathBuf, process::Command, sync::Arc, thread};
+use structopt::StructOpt;
+use transaction::Block;
+
+fn main() {
+    let config = Config::from_args();
+
+    common::setup_panic_handler();
+    let _sentry_guard = common::sentry::init();
+    let (logger, _global_logger_guard) = create_app_logger(o!());
+
+    let peer_manager = create_peer_manager(&config, &logger);
+
+    // Evidence of a network fork is kept next to the ledger, and halts syncing until removed.
+    std::fs::create_dir_all(&config.ledger_db).expect("Could not create ledger dir");
+    let fork_evidence_store = ForkEvidenceStore::open(&config.ledger_db.join("fork_evidence"))
+        .expect("Could not open fork evidence");
+    if let Some(fork_evidence) = fork_evidence_store.get() {
+        log::crit!(
+            logger,
+            "The network forked at block {}, ledger sync is halted",
+            fork_evidence.block_index
+        );
+    }
+
+    // Transaction data is fetched from the peers, unless transaction source URLs are given.
+    let _ledger_sync_service_thread = if config.tx_source_urls.is_empty() {
+        let transactions_fetcher =
+            GrpcTransactionsFetcher::new(peer_manager.clone(), logger.clone());
+        start_ledger_sync(
+            &config,
+            peer_manager,
+            transactions_fetcher,
+            fork_evidence_store.clone(),
+            &logger,
+        )
+    } else {
+        let transactions_fetcher =
+            ReqwestTransactionsFetcher::new(config.tx_source_urls.clone(), logger.clone())
+                .expect("Failed creating ReqwestTransactionsFetcher")
+                .with_required_block_signatures(
+                    config.trusted_block_signers.clone(),
+                    config.required_block_signatures,
+                );
+        match &config.tx_cache_dir {
+            Some(tx_cache_dir) => {
+                let transactions_fetcher = CachingTransactionsFetcher::new(
+                    transactions_fetcher,
+                    tx_cache_dir,
+                    config.tx_cache_size_mb * 1024 * 1024,
+                    logger.clone(),
+                )
+                .expect("Failed opening transactions cache");
+                start_ledger_sync(
+                    &config,
+                    peer_manager,
+                    transactions_fetcher,
+                    fork_evidence_store.clone(),
+                    &logger,
+                )
+            }
+            None => start_ledger_sync(
+                &config,
+                peer_manager,
+                transactions_fetcher,
+                fork_evidence_store.clone(),
+                &logger,
+            ),
+        }
+    };
+
+    let _health_server = config
+        .health_port
+        .map(|health_port| start_health_server(health_port, fork_evidence_store, &logger));
+
+    if let Some(metrics_listen_addr) = config.metrics_listen_addr.clone() {
+        start_metrics_server(metrics_listen_addr, &logger);
+    }
+
+    loop {
+        thread::sleep(config.poll_interval);
+    }
+}
+
+fn create_peer_manager(config: &Config, logger: &Logger) -> ConnectionManager<ThickClient> {
+    let measurement: Measurement = MrSigner::try_from(&sigstruct().mrsigner()[..])
+        .expect("Could not parse validator node MRSIGNER")
+        .into();
+    let grpc_env = Arc::new(
+        grpcio::EnvBuilder::new()
+            .name_prefix("RPC".to_string())
+            .build(),
+    );
+
+    let peers = config
+        .peers
+        .iter()
+        .map(|client_uri| {
+            ThickClient::new(
+                client_uri.clone(),
+                measurement,
+                grpc_env.clone(),
+                logger.clone(),
+            )
+            .expect("Could not create thick client.")
+        })
+        .collect();
+
+    ConnectionManager::new(peers, logger.clone())
+}
+
+fn start_ledger_sync<TF: TransactionsFetcher + 'static>(
+    config: &Config,
+    peer_manager: ConnectionManager<ThickClient>,
+    transactions_fetcher: TF,
+    fork_evidence_store: ForkEvidenceStore,
+    logger: &Logger,
+) -> LedgerSyncServiceThread {
+    let ledger_db = create_or_open_ledger_db(config, &transactions_fetcher, logger);
+
+    let mut ledger_sync_service = LedgerSyncService::new(
+        ledger_db.clone(),
+        peer_manager.clone(),
+        transactions_fetcher,
+        logger.clone(),
+    )
+    .with_fork_evidence_store(fork_evidence_store);
+    if let Some(checkpoint) = config.checkpoint.clone() {
+        ledger_sync_service = ledger_sync_service.with_checkpoint(checkpoint);
+    }
+    if let Some(post_append_hook) = config.post_append_hook.clone() {
+        ledger_sync_service = ledger_sync_service
+            .with_post_append_hook(create_post_append_hook(post_append_hook, logger.clone()));
+    }
+
+    match config.network_state {
+        NetworkStateKind::Polling => LedgerSyncServiceThread::new(
+            ledger_db,
+            ledger_sync_service,
+            PollingNetworkState::new(config.quorum_set(), peer_manager, logger.clone()),
+            config.poll_interval,
+            logger.clone(),
+        ),
+        NetworkStateKind::Subscription => LedgerSyncServiceThread::new(
+            ledger_db,
+            ledger_sync_service,
+            SubscriptionNetworkState::new(config.quorum_set(), peer_manager, logger.clone()),
+            config.poll_interval,
+            logger.clone(),
+        ),
+        NetworkStateKind::Scp => LedgerSyncServiceThread::new(
+            ledger_db,
+            ledger_sync_service,
+            ConsensusMsgNetworkState::new(
+                config.quorum_set(),
+                &config.scp_peers,
+                Arc::new(
+                    grpcio::EnvBuilder::new()
+                        .name_prefix("SCP-RPC".to_string())
+                        .build(),
+                ),
+                logger.clone(),
+            ),
+            config.poll_interval,
+            logger.clone(),
+        ),
+    }
+}
+
+/// Opens the ledger db, first creating it with the network's origin block if it does not exist.
+fn create_or_open_ledger_db(
+    config: &Config,
+    transactions_fetcher: &impl TransactionsFetcher,
+    logger: &Logger,
+) -> LedgerDB {
+    if !config.ledger_db.join("data.mdb").exists() {
+        log::info!(
+            logger,
+            "Ledger DB {:?} does not exist, bootstrapping from the network",
+            config.ledger_db
+        );
+        LedgerDB::create(config.ledger_db.clone()).expect("Could not create ledger_db");
+        let (block, transactions) = transactions_fetcher
+            .get_block_by_index(0)
+            .expect("Failed to download initial transactions");
+        let mut db = LedgerDB::open(config.ledger_db.clone()).expect("Could not open ledger_db");
+        db.append_block(&block, &transactions, None)
+            .expect("Failed to append initial transactions");
+    }
+
+    LedgerDB::open(config.ledger_db.clone()).expect("Could not open ledger_db")
+}
+
+/// A hook that runs `command` with the indexes of the first and last appended blocks.
+fn create_post_append_hook(command: PathBuf, logger: Logger) -> PostAppendHook {
+    Arc::new(move |blocks: &[Block]| {
+        let (first_block, last_block) = match (blocks.first(), blocks.last()) {
+            (Some(first_block), Some(last_block)) => (first_block, last_block),
+            _ => return,
+        };
+
+        match Command::new(&command)
+            .arg(first_block.index.to_string())
+            .arg(last_block.index.to_string())
+            .status()
+        {
+            Ok(status) if status.success() => {}
+            Ok(status) => {
+                log::warn!(logger, "Post-append hook {:?} failed: {}", command, status);
+            }
+            Err(err) => {
+                log::error!(
+                    logger,
+                    "Failed running post-append hook {:?}: {}",
+                    command,
+                    err
+                );
+            }
+        }
+    })
+}
+
+/// Serves the GRPC health check service, reporting NOT_SERVING once the network forked.
+fn start_health_server(
+    port: u16,
+    fork_evidence_store: ForkEvidenceStore,
+    logger: &Logger,
+) -> grpcio::Server {
+    let health_check_callback: Arc<dyn Fn(&str) -> HealthCheckStatus + Sync + Send> =
+        Arc::new(move |_| {
+            if fork_evidence_store.is_forked() {
+                HealthCheckStatus::NOT_SERVING
+            } else {
+                HealthCheckStatus::SERVING
+            }
+        });
+    let health_service =
+        HealthService::new(Some(health_check_callback), logger.clone()).into_service();
+
+    let env = Arc::new(
+        grpcio::EnvBuilder::new()
+            .name_prefix("Health-RPC".to_string())
+            .build(),
+    );
+    grpc_util::run_server(env, vec![health_service], port, logger)
+}
+
+/// Serves Prometheus metrics at /metrics, in a background thread.
+fn start_metrics_server(listen_addr: String, logger: &Logger) {
+    let server = Server::new(listen_addr.clone(), move |request| {
+        router!(request,
+            (GET) (/metrics) => {
+                let metric_families = prometheus::gather();
+                let encoder = prometheus::TextEncoder::new();
+                let mut buffer = vec![];
+                encoder.encode(&metric_families, &mut buffer).unwrap();
+                Response::text(String::from_utf8(buffer).unwrap_or_else(|_| "from_utf8 failed".to_string()))
+            },
+            _ => Response::empty_404()
+        )
+    })
+    .expect("failed creating metrics web server");
+
+    log::info!(logger, "Serving metrics on {}", listen_addr);
+    thread::Builder::new()
+        .name("MetricsServer".to_string())
+        .spawn(move || server.run())
+        .expect("Failed spawning MetricsServer thread");
+}
@@ -0,0 +1,549 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! An asynchronous version of the thick client, built on grpcio's asynchronous calls.
+
+use crate::{
+    async_traits::{
+        AsyncAttestedConnection, AsyncBlockchainConnection, AsyncUserTxConnection,
+        ConnectionFuture, ConnectionStream,
+    },
+    block_contents::BlockContents,
+    error::{Error, Result},
+    grpcio_utils::ConnectionUriGrpcioChannel,
+    thick::{
+        parse_propose_tx_response, ThickClientAttestationError, MC_NODE_PRODUCT_ID,
+        MC_SECURITY_VERSION,
+    },
+    traits::Connection,
+};
+use aes_gcm::Aes256Gcm;
+use attest::Measurement;
+use attest_ake::{ClientInitiate, Ready, Start, Transition};
+use attest_api::{attest::Message, attest_grpc::AttestedApiClient};
+use common::logger::{log, o, Logger};
+use futures::{future, sync::oneshot, Future, Stream};
+use grpcio::{ChannelBuilder, Environment, Error as GrpcError, RpcStatusCode};
+use keys::X25519;
+use mcrand::McRng;
+use mcserial::encode;
+use mcuri::{ConnectionUri, ConsensusClientUri as ClientUri};
+use mobilecoin_api::{
+    blockchain::{BlockByIDRequest, BlocksRequest, SubscribeBlocksRequest},
+    blockchain_grpc::BlockchainApiClient,
+    consensus_client_grpc::ConsensusClientApiClient,
+    empty::Empty,
+};
+use secrecy::{ExposeSecret, SecretVec};
+use sha2::Sha512;
+use std::{
+    cmp::Ordering,
+    convert::TryFrom,
+    fmt::{Display, Formatter, Result as FmtResult},
+    hash::{Hash, Hasher},
+    ops::Range,
+    result::Result as StdResult,
+    sync::{Arc, Mutex},
+};
+use transaction::{tx::Tx, Block, BlockID, BlockIndex};
+
+/// An asynchronous connection from a client to a consensus enclave. Clones share the same
+/// attested session. Attestations and calls encrypted for the session run one at a time:
+/// encrypted requests must reach the node in the order of their nonces, and concurrent
+/// attestations would replace each other's session. Other calls only wait for a session to be
+/// established, and then run concurrently.
+#[derive(Clone)]
+pub struct AsyncThickClient {
+    /// The destination's URI
+    uri: ClientUri,
+    /// The logging instance
+    logger: Logger,
+    /// The gRPC API client we will use for blockchain detail retrieval.
+    blockchain_api_client: BlockchainApiClient,
+    /// The gRPC API client we will use for attestation.
+    attested_api_client: AttestedApiClient,
+    /// The gRPC API client we will use for transaction submission.
+    consensus_client_api_client: ConsensusClientApiClient,
+    /// The expected node enclave measurement value.
+    expected_measurement: Measurement,
+    /// The AKE state machine object, if one is available.
+    enclave_connection: Arc<Mutex<Option<Ready<Aes256Gcm>>>>,
+    /// Runs attestations and encrypted calls one at a time.
+    call_queue: CallQueue,
+}
+
+impl AsyncThickClient {
+    /// Create a new attested connection to the given consensus node.
+    pub fn new(
+        uri: ClientUri,
+        expected_measurement: impl Into<Measurement>,
+        env: Arc<Environment>,
+        logger: Logger,
+    ) -> Result<Self> {
+        let logger = logger.new(o!("mc.cxn" => uri.to_string()));
+
+        let ch = ChannelBuilder::default_channel_builder(env).connect_to_uri(&uri, &logger);
+
+        let attested_api_client = AttestedApiClient::new(ch.clone());
+        let blockchain_api_client = BlockchainApiClient::new(ch.clone());
+        let consensus_client_api_client = ConsensusClientApiClient::new(ch);
+
+        Ok(Self {
+            uri,
+            logger,
+            blockchain_api_client,
+            consensus_client_api_client,
+            attested_api_client,
+            expected_measurement: expected_measurement.into(),
+            enclave_connection: Arc::new(Mutex::new(None)),
+            call_queue: CallQueue::default(),
+        })
+    }
+
+    // Attest first if needed, then make the call returned by `func`, which does not encrypt
+    // anything for the session. Runs concurrently with other calls once a session is established.
+    // The session is torn down if the node no longer recognizes it.
+    fn attested_call<T, Fut>(
+        &self,
+        func: impl FnOnce(&Self) -> Result<Fut> + Send + 'static,
+    ) -> ConnectionFuture<T>
+    where
+        T: Send + 'static,
+        Fut: Future<Item = T, Error = GrpcError> + Send + 'static,
+    {
+        let this = self.clone();
+        Box::new(self.attested().and_then(move |()| {
+            // A session established while the call is in flight must not be torn down if the
+            // call fails.
+            let binding = this.binding();
+            future::result(func(&this)).and_then(move |call| {
+                call.map_err(move |err| {
+                    if is_unauthenticated(&err) {
+                        if let Some(binding) = binding {
+                            this.deattest_session(&binding);
+                        }
+                    }
+                    Error::from(err)
+                })
+            })
+        }))
+    }
+
+    // Attest first if needed, then make the call returned by `func`, which encrypts a request for
+    // the session. Runs once the previous attestation or encrypted call is over, including the
+    // node's response, since the node decrypts requests in the order of their nonces. The session
+    // is torn down if the node no longer recognizes it.
+    fn encrypted_call<T, Fut>(
+        &self,
+        func: impl FnOnce(&Self) -> Result<Fut> + Send + 'static,
+    ) -> ConnectionFuture<T>
+    where
+        T: Send + 'static,
+        Fut: Future<Item = T, Error = GrpcError> + Send + 'static,
+    {
+        let this = self.clone();
+        self.call_queue.run(move || {
+            this.attest_if_needed().and_then(move |()| {
+                future::result(func(&this)).and_then(move |call| {
+                    call.map_err(move |err| {
+                        if is_unauthenticated(&err) {
+                            this.deattest();
+                        }
+                        Error::from(err)
+                    })
+                })
+            })
+        })
+    }
+
+    // Resolves once a session is established, attesting from the call queue if there is none.
+    fn attested(&self) -> ConnectionFuture<()> {
+        if self.is_attested() {
+            return Box::new(future::ok(()));
+        }
+        let this = self.clone();
+        self.call_queue.run(move || this.attest_if_needed())
+    }
+
+    // The channel binding of the current session, if any.
+    fn binding(&self) -> Option<Vec<u8>> {
+        self.enclave_connection
+            .lock()
+            .expect("mutex poisoned")
+            .as_ref()
+            .map(|enclave_connection| Vec::from(enclave_connection.binding()))
+    }
+
+    // Tear down the session with the given channel binding, unless it was already replaced.
+    fn deattest_session(&self, binding: &[u8]) {
+        let mut enclave_connection = self.enclave_connection.lock().expect("mutex poisoned");
+        if enclave_connection
+            .as_ref()
+            .map_or(false, |enclave_connection| {
+                enclave_connection.binding() == binding
+            })
+        {
+            log::trace!(self.logger, "Tearing down existing attested connection.");
+            *enclave_connection = None;
+        }
+    }
+
+    // Attest unless a session is already established. Must run from the call queue.
+    fn attest_if_needed(&self) -> ConnectionFuture<()> {
+        if self.is_attested() {
+            Box::new(future::ok(()))
+        } else {
+            Box::new(self.attest_now().map_err(Error::from))
+        }
+    }
+
+    // Replace the session with a new one. Must run from the call queue.
+    fn attest_now(&self) -> Box<dyn Future<Item = (), Error = ThickClientAttestationError> + Send> {
+        // If we have an existing attestation, nuke it.
+        self.deattest();
+
+        let start = || -> StdResult<_, ThickClientAttestationError> {
+            let mut csprng = McRng::default();
+
+            let initiator = Start::new(
+                self.uri.responder_id()?.to_string(),
+                self.expected_measurement,
+                MC_NODE_PRODUCT_ID,
+                MC_SECURITY_VERSION,
+                attest::DEBUG_ENCLAVE,
+            );
+
+            let init_input = ClientInitiate::<X25519, Aes256Gcm, Sha512>::default();
+            let (initiator, auth_request_output) = initiator.try_next(&mut csprng, init_input)?;
+
+            let auth_call = self
+                .attested_api_client
+                .auth_async(&auth_request_output.into())?;
+            Ok((initiator, auth_call))
+        };
+        let (initiator, auth_call) = match start() {
+            Ok(started) => started,
+            Err(err) => return Box::new(future::err(err)),
+        };
+
+        let enclave_connection = self.enclave_connection.clone();
+        Box::new(
+            auth_call
+                .map_err(ThickClientAttestationError::from)
+                .and_then(
+                    move |auth_response| -> StdResult<(), ThickClientAttestationError> {
+                        let mut csprng = McRng::default();
+                        let (initiator, _) =
+                            initiator.try_next(&mut csprng, auth_response.into())?;
+                        *enclave_connection.lock().expect("mutex poisoned") = Some(initiator);
+                        Ok(())
+                    },
+                ),
+        )
+    }
+
+    fn blocks_request(range: Range<BlockIndex>) -> Result<BlocksRequest> {
+        let mut request = BlocksRequest::new();
+        request.set_offset(range.start);
+        let limit = u32::try_from(range.end - range.start).or(Err(Error::RequestTooLarge))?;
+        request.set_limit(limit);
+        Ok(request)
+    }
+}
+
+impl Connection for AsyncThickClient {
+    type Uri = ClientUri;
+
+    fn uri(&self) -> Self::Uri {
+        self.uri.clone()
+    }
+}
+
+impl AsyncAttestedConnection for AsyncThickClient {
+    type Error = ThickClientAttestationError;
+
+    fn is_attested(&self) -> bool {
+        self.enclave_connection
+            .lock()
+            .expect("mutex poisoned")
+            .is_some()
+    }
+
+    fn attest_async(&self) -> Box<dyn Future<Item = (), Error = Self::Error> + Send> {
+        let this = self.clone();
+        self.call_queue.run(move || this.attest_now())
+    }
+
+    fn deattest(&self) {
+        let mut enclave_connection = self.enclave_connection.lock().expect("mutex poisoned");
+        if enclave_connection.is_some() {
+            log::trace!(self.logger, "Tearing down existing attested connection.");
+            *enclave_connection = None;
+        }
+    }
+}
+
+impl AsyncBlockchainConnection for AsyncThickClient {
+    fn fetch_blocks_async(&self, range: Range<BlockIndex>) -> ConnectionFuture<Vec<Block>> {
+        Box::new(
+            self.attested_call(move |this| {
+                let request = Self::blocks_request(range)?;
+                Ok(this.blockchain_api_client.get_blocks_async(&request)?)
+            })
+            .and_then(|response| {
+                response
+                    .get_blocks()
+                    .iter()
+                    .map(|proto_block| Block::try_from(proto_block).map_err(Error::from))
+                    .collect::<Result<Vec<Block>>>()
+            }),
+        )
+    }
+
+    fn fetch_block_ids_async(&self, range: Range<BlockIndex>) -> ConnectionFuture<Vec<BlockID>> {
+        Box::new(
+            self.attested_call(move |this| {
+                let request = Self::blocks_request(range)?;
+                Ok(this.blockchain_api_client.get_blocks_async(&request)?)
+            })
+            .and_then(|response| {
+                response
+                    .get_blocks()
+                    .iter()
+                    .map(|proto_block| BlockID::try_from(&proto_block.id[..]).map_err(Error::from))
+                    .collect::<Result<Vec<BlockID>>>()
+            }),
+        )
+    }
+
+    fn fetch_block_height_async(&self) -> ConnectionFuture<BlockIndex> {
+        Box::new(
+            self.attested_call(|this| {
+                Ok(this
+                    .blockchain_api_client
+                    .get_last_block_info_async(&Empty::new())?)
+            })
+            .map(|response| response.index),
+        )
+    }
+
+    fn fetch_block_by_id_async(&self, block_id: &BlockID) -> ConnectionFuture<BlockContents> {
+        let mut request = BlockByIDRequest::new();
+        request.set_id(block_id.0.to_vec());
+
+        Box::new(
+            self.attested_call(move |this| {
+                Ok(this.blockchain_api_client.get_block_by_id_async(&request)?)
+            })
+            .and_then(|s3_block| BlockContents::try_from(&s3_block).map_err(Error::from)),
+        )
+    }
+
+    fn fetch_blocks_with_transactions_async(
+        &self,
+        range: Range<BlockIndex>,
+    ) -> ConnectionFuture<Vec<BlockContents>> {
+        Box::new(
+            self.attested_call(move |this| {
+                let request = Self::blocks_request(range)?;
+                Ok(this
+                    .blockchain_api_client
+                    .get_blocks_with_transactions_async(&request)?)
+            })
+            .and_then(|response| {
+                response
+                    .get_blocks()
+                    .iter()
+                    .map(|s3_block| BlockContents::try_from(s3_block).map_err(Error::from))
+                    .collect::<Result<Vec<BlockContents>>>()
+            }),
+        )
+    }
+
+    fn subscribe_blocks_async(&self, from_index: BlockIndex) -> ConnectionStream<BlockContents> {
+        let mut request = SubscribeBlocksRequest::new();
+        request.set_from_index(from_index);
+
+        // Attest first if needed. Unlike unary calls, the session is not torn down when the
+        // subscription fails, since the failure may come long after the call was made.
+        let attested = self.attested();
+
+        let this = self.clone();
+        Box::new(
+            attested
+                .and_then(move |()| -> Result<_> {
+                    Ok(this.blockchain_api_client.subscribe_blocks(&request)?)
+                })
+                .map(|receiver| {
+                    receiver.map_err(Error::from).and_then(|s3_block| {
+                        BlockContents::try_from(&s3_block).map_err(Error::from)
+                    })
+                })
+                .flatten_stream(),
+        )
+    }
+}
+
+impl AsyncUserTxConnection for AsyncThickClient {
+    fn propose_tx_async(&self, tx: &Tx) -> ConnectionFuture<BlockIndex> {
+        // Don't leave the plaintext serialization floating around
+        let tx_plaintext = SecretVec::new(encode(tx));
+
+        Box::new(
+            self.encrypted_call(move |this| {
+                let mut msg = Message::new();
+                {
+                    let mut enclave_connection =
+                        this.enclave_connection.lock().expect("mutex poisoned");
+                    let enclave_connection = enclave_connection
+                        .as_mut()
+                        .ok_or_else(|| Error::Other("Session torn down before use".to_string()))?;
+                    msg.set_channel_id(Vec::from(enclave_connection.binding()));
+                    msg.set_data(
+                        enclave_connection.encrypt(&[], tx_plaintext.expose_secret().as_ref())?,
+                    );
+                }
+                Ok(this
+                    .consensus_client_api_client
+                    .client_tx_propose_async(&msg)?)
+            })
+            .and_then(|response| parse_propose_tx_response(&response)),
+        )
+    }
+}
+
+/// Whether the node no longer recognizes the session a call was made with.
+fn is_unauthenticated(err: &GrpcError) -> bool {
+    match err {
+        GrpcError::RpcFailure(rpc_status) => rpc_status.status == RpcStatusCode::UNAUTHENTICATED,
+        _ => false,
+    }
+}
+
+/// Runs the calls made through it one at a time, in the order they were made. A call starts once
+/// the previous one is over, or was dropped.
+#[derive(Clone, Default)]
+struct CallQueue {
+    /// Resolves once the last call made is over.
+    last_call_done: Arc<Mutex<Option<oneshot::Receiver<()>>>>,
+}
+
+impl CallQueue {
+    fn run<T, E, Fut>(
+        &self,
+        call: impl FnOnce() -> Fut + Send + 'static,
+    ) -> Box<dyn Future<Item = T, Error = E> + Send>
+    where
+        T: Send + 'static,
+        E: Send + 'static,
+        Fut: Future<Item = T, Error = E> + Send + 'static,
+    {
+        let (done_sender, done_receiver) = oneshot::channel();
+        let prev_call_done = self
+            .last_call_done
+            .lock()
+            .expect("mutex poisoned")
+            .replace(done_receiver);
+
+        // The previous call being dropped (`Canceled`) is as good as it being over.
+        let prev_call_done: Box<dyn Future<Item = (), Error = ()> + Send> = match prev_call_done {
+            Some(prev_call_done) => Box::new(prev_call_done.then(|_| Ok(()))),
+            None => Box::new(future::ok(())),
+        };
+
+        Box::new(prev_call_done.then(move |_| call()).then(move |result| {
+            let _ = done_sender.send(());
+            result
+        }))
+    }
+}
+
+impl Display for AsyncThickClient {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{}", self.uri)
+    }
+}
+
+impl Eq for AsyncThickClient {}
+
+impl Hash for AsyncThickClient {
+    fn hash<H: Hasher>(&self, hasher: &mut H) {
+        self.uri.addr().hash(hasher);
+    }
+}
+
+impl PartialEq for AsyncThickClient {
+    fn eq(&self, other: &Self) -> bool {
+        self.uri.addr() == other.uri.addr()
+    }
+}
+
+impl Ord for AsyncThickClient {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.uri.addr().cmp(&other.uri.addr())
+    }
+}
+
+impl PartialOrd for AsyncThickClient {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.uri.addr().partial_cmp(&other.uri.addr())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{
+        executor::{self, Notify, NotifyHandle},
+        Async,
+    };
+    use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+
+    struct NoopNotify;
+
+    impl Notify for NoopNotify {
+        fn notify(&self, _id: usize) {}
+    }
+
+    #[test]
+    // Concurrent calls run one after the other, whether the previous call completes or is dropped.
+    fn test_call_queue_runs_calls_one_at_a_time() {
+        let call_queue = CallQueue::default();
+        let notify = NotifyHandle::from(Arc::new(NoopNotify));
+
+        // The first call is in flight until `first_response` is sent.
+        let (first_response, first_call_done) = oneshot::channel::<u32>();
+        let mut first =
+            executor::spawn(call_queue.run(move || first_call_done.map_err(|_| "canceled")));
+
+        // The second and third calls are made while the first one is in flight.
+        let second_started = Arc::new(AtomicBool::new(false));
+        let second_started_clone = second_started.clone();
+        let mut second = executor::spawn(call_queue.run(move || {
+            second_started_clone.store(true, AtomicOrdering::SeqCst);
+            future::ok::<u32, &'static str>(2)
+        }));
+        let third_started = Arc::new(AtomicBool::new(false));
+        let third_started_clone = third_started.clone();
+        let third = call_queue.run(move || {
+            third_started_clone.store(true, AtomicOrdering::SeqCst);
+            future::ok::<u32, &'static str>(3)
+        });
+
+        assert_eq!(first.poll_future_notify(&notify, 0), Ok(Async::NotReady));
+        assert_eq!(second.poll_future_notify(&notify, 0), Ok(Async::NotReady));
+        assert!(!second_started.load(AtomicOrdering::SeqCst));
+
+        // Once the first call is over, the second one runs.
+        first_response.send(1).unwrap();
+        assert_eq!(first.poll_future_notify(&notify, 0), Ok(Async::Ready(1)));
+        assert_eq!(second.poll_future_notify(&notify, 0), Ok(Async::Ready(2)));
+
+        // Dropping a call does not hold up the ones made after it.
+        let dropped = call_queue.run(|| future::empty::<u32, &'static str>());
+        let fourth = call_queue.run(|| future::ok::<u32, &'static str>(4));
+        drop(dropped);
+        assert_eq!(third.wait(), Ok(3));
+        assert!(third_started.load(AtomicOrdering::SeqCst));
+        assert_eq!(fourth.wait(), Ok(4));
+    }
+}
@@ -0,0 +1,64 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Asynchronous versions of the connection traits. Calls return futures instead of blocking,
+//! and take `&self`, so that a connection can be shared without locking it.
+
+use crate::{
+    block_contents::BlockContents,
+    error::Error,
+    traits::{AttestationError, Connection},
+};
+use futures::{Future, Stream};
+use grpcio::Error as GrpcError;
+use std::ops::Range;
+use transaction::{tx::Tx, Block, BlockID, BlockIndex};
+
+/// The result of an asynchronous call.
+pub type ConnectionFuture<T> = Box<dyn Future<Item = T, Error = Error> + Send>;
+
+/// The items of an asynchronous call that returns several over time.
+pub type ConnectionStream<T> = Box<dyn Stream<Item = T, Error = Error> + Send>;
+
+pub trait AsyncAttestedConnection: Connection {
+    type Error: AttestationError + From<GrpcError> + 'static;
+
+    fn is_attested(&self) -> bool;
+
+    /// Attest to the node, replacing any existing session.
+    fn attest_async(&self) -> Box<dyn Future<Item = (), Error = Self::Error> + Send>;
+
+    fn deattest(&self);
+}
+
+/// An asynchronous connection trait providing APIs for use in retrieving blocks from a consensus
+/// node.
+pub trait AsyncBlockchainConnection: Connection {
+    /// Retrieve the block metadata from the blockchain service.
+    fn fetch_blocks_async(&self, range: Range<BlockIndex>) -> ConnectionFuture<Vec<Block>>;
+
+    /// Retrieve the BlockIDs (hashes) of the given blocks from the blockchain service.
+    fn fetch_block_ids_async(&self, range: Range<BlockIndex>) -> ConnectionFuture<Vec<BlockID>>;
+
+    /// Retrieve the consensus node's current block height
+    fn fetch_block_height_async(&self) -> ConnectionFuture<BlockIndex>;
+
+    /// Retrieve the contents of the block with the given ID.
+    fn fetch_block_by_id_async(&self, block_id: &BlockID) -> ConnectionFuture<BlockContents>;
+
+    /// Retrieve the contents of the given blocks, transactions and signatures included.
+    fn fetch_blocks_with_transactions_async(
+        &self,
+        range: Range<BlockIndex>,
+    ) -> ConnectionFuture<Vec<BlockContents>>;
+
+    /// Subscribe to the contents of every block from `from_index` on, including blocks appended
+    /// after the call.
+    fn subscribe_blocks_async(&self, from_index: BlockIndex) -> ConnectionStream<BlockContents>;
+}
+
+/// An asynchronous connection trait supporting the submission of transactions to a node
+pub trait AsyncUserTxConnection: Connection {
+    /// Propose a transaction over the encrypted channel.
+    /// Resolves to the number of blocks in the ledger at the time the call was received.
+    fn propose_tx_async(&self, tx: &Tx) -> ConnectionFuture<BlockIndex>;
+}
@@ -0,0 +1,205 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Verification reports of the nodes we have attested to, optionally persisted to disk so that
+//! they survive restarts.
+//!
+//! The reports only decide the order in which `AttestationRefresher` attests to nodes without a
+//! session, e.g. after a restart. They are never used in place of an attestation: every
+//! attestation performs a full handshake and verifies the report the node presents.
+
+use attest::VerificationReport;
+use common::{
+    logger::{log, Logger},
+    ResponderId,
+};
+use failure::Fail;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::Error as IoError,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+#[derive(Debug, Fail)]
+pub enum AttestationCacheError {
+    #[fail(display = "IO error: {}", _0)]
+    Io(IoError),
+    #[fail(display = "Serialization error: {}", _0)]
+    Serialization(mcserial::encode::Error),
+    #[fail(display = "Deserialization error: {}", _0)]
+    Deserialization(mcserial::decode::Error),
+}
+
+impl From<IoError> for AttestationCacheError {
+    fn from(src: IoError) -> Self {
+        AttestationCacheError::Io(src)
+    }
+}
+
+impl From<mcserial::encode::Error> for AttestationCacheError {
+    fn from(src: mcserial::encode::Error) -> Self {
+        AttestationCacheError::Serialization(src)
+    }
+}
+
+impl From<mcserial::decode::Error> for AttestationCacheError {
+    fn from(src: mcserial::decode::Error) -> Self {
+        AttestationCacheError::Deserialization(src)
+    }
+}
+
+/// A verification report, and when it was verified.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct VerifiedReport {
+    pub report: VerificationReport,
+
+    /// Seconds since the UNIX epoch.
+    pub verified_at: u64,
+}
+
+impl VerifiedReport {
+    /// Time elapsed since the report was verified.
+    pub fn age(&self) -> Duration {
+        let verified_at = UNIX_EPOCH + Duration::from_secs(self.verified_at);
+        SystemTime::now()
+            .duration_since(verified_at)
+            .unwrap_or_default()
+    }
+}
+
+/// The most recently verified report of each node. Clones share the same reports.
+#[derive(Clone)]
+pub struct AttestationCache {
+    reports: Arc<Mutex<BTreeMap<ResponderId, VerifiedReport>>>,
+
+    /// File the reports are persisted to, if any.
+    path: Option<PathBuf>,
+
+    logger: Logger,
+}
+
+impl AttestationCache {
+    /// A cache that is only kept in memory.
+    pub fn new(logger: Logger) -> Self {
+        Self {
+            reports: Arc::new(Mutex::new(BTreeMap::new())),
+            path: None,
+            logger,
+        }
+    }
+
+    /// A cache persisted to `path`. Reports previously persisted there are loaded.
+    pub fn open(path: PathBuf, logger: Logger) -> Result<Self, AttestationCacheError> {
+        let reports = if path.exists() {
+            mcserial::deserialize(&fs::read(&path)?)?
+        } else {
+            BTreeMap::new()
+        };
+        Ok(Self {
+            reports: Arc::new(Mutex::new(reports)),
+            path: Some(path),
+            logger,
+        })
+    }
+
+    /// The report most recently verified for `responder_id`.
+    pub fn get(&self, responder_id: &ResponderId) -> Option<VerifiedReport> {
+        self.reports
+            .lock()
+            .expect("mutex poisoned")
+            .get(responder_id)
+            .cloned()
+    }
+
+    /// Record that `report` was just verified for `responder_id`.
+    pub fn insert(&self, responder_id: &ResponderId, report: VerificationReport) {
+        let verified_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+
+        let mut reports = self.reports.lock().expect("mutex poisoned");
+        reports.insert(
+            responder_id.clone(),
+            VerifiedReport {
+                report,
+                verified_at,
+            },
+        );
+        self.persist(&reports);
+    }
+
+    /// Forget the report of `responder_id`.
+    pub fn remove(&self, responder_id: &ResponderId) {
+        let mut reports = self.reports.lock().expect("mutex poisoned");
+        if reports.remove(responder_id).is_some() {
+            self.persist(&reports);
+        }
+    }
+
+    // Write the reports to disk. Failures are logged, since the cache is only an optimization.
+    fn persist(&self, reports: &BTreeMap<ResponderId, VerifiedReport>) {
+        if let Some(path) = &self.path {
+            if let Err(err) = Self::write(path, reports) {
+                log::warn!(
+                    self.logger,
+                    "Failed persisting attestation cache to {:?}: {}",
+                    path,
+                    err
+                );
+            }
+        }
+    }
+
+    // Write to a temporary file first, so that a crash never leaves a partially written cache.
+    fn write(
+        path: &Path,
+        reports: &BTreeMap<ResponderId, VerifiedReport>,
+    ) -> Result<(), AttestationCacheError> {
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, mcserial::serialize(reports)?)?;
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::logger::test_with_logger;
+    use std::str::FromStr;
+    use tempdir::TempDir;
+
+    fn test_report(n: u8) -> VerificationReport {
+        VerificationReport {
+            http_body: format!("report {}", n),
+            ..Default::default()
+        }
+    }
+
+    #[test_with_logger]
+    // Reports persisted by one cache are loaded by the next one opened on the same file.
+    fn test_persist_reports(logger: Logger) {
+        let temp_dir = TempDir::new("attestation_cache").unwrap();
+        let path = temp_dir.path().join("reports");
+        let node1 = ResponderId::from_str("node1.test.com:443").unwrap();
+        let node2 = ResponderId::from_str("node2.test.com:443").unwrap();
+
+        {
+            let cache = AttestationCache::open(path.clone(), logger.clone()).unwrap();
+            assert!(cache.get(&node1).is_none());
+            cache.insert(&node1, test_report(1));
+            cache.insert(&node2, test_report(2));
+            cache.remove(&node2);
+        }
+
+        let cache = AttestationCache::open(path, logger).unwrap();
+        let verified = cache.get(&node1).unwrap();
+        assert_eq!(verified.report, test_report(1));
+        assert!(verified.age() < Duration::from_secs(60));
+        assert!(cache.get(&node2).is_none());
+    }
+}
@@ -0,0 +1,167 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Attests to nodes in the background, so that calls rarely have to wait for a handshake.
+//!
+//! Sessions are renewed shortly before they expire, and nodes without a session are attested to
+//! ahead of their first call. The refresher attests to at most one node at a time. Calls to a node
+//! that does not have a session yet still attest to it on demand, so after a restart, calls made
+//! before the refresher reaches their node pay for a full attestation.
+
+use crate::{
+    attestation_cache::AttestationCache, manager::ConnectionManager, sync::SyncConnection,
+    traits::AttestedConnection,
+};
+use common::{
+    logger::{log, Logger},
+    HashMap, ResponderId,
+};
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// How long before a session expires it is renewed.
+pub const DEFAULT_REFRESH_MARGIN: Duration = Duration::from_secs(5 * 60);
+
+/// Time between attestations.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Time before attesting again to a node without a session, after failing to.
+const RETRY_INTERVAL: Duration = Duration::from_secs(30);
+
+pub struct AttestationRefresher {
+    join_handle: Option<thread::JoinHandle<()>>,
+    stop_requested: Arc<AtomicBool>,
+}
+
+impl AttestationRefresher {
+    /// Start refreshing the connections of `manager`. When given, `attestation_cache` decides
+    /// which nodes without a session are attested to first: the ones verified longest ago.
+    pub fn start<C: AttestedConnection + 'static>(
+        manager: ConnectionManager<C>,
+        attestation_cache: Option<AttestationCache>,
+        refresh_margin: Duration,
+        logger: Logger,
+    ) -> Self {
+        let stop_requested = Arc::new(AtomicBool::new(false));
+        let thread_stop_requested = stop_requested.clone();
+        let join_handle = Some(
+            thread::Builder::new()
+                .name("AttestationRefresher".into())
+                .spawn(move || {
+                    Self::thread_entrypoint(
+                        manager,
+                        attestation_cache,
+                        refresh_margin,
+                        thread_stop_requested,
+                        logger,
+                    )
+                })
+                .expect("Failed spawning AttestationRefresher thread"),
+        );
+
+        Self {
+            join_handle,
+            stop_requested,
+        }
+    }
+
+    pub fn stop(&mut self) {
+        self.stop_requested.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.join_handle.take() {
+            thread
+                .join()
+                .expect("AttestationRefresher thread join failed");
+        }
+    }
+
+    fn thread_entrypoint<C: AttestedConnection + 'static>(
+        manager: ConnectionManager<C>,
+        attestation_cache: Option<AttestationCache>,
+        refresh_margin: Duration,
+        stop_requested: Arc<AtomicBool>,
+        logger: Logger,
+    ) {
+        log::debug!(logger, "AttestationRefresher thread has started.");
+
+        let mut last_attempts: HashMap<ResponderId, Instant> = HashMap::default();
+
+        loop {
+            if stop_requested.load(Ordering::SeqCst) {
+                log::debug!(logger, "AttestationRefresher stop requested.");
+                break;
+            }
+
+            if let Some((responder_id, conn)) = Self::next_due(
+                &manager,
+                attestation_cache.as_ref(),
+                refresh_margin,
+                &last_attempts,
+            ) {
+                log::debug!(logger, "Refreshing attestation to {}", responder_id);
+                last_attempts.insert(responder_id.clone(), Instant::now());
+                if let Err(err) = conn.write().attest() {
+                    log::info!(
+                        logger,
+                        "Failed refreshing attestation to {}: {}",
+                        responder_id,
+                        err
+                    );
+                }
+            }
+
+            thread::sleep(REFRESH_INTERVAL);
+        }
+    }
+
+    // The connection most in need of attestation, if any needs it now.
+    fn next_due<C: AttestedConnection>(
+        manager: &ConnectionManager<C>,
+        attestation_cache: Option<&AttestationCache>,
+        refresh_margin: Duration,
+        last_attempts: &HashMap<ResponderId, Instant>,
+    ) -> Option<(ResponderId, SyncConnection<C>)> {
+        let now = Instant::now();
+        manager
+            .id_to_conn()
+            .into_iter()
+            .filter_map(|(responder_id, conn)| {
+                let priority = {
+                    let inner = conn.read();
+                    if inner.is_attested() {
+                        // Sessions closest to expiring are renewed first.
+                        let refresh_at = inner.session_expiry()?.checked_sub(refresh_margin)?;
+                        if refresh_at > now {
+                            return None;
+                        }
+                        (1, refresh_at, 0)
+                    } else {
+                        if last_attempts.get(&responder_id).map_or(false, |attempt| {
+                            now.duration_since(*attempt) < RETRY_INTERVAL
+                        }) {
+                            return None;
+                        }
+                        // Nodes without a session come first, unverified ones before the others.
+                        let verified_at = attestation_cache
+                            .and_then(|cache| cache.get(&responder_id))
+                            .map(|verified| verified.verified_at)
+                            .unwrap_or(0);
+                        (0, now, verified_at)
+                    }
+                };
+                Some((priority, responder_id, conn))
+            })
+            .min_by_key(|(priority, _responder_id, _conn)| *priority)
+            .map(|(_priority, responder_id, conn)| (responder_id, conn))
+    }
+}
+
+impl Drop for AttestationRefresher {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
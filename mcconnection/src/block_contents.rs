@@ -0,0 +1,54 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! The contents of a block, as served by the blockchain API.
+
+use mobilecoin_api::{blockchain::S3Block, ConversionError};
+use std::convert::TryFrom;
+use transaction::{Block, BlockSignature, BlockSignatureSet, RedactedTx};
+
+/// A block, its transactions and the signatures over it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BlockContents {
+    pub block: Block,
+    pub transactions: Vec<RedactedTx>,
+
+    /// The signature the block was appended with, if any.
+    pub signature: Option<BlockSignature>,
+
+    /// Every known signature over the block, including `signature`.
+    pub signature_set: BlockSignatureSet,
+}
+
+impl TryFrom<&S3Block> for BlockContents {
+    type Error = ConversionError;
+
+    fn try_from(source: &S3Block) -> Result<Self, Self::Error> {
+        let block = Block::try_from(source.get_block())?;
+        let transactions = source
+            .get_transactions()
+            .iter()
+            .map(RedactedTx::try_from)
+            .collect::<Result<Vec<RedactedTx>, _>>()?;
+        let signature = source
+            .signature
+            .as_ref()
+            .map(BlockSignature::try_from)
+            .transpose()?;
+        let mut signature_set = source
+            .signature_set
+            .as_ref()
+            .map(BlockSignatureSet::try_from)
+            .transpose()?
+            .unwrap_or_default();
+        if let Some(signature) = signature.as_ref() {
+            signature_set.insert(signature.clone());
+        }
+
+        Ok(Self {
+            block,
+            transactions,
+            signature,
+            signature_set,
+        })
+    }
+}
@@ -0,0 +1,101 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Adapts asynchronous connections to the synchronous connection traits, so that they can be
+//! used with `ConnectionManager`, `SyncConnection` and the code built on them.
+
+use crate::{
+    async_traits::{AsyncAttestedConnection, AsyncBlockchainConnection, AsyncUserTxConnection},
+    block_contents::BlockContents,
+    error::Result,
+    traits::{
+        AttestedConnection, BlockSubscription, BlockchainConnection, Connection, UserTxConnection,
+    },
+};
+use futures::{Future, Stream};
+use std::{
+    fmt::{Display, Formatter, Result as FmtResult},
+    ops::Range,
+    result::Result as StdResult,
+};
+use transaction::{tx::Tx, Block, BlockID, BlockIndex};
+
+/// Implements the synchronous connection traits by waiting on the futures of an asynchronous
+/// connection.
+#[derive(Clone, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct BlockingConnection<C: Connection>(pub C);
+
+impl<C: Connection> BlockingConnection<C> {
+    pub fn new(inner: C) -> Self {
+        Self(inner)
+    }
+
+    /// The asynchronous connection.
+    pub fn inner(&self) -> &C {
+        &self.0
+    }
+}
+
+impl<C: Connection> Display for BlockingConnection<C> {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl<C: Connection> Connection for BlockingConnection<C> {
+    type Uri = C::Uri;
+
+    fn uri(&self) -> Self::Uri {
+        self.0.uri()
+    }
+}
+
+impl<C: AsyncAttestedConnection> AttestedConnection for BlockingConnection<C> {
+    type Error = C::Error;
+
+    fn is_attested(&self) -> bool {
+        self.0.is_attested()
+    }
+
+    fn attest(&mut self) -> StdResult<(), Self::Error> {
+        self.0.attest_async().wait()
+    }
+
+    fn deattest(&mut self) {
+        self.0.deattest()
+    }
+}
+
+impl<C: AsyncBlockchainConnection> BlockchainConnection for BlockingConnection<C> {
+    fn fetch_blocks(&mut self, range: Range<BlockIndex>) -> Result<Vec<Block>> {
+        self.0.fetch_blocks_async(range).wait()
+    }
+
+    fn fetch_block_ids(&mut self, range: Range<BlockIndex>) -> Result<Vec<BlockID>> {
+        self.0.fetch_block_ids_async(range).wait()
+    }
+
+    fn fetch_block_height(&mut self) -> Result<BlockIndex> {
+        self.0.fetch_block_height_async().wait()
+    }
+
+    fn fetch_block_by_id(&mut self, block_id: &BlockID) -> Result<BlockContents> {
+        self.0.fetch_block_by_id_async(block_id).wait()
+    }
+
+    fn fetch_blocks_with_transactions(
+        &mut self,
+        range: Range<BlockIndex>,
+    ) -> Result<Vec<BlockContents>> {
+        self.0.fetch_blocks_with_transactions_async(range).wait()
+    }
+
+    fn subscribe_blocks(&mut self, from_index: BlockIndex) -> Result<BlockSubscription> {
+        Ok(Box::new(self.0.subscribe_blocks_async(from_index).wait()))
+    }
+}
+
+impl<C: AsyncUserTxConnection> UserTxConnection for BlockingConnection<C> {
+    fn propose_tx(&mut self, tx: &Tx) -> Result<BlockIndex> {
+        self.0.propose_tx_async(tx).wait()
+    }
+}
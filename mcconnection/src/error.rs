@@ -35,6 +35,12 @@ pub enum Error {
     Attestation(Box<dyn AttestationError + 'static>),
     #[fail(display = "Transaction validation failure: {}", _0)]
     TransactionValidation(TransactionValidationError),
+    #[fail(display = "Fee too low for current load, minimum fee is {}", _0)]
+    FeeTooLowForCurrentLoad(u64),
+    #[fail(display = "Transaction not admitted by the node: {:?}", _0)]
+    NotAdmitted(ProposeTxResult),
+    #[fail(display = "No connections available")]
+    NoConnections,
     #[fail(display = "Other: {}", _0)]
     Other(String),
 }
@@ -45,6 +51,7 @@ impl Error {
         match self {
             Error::Grpc(_ge) => true,
             Error::Attestation(_ae) => true,
+            Error::NotAdmitted(_result) => true,
             _ => false,
         }
     }
@@ -2,21 +2,38 @@
 
 //! Connection support
 
+mod async_thick;
+mod async_traits;
+mod attestation_cache;
+mod attestation_refresher;
+mod block_contents;
+mod blocking;
 mod error;
 mod grpcio_utils;
 mod manager;
+mod selector;
 mod sync;
 mod thick;
 mod traits;
 
 pub use self::{
+    async_thick::AsyncThickClient,
+    async_traits::{
+        AsyncAttestedConnection, AsyncBlockchainConnection, AsyncUserTxConnection,
+        ConnectionFuture, ConnectionStream,
+    },
+    attestation_cache::{AttestationCache, AttestationCacheError, VerifiedReport},
+    attestation_refresher::{AttestationRefresher, DEFAULT_REFRESH_MARGIN},
+    block_contents::BlockContents,
+    blocking::BlockingConnection,
     error::{Error, Result, RetryError, RetryResult},
     grpcio_utils::{ConnectionUriGrpcioChannel, ConnectionUriGrpcioServer},
     manager::ConnectionManager,
+    selector::{ConnectionSelector, ConnectionStats},
     sync::SyncConnection,
-    thick::{ThickClient, ThickClientAttestationError},
+    thick::{ThickClient, ThickClientAttestationError, DEFAULT_SESSION_LIFETIME},
     traits::{
-        AttestationError, AttestedConnection, BlockchainConnection, Connection,
+        AttestationError, AttestedConnection, BlockSubscription, BlockchainConnection, Connection,
         RetryableBlockchainConnection, RetryableUserTxConnection, UserTxConnection,
     },
 };
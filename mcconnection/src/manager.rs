@@ -11,7 +11,7 @@ use mcuri::ConnectionUri;
 use std::{
     collections::BTreeMap,
     iter::FromIterator,
-    sync::{Arc, RwLock, RwLockReadGuard},
+    sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard},
 };
 
 struct ConnectionManagerInner<C: Connection> {
@@ -37,26 +37,57 @@ impl<C: Connection> ConnectionManager<C> {
     pub fn new(conns: Vec<C>, logger: Logger) -> Self {
         Self {
             inner: Arc::new(RwLock::new(ConnectionManagerInner {
-                id_to_conn: BTreeMap::from_iter(conns.into_iter().map(|conn| {
-                    let name = conn.to_string();
-                    let responder_id = conn.uri().responder_id().unwrap_or_else(|_| {
-                        panic!(
-                            "Could not create responder_id from {:?}",
-                            conn.uri().to_string()
-                        )
-                    });
-                    let sync_conn =
-                        SyncConnection::new(conn, logger.new(o!("mc.peers.peer_name" => name)));
-                    (responder_id, sync_conn)
-                })),
+                id_to_conn: BTreeMap::from_iter(
+                    conns.into_iter().map(|conn| Self::sync_conn(conn, &logger)),
+                ),
             })),
         }
     }
 
+    /// Replace the managed connections with `conns`. Existing connections whose URI is unchanged
+    /// are kept as they are, so they do not need to reconnect. This affects all clones of this
+    /// manager.
+    pub fn update(&self, conns: Vec<C>, logger: Logger) {
+        let mut inner = self.write();
+        let id_to_conn = conns
+            .into_iter()
+            .map(|conn| {
+                let responder_id = Self::responder_id(&conn);
+                match inner.id_to_conn.get(&responder_id) {
+                    Some(existing) if existing.uri() == conn.uri() => {
+                        (responder_id, existing.clone())
+                    }
+                    _ => Self::sync_conn(conn, &logger),
+                }
+            })
+            .collect();
+        inner.id_to_conn = id_to_conn;
+    }
+
+    fn responder_id(conn: &C) -> ResponderId {
+        conn.uri().responder_id().unwrap_or_else(|_| {
+            panic!(
+                "Could not create responder_id from {:?}",
+                conn.uri().to_string()
+            )
+        })
+    }
+
+    fn sync_conn(conn: C, logger: &Logger) -> (ResponderId, SyncConnection<C>) {
+        let name = conn.to_string();
+        let responder_id = Self::responder_id(&conn);
+        let sync_conn = SyncConnection::new(conn, logger.new(o!("mc.peers.peer_name" => name)));
+        (responder_id, sync_conn)
+    }
+
     fn read(&self) -> RwLockReadGuard<ConnectionManagerInner<C>> {
         self.inner.read().expect("ConnectionManager lock poisoned")
     }
 
+    fn write(&self) -> RwLockWriteGuard<ConnectionManagerInner<C>> {
+        self.inner.write().expect("ConnectionManager lock poisoned")
+    }
+
     /// Retrieve a vector of all the connection URLs owned by this manager.
     pub fn responder_ids(&self) -> Vec<ResponderId> {
         self.read().id_to_conn.keys().cloned().collect()
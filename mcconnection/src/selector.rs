@@ -0,0 +1,247 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Picks which nodes to submit user transactions to, based on how they have been responding.
+
+use crate::{
+    error::{Error, RetryError, RetryResult},
+    manager::ConnectionManager,
+    sync::SyncConnection,
+    traits::{Connection, RetryableUserTxConnection, UserTxConnection},
+};
+use common::{
+    logger::{log, Logger},
+    HashMap, ResponderId,
+};
+use std::{
+    iter,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+use transaction::{tx::Tx, BlockIndex};
+
+/// Weight given to the latest latency sample in the moving average.
+const LATENCY_WEIGHT: f64 = 0.2;
+
+/// How a node has been responding to our calls.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ConnectionStats {
+    /// Moving average of how long calls to the node take. None until a call has completed.
+    pub latency: Option<Duration>,
+
+    /// Number of calls that failed since the last one that did not.
+    pub consecutive_failures: u32,
+
+    /// Number of calls the node responded to.
+    pub total_successes: u64,
+
+    /// Number of calls that failed.
+    pub total_failures: u64,
+}
+
+/// Submits user transactions to the healthiest nodes of a `ConnectionManager`.
+///
+/// Nodes are ranked by the number of calls that failed since their last successful one, then by
+/// latency. Nodes that rank the same take turns. When a node fails with an error that another
+/// node may not have (e.g. an attestation failure), the transaction is submitted to the next node.
+pub struct ConnectionSelector<C: Connection> {
+    manager: ConnectionManager<C>,
+
+    /// Stats of each node, shared by clones.
+    stats: Arc<Mutex<HashMap<ResponderId, ConnectionStats>>>,
+
+    /// Number of nodes to submit each transaction to at the same time.
+    parallel_submissions: usize,
+
+    /// Rotates nodes that rank the same, so that they share the load.
+    offset: Arc<AtomicUsize>,
+
+    logger: Logger,
+}
+
+impl<C: Connection> Clone for ConnectionSelector<C> {
+    fn clone(&self) -> Self {
+        Self {
+            manager: self.manager.clone(),
+            stats: self.stats.clone(),
+            parallel_submissions: self.parallel_submissions,
+            offset: self.offset.clone(),
+            logger: self.logger.clone(),
+        }
+    }
+}
+
+impl<C: Connection> ConnectionSelector<C> {
+    pub fn new(manager: ConnectionManager<C>, logger: Logger) -> Self {
+        Self {
+            manager,
+            stats: Arc::new(Mutex::new(HashMap::default())),
+            parallel_submissions: 1,
+            offset: Arc::new(AtomicUsize::new(0)),
+            logger,
+        }
+    }
+
+    /// Submit each transaction to `parallel_submissions` nodes at the same time, instead of one.
+    pub fn with_parallel_submissions(mut self, parallel_submissions: usize) -> Self {
+        self.parallel_submissions = parallel_submissions.max(1);
+        self
+    }
+
+    /// Stats of the node identified by `responder_id`.
+    pub fn stats(&self, responder_id: &ResponderId) -> ConnectionStats {
+        self.stats
+            .lock()
+            .expect("mutex poisoned")
+            .get(responder_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Record that a call to `responder_id` completed after `latency`.
+    pub fn record_success(&self, responder_id: &ResponderId, latency: Duration) {
+        let mut stats = self.stats.lock().expect("mutex poisoned");
+        let stats = stats.entry(responder_id.clone()).or_default();
+        stats.latency = Some(match stats.latency {
+            Some(average) => average
+                .mul_f64(1.0 - LATENCY_WEIGHT)
+                .checked_add(latency.mul_f64(LATENCY_WEIGHT))
+                .unwrap_or(latency),
+            None => latency,
+        });
+        stats.consecutive_failures = 0;
+        stats.total_successes += 1;
+    }
+
+    /// Record that a call to `responder_id` failed.
+    pub fn record_failure(&self, responder_id: &ResponderId) {
+        let mut stats = self.stats.lock().expect("mutex poisoned");
+        let stats = stats.entry(responder_id.clone()).or_default();
+        stats.consecutive_failures = stats.consecutive_failures.saturating_add(1);
+        stats.total_failures += 1;
+    }
+
+    /// Connections ordered from the healthiest node to the least healthy one.
+    pub fn ranked_conns(&self) -> Vec<(ResponderId, SyncConnection<C>)> {
+        let mut conns: Vec<_> = self.manager.id_to_conn().into_iter().collect();
+        if conns.is_empty() {
+            return conns;
+        }
+
+        let offset = self.offset.fetch_add(1, Ordering::SeqCst) % conns.len();
+        conns.rotate_left(offset);
+
+        let stats = self.stats.lock().expect("mutex poisoned");
+        conns.sort_by_key(|(responder_id, _conn)| {
+            stats
+                .get(responder_id)
+                .map(|stats| {
+                    (
+                        stats.consecutive_failures,
+                        stats.latency.unwrap_or_default(),
+                    )
+                })
+                .unwrap_or_default()
+        });
+        conns
+    }
+}
+
+impl<C: UserTxConnection + 'static> ConnectionSelector<C> {
+    /// Propose a transaction to the healthiest nodes, failing over to the next ones as needed.
+    /// Returns the number of blocks in the ledger of the first node that accepted it.
+    pub fn propose_tx(&self, tx: &Tx) -> RetryResult<BlockIndex> {
+        let mut last_err: Option<RetryError<Error>> = None;
+
+        for batch in self.ranked_conns().chunks(self.parallel_submissions) {
+            let results: Box<dyn Iterator<Item = RetryResult<BlockIndex>>> = if batch.len() == 1 {
+                let (responder_id, conn) = &batch[0];
+                Box::new(iter::once(self.propose_tx_to(responder_id, conn, tx)))
+            } else {
+                Box::new(self.spawn_propose_tx(batch, tx).into_iter())
+            };
+
+            for result in results {
+                match result {
+                    Ok(block_height) => return Ok(block_height),
+                    Err(err) => {
+                        // Errors that failing over would not fix are the ones worth reporting.
+                        if last_err.as_ref().map_or(true, Self::should_failover) {
+                            last_err = Some(err);
+                        }
+                    }
+                }
+            }
+
+            if let Some(err) = &last_err {
+                if !Self::should_failover(err) {
+                    break;
+                }
+            }
+        }
+
+        Err(last_err.unwrap_or_else(|| RetryError::Operation {
+            error: Error::NoConnections,
+            total_delay: Duration::default(),
+            tries: 0,
+        }))
+    }
+
+    // Propose a transaction to a single node, and update its stats.
+    fn propose_tx_to(
+        &self,
+        responder_id: &ResponderId,
+        conn: &SyncConnection<C>,
+        tx: &Tx,
+    ) -> RetryResult<BlockIndex> {
+        let start = Instant::now();
+        let result = conn.propose_tx(tx, iter::empty());
+        match &result {
+            Err(err) if Self::should_failover(err) => {
+                log::debug!(
+                    self.logger,
+                    "Failed proposing tx to {}: {:?}",
+                    responder_id,
+                    err
+                );
+                self.record_failure(responder_id);
+            }
+            // Either the node accepted the transaction, or it rejected the transaction itself.
+            _ => self.record_success(responder_id, start.elapsed()),
+        }
+        result
+    }
+
+    // Propose a transaction to each node of `batch` from its own thread. Results are received in
+    // the order they complete.
+    fn spawn_propose_tx(
+        &self,
+        batch: &[(ResponderId, SyncConnection<C>)],
+        tx: &Tx,
+    ) -> mpsc::Receiver<RetryResult<BlockIndex>> {
+        let (sender, receiver) = mpsc::channel();
+        for (responder_id, conn) in batch {
+            let selector = self.clone();
+            let sender = sender.clone();
+            let responder_id = responder_id.clone();
+            let conn = conn.clone();
+            let tx = tx.clone();
+            thread::spawn(move || {
+                // The receiver is gone once another node has accepted the transaction.
+                let _ = sender.send(selector.propose_tx_to(&responder_id, &conn, &tx));
+            });
+        }
+        receiver
+    }
+
+    // Errors that another node may not run into.
+    fn should_failover(err: &RetryError<Error>) -> bool {
+        match err {
+            RetryError::Operation { error, .. } => error.should_retry(),
+            RetryError::Internal(_) => true,
+        }
+    }
+}
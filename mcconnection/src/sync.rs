@@ -3,6 +3,7 @@
 //! A synchronous connection wrapper around an inner (thread-unsafe) connection
 
 use crate::{
+    block_contents::BlockContents,
     error::RetryResult,
     traits::{
         BlockchainConnection, Connection, RetryableBlockchainConnection, RetryableUserTxConnection,
@@ -213,6 +214,34 @@ impl<BC: BlockchainConnection> RetryableBlockchainConnection for SyncConnection<
             retry_iterator
         )
     }
+
+    fn fetch_block_by_id(
+        &self,
+        block_id: &BlockID,
+        retry_iterator: impl IntoIterator<Item = Duration>,
+    ) -> RetryResult<BlockContents> {
+        impl_sync_connection_retry!(
+            self.write(),
+            self.logger,
+            fetch_block_by_id,
+            retry_iterator,
+            block_id
+        )
+    }
+
+    fn fetch_blocks_with_transactions(
+        &self,
+        range: Range<BlockIndex>,
+        retry_iterator: impl IntoIterator<Item = Duration>,
+    ) -> RetryResult<Vec<BlockContents>> {
+        impl_sync_connection_retry!(
+            self.write(),
+            self.logger,
+            fetch_blocks_with_transactions,
+            retry_iterator,
+            range.clone()
+        )
+    }
 }
 
 impl<UTC: UserTxConnection> RetryableUserTxConnection for SyncConnection<UTC> {
@@ -4,10 +4,13 @@
 //! The attested client implementation.
 
 use crate::{
+    attestation_cache::AttestationCache,
+    block_contents::BlockContents,
     error::{Error, Result},
     grpcio_utils::ConnectionUriGrpcioChannel,
     traits::{
-        AttestationError, AttestedConnection, BlockchainConnection, Connection, UserTxConnection,
+        AttestationError, AttestedConnection, BlockSubscription, BlockchainConnection, Connection,
+        UserTxConnection,
     },
 };
 use aes_gcm::Aes256Gcm;
@@ -19,6 +22,7 @@ use common::{
     trace_time,
 };
 use failure::Fail;
+use futures::Stream;
 use grpcio::{ChannelBuilder, Environment, Error as GrpcError};
 use keys::X25519;
 use mcnoise::CipherError;
@@ -26,8 +30,10 @@ use mcrand::McRng;
 use mcserial::encode;
 use mcuri::{ConnectionUri, ConsensusClientUri as ClientUri, UriConversionError};
 use mobilecoin_api::{
-    blockchain::BlocksRequest, blockchain_grpc::BlockchainApiClient,
-    consensus_client_grpc::ConsensusClientApiClient, consensus_common::ProposeTxResult,
+    blockchain::{BlockByIDRequest, BlocksRequest, SubscribeBlocksRequest},
+    blockchain_grpc::BlockchainApiClient,
+    consensus_client_grpc::ConsensusClientApiClient,
+    consensus_common::{ProposeTxResponse, ProposeTxResult},
     empty::Empty,
 };
 use secrecy::{ExposeSecret, SecretVec};
@@ -40,12 +46,16 @@ use std::{
     ops::Range,
     result::Result as StdResult,
     sync::Arc,
+    time::{Duration, Instant},
 };
 use transaction::{tx::Tx, Block, BlockID, BlockIndex};
 
 // FIXME: MC-530 (better place to store MobileCoin-specific enclave details)
-const MC_NODE_PRODUCT_ID: u16 = 1;
-const MC_SECURITY_VERSION: u16 = 1;
+pub(crate) const MC_NODE_PRODUCT_ID: u16 = 1;
+pub(crate) const MC_SECURITY_VERSION: u16 = 1;
+
+/// How long an attested session is used before attesting again.
+pub const DEFAULT_SESSION_LIFETIME: Duration = Duration::from_secs(60 * 60);
 
 #[derive(Debug, Fail)]
 pub enum ThickClientAttestationError {
@@ -108,6 +118,12 @@ pub struct ThickClient {
     expected_measurement: Measurement,
     /// The AKE state machine object, if one is available.
     enclave_connection: Option<Ready<Aes256Gcm>>,
+    /// When the current attested session was established.
+    attested_at: Option<Instant>,
+    /// How long an attested session is used before attesting again.
+    session_lifetime: Duration,
+    /// Where verified reports are recorded, if anywhere.
+    attestation_cache: Option<AttestationCache>,
 }
 
 impl ThickClient {
@@ -134,8 +150,23 @@ impl ThickClient {
             attested_api_client,
             expected_measurement: expected_measurement.into(),
             enclave_connection: None,
+            attested_at: None,
+            session_lifetime: DEFAULT_SESSION_LIFETIME,
+            attestation_cache: None,
         })
     }
+
+    /// Attest again once a session has been used for `session_lifetime`.
+    pub fn with_session_lifetime(mut self, session_lifetime: Duration) -> Self {
+        self.session_lifetime = session_lifetime;
+        self
+    }
+
+    /// Record the verification report of the node in `attestation_cache` after each attestation.
+    pub fn with_attestation_cache(mut self, attestation_cache: AttestationCache) -> Self {
+        self.attestation_cache = Some(attestation_cache);
+        self
+    }
 }
 
 impl Connection for ThickClient {
@@ -150,7 +181,18 @@ impl AttestedConnection for ThickClient {
     type Error = ThickClientAttestationError;
 
     fn is_attested(&self) -> bool {
-        self.enclave_connection.is_some()
+        match self.session_expiry() {
+            Some(expiry) => Instant::now() < expiry,
+            None => false,
+        }
+    }
+
+    fn session_expiry(&self) -> Option<Instant> {
+        if self.enclave_connection.is_none() {
+            return None;
+        }
+        self.attested_at
+            .map(|attested_at| attested_at + self.session_lifetime)
     }
 
     fn attest(&mut self) -> StdResult<(), Self::Error> {
@@ -160,8 +202,9 @@ impl AttestedConnection for ThickClient {
 
         let mut csprng = McRng::default();
 
+        let responder_id = self.uri.responder_id()?;
         let initiator = Start::new(
-            self.uri.responder_id()?.to_string(),
+            responder_id.to_string(),
             self.expected_measurement,
             MC_NODE_PRODUCT_ID,
             MC_SECURITY_VERSION,
@@ -173,17 +216,31 @@ impl AttestedConnection for ThickClient {
 
         let auth_response = self.attested_api_client.auth(&auth_request_output.into())?;
 
-        let (initiator, _) = initiator.try_next(&mut csprng, auth_response.into())?;
+        let (initiator, report) = match initiator.try_next(&mut csprng, auth_response.into()) {
+            Ok(result) => result,
+            Err(err) => {
+                // Whatever was verified before no longer holds.
+                if let Some(attestation_cache) = &self.attestation_cache {
+                    attestation_cache.remove(&responder_id);
+                }
+                return Err(err.into());
+            }
+        };
 
+        if let Some(attestation_cache) = &self.attestation_cache {
+            attestation_cache.insert(&responder_id, report);
+        }
         self.enclave_connection = Some(initiator);
+        self.attested_at = Some(Instant::now());
 
         Ok(())
     }
 
     fn deattest(&mut self) {
-        if self.is_attested() {
+        if self.enclave_connection.is_some() {
             log::trace!(self.logger, "Tearing down existing attested connection.");
             self.enclave_connection = None;
+            self.attested_at = None;
         }
     }
 }
@@ -229,6 +286,53 @@ impl BlockchainConnection for ThickClient {
             })?
             .index)
     }
+
+    fn fetch_block_by_id(&mut self, block_id: &BlockID) -> Result<BlockContents> {
+        trace_time!(self.logger, "ThickClient::fetch_block_by_id");
+
+        let mut request = BlockByIDRequest::new();
+        request.set_id(block_id.0.to_vec());
+
+        let s3_block =
+            self.attested_call(|this| this.blockchain_api_client.get_block_by_id(&request))?;
+        Ok(BlockContents::try_from(&s3_block)?)
+    }
+
+    fn fetch_blocks_with_transactions(
+        &mut self,
+        range: Range<BlockIndex>,
+    ) -> Result<Vec<BlockContents>> {
+        trace_time!(self.logger, "ThickClient::fetch_blocks_with_transactions");
+
+        let mut request = BlocksRequest::new();
+        request.set_offset(range.start);
+        let limit = u32::try_from(range.end - range.start).or(Err(Error::RequestTooLarge))?;
+        request.set_limit(limit);
+
+        self.attested_call(|this| {
+            this.blockchain_api_client
+                .get_blocks_with_transactions(&request)
+        })?
+        .get_blocks()
+        .iter()
+        .map(|s3_block| BlockContents::try_from(s3_block).map_err(Error::from))
+        .collect::<Result<Vec<BlockContents>>>()
+    }
+
+    fn subscribe_blocks(&mut self, from_index: BlockIndex) -> Result<BlockSubscription> {
+        trace_time!(self.logger, "ThickClient::subscribe_blocks");
+
+        let mut request = SubscribeBlocksRequest::new();
+        request.set_from_index(from_index);
+
+        let receiver =
+            self.attested_call(|this| this.blockchain_api_client.subscribe_blocks(&request))?;
+        Ok(Box::new(
+            receiver
+                .wait()
+                .map(|s3_block| Ok(BlockContents::try_from(&s3_block?)?)),
+        ))
+    }
 }
 
 impl UserTxConnection for ThickClient {
@@ -255,11 +359,22 @@ impl UserTxConnection for ThickClient {
         let resp =
             self.attested_call(|this| this.consensus_client_api_client.client_tx_propose(&msg))?;
 
-        if resp.get_result() == ProposeTxResult::Ok {
-            Ok(resp.get_num_blocks())
-        } else {
-            Err(resp.get_result().into())
+        parse_propose_tx_response(&resp)
+    }
+}
+
+/// The number of blocks in the ledger of the node, if it accepted the transaction.
+pub(crate) fn parse_propose_tx_response(resp: &ProposeTxResponse) -> Result<BlockIndex> {
+    match resp.get_result() {
+        ProposeTxResult::Ok => Ok(resp.get_num_blocks()),
+        ProposeTxResult::FeeTooLowForCurrentLoad => {
+            Err(Error::FeeTooLowForCurrentLoad(resp.get_minimum_fee()))
         }
+        result @ ProposeTxResult::OverCapacity
+        | result @ ProposeTxResult::ClientRateLimited
+        | result @ ProposeTxResult::SourceRateLimited
+        | result @ ProposeTxResult::TxCacheFull => Err(Error::NotAdmitted(result)),
+        result => Err(result.into()),
     }
 }
 
@@ -2,7 +2,10 @@
 
 //! Traits which connection implementations can implement.
 
-use crate::error::{Result, RetryResult};
+use crate::{
+    block_contents::BlockContents,
+    error::{Result, RetryResult},
+};
 use grpcio::{Error as GrpcError, RpcStatusCode};
 use mcuri::ConnectionUri;
 use std::{
@@ -10,7 +13,7 @@ use std::{
     hash::Hash,
     ops::Range,
     result::Result as StdResult,
-    time::Duration,
+    time::{Duration, Instant},
 };
 use transaction::{tx::Tx, Block, BlockID, BlockIndex};
 
@@ -29,6 +32,11 @@ pub trait AttestedConnection: Connection {
 
     fn is_attested(&self) -> bool;
 
+    /// When the current attested session stops being used, if there is one that expires.
+    fn session_expiry(&self) -> Option<Instant> {
+        None
+    }
+
     fn attest(&mut self) -> StdResult<(), Self::Error>;
 
     fn deattest(&mut self);
@@ -53,6 +61,10 @@ pub trait AttestedConnection: Connection {
     }
 }
 
+/// Blocks sent by a node as they are appended to its ledger. Iteration blocks until the next block
+/// arrives, and ends when the node closes the subscription.
+pub type BlockSubscription = Box<dyn Iterator<Item = Result<BlockContents>> + Send>;
+
 /// A connection trait providing APIs for use in retrieving blocks from a consensus node.
 pub trait BlockchainConnection: Connection {
     /// Retrieve the block metadata from the blockchain service.
@@ -63,6 +75,19 @@ pub trait BlockchainConnection: Connection {
 
     /// Retrieve the consensus node's current block height
     fn fetch_block_height(&mut self) -> Result<BlockIndex>;
+
+    /// Retrieve the contents of the block with the given ID.
+    fn fetch_block_by_id(&mut self, block_id: &BlockID) -> Result<BlockContents>;
+
+    /// Retrieve the contents of the given blocks, transactions and signatures included.
+    fn fetch_blocks_with_transactions(
+        &mut self,
+        range: Range<BlockIndex>,
+    ) -> Result<Vec<BlockContents>>;
+
+    /// Subscribe to the contents of every block from `from_index` on, including blocks appended
+    /// after the call.
+    fn subscribe_blocks(&mut self, from_index: BlockIndex) -> Result<BlockSubscription>;
 }
 
 /// A trait which supports supporting the submission of transactions to a node
@@ -96,6 +121,20 @@ pub trait RetryableBlockchainConnection {
         &self,
         retry_iterator: impl IntoIterator<Item = Duration>,
     ) -> RetryResult<BlockIndex>;
+
+    /// Retrieve the contents of the block with the given ID.
+    fn fetch_block_by_id(
+        &self,
+        block_id: &BlockID,
+        retry_iterator: impl IntoIterator<Item = Duration>,
+    ) -> RetryResult<BlockContents>;
+
+    /// Retrieve the contents of the given blocks, transactions and signatures included.
+    fn fetch_blocks_with_transactions(
+        &self,
+        range: Range<BlockIndex>,
+        retry_iterator: impl IntoIterator<Item = Duration>,
+    ) -> RetryResult<Vec<BlockContents>>;
 }
 
 /// A trait which supports re-trying transaction submission
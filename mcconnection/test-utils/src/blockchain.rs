@@ -2,9 +2,11 @@
 
 //! Connection mock and test utilities
 
+use futures::future;
 use ledger_db::{test_utils::MockLedger, Ledger};
 use mcconnection::{
-    BlockchainConnection, Connection, Error as ConnectionError, Result as ConnectionResult,
+    AsyncBlockchainConnection, BlockContents, BlockSubscription, BlockchainConnection, Connection,
+    ConnectionFuture, ConnectionStream, Error as ConnectionError, Result as ConnectionResult,
     UserTxConnection,
 };
 use mcuri::{ConnectionUri, ConsensusClientUri};
@@ -38,6 +40,27 @@ impl MockBlockchainConnection {
             latency_millis,
         }
     }
+
+    fn block_contents(&self, block_index: BlockIndex) -> ConnectionResult<BlockContents> {
+        let block = self
+            .ledger
+            .get_block(block_index)
+            .or(Err(ConnectionError::NotFound))?;
+        let transactions = self
+            .ledger
+            .get_transactions_by_block(block_index)
+            .or(Err(ConnectionError::NotFound))?;
+        let signature_set = self
+            .ledger
+            .get_block_signature_set(block_index)
+            .or(Err(ConnectionError::NotFound))?;
+        Ok(BlockContents {
+            block,
+            transactions,
+            signature: None,
+            signature_set,
+        })
+    }
 }
 
 impl Display for MockBlockchainConnection {
@@ -108,6 +131,72 @@ impl BlockchainConnection for MockBlockchainConnection {
     fn fetch_block_height(&mut self) -> ConnectionResult<BlockIndex> {
         unimplemented!()
     }
+
+    fn fetch_block_by_id(&mut self, block_id: &BlockID) -> ConnectionResult<BlockContents> {
+        thread::sleep(Duration::from_millis(self.latency_millis));
+
+        let block_index = self
+            .ledger
+            .get_block_index_by_block_id(block_id)
+            .or(Err(ConnectionError::NotFound))?;
+        self.block_contents(block_index)
+    }
+
+    fn fetch_blocks_with_transactions(
+        &mut self,
+        range: Range<BlockIndex>,
+    ) -> ConnectionResult<Vec<BlockContents>> {
+        thread::sleep(Duration::from_millis(self.latency_millis));
+
+        let num_blocks = self.ledger.num_blocks().unwrap();
+        if range.start >= num_blocks {
+            return Err(ConnectionError::NotFound);
+        }
+
+        (range.start..min(range.end, num_blocks))
+            .map(|block_index| self.block_contents(block_index))
+            .collect()
+    }
+
+    /// The subscription ends with the last block of the mock ledger.
+    fn subscribe_blocks(&mut self, from_index: BlockIndex) -> ConnectionResult<BlockSubscription> {
+        let num_blocks = self.ledger.num_blocks().unwrap();
+        let conn = self.clone();
+        Ok(Box::new(
+            (from_index..num_blocks).map(move |block_index| conn.block_contents(block_index)),
+        ))
+    }
+}
+
+impl AsyncBlockchainConnection for MockBlockchainConnection {
+    fn fetch_blocks_async(&self, range: Range<BlockIndex>) -> ConnectionFuture<Vec<Block>> {
+        Box::new(future::result(self.clone().fetch_blocks(range)))
+    }
+
+    fn fetch_block_ids_async(&self, _range: Range<BlockIndex>) -> ConnectionFuture<Vec<BlockID>> {
+        unimplemented!()
+    }
+
+    fn fetch_block_height_async(&self) -> ConnectionFuture<BlockIndex> {
+        unimplemented!()
+    }
+
+    fn fetch_block_by_id_async(&self, block_id: &BlockID) -> ConnectionFuture<BlockContents> {
+        Box::new(future::result(self.clone().fetch_block_by_id(block_id)))
+    }
+
+    fn fetch_blocks_with_transactions_async(
+        &self,
+        range: Range<BlockIndex>,
+    ) -> ConnectionFuture<Vec<BlockContents>> {
+        Box::new(future::result(
+            self.clone().fetch_blocks_with_transactions(range),
+        ))
+    }
+
+    fn subscribe_blocks_async(&self, _from_index: BlockIndex) -> ConnectionStream<BlockContents> {
+        unimplemented!()
+    }
 }
 
 impl UserTxConnection for MockBlockchainConnection {
@@ -121,6 +210,7 @@ mod tests {
     use super::*;
     use crate::test_client_uri;
     use ledger_db::test_utils::get_mock_ledger;
+    use mcconnection::BlockingConnection;
 
     #[test]
     // Mock peer should return the correct range of blocks.
@@ -155,4 +245,41 @@ mod tests {
             assert_eq!(blocks.len(), 5)
         }
     }
+
+    #[test]
+    // The blocking adapter waits on the futures of the asynchronous connection.
+    fn blocking_fetch_blocks() {
+        let mock_ledger = get_mock_ledger(25);
+        let mut conn = BlockingConnection::new(MockBlockchainConnection::new(
+            test_client_uri(123),
+            mock_ledger,
+            0,
+        ));
+
+        let blocks = conn.fetch_blocks(4..10).unwrap();
+        assert_eq!(blocks.len(), 6);
+
+        assert!(conn.fetch_blocks(25..28).is_err());
+    }
+
+    #[test]
+    // Mock peer should return the contents of blocks, by ID or by range.
+    fn fetch_block_contents() {
+        let mock_ledger = get_mock_ledger(25);
+        let block = mock_ledger.get_block(7).unwrap();
+        let transactions = mock_ledger.get_transactions_by_block(7).unwrap();
+        let mut mock_peer = MockBlockchainConnection::new(test_client_uri(123), mock_ledger, 0);
+
+        let contents = mock_peer.fetch_block_by_id(&block.id).unwrap();
+        assert_eq!(contents.block, block);
+        assert_eq!(contents.transactions, transactions);
+
+        let contents = mock_peer.fetch_blocks_with_transactions(20..30).unwrap();
+        let indexes: Vec<BlockIndex> = contents.iter().map(|c| c.block.index).collect();
+        assert_eq!(indexes, vec![20, 21, 22, 23, 24]);
+
+        let subscription = mock_peer.subscribe_blocks(22).unwrap();
+        let indexes: Vec<BlockIndex> = subscription.map(|c| c.unwrap().block.index).collect();
+        assert_eq!(indexes, vec![22, 23, 24]);
+    }
 }
@@ -14,3 +14,146 @@ pub fn test_client_uri(node_id: u32) -> ConsensusClientUri {
 }
 
 pub use self::{blockchain::MockBlockchainConnection, user_tx::MockUserTxConnection};
+
+#[cfg(test)]
+mod connection_selector_tests {
+    use super::*;
+    use common::{
+        logger::{test_with_logger, Logger},
+        ResponderId,
+    };
+    use mcconnection::{
+        ConnectionManager, ConnectionSelector, Error as ConnectionError, RetryError,
+    };
+    use std::{
+        thread,
+        time::{Duration, Instant},
+    };
+    use transaction::tx::Tx;
+
+    fn test_selector(num_nodes: u32, logger: Logger) -> ConnectionSelector<MockUserTxConnection> {
+        let conns = (1..=num_nodes)
+            .map(|node_id| MockUserTxConnection::new(test_client_uri(node_id)))
+            .collect();
+        ConnectionSelector::new(ConnectionManager::new(conns, logger.clone()), logger)
+    }
+
+    fn ranked_ids(selector: &ConnectionSelector<MockUserTxConnection>) -> Vec<ResponderId> {
+        selector
+            .ranked_conns()
+            .into_iter()
+            .map(|(responder_id, _conn)| responder_id)
+            .collect()
+    }
+
+    #[test_with_logger]
+    // Nodes with recent failures rank last, and faster nodes rank first.
+    fn test_ranked_conns(logger: Logger) {
+        let selector = test_selector(3, logger);
+        let responder_ids = ranked_ids(&selector);
+
+        selector.record_failure(&responder_ids[0]);
+        selector.record_success(&responder_ids[0], Duration::from_millis(1));
+        selector.record_failure(&responder_ids[0]);
+        selector.record_success(&responder_ids[1], Duration::from_millis(50));
+        selector.record_success(&responder_ids[2], Duration::from_millis(10));
+
+        let stats = selector.stats(&responder_ids[0]);
+        assert_eq!(stats.consecutive_failures, 1);
+        assert_eq!(stats.total_failures, 2);
+        assert_eq!(stats.total_successes, 1);
+
+        let expected: Vec<_> = [2, 1, 0]
+            .iter()
+            .map(|i| responder_ids[*i].clone())
+            .collect();
+        for _ in 0..3 {
+            assert_eq!(ranked_ids(&selector), expected);
+        }
+    }
+
+    #[test_with_logger]
+    // Nodes that rank the same take turns.
+    fn test_ranked_conns_rotate(logger: Logger) {
+        let selector = test_selector(3, logger);
+        let first = ranked_ids(&selector)[0].clone();
+        let second = ranked_ids(&selector)[0].clone();
+        assert_ne!(first, second);
+    }
+
+    #[test_with_logger]
+    // A transaction is submitted to the next node when the first one fails attestation.
+    fn test_failover_on_attestation_error(logger: Logger) {
+        let selector = test_selector(2, logger);
+        let conns = selector.ranked_conns();
+        let (failing_id, failing_conn) = &conns[0];
+        let (healthy_id, healthy_conn) = &conns[1];
+        failing_conn.write().fail_attestation = true;
+
+        // Make sure the failing node is tried first.
+        selector.record_success(failing_id, Duration::from_millis(1));
+        selector.record_success(healthy_id, Duration::from_millis(50));
+
+        assert_eq!(selector.propose_tx(&Tx::default()).unwrap(), 1);
+        assert_eq!(selector.stats(failing_id).consecutive_failures, 1);
+        assert_eq!(selector.stats(healthy_id).total_successes, 2);
+        assert_eq!(healthy_conn.read().submitted_txs.len(), 1);
+
+        // The failing node now ranks last, so it is not tried again.
+        assert_eq!(selector.propose_tx(&Tx::default()).unwrap(), 1);
+        assert_eq!(selector.stats(failing_id).total_failures, 1);
+        assert_eq!(healthy_conn.read().submitted_txs.len(), 2);
+    }
+
+    #[test_with_logger]
+    // Proposing fails with the last error when every node fails.
+    fn test_all_nodes_fail(logger: Logger) {
+        let selector = test_selector(2, logger);
+        for (_responder_id, conn) in selector.ranked_conns() {
+            conn.write().fail_attestation = true;
+        }
+
+        match selector.propose_tx(&Tx::default()) {
+            Err(RetryError::Operation {
+                error: ConnectionError::Attestation(_),
+                ..
+            }) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    #[test_with_logger]
+    // Proposing fails when there are no nodes to submit to.
+    fn test_no_connections(logger: Logger) {
+        let selector = test_selector(0, logger);
+        match selector.propose_tx(&Tx::default()) {
+            Err(RetryError::Operation {
+                error: ConnectionError::NoConnections,
+                tries: 0,
+                ..
+            }) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    #[test_with_logger]
+    // A transaction is submitted to several nodes at the same time.
+    fn test_parallel_submissions(logger: Logger) {
+        let selector = test_selector(3, logger).with_parallel_submissions(2);
+        assert_eq!(selector.propose_tx(&Tx::default()).unwrap(), 1);
+
+        // The slower submission may still be in flight.
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let num_submitted = || {
+            selector
+                .ranked_conns()
+                .iter()
+                .map(|(_responder_id, conn)| conn.read().submitted_txs.len())
+                .sum::<usize>()
+        };
+        while num_submitted() < 2 && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(num_submitted(), 2);
+    }
+}
@@ -2,7 +2,10 @@
 
 //! User Transaction Connection Mock
 
-use mcconnection::{Connection, Result as ConnectionResult, UserTxConnection};
+use mcconnection::{
+    AttestationError, Connection, Error as ConnectionError, Result as ConnectionResult,
+    UserTxConnection,
+};
 use mcuri::{ConnectionUri, ConsensusClientUri};
 use std::{
     cmp::Ordering,
@@ -15,6 +18,8 @@ use transaction::{tx::Tx, BlockIndex};
 pub struct MockUserTxConnection {
     uri: ConsensusClientUri,
     pub submitted_txs: Vec<Tx>,
+    /// When set, proposing a transaction fails with an attestation error.
+    pub fail_attestation: bool,
 }
 
 impl MockUserTxConnection {
@@ -22,10 +27,22 @@ impl MockUserTxConnection {
         MockUserTxConnection {
             uri,
             submitted_txs: Vec::new(),
+            fail_attestation: false,
         }
     }
 }
 
+#[derive(Debug)]
+pub struct MockAttestationError;
+
+impl Display for MockAttestationError {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "Mock attestation failure")
+    }
+}
+
+impl AttestationError for MockAttestationError {}
+
 impl Display for MockUserTxConnection {
     fn fmt(&self, f: &mut Formatter) -> FmtResult {
         write!(f, "{}", self.uri())
@@ -68,6 +85,9 @@ impl Connection for MockUserTxConnection {
 
 impl UserTxConnection for MockUserTxConnection {
     fn propose_tx(&mut self, tx: &Tx) -> ConnectionResult<BlockIndex> {
+        if self.fail_attestation {
+            return Err(ConnectionError::from(MockAttestationError));
+        }
         self.submitted_txs.push(tx.clone());
         Ok(1)
     }
@@ -235,3 +235,18 @@ class mob_client:
         request = api.GetTxStatusAsReceiverRequest(receipt=receiver_tx_receipt)
         response = self.stub.GetTxStatusAsReceiver(request)
         return response.status
+
+    def create_payment_proof(self, tx_proposal, outlay_index):
+        """ Get a proof that the sender of a tx proposal paid one of its outlays.
+        """
+        request = api.CreatePaymentProofRequest(tx_proposal=tx_proposal,
+                                                outlay_index=outlay_index)
+        response = self.stub.CreatePaymentProof(request)
+        return response.payment_proof
+
+    def verify_payment_proof(self, payment_proof):
+        """ Check a payment proof against the ledger.
+        """
+        request = api.VerifyPaymentProofRequest(payment_proof=payment_proof)
+        response = self.stub.VerifyPaymentProof(request)
+        return response.status, response.value
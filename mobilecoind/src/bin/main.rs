@@ -6,7 +6,11 @@ use attest::MrSigner;
 use common::logger::{create_app_logger, log, o, Logger};
 use consensus_enclave_measurement::sigstruct;
 use ledger_db::{Ledger, LedgerDB};
-use ledger_sync::{LedgerSyncServiceThread, PollingNetworkState, ReqwestTransactionsFetcher};
+use ledger_sync::{
+    CachingTransactionsFetcher, ForkEvidenceStore, GrpcTransactionsFetcher, LedgerSyncService,
+    LedgerSyncServiceThread, PollingNetworkState, ReqwestTransactionsFetcher, TransactionsFetcher,
+};
+use mcconnection::{AttestationRefresher, ConnectionManager, ThickClient, DEFAULT_REFRESH_MARGIN};
 use mobilecoind::{
     config::Config, database::Database, payments::TransactionsManager, service::Service,
 };
@@ -21,31 +25,93 @@ fn main() {
     let (logger, _global_logger_guard) = create_app_logger(o!());
 
     // Create peer manager.
+    let attestation_cache = config.peers_config.attestation_cache(&logger);
     let peer_manager = config.peers_config.create_peer_manager(
         MrSigner::try_from(&sigstruct().mrsigner()[..])
             .expect("Could not parse validator node MRSIGNER"),
+        attestation_cache.clone(),
         &logger,
     );
 
+    // Attest to peers in the background, one at a time.
+    let _attestation_refresher = AttestationRefresher::start(
+        peer_manager.clone(),
+        attestation_cache,
+        DEFAULT_REFRESH_MARGIN.min(config.peers_config.session_lifetime / 2),
+        logger.clone(),
+    );
+
     // Create network state, transactions fetcher and ledger sync.
     let network_state =
         PollingNetworkState::new(config.quorum_set(), peer_manager.clone(), logger.clone());
 
-    let transactions_fetcher =
-        ReqwestTransactionsFetcher::new(config.tx_source_urls.clone(), logger.clone())
-            .expect("Failed creating ReqwestTransactionsFetcher");
+    assert!(
+        config.required_block_signatures <= config.trusted_block_signers.len(),
+        "--required-block-signatures cannot exceed the number of --trusted-block-signer keys"
+    );
 
-    // Create the ledger_db.
-    let ledger_db = create_or_open_ledger_db(&config, &logger, &transactions_fetcher);
+    // Evidence of a network fork is kept next to the ledger, and halts syncing until removed.
+    std::fs::create_dir_all(config.ledger_db.clone()).expect("Could not create ledger dir");
+    let fork_evidence_store =
+        ForkEvidenceStore::open(&Path::new(&config.ledger_db).join("fork_evidence"))
+            .expect("Could not open fork evidence");
+    if let Some(fork_evidence) = fork_evidence_store.get() {
+        log::crit!(
+            logger,
+            "The network forked at block {}, ledger sync is halted",
+            fork_evidence.block_index
+        );
+    }
 
-    let _ledger_sync_service_thread = LedgerSyncServiceThread::new(
-        ledger_db.clone(),
-        peer_manager.clone(),
-        network_state,
-        transactions_fetcher,
-        config.poll_interval,
-        logger.clone(),
-    );
+    // Create the ledger_db and start syncing it. Transaction data is fetched from the peers,
+    // unless transaction source URLs are given.
+    let (ledger_db, _ledger_sync_service_thread) = if config.tx_source_urls.is_empty() {
+        let transactions_fetcher =
+            GrpcTransactionsFetcher::new(peer_manager.clone(), logger.clone());
+        start_ledger_sync(
+            &config,
+            peer_manager.clone(),
+            network_state,
+            transactions_fetcher,
+            fork_evidence_store.clone(),
+            &logger,
+        )
+    } else {
+        let transactions_fetcher =
+            ReqwestTransactionsFetcher::new(config.tx_source_urls.clone(), logger.clone())
+                .expect("Failed creating ReqwestTransactionsFetcher")
+                .with_required_block_signatures(
+                    config.trusted_block_signers.clone(),
+                    config.required_block_signatures,
+                );
+        match &config.tx_cache_dir {
+            Some(tx_cache_dir) => {
+                let transactions_fetcher = CachingTransactionsFetcher::new(
+                    transactions_fetcher,
+                    tx_cache_dir,
+                    config.tx_cache_size_mb * 1024 * 1024,
+                    logger.clone(),
+                )
+                .expect("Failed opening transactions cache");
+                start_ledger_sync(
+                    &config,
+                    peer_manager.clone(),
+                    network_state,
+                    transactions_fetcher,
+                    fork_evidence_store.clone(),
+                    &logger,
+                )
+            }
+            None => start_ledger_sync(
+                &config,
+                peer_manager.clone(),
+                network_state,
+                transactions_fetcher,
+                fork_evidence_store.clone(),
+                &logger,
+            ),
+        }
+    };
 
     // Potentially launch API server
     match (&config.mobilecoind_db, &config.service_port) {
@@ -62,12 +128,14 @@ fn main() {
                 mobilecoind_db.clone(),
                 peer_manager,
                 logger.clone(),
-            );
+            )
+            .with_parallel_submissions(config.parallel_submissions);
 
             let _api_server = Service::new(
                 ledger_db,
                 mobilecoind_db,
                 transactions_manager,
+                fork_evidence_store,
                 *service_port,
                 config.num_workers,
                 logger,
@@ -93,10 +161,42 @@ fn main() {
     }
 }
 
+fn start_ledger_sync<TF: TransactionsFetcher + 'static>(
+    config: &Config,
+    peer_manager: ConnectionManager<ThickClient>,
+    network_state: PollingNetworkState<ThickClient>,
+    transactions_fetcher: TF,
+    fork_evidence_store: ForkEvidenceStore,
+    logger: &Logger,
+) -> (LedgerDB, LedgerSyncServiceThread) {
+    let ledger_db = create_or_open_ledger_db(config, logger, &transactions_fetcher);
+
+    let mut ledger_sync_service = LedgerSyncService::new(
+        ledger_db.clone(),
+        peer_manager,
+        transactions_fetcher,
+        logger.clone(),
+    )
+    .with_fork_evidence_store(fork_evidence_store);
+    if let Some(checkpoint) = config.checkpoint.clone() {
+        ledger_sync_service = ledger_sync_service.with_checkpoint(checkpoint);
+    }
+
+    let ledger_sync_service_thread = LedgerSyncServiceThread::new(
+        ledger_db.clone(),
+        ledger_sync_service,
+        network_state,
+        config.poll_interval,
+        logger.clone(),
+    );
+
+    (ledger_db, ledger_sync_service_thread)
+}
+
 fn create_or_open_ledger_db(
     config: &Config,
     logger: &Logger,
-    transactions_fetcher: &ReqwestTransactionsFetcher,
+    transactions_fetcher: &impl TransactionsFetcher,
 ) -> LedgerDB {
     // Check if the ledger DB file exists, and if not, try and copy from bootstrap database.
     let ledger_db_file = Path::new(&config.ledger_db).join("data.mdb");
@@ -137,7 +237,7 @@ fn create_or_open_ledger_db(
                     .expect("Could not create ledger dir");
                 LedgerDB::create(config.ledger_db.clone()).expect("Could not create ledger_db");
                 let (block, transactions) = transactions_fetcher
-                    .get_origin_block_and_transactions()
+                    .get_block_by_index(0)
                     .expect("Failed to download initial transactions");
                 let mut db =
                     LedgerDB::open(config.ledger_db.clone()).expect("Could not open ledger_db");
@@ -3,8 +3,13 @@
 //! Configuration parameters for mobilecoind
 
 use attest::Measurement;
-use common::{logger::Logger, ResponderId};
-use mcconnection::{ConnectionManager, ThickClient};
+use common::{
+    logger::{log, Logger},
+    ResponderId,
+};
+use keys::{DistinguishedEncoding, Ed25519Public};
+use ledger_sync::Checkpoint;
+use mcconnection::{AttestationCache, ConnectionManager, ThickClient};
 use mcuri::{ConnectionUri, ConsensusClientUri};
 use scp::QuorumSet;
 use std::{path::PathBuf, str::FromStr, sync::Arc, time::Duration};
@@ -31,16 +36,43 @@ pub struct Config {
     #[structopt(long, parse(try_from_str=parse_quorum_set_from_json))]
     quorum_set: Option<QuorumSet<ResponderId>>,
 
-    /// URLs to use for transaction data.
+    /// URLs to use for transaction data. If none are given, transaction data is fetched from the
+    /// peers.
     ///
     /// For example: https://s3-us-west-1.amazonaws.com/mobilecoin.chain/node1.test.mobilecoin.com/
-    #[structopt(long = "tx-source-url", required = true, min_values = 1)]
+    #[structopt(long = "tx-source-url")]
     pub tx_source_urls: Vec<String>,
 
     /// How many seconds to wait between polling.
     #[structopt(long, default_value = "5", parse(try_from_str=parse_duration_in_seconds))]
     pub poll_interval: Duration,
 
+    /// Base64 DER-encoded public key of a validator whose block signatures are trusted. May be
+    /// specified multiple times.
+    #[structopt(long = "trusted-block-signer", parse(try_from_str=public_key_from_base64))]
+    pub trusted_block_signers: Vec<Ed25519Public>,
+
+    /// Minimum number of valid signatures by trusted block signers a block fetched from a
+    /// transaction source must carry.
+    #[structopt(long, default_value = "0")]
+    pub required_block_signatures: usize,
+
+    /// Trusted block to quickly sync up to from the transaction sources, before syncing with
+    /// peers. Blocks up to it are trusted because they hash-chain to it.
+    ///
+    /// The checkpoint is given as the block index and hex-encoded block ID, e.g. 1234:7a3f...
+    #[structopt(long)]
+    pub checkpoint: Option<Checkpoint>,
+
+    /// Directory to cache blocks fetched from the transaction sources in. May be shared by several
+    /// processes on one host.
+    #[structopt(long, parse(from_os_str))]
+    pub tx_cache_dir: Option<PathBuf>,
+
+    /// Maximum size of the transactions cache, in megabytes.
+    #[structopt(long, default_value = "1024")]
+    pub tx_cache_size_mb: u64,
+
     // Mobilecoind specific arguments
     /// Path to mobilecoind database used to store transactions and accounts.
     #[structopt(long, parse(from_os_str))]
@@ -54,12 +86,25 @@ pub struct Config {
     /// Defaults to number of logical CPU cores.
     #[structopt(long)]
     pub num_workers: Option<usize>,
+
+    /// Number of validator nodes each transaction is submitted to at the same time. The others
+    /// are only tried when these fail.
+    #[structopt(long, default_value = "1")]
+    pub parallel_submissions: usize,
 }
 
 fn parse_duration_in_seconds(src: &str) -> Result<Duration, std::num::ParseIntError> {
     Ok(Duration::from_secs(u64::from_str(src)?))
 }
 
+fn public_key_from_base64(src: &str) -> Result<Ed25519Public, String> {
+    let pubkey_bytes = base64::decode_config(src, base64::STANDARD)
+        .map_err(|err| format!("Could not decode public key from base64 {:?}", err))?;
+
+    Ed25519Public::try_from_der(pubkey_bytes.as_slice())
+        .map_err(|err| format!("Could not get Ed25519Public from der {:?}", err))
+}
+
 fn parse_quorum_set_from_json(src: &str) -> Result<QuorumSet<ResponderId>, String> {
     Ok(serde_json::from_str(src)
         .map_err(|err| format!("Error parsing quorum set {}: {:?}", src, err))?)
@@ -97,6 +142,16 @@ pub struct PeersConfig {
     /// validator nodes to connect to.
     #[structopt(long = "peer", required = true, min_values = 1)]
     pub peers: Vec<ConsensusClientUri>,
+
+    /// How many seconds an attested session with a validator node is used before attesting again.
+    #[structopt(long, default_value = "3600", parse(try_from_str=parse_duration_in_seconds))]
+    pub session_lifetime: Duration,
+
+    /// File to persist the verification reports of validator nodes to, across restarts. After a
+    /// restart, nodes are attested to in the background, the ones verified longest ago first.
+    /// Each attestation still performs a full handshake and report verification.
+    #[structopt(long, parse(from_os_str))]
+    pub attestation_cache: Option<PathBuf>,
 }
 
 impl PeersConfig {
@@ -110,10 +165,28 @@ impl PeersConfig {
             .collect()
     }
 
+    /// Open the attestation cache, if one is configured. Falls back to an in-memory cache if the
+    /// file cannot be read.
+    pub fn attestation_cache(&self, logger: &Logger) -> Option<AttestationCache> {
+        let path = self.attestation_cache.as_ref()?;
+        Some(
+            AttestationCache::open(path.clone(), logger.clone()).unwrap_or_else(|err| {
+                log::warn!(
+                    logger,
+                    "Failed opening attestation cache {:?}, starting empty: {}",
+                    path,
+                    err
+                );
+                AttestationCache::new(logger.clone())
+            }),
+        )
+    }
+
     pub fn create_peers(
         &self,
         expected_measurement: impl Into<Measurement>,
         grpc_env: Arc<grpcio::Environment>,
+        attestation_cache: Option<AttestationCache>,
         logger: Logger,
     ) -> Vec<ThickClient> {
         let measurement = expected_measurement.into();
@@ -121,13 +194,20 @@ impl PeersConfig {
         self.peers
             .iter()
             .map(|client_uri| {
-                ThickClient::new(
+                let peer = ThickClient::new(
                     client_uri.clone(),
                     measurement,
                     grpc_env.clone(),
                     logger.clone(),
                 )
                 .expect("Could not create thick client.")
+                .with_session_lifetime(self.session_lifetime);
+                match &attestation_cache {
+                    Some(attestation_cache) => {
+                        peer.with_attestation_cache(attestation_cache.clone())
+                    }
+                    None => peer,
+                }
             })
             .collect()
     }
@@ -135,6 +215,7 @@ impl PeersConfig {
     pub fn create_peer_manager(
         &self,
         measurement: impl Into<Measurement>,
+        attestation_cache: Option<AttestationCache>,
         logger: &Logger,
     ) -> ConnectionManager<ThickClient> {
         let grpc_env = Arc::new(
@@ -142,7 +223,7 @@ impl PeersConfig {
                 .name_prefix("RPC".to_string())
                 .build(),
         );
-        let peers = self.create_peers(measurement, grpc_env, logger.clone());
+        let peers = self.create_peers(measurement, grpc_env, attestation_cache, logger.clone());
 
         ConnectionManager::new(peers, logger.clone())
     }
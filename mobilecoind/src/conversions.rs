@@ -8,14 +8,17 @@ use crate::{
 };
 
 use common::HashMap;
+use keys::RistrettoPublic;
 use mobilecoind_api::{self, ConversionError};
 use protobuf::RepeatedField;
 use std::{convert::TryFrom, iter::FromIterator};
 use transaction::{
     account_keys::PublicAddress,
-    ring_signature::KeyImage,
+    fee_schedule::FeeSchedule,
+    ring_signature::{CurveScalar, KeyImage},
     tx::{Tx, TxOut},
 };
+use transaction_std::PaymentProof;
 
 impl From<&UnspentTxOut> for mobilecoind_api::UnspentTxOut {
     fn from(src: &UnspentTxOut) -> Self {
@@ -27,6 +30,7 @@ impl From<&UnspentTxOut> for mobilecoind_api::UnspentTxOut {
         dst.set_value(src.value);
         dst.set_attempted_spend_height(src.attempted_spend_height);
         dst.set_attempted_spend_tombstone(src.attempted_spend_tombstone);
+        dst.set_memo(src.memo.clone());
 
         dst
     }
@@ -42,6 +46,7 @@ impl TryFrom<&mobilecoind_api::UnspentTxOut> for UnspentTxOut {
         let value = src.value;
         let attempted_spend_height = src.attempted_spend_height;
         let attempted_spend_tombstone = src.attempted_spend_tombstone;
+        let memo = src.memo.clone();
 
         Ok(Self {
             tx_out,
@@ -50,6 +55,7 @@ impl TryFrom<&mobilecoind_api::UnspentTxOut> for UnspentTxOut {
             value,
             attempted_spend_height,
             attempted_spend_tombstone,
+            memo,
         })
     }
 }
@@ -76,6 +82,58 @@ impl TryFrom<&mobilecoind_api::Outlay> for Outlay {
     }
 }
 
+impl From<&PaymentProof> for mobilecoind_api::PaymentProof {
+    fn from(src: &PaymentProof) -> Self {
+        let mut dst = Self::new();
+
+        dst.set_recipient((&src.recipient).into());
+        dst.set_tx_out_hash(src.tx_out_hash.to_vec());
+        dst.set_shared_secret((&src.shared_secret).into());
+        dst.set_challenge((&src.challenge).into());
+        dst.set_response((&src.response).into());
+
+        dst
+    }
+}
+
+impl TryFrom<&mobilecoind_api::PaymentProof> for PaymentProof {
+    type Error = ConversionError;
+
+    fn try_from(src: &mobilecoind_api::PaymentProof) -> Result<Self, Self::Error> {
+        let recipient = PublicAddress::try_from(src.get_recipient())?;
+
+        if src.get_tx_out_hash().len() != 32 {
+            return Err(mobilecoin_api::ConversionError::ArrayCastError.into());
+        }
+        let mut tx_out_hash = [0u8; 32];
+        tx_out_hash.copy_from_slice(src.get_tx_out_hash());
+
+        let shared_secret = RistrettoPublic::try_from(src.get_shared_secret())?;
+        let challenge = CurveScalar::try_from(src.get_challenge())?;
+        let response = CurveScalar::try_from(src.get_response())?;
+
+        Ok(Self {
+            recipient,
+            tx_out_hash,
+            shared_secret,
+            challenge,
+            response,
+        })
+    }
+}
+
+impl From<&FeeSchedule> for mobilecoind_api::FeeSchedule {
+    fn from(src: &FeeSchedule) -> Self {
+        let mut dst = Self::new();
+
+        dst.set_base_fee(src.base_fee);
+        dst.set_per_input_fee(src.per_input_fee);
+        dst.set_per_output_fee(src.per_output_fee);
+
+        dst
+    }
+}
+
 impl From<&TxProposal> for mobilecoind_api::TxProposal {
     fn from(src: &TxProposal) -> mobilecoind_api::TxProposal {
         let mut dst = mobilecoind_api::TxProposal::new();
@@ -93,6 +151,12 @@ impl From<&TxProposal> for mobilecoind_api::TxProposal {
                 .iter()
                 .map(|(key, val)| (*key as u64, *val as u64)),
         ));
+        dst.set_outlay_payment_proof_list(RepeatedField::from_vec(
+            src.outlay_payment_proofs
+                .iter()
+                .map(|payment_proof| payment_proof.into())
+                .collect(),
+        ));
 
         dst
     }
@@ -137,11 +201,23 @@ impl TryFrom<&mobilecoind_api::TxProposal> for TxProposal {
             }
         }
 
+        let outlay_payment_proofs = src
+            .get_outlay_payment_proof_list()
+            .iter()
+            .map(PaymentProof::try_from)
+            .collect::<Result<Vec<PaymentProof>, ConversionError>>()?;
+
+        // Proposals may omit payment proofs, but otherwise have one per outlay.
+        if !outlay_payment_proofs.is_empty() && outlay_payment_proofs.len() != outlays.len() {
+            return Err(ConversionError::IndexOutOfBounds);
+        }
+
         Ok(Self {
             utxos,
             outlays,
             tx,
             outlay_index_to_tx_out_index,
+            outlay_payment_proofs,
         })
     }
 }
@@ -149,12 +225,33 @@ impl TryFrom<&mobilecoind_api::TxProposal> for TxProposal {
 #[cfg(test)]
 mod test {
     use super::*;
-    use keys::{FromRandom, RistrettoPublic};
+    use keys::{FromRandom, RistrettoPrivate, RistrettoPublic};
     use ledger_db::Ledger;
     use rand::{rngs::StdRng, SeedableRng};
-    use transaction::{account_keys::AccountKey, amount::Amount, ring_signature::Blinding};
+    use transaction::{
+        account_keys::AccountKey, amount::Amount, encrypted_fog_hint::EncryptedFogHint,
+        ring_signature::Blinding,
+    };
     use transaction_test_utils::{create_ledger, create_transaction, initialize_ledger};
 
+    /// Creates a proof for a new TxOut paying `value` to `recipient`.
+    fn create_payment_proof(
+        value: u64,
+        recipient: &PublicAddress,
+        rng: &mut StdRng,
+    ) -> PaymentProof {
+        let tx_private_key = RistrettoPrivate::from_random(rng);
+        let tx_out = TxOut::new(
+            value,
+            recipient,
+            &tx_private_key,
+            EncryptedFogHint::fake_onetime_hint(rng),
+            rng,
+        )
+        .unwrap();
+        PaymentProof::new(&tx_out, recipient, &tx_private_key, rng).unwrap()
+    }
+
     #[test]
     fn test_unspent_tx_out_conversion() {
         let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
@@ -170,6 +267,7 @@ mod test {
             target_key: RistrettoPublic::from_random(&mut rng).into(),
             public_key: RistrettoPublic::from_random(&mut rng).into(),
             e_account_hint: (&[0u8; 128]).into(),
+            e_memo: None,
         };
 
         let subaddress_index = 123;
@@ -177,6 +275,7 @@ mod test {
         let value = 789;
         let attempted_spend_height = 1000;
         let attempted_spend_tombstone = 1234;
+        let memo = b"invoice #1234".to_vec();
 
         let rust = UnspentTxOut {
             tx_out: tx_out.clone(),
@@ -185,6 +284,7 @@ mod test {
             value,
             attempted_spend_height,
             attempted_spend_tombstone,
+            memo: memo.clone(),
         };
 
         let proto = mobilecoind_api::UnspentTxOut::from(&rust);
@@ -198,6 +298,7 @@ mod test {
         assert_eq!(value, proto.value);
         assert_eq!(attempted_spend_height, proto.attempted_spend_height);
         assert_eq!(attempted_spend_tombstone, proto.attempted_spend_tombstone);
+        assert_eq!(memo, proto.memo);
 
         // Proto -> Rust
         assert_eq!(rust, UnspentTxOut::try_from(&proto).unwrap());
@@ -225,6 +326,30 @@ mod test {
         assert_eq!(rust, Outlay::try_from(&proto).unwrap());
     }
 
+    #[test]
+    fn test_payment_proof_conversion() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let public_addr = AccountKey::random(&mut rng).default_subaddress();
+
+        // Rust -> Proto
+        let rust = create_payment_proof(1234, &public_addr, &mut rng);
+        let proto = mobilecoind_api::PaymentProof::from(&rust);
+
+        assert_eq!(
+            PublicAddress::try_from(proto.get_recipient()).unwrap(),
+            public_addr
+        );
+        assert_eq!(proto.get_tx_out_hash(), &rust.tx_out_hash[..]);
+
+        // Proto -> Rust
+        assert_eq!(rust, PaymentProof::try_from(&proto).unwrap());
+
+        // A hash of the wrong length should be rejected.
+        let mut proto = proto;
+        proto.set_tx_out_hash(vec![1u8; 31]);
+        assert!(PaymentProof::try_from(&proto).is_err());
+    }
+
     #[test]
     fn test_tx_proposal_conversion() {
         let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
@@ -259,6 +384,7 @@ mod test {
                 target_key: RistrettoPublic::from_random(&mut rng).into(),
                 public_key: RistrettoPublic::from_random(&mut rng).into(),
                 e_account_hint: (&[0u8; 128]).into(),
+                e_memo: None,
             };
 
             let subaddress_index = 123;
@@ -266,6 +392,7 @@ mod test {
             let value = 789;
             let attempted_spend_height = 1000;
             let attempted_spend_tombstone = 1234;
+            let memo = Vec::new();
 
             UnspentTxOut {
                 tx_out: tx_out.clone(),
@@ -274,6 +401,7 @@ mod test {
                 value,
                 attempted_spend_height,
                 attempted_spend_tombstone,
+                memo,
             }
         };
 
@@ -287,12 +415,15 @@ mod test {
 
         let outlay_index_to_tx_out_index = HashMap::from_iter(vec![(0, 0)]);
 
+        let payment_proof = create_payment_proof(outlay.value, &outlay.receiver, &mut rng);
+
         // Rust -> Proto
         let rust = TxProposal {
             utxos: vec![utxo],
             outlays: vec![outlay],
             tx,
             outlay_index_to_tx_out_index,
+            outlay_payment_proofs: vec![payment_proof],
         };
 
         let proto = mobilecoind_api::TxProposal::from(&rust);
@@ -312,6 +443,11 @@ mod test {
 
         assert_eq!(rust.tx, Tx::try_from(proto.get_tx()).unwrap());
 
+        assert_eq!(
+            rust.outlay_payment_proofs,
+            vec![PaymentProof::try_from(&proto.get_outlay_payment_proof_list()[0]).unwrap()],
+        );
+
         // Proto -> Rust
         assert_eq!(rust, TxProposal::try_from(&proto).unwrap());
     }
@@ -10,26 +10,19 @@ use common::{
 };
 use keys::RistrettoPublic;
 use ledger_db::{Error as LedgerError, Ledger, LedgerDB};
-use mcconnection::{ConnectionManager, RetryableUserTxConnection, UserTxConnection};
+use mcconnection::{ConnectionManager, ConnectionSelector, UserTxConnection};
 use mcrand::{CryptoRng, RngCore};
 use rand::Rng;
-use std::{
-    cmp::Reverse,
-    convert::TryFrom,
-    iter::{empty, FromIterator},
-    sync::{
-        atomic::{AtomicUsize, Ordering},
-        Arc,
-    },
-};
+use std::{cmp::Reverse, convert::TryFrom, iter::FromIterator};
 use transaction::{
     account_keys::{AccountKey, PublicAddress},
-    constants::{BASE_FEE, MAX_INPUTS, MIN_RING_SIZE},
+    constants::{MAX_INPUTS, MIN_RING_SIZE},
+    fee_schedule::FeeSchedule,
     onetime_keys::{compute_key_image, recover_onetime_private_key},
     tx::{Tx, TxOut, TxOutMembershipProof},
     BlockIndex,
 };
-use transaction_std::{InputCredentials, TransactionBuilder};
+use transaction_std::{InputCredentials, PaymentProof, TransactionBuilder};
 
 /// Default number of blocks used for calculating transaction tombstone block number.
 // TODO support for making this configurable
@@ -63,6 +56,9 @@ pub struct TxProposal {
     /// A map of outlay index -> TxOut index in the Tx object.
     /// This is needed to map recipients to their respective TxOuts.
     pub outlay_index_to_tx_out_index: HashMap<usize, usize>,
+
+    /// Proofs that the sender paid each outlay, in the order of `outlays`.
+    pub outlay_payment_proofs: Vec<PaymentProof>,
 }
 
 impl TxProposal {
@@ -78,14 +74,11 @@ pub struct TransactionsManager<T: UserTxConnection + 'static> {
     /// mobilecoind database.
     mobilecoind_db: Database,
 
-    /// Peer manager, for communicating with validator nodes.
-    peer_manager: ConnectionManager<T>,
+    /// Picks the validator nodes transactions are submitted to.
+    selector: ConnectionSelector<T>,
 
     /// Logger.
     logger: Logger,
-
-    /// Monotonically increasing counter. This is used for node round-robin selection.
-    submit_node_offset: Arc<AtomicUsize>,
 }
 
 impl<T: UserTxConnection + 'static> Clone for TransactionsManager<T> {
@@ -93,9 +86,8 @@ impl<T: UserTxConnection + 'static> Clone for TransactionsManager<T> {
         Self {
             ledger_db: self.ledger_db.clone(),
             mobilecoind_db: self.mobilecoind_db.clone(),
-            peer_manager: self.peer_manager.clone(),
+            selector: self.selector.clone(),
             logger: self.logger.clone(),
-            submit_node_offset: self.submit_node_offset.clone(),
         }
     }
 }
@@ -107,16 +99,22 @@ impl<T: UserTxConnection + 'static> TransactionsManager<T> {
         peer_manager: ConnectionManager<T>,
         logger: Logger,
     ) -> Self {
-        let mut rng = rand::thread_rng();
         Self {
             ledger_db,
             mobilecoind_db,
-            peer_manager,
+            selector: ConnectionSelector::new(peer_manager, logger.clone()),
             logger,
-            submit_node_offset: Arc::new(AtomicUsize::new(rng.next_u64() as usize)),
         }
     }
 
+    /// Submit each transaction to `parallel_submissions` validator nodes at the same time.
+    pub fn with_parallel_submissions(mut self, parallel_submissions: usize) -> Self {
+        self.selector = self
+            .selector
+            .with_parallel_submissions(parallel_submissions);
+        self
+    }
+
     pub fn build_transaction(
         &self,
         sender_monitor_id: &MonitorId,
@@ -152,17 +150,32 @@ impl<T: UserTxConnection + 'static> TransactionsManager<T> {
             total_value
         );
 
-        // Figure out the fee.
-        let fee = if opt_fee > 0 { opt_fee } else { BASE_FEE };
-
-        // Select the UTXOs to be used for this transaction.
-        let selected_utxos =
-            Self::select_utxos_for_value(inputs, total_value + fee, MAX_INPUTS as usize)?;
+        // Select the UTXOs to be used for this transaction, and figure out the fee.
+        let (selected_utxos, fee) = if opt_fee > 0 {
+            let selected_utxos = Self::select_utxos_for_fee(
+                inputs,
+                total_value,
+                outlays.len(),
+                opt_fee,
+                &FeeSchedule::current(),
+                MAX_INPUTS as usize,
+            )?;
+            (selected_utxos, opt_fee)
+        } else {
+            Self::select_utxos_for_payment(
+                inputs,
+                total_value,
+                outlays.len(),
+                &FeeSchedule::current(),
+                MAX_INPUTS as usize,
+            )?
+        };
         log::trace!(
             logger,
-            "Selected {} utxos ({:?})",
+            "Selected {} utxos ({:?}), fee {}",
             selected_utxos.len(),
             selected_utxos,
+            fee,
         );
 
         // Get membership proofs for selected utxos.
@@ -236,6 +249,7 @@ impl<T: UserTxConnection + 'static> TransactionsManager<T> {
         let (selected_utxos, fee) = Self::select_utxos_for_optimization(
             num_blocks_in_ledger,
             &inputs,
+            &FeeSchedule::current(),
             MAX_INPUTS as usize,
         )?;
 
@@ -310,17 +324,10 @@ impl<T: UserTxConnection + 'static> TransactionsManager<T> {
 
     /// Submit a previously built tx proposal to the network.
     pub fn submit_tx_proposal(&self, tx_proposal: &TxProposal) -> Result<u64, Error> {
-        // Pick a peer to submit to.
-        let idx = self.submit_node_offset.fetch_add(1, Ordering::SeqCst);
-        let responder_ids = self.peer_manager.responder_ids();
-        let responder_id = &responder_ids[idx % responder_ids.len()];
-
-        // Try and submit.
+        // Submit to the healthiest nodes, failing over to the others as needed.
         let block_height = self
-            .peer_manager
-            .conn(responder_id)
-            .ok_or(Error::NodeNotFound)?
-            .propose_tx(&tx_proposal.tx, empty())
+            .selector
+            .propose_tx(&tx_proposal.tx)
             .map_err(Error::from)?;
 
         log::info!(
@@ -388,6 +395,61 @@ impl<T: UserTxConnection + 'static> TransactionsManager<T> {
         Ok(selected_utxos)
     }
 
+    /// Returns a subset of UTXOs totalling at least the given amount plus the given fee. Errors if
+    /// the fee is below the minimum fee of spending them to the given number of outputs, plus an
+    /// output for change if there is any.
+    fn select_utxos_for_fee(
+        utxos: &[UnspentTxOut],
+        value: u64,
+        num_outputs: usize,
+        fee: u64,
+        fee_schedule: &FeeSchedule,
+        max_inputs: usize,
+    ) -> Result<Vec<UnspentTxOut>, Error> {
+        let value_and_fee = value.checked_add(fee).ok_or(Error::InsufficientFunds)?;
+        let selected_utxos = Self::select_utxos_for_value(utxos, value_and_fee, max_inputs)?;
+
+        let selected_value: u64 = selected_utxos.iter().map(|utxo| utxo.value).sum();
+        let num_outputs = if selected_value > value_and_fee {
+            num_outputs + 1
+        } else {
+            num_outputs
+        };
+        let minimum_fee = fee_schedule.minimum_fee(selected_utxos.len(), num_outputs);
+        if fee < minimum_fee {
+            return Err(Error::InvalidArgument(
+                "fee".to_owned(),
+                format!("{} is below the minimum fee of {}", fee, minimum_fee),
+            ));
+        }
+
+        Ok(selected_utxos)
+    }
+
+    /// Returns a subset of UTXOs totalling at least the given amount plus the fee of spending
+    /// them, along with that fee. The fee pays for the selected inputs, the given number of
+    /// outputs, and an output for change.
+    fn select_utxos_for_payment(
+        utxos: &[UnspentTxOut],
+        value: u64,
+        num_outputs: usize,
+        fee_schedule: &FeeSchedule,
+        max_inputs: usize,
+    ) -> Result<(Vec<UnspentTxOut>, u64), Error> {
+        let num_outputs = num_outputs + 1;
+        let mut fee = fee_schedule.minimum_fee(1, num_outputs);
+        loop {
+            // Selecting more inputs raises the fee, which may in turn require more inputs.
+            let value_and_fee = value.checked_add(fee).ok_or(Error::InsufficientFunds)?;
+            let selected_utxos = Self::select_utxos_for_value(utxos, value_and_fee, max_inputs)?;
+            let minimum_fee = fee_schedule.minimum_fee(selected_utxos.len(), num_outputs);
+            if fee >= minimum_fee {
+                return Ok((selected_utxos, fee));
+            }
+            fee = minimum_fee;
+        }
+    }
+
     /// Select UTXOs for optimization. The current strategy is to to attempt to add the maximum number
     /// of small UTXOs into the biggest one, which is the one most likely to be used when spending.
     /// The assumption is that if we maintain it as the biggest, we're less likely to need multiple
@@ -402,6 +464,7 @@ impl<T: UserTxConnection + 'static> TransactionsManager<T> {
     fn select_utxos_for_optimization(
         num_blocks_in_ledger: u64,
         inputs: &[UnspentTxOut],
+        fee_schedule: &FeeSchedule,
         max_inputs: usize,
     ) -> Result<(Vec<UnspentTxOut>, u64), Error> {
         if max_inputs < 2 {
@@ -441,8 +504,9 @@ impl<T: UserTxConnection + 'static> TransactionsManager<T> {
                 total += utxo.value;
             }
 
-            // Calculate the fee - right now this is constant.
-            let fee = BASE_FEE;
+            // Calculate the fee of spending the selected UTXOs and the biggest one to a single
+            // output.
+            let fee = fee_schedule.minimum_fee(selected_utxos.len() + 1, 1);
 
             // See if the total amount we are trying to merge into our biggest UTXO is bigger than the fee.
             // If it's smaller, the merge would just lose us money.
@@ -657,11 +721,16 @@ impl<T: UserTxConnection + 'static> TransactionsManager<T> {
         // Add outputs to our destinations.
         let mut total_value = 0;
         let mut tx_out_to_outlay_index = HashMap::default();
+        let mut outlay_payment_proofs = Vec::with_capacity(destinations.len());
         for (i, outlay) in destinations.iter().enumerate() {
             let tx_out = tx_builder
-                .add_output(outlay.value, &outlay.receiver, None, rng)
+                .add_output(outlay.value, &outlay.receiver, None, None, rng)
                 .map_err(|err| Error::TxBuildError(format!("failed adding output: {}", err)))?;
 
+            outlay_payment_proofs.push(tx_builder.payment_proof(&tx_out, rng).map_err(|err| {
+                Error::TxBuildError(format!("failed creating payment proof: {}", err))
+            })?);
+
             tx_out_to_outlay_index.insert(tx_out, i);
 
             total_value += outlay.value;
@@ -674,7 +743,7 @@ impl<T: UserTxConnection + 'static> TransactionsManager<T> {
         if total_value > input_value {
             return Err(Error::InsufficientFunds);
         }
-        let change = input_value - total_value - tx_builder.fee;
+        let change = input_value - total_value - fee;
 
         // If we do, add an output for that as well.
         if change > 0 {
@@ -683,6 +752,7 @@ impl<T: UserTxConnection + 'static> TransactionsManager<T> {
                     change,
                     &from_account_key.subaddress(change_subaddress),
                     None,
+                    None,
                     rng,
                 )
                 .map_err(|err| {
@@ -733,6 +803,7 @@ impl<T: UserTxConnection + 'static> TransactionsManager<T> {
             outlays: destinations.to_vec(),
             tx,
             outlay_index_to_tx_out_index,
+            outlay_payment_proofs,
         })
     }
 }
@@ -767,6 +838,7 @@ mod test {
                 value: 1,
                 attempted_spend_height: 0,
                 attempted_spend_tombstone: 0,
+                memo: Vec::new(),
             })
             .collect()
     }
@@ -829,8 +901,180 @@ mod test {
         };
     }
 
+    #[test]
+    fn test_select_utxos_for_payment_pays_for_selected_inputs() {
+        let fee_schedule = FeeSchedule {
+            base_fee: 10,
+            per_input_fee: 100,
+            per_output_fee: 1,
+        };
+
+        let mut utxos = generate_utxos(5);
+
+        utxos[0].value = 100;
+        utxos[1].value = 200;
+        utxos[2].value = 300;
+        utxos[3].value = 2000;
+        utxos[4].value = 1000;
+
+        // Sending 100 to one recipient with a single input would cost 112. That requires two
+        // inputs, which cost 212, which in turn requires three inputs, costing 312.
+        let (selected_utxos, fee) = TransactionsManager::<ThickClient>::select_utxos_for_payment(
+            &utxos,
+            100,
+            1,
+            &fee_schedule,
+            utxos.len(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            selected_utxos,
+            vec![utxos[0].clone(), utxos[1].clone(), utxos[2].clone()]
+        );
+        assert_eq!(fee, 312);
+        assert_eq!(fee, fee_schedule.minimum_fee(3, 2));
+
+        // With only two inputs allowed, 200 + 300 cover 100 plus the fee of 212.
+        let (selected_utxos, fee) = TransactionsManager::<ThickClient>::select_utxos_for_payment(
+            &utxos,
+            100,
+            1,
+            &fee_schedule,
+            2,
+        )
+        .unwrap();
+
+        assert_eq!(selected_utxos, vec![utxos[1].clone(), utxos[2].clone()]);
+        assert_eq!(fee, 212);
+    }
+
+    #[test]
+    fn test_select_utxos_for_payment_errors_if_fee_is_unaffordable() {
+        let fee_schedule = FeeSchedule::current();
+        let utxos = generate_utxos(10);
+        // The utxos sum to 10, which does not cover the fee.
+        match TransactionsManager::<ThickClient>::select_utxos_for_payment(
+            &utxos,
+            1,
+            1,
+            &fee_schedule,
+            100,
+        ) {
+            Err(Error::InsufficientFunds) => {
+                // Expected.
+            }
+            _ => panic!("Did not get expected error"),
+        };
+
+        // The value plus the fee overflows.
+        match TransactionsManager::<ThickClient>::select_utxos_for_payment(
+            &utxos,
+            u64::max_value(),
+            1,
+            &fee_schedule,
+            100,
+        ) {
+            Err(Error::InsufficientFunds) => {
+                // Expected.
+            }
+            _ => panic!("Did not get expected error"),
+        };
+    }
+
+    #[test]
+    fn test_select_utxos_for_fee_checks_fee_schedule() {
+        let fee_schedule = FeeSchedule {
+            base_fee: 10,
+            per_input_fee: 100,
+            per_output_fee: 1,
+        };
+
+        let mut utxos = generate_utxos(5);
+
+        utxos[0].value = 100;
+        utxos[1].value = 200;
+        utxos[2].value = 300;
+        utxos[3].value = 2000;
+        utxos[4].value = 1000;
+
+        // 100 + 200 cover 100 plus a fee of 112, but spending two inputs to two outputs costs 212.
+        match TransactionsManager::<ThickClient>::select_utxos_for_fee(
+            &utxos,
+            100,
+            1,
+            112,
+            &fee_schedule,
+            utxos.len(),
+        ) {
+            Err(Error::InvalidArgument(argument, _)) => {
+                assert_eq!(argument, "fee");
+            }
+            _ => panic!("Did not get expected error"),
+        };
+
+        // A fee of 312 pays for the three inputs it requires.
+        let selected_utxos = TransactionsManager::<ThickClient>::select_utxos_for_fee(
+            &utxos,
+            100,
+            1,
+            312,
+            &fee_schedule,
+            utxos.len(),
+        )
+        .unwrap();
+        assert_eq!(
+            selected_utxos,
+            vec![utxos[0].clone(), utxos[1].clone(), utxos[2].clone()]
+        );
+
+        // Spending 2000 exactly needs no output for change, so a fee of 111 is enough.
+        let selected_utxos = TransactionsManager::<ThickClient>::select_utxos_for_fee(
+            &utxos,
+            1889,
+            1,
+            111,
+            &fee_schedule,
+            1,
+        )
+        .unwrap();
+        assert_eq!(selected_utxos, vec![utxos[3].clone()]);
+
+        // With change, the same fee is too low.
+        match TransactionsManager::<ThickClient>::select_utxos_for_fee(
+            &utxos,
+            1888,
+            1,
+            111,
+            &fee_schedule,
+            1,
+        ) {
+            Err(Error::InvalidArgument(argument, _)) => {
+                assert_eq!(argument, "fee");
+            }
+            _ => panic!("Did not get expected error"),
+        };
+
+        // The value plus the fee overflows.
+        match TransactionsManager::<ThickClient>::select_utxos_for_fee(
+            &utxos,
+            u64::max_value(),
+            1,
+            312,
+            &fee_schedule,
+            utxos.len(),
+        ) {
+            Err(Error::InsufficientFunds) => {
+                // Expected.
+            }
+            _ => panic!("Did not get expected error"),
+        };
+    }
+
     #[test]
     fn test_select_utxos_for_optimization_selects_smallest_inputs() {
+        let fee_schedule = FeeSchedule::current();
+
         // Optimizing with max_inputs=2 should select 100, 2000
         {
             let mut utxos = generate_utxos(6);
@@ -843,11 +1087,16 @@ mod test {
             utxos[5].value = 1000;
 
             let (selected_utxos, fee) =
-                TransactionsManager::<ThickClient>::select_utxos_for_optimization(1000, &utxos, 2)
-                    .unwrap();
+                TransactionsManager::<ThickClient>::select_utxos_for_optimization(
+                    1000,
+                    &utxos,
+                    &fee_schedule,
+                    2,
+                )
+                .unwrap();
 
             assert_eq!(selected_utxos, vec![utxos[0].clone(), utxos[4].clone()]);
-            assert_eq!(fee, BASE_FEE);
+            assert_eq!(fee, fee_schedule.minimum_fee(2, 1));
         }
 
         // Optimizing with max_inputs=3 should select 100, 150, 2000;
@@ -862,20 +1111,27 @@ mod test {
             utxos[5].value = 1000;
 
             let (selected_utxos, fee) =
-                TransactionsManager::<ThickClient>::select_utxos_for_optimization(1000, &utxos, 3)
-                    .unwrap();
+                TransactionsManager::<ThickClient>::select_utxos_for_optimization(
+                    1000,
+                    &utxos,
+                    &fee_schedule,
+                    3,
+                )
+                .unwrap();
 
             assert_eq!(
                 selected_utxos,
                 vec![utxos[0].clone(), utxos[2].clone(), utxos[4].clone()]
             );
-            assert_eq!(fee, BASE_FEE);
+            assert_eq!(fee, fee_schedule.minimum_fee(3, 1));
         }
     }
 
     // Test behavior around the fee amount (off by one, exact fee, etc).
     #[test]
     fn test_select_utxos_for_optimization_behavior_around_fee() {
+        let fee_schedule = FeeSchedule::current();
+
         // When the sum of available UTXOs is lower than the fee, no merging will take place.
         {
             let mut utxos = generate_utxos(6);
@@ -889,11 +1145,14 @@ mod test {
 
             assert!(
                 utxos[0].value + utxos[1].value + utxos[2].value + utxos[3].value + utxos[5].value
-                    < BASE_FEE
+                    < fee_schedule.minimum_fee(6, 1)
             );
 
             let result = TransactionsManager::<ThickClient>::select_utxos_for_optimization(
-                1000, &utxos, 100,
+                1000,
+                &utxos,
+                &fee_schedule,
+                100,
             );
             assert!(result.is_err());
         }
@@ -902,11 +1161,14 @@ mod test {
         {
             let mut utxos = generate_utxos(2);
 
-            utxos[0].value = BASE_FEE;
+            utxos[0].value = fee_schedule.minimum_fee(2, 1);
             utxos[1].value = 2000;
 
             let result = TransactionsManager::<ThickClient>::select_utxos_for_optimization(
-                1000, &utxos, 100,
+                1000,
+                &utxos,
+                &fee_schedule,
+                100,
             );
             assert!(result.is_err());
         }
@@ -915,38 +1177,49 @@ mod test {
         {
             let mut utxos = generate_utxos(4);
 
-            utxos[0].value = BASE_FEE;
+            utxos[0].value = fee_schedule.minimum_fee(3, 1);
             utxos[1].value = 2000;
             utxos[2].value = 1;
             utxos[3].value = 2;
 
             let (selected_utxos, fee) =
-                TransactionsManager::<ThickClient>::select_utxos_for_optimization(1000, &utxos, 3)
-                    .unwrap();
+                TransactionsManager::<ThickClient>::select_utxos_for_optimization(
+                    1000,
+                    &utxos,
+                    &fee_schedule,
+                    3,
+                )
+                .unwrap();
             // Since we're limited to 3 inputs, the lowest input (of value 1) is going to get excluded.
             assert_eq!(
                 selected_utxos,
                 vec![utxos[3].clone(), utxos[0].clone(), utxos[1].clone()]
             );
-            assert_eq!(fee, BASE_FEE);
+            assert_eq!(fee, fee_schedule.minimum_fee(3, 1));
         }
     }
 
     // Attempting to select from a set of less than 2 UTXOs should fail
     #[test]
     fn test_select_utxos_for_optimizations_errors_on_less_than_2_utxos() {
+        let fee_schedule = FeeSchedule::current();
         let mut utxos = generate_utxos(2);
 
         utxos[0].value = 2000;
         utxos[1].value = 2000;
 
-        let result =
-            TransactionsManager::<ThickClient>::select_utxos_for_optimization(1000, &[], 100);
+        let result = TransactionsManager::<ThickClient>::select_utxos_for_optimization(
+            1000,
+            &[],
+            &fee_schedule,
+            100,
+        );
         assert!(result.is_err());
 
         let result = TransactionsManager::<ThickClient>::select_utxos_for_optimization(
             1000,
             &utxos[0..1],
+            &fee_schedule,
             100,
         );
         assert!(result.is_err());
@@ -955,6 +1228,7 @@ mod test {
         let result = TransactionsManager::<ThickClient>::select_utxos_for_optimization(
             1000,
             &utxos[0..2],
+            &fee_schedule,
             2,
         );
         assert!(result.is_ok());
@@ -962,6 +1236,7 @@ mod test {
         let result = TransactionsManager::<ThickClient>::select_utxos_for_optimization(
             1000,
             &utxos[0..2],
+            &fee_schedule,
             3,
         );
         assert!(result.is_err());
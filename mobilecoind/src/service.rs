@@ -23,6 +23,7 @@ use grpc_util::{rpc_internal_error, rpc_logger, send_result};
 use grpcio::{RpcContext, RpcStatus, RpcStatusCode, UnarySink};
 use keys::RistrettoPublic;
 use ledger_db::{Ledger, LedgerDB};
+use ledger_sync::ForkEvidenceStore;
 use mc_b58_payloads::payloads::{RequestPayload, TransferPayload};
 use mcconnection::UserTxConnection;
 use mcserial::ReprBytes32;
@@ -31,9 +32,10 @@ use protobuf::RepeatedField;
 use std::{convert::TryFrom, sync::Arc};
 use transaction::{
     account_keys::{AccountKey, PublicAddress},
+    fee_schedule::FeeSchedule,
     ring_signature::KeyImage,
 };
-use transaction_std::identity::RootIdentity;
+use transaction_std::{identity::RootIdentity, PaymentProof};
 
 pub struct Service {
     /// Sync thread.
@@ -48,6 +50,7 @@ impl Service {
         ledger_db: LedgerDB,
         mobilecoind_db: Database,
         transactions_manager: TransactionsManager<T>,
+        fork_evidence_store: ForkEvidenceStore,
         port: u16,
         num_workers: Option<usize>,
         logger: Logger,
@@ -70,6 +73,7 @@ impl Service {
             transactions_manager,
             ledger_db,
             mobilecoind_db,
+            fork_evidence_store,
             logger.clone(),
         );
 
@@ -99,6 +103,7 @@ pub struct ServiceApi<T: UserTxConnection + 'static> {
     transactions_manager: TransactionsManager<T>,
     ledger_db: LedgerDB,
     mobilecoind_db: Database,
+    fork_evidence_store: ForkEvidenceStore,
     logger: Logger,
 }
 
@@ -108,6 +113,7 @@ impl<T: UserTxConnection + 'static> Clone for ServiceApi<T> {
             transactions_manager: self.transactions_manager.clone(),
             ledger_db: self.ledger_db.clone(),
             mobilecoind_db: self.mobilecoind_db.clone(),
+            fork_evidence_store: self.fork_evidence_store.clone(),
             logger: self.logger.clone(),
         }
     }
@@ -118,12 +124,14 @@ impl<T: UserTxConnection + 'static> ServiceApi<T> {
         transactions_manager: TransactionsManager<T>,
         ledger_db: LedgerDB,
         mobilecoind_db: Database,
+        fork_evidence_store: ForkEvidenceStore,
         logger: Logger,
     ) -> Self {
         Self {
             transactions_manager,
             ledger_db,
             mobilecoind_db,
+            fork_evidence_store,
             logger,
         }
     }
@@ -747,6 +755,18 @@ impl<T: UserTxConnection + 'static> ServiceApi<T> {
         let mut response = mobilecoind_api::GetLedgerInfoResponse::new();
         response.set_block_count(num_blocks);
         response.set_txo_count(num_txos);
+        response.set_fee_schedule((&FeeSchedule::current()).into());
+        if let Some(fork_evidence) = self.fork_evidence_store.get() {
+            response.set_fork_detected(true);
+            response.set_fork_block_index(fork_evidence.block_index);
+            response.set_fork_block_ids(RepeatedField::from_vec(
+                fork_evidence
+                    .conflicting_blocks
+                    .iter()
+                    .map(|conflicting_block| conflicting_block.block.id.0.to_vec())
+                    .collect(),
+            ));
+        }
         Ok(response)
     }
 
@@ -917,6 +937,100 @@ impl<T: UserTxConnection + 'static> ServiceApi<T> {
         Ok(response)
     }
 
+    fn create_payment_proof_impl(
+        &mut self,
+        request: mobilecoind_api::CreatePaymentProofRequest,
+    ) -> Result<mobilecoind_api::CreatePaymentProofResponse, RpcStatus> {
+        // Get TxProposal from request.
+        let tx_proposal = TxProposal::try_from(request.get_tx_proposal())
+            .map_err(|err| rpc_internal_error("tx_proposal.try_from", err, &self.logger))?;
+
+        // Proofs can only be created by whoever built the proposal, and are part of it.
+        let outlay_index = request.outlay_index as usize;
+        if outlay_index >= tx_proposal.outlays.len() {
+            return Err(RpcStatus::new(
+                RpcStatusCode::INVALID_ARGUMENT,
+                Some("outlay_index".to_string()),
+            ));
+        }
+        let payment_proof = tx_proposal
+            .outlay_payment_proofs
+            .get(outlay_index)
+            .ok_or_else(|| {
+                RpcStatus::new(
+                    RpcStatusCode::INVALID_ARGUMENT,
+                    Some("tx_proposal.outlay_payment_proof_list".to_string()),
+                )
+            })?;
+
+        // Sanity-test the proof against the outlay's TxOut, so that a proof handed out holds once
+        // the transaction lands in the ledger.
+        let outlay = &tx_proposal.outlays[outlay_index];
+        let tx_out_index = tx_proposal.outlay_index_to_tx_out_index[&outlay_index];
+        let tx_out = &tx_proposal.tx.prefix.outputs[tx_out_index];
+        match payment_proof.verify(tx_out) {
+            Ok(value) if value == outlay.value && payment_proof.recipient == outlay.receiver => {}
+            _ => {
+                return Err(RpcStatus::new(
+                    RpcStatusCode::INVALID_ARGUMENT,
+                    Some("tx_proposal.outlay_payment_proof_list".to_string()),
+                ));
+            }
+        }
+
+        let mut response = mobilecoind_api::CreatePaymentProofResponse::new();
+        response.set_payment_proof(payment_proof.into());
+        Ok(response)
+    }
+
+    fn verify_payment_proof_impl(
+        &mut self,
+        request: mobilecoind_api::VerifyPaymentProofRequest,
+    ) -> Result<mobilecoind_api::VerifyPaymentProofResponse, RpcStatus> {
+        // Get PaymentProof from request.
+        let payment_proof = PaymentProof::try_from(request.get_payment_proof())
+            .map_err(|err| rpc_internal_error("payment_proof.try_from", err, &self.logger))?;
+
+        // Find the TxOut the proof is for in the ledger.
+        let tx_out_index = match self
+            .ledger_db
+            .get_tx_out_index_by_hash(&payment_proof.tx_out_hash)
+        {
+            Ok(tx_out_index) => tx_out_index,
+            Err(ledger_db::Error::NotFound) => {
+                let mut response = mobilecoind_api::VerifyPaymentProofResponse::new();
+                response.set_status(mobilecoind_api::PaymentProofStatus::TxOutNotFound);
+                return Ok(response);
+            }
+            Err(err) => {
+                return Err(rpc_internal_error(
+                    "ledger_db.get_tx_out_index_by_hash",
+                    err,
+                    &self.logger,
+                ));
+            }
+        };
+        let tx_out = self
+            .ledger_db
+            .get_tx_out_by_index(tx_out_index)
+            .map_err(|err| {
+                rpc_internal_error("ledger_db.get_tx_out_by_index", err, &self.logger)
+            })?;
+
+        let mut response = mobilecoind_api::VerifyPaymentProofResponse::new();
+        match payment_proof.verify(&tx_out) {
+            Ok(value) => {
+                response.set_status(mobilecoind_api::PaymentProofStatus::Valid);
+                response.set_value(value);
+            }
+            Err(err) => {
+                log::debug!(self.logger, "Payment proof is invalid: {}", err);
+                response.set_status(mobilecoind_api::PaymentProofStatus::Invalid);
+            }
+        }
+        Ok(response)
+    }
+
     fn get_balance_impl(
         &mut self,
         request: mobilecoind_api::GetBalanceRequest,
@@ -1045,6 +1159,8 @@ build_api! {
     get_block_info GetBlockInfoRequest GetBlockInfoResponse get_block_info_impl,
     get_tx_status_as_sender GetTxStatusAsSenderRequest GetTxStatusAsSenderResponse get_tx_status_as_sender_impl,
     get_tx_status_as_receiver GetTxStatusAsReceiverRequest GetTxStatusAsReceiverResponse get_tx_status_as_receiver_impl,
+    create_payment_proof CreatePaymentProofRequest CreatePaymentProofResponse create_payment_proof_impl,
+    verify_payment_proof VerifyPaymentProofRequest VerifyPaymentProofResponse verify_payment_proof_impl,
     get_balance GetBalanceRequest GetBalanceResponse get_balance_impl,
     send_payment SendPaymentRequest SendPaymentResponse send_payment_impl
 }
@@ -1066,7 +1182,7 @@ mod test {
     use std::{convert::TryFrom, iter::FromIterator};
     use transaction::{
         account_keys::{AccountKey, PublicAddress, DEFAULT_SUBADDRESS_INDEX},
-        constants::{BASE_FEE, MAX_INPUTS, MIN_RING_SIZE},
+        constants::{MAX_INPUTS, MIN_RING_SIZE},
         get_tx_out_shared_secret,
         onetime_keys::{compute_key_image, recover_onetime_private_key},
         tx::{Tx, TxOut},
@@ -1350,6 +1466,7 @@ mod test {
                     value: test_utils::PER_RECIPIENT_AMOUNT,
                     attempted_spend_height: 0,
                     attempted_spend_tombstone: 0,
+                    memo: Vec::new(),
                 }
             })
             .collect();
@@ -1478,6 +1595,19 @@ mod test {
             .unwrap();
         assert_eq!(response.block_count, ledger_db.num_blocks().unwrap());
         assert_eq!(response.txo_count, ledger_db.num_txos().unwrap());
+        assert!(!response.fork_detected);
+        assert!(response.fork_block_ids.is_empty());
+
+        let fee_schedule = FeeSchedule::current();
+        assert_eq!(response.get_fee_schedule().base_fee, fee_schedule.base_fee);
+        assert_eq!(
+            response.get_fee_schedule().per_input_fee,
+            fee_schedule.per_input_fee
+        );
+        assert_eq!(
+            response.get_fee_schedule().per_output_fee,
+            fee_schedule.per_output_fee
+        );
     }
 
     #[test_with_logger]
@@ -1755,12 +1885,16 @@ mod test {
                 outlays.len() + 1
             ); // Extra output for change.
 
+            // The fee pays for each input and output.
+            let expected_fee =
+                FeeSchedule::current().minimum_fee(expected_num_inputs as usize, outlays.len() + 1);
+
             // Sanity test output amounts
             let tx = Tx::try_from(tx_proposal.get_tx()).unwrap();
 
             let change = test_utils::PER_RECIPIENT_AMOUNT
                 - outlays.iter().map(|outlay| outlay.value).sum::<u64>()
-                - BASE_FEE;
+                - expected_fee;
 
             for (account_key, tx_out, expected_amount) in &[
                 (&receiver1, &tx.prefix.outputs[0], outlays[0].value),
@@ -1775,8 +1909,8 @@ mod test {
             }
 
             // Santity test fee
-            assert_eq!(tx_proposal.get_fee(), BASE_FEE);
-            assert_eq!(tx_proposal.get_tx().get_prefix().fee, BASE_FEE);
+            assert_eq!(tx_proposal.get_fee(), expected_fee);
+            assert_eq!(tx_proposal.get_tx().get_prefix().fee, expected_fee);
 
             // Sanity test tombstone block
             let num_blocks = ledger_db.num_blocks().unwrap();
@@ -1840,6 +1974,152 @@ mod test {
         }
     }
 
+    #[test_with_logger]
+    fn test_payment_proofs(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+
+        let sender = AccountKey::random(&mut rng);
+        let data = MonitorData::new(
+            sender.clone(),
+            0,  // first_subaddress
+            20, // num_subaddresses
+            0,  // first_block
+        )
+        .unwrap();
+
+        // 1 known recipient, 3 random recipients and no monitors.
+        let (mut ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(
+                3,
+                &vec![sender.default_subaddress()],
+                &vec![],
+                logger.clone(),
+                &mut rng,
+            );
+
+        // Insert into database.
+        let monitor_id = mobilecoind_db.add_monitor(&data).unwrap();
+
+        // Allow the new monitor to process the ledger.
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        // Get list of unspent tx outs
+        let utxos = mobilecoind_db
+            .get_utxos_for_subaddress(&monitor_id, 0)
+            .unwrap();
+        assert!(!utxos.is_empty());
+
+        // Pay two random recipients.
+        let outlays = vec![
+            Outlay {
+                value: 123,
+                receiver: AccountKey::random(&mut rng).default_subaddress(),
+            },
+            Outlay {
+                value: 456,
+                receiver: AccountKey::random(&mut rng).default_subaddress(),
+            },
+        ];
+
+        let mut request = mobilecoind_api::GenerateTxRequest::new();
+        request.set_sender_monitor_id(monitor_id.to_vec());
+        request.set_change_subaddress(0);
+        request.set_input_list(RepeatedField::from_vec(
+            utxos
+                .iter()
+                .map(mobilecoind_api::UnspentTxOut::from)
+                .collect(),
+        ));
+        request.set_outlay_list(RepeatedField::from_vec(
+            outlays.iter().map(mobilecoind_api::Outlay::from).collect(),
+        ));
+
+        let response = client.generate_tx(&request).unwrap();
+        let tx_proposal = response.get_tx_proposal();
+        assert_eq!(
+            tx_proposal.get_outlay_payment_proof_list().len(),
+            outlays.len()
+        );
+
+        // Create a proof for the second outlay.
+        let mut request = mobilecoind_api::CreatePaymentProofRequest::new();
+        request.set_tx_proposal(tx_proposal.clone());
+        request.set_outlay_index(1);
+
+        let response = client.create_payment_proof(&request).unwrap();
+        let payment_proof = PaymentProof::try_from(response.get_payment_proof()).unwrap();
+        assert_eq!(payment_proof.recipient, outlays[1].receiver);
+
+        // An out of bounds outlay index should fail.
+        {
+            let mut request = request.clone();
+            request.set_outlay_index(2);
+            assert!(client.create_payment_proof(&request).is_err());
+        }
+
+        // A proposal without proofs should fail.
+        {
+            let mut request = request.clone();
+            request.mut_tx_proposal().clear_outlay_payment_proof_list();
+            assert!(client.create_payment_proof(&request).is_err());
+        }
+
+        // The TxOut is not in the ledger yet.
+        let mut request = mobilecoind_api::VerifyPaymentProofRequest::new();
+        request.set_payment_proof(response.get_payment_proof().clone());
+        {
+            let response = client.verify_payment_proof(&request).unwrap();
+            assert_eq!(
+                response.get_status(),
+                mobilecoind_api::PaymentProofStatus::TxOutNotFound
+            );
+        }
+
+        // Append the transaction to the ledger.
+        {
+            let tx = Tx::try_from(tx_proposal.get_tx()).unwrap();
+            let redacted_transactions = vec![tx.redact()];
+
+            let num_blocks = ledger_db.num_blocks().unwrap();
+            let parent = ledger_db.get_block(num_blocks - 1).unwrap();
+            let new_block = Block::new(
+                BLOCK_VERSION,
+                &parent.id,
+                num_blocks as BlockIndex,
+                &Default::default(),
+                &redacted_transactions,
+            );
+            ledger_db
+                .append_block(&new_block, &redacted_transactions, None)
+                .unwrap();
+        }
+
+        // The proof should now verify against the ledger.
+        {
+            let response = client.verify_payment_proof(&request).unwrap();
+            assert_eq!(
+                response.get_status(),
+                mobilecoind_api::PaymentProofStatus::Valid
+            );
+            assert_eq!(response.get_value(), outlays[1].value);
+        }
+
+        // A proof claiming another recipient should be invalid.
+        {
+            let mut payment_proof = payment_proof;
+            payment_proof.recipient = outlays[0].receiver.clone();
+
+            let mut request = mobilecoind_api::VerifyPaymentProofRequest::new();
+            request.set_payment_proof((&payment_proof).into());
+
+            let response = client.verify_payment_proof(&request).unwrap();
+            assert_eq!(
+                response.get_status(),
+                mobilecoind_api::PaymentProofStatus::Invalid
+            );
+        }
+    }
+
     #[test_with_logger]
     fn test_generate_transfer_code_tx(logger: Logger) {
         let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
@@ -2010,6 +2290,7 @@ mod test {
         let tx_proposal = TxProposal::try_from(response.get_tx_proposal()).unwrap();
 
         let expected_num_inputs: usize = MAX_INPUTS as usize;
+        let expected_fee = FeeSchedule::current().minimum_fee(expected_num_inputs, 1);
         assert_eq!(tx_proposal.utxos.len(), expected_num_inputs);
         assert_eq!(tx_proposal.tx.prefix.inputs.len(), expected_num_inputs);
 
@@ -2022,7 +2303,7 @@ mod test {
             tx_proposal.outlays[0].value,
             // Each UTXO we have has PER_RECIPIENT_AMOUNT coins. We will be merging MAX_INPUTS of those
             // into a single output, minus the fee.
-            (PER_RECIPIENT_AMOUNT * MAX_INPUTS as u64) - BASE_FEE,
+            (PER_RECIPIENT_AMOUNT * MAX_INPUTS as u64) - expected_fee,
         );
 
         assert_eq!(tx_proposal.outlay_index_to_tx_out_index.len(), 1);
@@ -2037,8 +2318,8 @@ mod test {
         assert_eq!(value, tx_proposal.outlays[0].value);
 
         // Santity test fee
-        assert_eq!(tx_proposal.fee(), BASE_FEE);
-        assert_eq!(tx_proposal.tx.prefix.fee, BASE_FEE);
+        assert_eq!(tx_proposal.fee(), expected_fee);
+        assert_eq!(tx_proposal.tx.prefix.fee, expected_fee);
 
         // Sanity test tombstone block
         let num_blocks = ledger_db.num_blocks().unwrap();
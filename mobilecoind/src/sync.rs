@@ -401,6 +401,11 @@ fn match_redacted_txs_into_utxos(
 
             let key_image = compute_key_image(&onetime_private_key);
 
+            let memo = tx_out
+                .decrypt_memo(&shared_secret)
+                .map(|memo| memo.as_ref().to_vec())
+                .unwrap_or_default();
+
             results.push(UnspentTxOut {
                 tx_out: tx_out.clone(),
                 subaddress_index: subaddress_id.index,
@@ -408,6 +413,7 @@ fn match_redacted_txs_into_utxos(
                 value,
                 attempted_spend_height: 0,
                 attempted_spend_tombstone: 0,
+                memo,
             });
         }
     }
@@ -15,6 +15,7 @@ use common::logger::{log, Logger};
 use grpcio::{ChannelBuilder, EnvBuilder};
 use keys::{FromRandom, RistrettoPrivate};
 use ledger_db::{Ledger, LedgerDB};
+use ledger_sync::ForkEvidenceStore;
 use mcconnection::ConnectionManager;
 use mcconnection_tests::{test_client_uri, MockUserTxConnection};
 use mcrand::{CryptoRng, RngCore};
@@ -211,6 +212,7 @@ fn setup_server(
         ledger_db,
         mobilecoind_db,
         transactions_manager,
+        ForkEvidenceStore::default(),
         test_port,
         None,
         logger,
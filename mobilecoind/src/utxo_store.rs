@@ -52,6 +52,10 @@ pub struct UnspentTxOut {
     /// The tombstone block used when we attempted to spend the UTXO.
     #[prost(uint64, tag = "6")]
     pub attempted_spend_tombstone: u64,
+
+    /// The decrypted memo of this TxOut, e.g. an invoice ID. Empty if it has no memo.
+    #[prost(bytes, tag = "7")]
+    pub memo: Vec<u8>,
 }
 
 /// Type used as the key in the utxo_id_to_utxo  database.
@@ -430,6 +434,7 @@ mod test {
                     value: idx,
                     attempted_spend_height: 0,
                     attempted_spend_tombstone: 0,
+                    memo: Vec::new(),
                 }
             })
             .collect();
@@ -5,6 +5,7 @@
 use crate::{
     consensus_msg::{ConsensusMsg, TxProposeAAD},
     error::{PeerAttestationError, Result},
+    peer_exchange::PeerExchangeMsg,
     traits::ConsensusConnection,
 };
 use attest_api::attest_grpc::AttestedApiClient;
@@ -15,17 +16,21 @@ use common::{
 };
 use consensus_enclave_api::{ConsensusEnclaveProxy, TxContext, WellFormedEncryptedTx};
 use core::fmt::{Display, Formatter, Result as FmtResult};
+use futures::Stream;
 use grpcio::{ChannelBuilder, Environment};
 use mcconnection::{
-    AttestedConnection, BlockchainConnection, Connection, ConnectionUriGrpcioChannel,
-    Error as ConnectionError, Result as ConnectionResult,
+    AttestedConnection, BlockContents, BlockSubscription, BlockchainConnection, Connection,
+    ConnectionUriGrpcioChannel, Error as ConnectionError, Result as ConnectionResult,
 };
 use mcserial::{deserialize, serialize};
 use mcuri::{ConnectionUri, ConsensusPeerUri as PeerUri};
 use mobilecoin_api::{
-    blockchain::BlocksRequest,
+    blockchain::{BlockByIDRequest, BlocksRequest, SubscribeBlocksRequest},
     blockchain_grpc::BlockchainApiClient,
-    consensus_peer::{ConsensusMsg as GrpcConsensusMsg, FetchTxsRequest as GrpcFetchTxsRequest},
+    consensus_peer::{
+        ConsensusMsg as GrpcConsensusMsg, FetchTxsRequest as GrpcFetchTxsRequest,
+        PeerExchangeMsg as GrpcPeerExchangeMsg,
+    },
     consensus_peer_grpc::ConsensusPeerApiClient,
     empty::Empty,
 };
@@ -220,6 +225,57 @@ impl<Enclave: ConsensusEnclaveProxy> BlockchainConnection for PeerConnection<Enc
             })?
             .index)
     }
+
+    fn fetch_block_by_id(&mut self, block_id: &BlockID) -> ConnectionResult<BlockContents> {
+        trace_time!(self.logger, "PeerConnection::fetch_block_by_id");
+
+        let mut request = BlockByIDRequest::new();
+        request.set_id(block_id.0.to_vec());
+
+        let s3_block =
+            self.attested_call(|this| this.blockchain_api_client.get_block_by_id(&request))?;
+        Ok(BlockContents::try_from(&s3_block)?)
+    }
+
+    fn fetch_blocks_with_transactions(
+        &mut self,
+        range: Range<BlockIndex>,
+    ) -> ConnectionResult<Vec<BlockContents>> {
+        trace_time!(
+            self.logger,
+            "PeerConnection::fetch_blocks_with_transactions"
+        );
+
+        let mut request = BlocksRequest::new();
+        request.set_offset(range.start);
+        let limit =
+            u32::try_from(range.end - range.start).or(Err(ConnectionError::RequestTooLarge))?;
+        request.set_limit(limit);
+
+        self.attested_call(|this| {
+            this.blockchain_api_client
+                .get_blocks_with_transactions(&request)
+        })?
+        .get_blocks()
+        .iter()
+        .map(|s3_block| BlockContents::try_from(s3_block).map_err(ConnectionError::from))
+        .collect::<ConnectionResult<Vec<BlockContents>>>()
+    }
+
+    fn subscribe_blocks(&mut self, from_index: BlockIndex) -> ConnectionResult<BlockSubscription> {
+        trace_time!(self.logger, "PeerConnection::subscribe_blocks");
+
+        let mut request = SubscribeBlocksRequest::new();
+        request.set_from_index(from_index);
+
+        let receiver =
+            self.attested_call(|this| this.blockchain_api_client.subscribe_blocks(&request))?;
+        Ok(Box::new(
+            receiver
+                .wait()
+                .map(|s3_block| Ok(BlockContents::try_from(&s3_block?)?)),
+        ))
+    }
 }
 
 impl<Enclave: ConsensusEnclaveProxy> ConsensusConnection for PeerConnection<Enclave> {
@@ -293,4 +349,13 @@ impl<Enclave: ConsensusEnclaveProxy> ConsensusConnection for PeerConnection<Encl
             Ok(Some(msg))
         }
     }
+
+    fn exchange_peers(&mut self, msg: &PeerExchangeMsg) -> Result<PeerExchangeMsg> {
+        let mut grpc_msg = GrpcPeerExchangeMsg::default();
+        grpc_msg.set_payload(serialize(&msg)?);
+
+        let response =
+            self.attested_call(|this| this.consensus_api_client.exchange_peers(&grpc_msg))?;
+        Ok(deserialize::<PeerExchangeMsg>(response.get_payload())?)
+    }
 }
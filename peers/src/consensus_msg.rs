@@ -11,7 +11,7 @@ use scp::Msg;
 use serde::{Deserialize, Serialize};
 use sha2::{digest::Digest, Sha256};
 use std::{convert::TryFrom, result::Result as StdResult};
-use transaction::{tx::TxHash, BlockID};
+use transaction::{tx::TxHash, BlockID, BlockSignature};
 
 /// A consensus message holds the data that is exchanged by consensus service nodes as part of the
 /// process of reaching agreement on the contents of the next block.
@@ -25,6 +25,11 @@ pub struct ConsensusMsg {
 
     /// The signature of the scp_msg.
     pub signature: Ed25519Signature,
+
+    /// The issuer's signature over the block the message is trying to append values to, if the
+    /// issuer has one. This is how validators collect signatures from each other for the blocks
+    /// they externalized.
+    pub prev_block_signature: Option<BlockSignature>,
 }
 
 /// A consensus message that has passed signature validation.
@@ -45,6 +50,10 @@ impl VerifiedConsensusMsg {
     pub fn signature(&self) -> &Ed25519Signature {
         &self.inner.signature
     }
+
+    pub fn prev_block_signature(&self) -> Option<&BlockSignature> {
+        self.inner.prev_block_signature.as_ref()
+    }
 }
 
 impl TryFrom<ConsensusMsg> for VerifiedConsensusMsg {
@@ -125,6 +134,11 @@ impl ConsensusMsg {
         }
 
         let prev_block = ledger.get_block(scp_msg.slot_index - 1)?;
+        let prev_block_signature = match ledger.get_block_signature(scp_msg.slot_index - 1) {
+            Ok(signature) => Some(signature),
+            Err(ledger_db::Error::NotFound) => None,
+            Err(err) => return Err(err.into()),
+        };
 
         let contents_hash = Sha256::digest(
             &[
@@ -140,6 +154,7 @@ impl ConsensusMsg {
             scp_msg,
             prev_block_id: prev_block.id,
             signature,
+            prev_block_signature,
         })
     }
 
@@ -0,0 +1,44 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+use common::ResponderId;
+use lazy_static;
+use metrics::{IntGauge, OpMetrics};
+
+lazy_static::lazy_static! {
+    pub static ref OP_COUNTERS: OpMetrics = OpMetrics::new_and_registered("peers");
+}
+
+/// Traffic counters of a single peer, labeled with the peer's responder id.
+pub struct PeerCounters {
+    // Consensus messages waiting to be sent to the peer.
+    pub queued_consensus_msgs: IntGauge,
+
+    // Transaction relays waiting to be sent to the peer.
+    pub queued_tx_relays: IntGauge,
+
+    // Messages delivered to the peer.
+    pub msgs_sent: IntGauge,
+
+    // Bytes of messages delivered to the peer.
+    pub bytes_sent: IntGauge,
+
+    // Messages that could not be delivered, or were dropped because they became stale.
+    pub msgs_dropped: IntGauge,
+
+    // Time it took to deliver the last message to the peer, including retries.
+    pub send_latency_ms: IntGauge,
+}
+
+impl PeerCounters {
+    pub fn new(responder_id: &ResponderId) -> Self {
+        let responder_id = responder_id.to_string();
+        Self {
+            queued_consensus_msgs: OP_COUNTERS.peer_gauge("queued_consensus_msgs", &responder_id),
+            queued_tx_relays: OP_COUNTERS.peer_gauge("queued_tx_relays", &responder_id),
+            msgs_sent: OP_COUNTERS.peer_gauge("msgs_sent", &responder_id),
+            bytes_sent: OP_COUNTERS.peer_gauge("bytes_sent", &responder_id),
+            msgs_dropped: OP_COUNTERS.peer_gauge("msgs_dropped", &responder_id),
+            send_latency_ms: OP_COUNTERS.peer_gauge("send_latency_ms", &responder_id),
+        }
+    }
+}
@@ -2,7 +2,7 @@
 
 //! A Peer-to-Peer networking error.
 
-use crate::ConsensusMsgError;
+use crate::{ConsensusMsgError, PeerExchangeMsgError};
 use consensus_enclave_api::Error as EnclaveError;
 use failure::Fail;
 use grpcio::Error as GrpcError;
@@ -54,6 +54,9 @@ pub enum Error {
     /// Consensus message error.
     #[fail(display = "Conensus message: {}", _0)]
     ConsensusMsg(ConsensusMsgError),
+    /// Peer exchange message error.
+    #[fail(display = "Peer exchange message: {}", _0)]
+    PeerExchangeMsg(PeerExchangeMsgError),
     /// Some other error.
     #[fail(display = "Unknown peering issue")]
     Other,
@@ -138,6 +141,12 @@ impl From<ConsensusMsgError> for Error {
     }
 }
 
+impl From<PeerExchangeMsgError> for Error {
+    fn from(src: PeerExchangeMsgError) -> Self {
+        Self::PeerExchangeMsg(src)
+    }
+}
+
 #[derive(Debug, Fail)]
 pub enum PeerAttestationError {
     #[fail(display = "gRPC failure during attestation: {}", _0)]
@@ -6,7 +6,11 @@ extern crate alloc;
 
 mod connection;
 mod consensus_msg;
+mod counters;
 mod error;
+mod peer_exchange;
+mod peer_health;
+mod peer_queue;
 mod sync;
 mod threaded_broadcaster;
 mod threaded_broadcaster_retry;
@@ -16,8 +20,13 @@ pub use crate::{
     connection::PeerConnection,
     consensus_msg::{ConsensusMsg, ConsensusMsgError, TxProposeAAD, VerifiedConsensusMsg},
     error::{Error, Result},
+    peer_exchange::{PeerExchangeMsg, PeerExchangeMsgError},
+    peer_health::{PeerHealthScores, DEFAULT_HEALTH_SCORE},
     threaded_broadcaster::ThreadedBroadcaster,
     threaded_broadcaster_retry::{
+        CircuitBreakerRetryPolicy as ThreadedBroadcasterCircuitBreakerRetryPolicy,
+        DynamicRetryPolicy as ThreadedBroadcasterDynamicRetryPolicy,
+        ExponentialBackoffRetryPolicy as ThreadedBroadcasterExponentialBackoffRetryPolicy,
         FibonacciRetryPolicy as ThreadedBroadcasterFibonacciRetryPolicy,
         RetryPolicy as ThreadedBroadcasterRetryPolicy, DEFAULT_RETRY_MAX_ATTEMPTS,
     },
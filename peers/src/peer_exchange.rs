@@ -0,0 +1,138 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Messages used by peers to tell each other about the peers they know of.
+
+use common::NodeID;
+use ed25519::signature::Error as SignatureError;
+use failure::Fail;
+use keys::{Ed25519Pair, Ed25519Signature, Signer, Verifier};
+use mcuri::ConsensusPeerUri as PeerUri;
+use serde::{Deserialize, Serialize};
+use sha2::{digest::Digest, Sha256};
+use std::result::Result as StdResult;
+
+/// A list of peers known to the issuing node, signed with the issuer's message signing key.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct PeerExchangeMsg {
+    /// The node that issued this message.
+    pub sender_id: NodeID,
+
+    /// Peers known to the issuer.
+    pub known_peers: Vec<PeerUri>,
+
+    /// The issuer's signature over `sender_id` and `known_peers`.
+    pub signature: Ed25519Signature,
+}
+
+#[derive(Debug, Fail)]
+pub enum PeerExchangeMsgError {
+    #[fail(display = "Serialization")]
+    Serialization,
+
+    #[fail(display = "Signature error: {}", _0)]
+    SignatureError(SignatureError),
+}
+
+impl From<mcserial::encode::Error> for PeerExchangeMsgError {
+    fn from(_src: mcserial::encode::Error) -> Self {
+        PeerExchangeMsgError::Serialization
+    }
+}
+
+impl From<SignatureError> for PeerExchangeMsgError {
+    fn from(src: SignatureError) -> Self {
+        PeerExchangeMsgError::SignatureError(src)
+    }
+}
+
+impl PeerExchangeMsg {
+    pub fn new(
+        sender_id: NodeID,
+        known_peers: Vec<PeerUri>,
+        signer_key: &Ed25519Pair,
+    ) -> StdResult<Self, PeerExchangeMsgError> {
+        let contents_hash = Self::contents_hash(&sender_id, &known_peers)?;
+        let signature = signer_key.try_sign(&contents_hash)?;
+
+        Ok(Self {
+            sender_id,
+            known_peers,
+            signature,
+        })
+    }
+
+    pub fn verify_signature(&self) -> StdResult<(), PeerExchangeMsgError> {
+        let contents_hash = Self::contents_hash(&self.sender_id, &self.known_peers)?;
+        Ok(self
+            .sender_id
+            .public_key
+            .verify(&contents_hash, &self.signature)?)
+    }
+
+    fn contents_hash(
+        sender_id: &NodeID,
+        known_peers: &[PeerUri],
+    ) -> StdResult<Vec<u8>, PeerExchangeMsgError> {
+        Ok(Sha256::digest(
+            &[
+                mcserial::serialize(sender_id)?,
+                mcserial::serialize(known_peers)?,
+            ]
+            .concat(),
+        )
+        .to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use peers_tests::{test_node_id_and_signer, test_peer_uri};
+
+    fn create_msg() -> PeerExchangeMsg {
+        let (node_id, signer_key) = test_node_id_and_signer(1);
+        PeerExchangeMsg::new(
+            node_id,
+            vec![test_peer_uri(2), test_peer_uri(3)],
+            &signer_key,
+        )
+        .unwrap()
+    }
+
+    // Correctly-constructed signature should verify.
+    #[test]
+    fn test_correct_signature() {
+        assert!(create_msg().verify_signature().is_ok());
+    }
+
+    // Signature verification should fail if the list of peers was tampered with.
+    #[test]
+    fn test_signature_fails_if_peers_changed() {
+        let mut msg = create_msg();
+        msg.known_peers.push(test_peer_uri(4));
+        match msg.verify_signature() {
+            Ok(_) => panic!("Signature verification should fail"),
+            Err(PeerExchangeMsgError::SignatureError(_)) => {}
+            Err(e) => panic!("Signature failed with unexpected error {:?}", e),
+        }
+    }
+
+    // Signature verification should fail if signed by a different key.
+    #[test]
+    fn test_signature_fails_if_sender_changed() {
+        let mut msg = create_msg();
+        let (other_node_id, _) = test_node_id_and_signer(5);
+        msg.sender_id = other_node_id;
+        assert!(msg.verify_signature().is_err());
+    }
+
+    // PeerExchangeMsg should serialize and deserialize correctly.
+    #[test]
+    fn test_serialization() {
+        let msg = create_msg();
+        let serialized = mcserial::serialize(&msg).unwrap();
+        let deserialized: PeerExchangeMsg = mcserial::deserialize(&serialized).unwrap();
+        assert_eq!(msg, deserialized);
+        assert!(deserialized.verify_signature().is_ok());
+    }
+}
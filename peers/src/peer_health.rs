@@ -0,0 +1,130 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Per-peer health scores, based on the outcome of recent requests to each peer.
+
+use common::{HashMap, ResponderId};
+use std::sync::{Arc, Mutex};
+
+/// Score of a peer we have no outcomes for yet.
+pub const DEFAULT_HEALTH_SCORE: f64 = 1.0;
+
+/// Weight given to the latest outcome. Higher values make the score react faster.
+const OUTCOME_WEIGHT: f64 = 0.2;
+
+/// Tracks a score between 0.0 (every recent request failed) and 1.0 (every recent request
+/// succeeded) for each peer. Clones share the same scores.
+#[derive(Clone, Default)]
+pub struct PeerHealthScores {
+    scores: Arc<Mutex<HashMap<ResponderId, f64>>>,
+}
+
+impl PeerHealthScores {
+    /// Record that a request to `responder_id` succeeded.
+    pub fn record_success(&self, responder_id: &ResponderId) {
+        self.record_outcome(responder_id, 1.0);
+    }
+
+    /// Record that a request to `responder_id` failed, after any retries.
+    pub fn record_failure(&self, responder_id: &ResponderId) {
+        self.record_outcome(responder_id, 0.0);
+    }
+
+    /// The current score of `responder_id`.
+    pub fn score(&self, responder_id: &ResponderId) -> f64 {
+        self.scores
+            .lock()
+            .expect("mutex poisoned")
+            .get(responder_id)
+            .cloned()
+            .unwrap_or(DEFAULT_HEALTH_SCORE)
+    }
+
+    /// Sort `responder_ids` from the healthiest to the least healthy peer. Peers with equal
+    /// scores keep their relative order.
+    pub fn sort_by_score(&self, responder_ids: &mut Vec<ResponderId>) {
+        let scores = self.scores.lock().expect("mutex poisoned");
+        let score = |responder_id: &ResponderId| {
+            scores
+                .get(responder_id)
+                .cloned()
+                .unwrap_or(DEFAULT_HEALTH_SCORE)
+        };
+        responder_ids.sort_by(|a, b| {
+            score(b)
+                .partial_cmp(&score(a))
+                .expect("scores are never NaN")
+        });
+    }
+
+    /// Forget the scores of peers not in `responder_ids`.
+    pub fn retain(&self, responder_ids: &[ResponderId]) {
+        self.scores
+            .lock()
+            .expect("mutex poisoned")
+            .retain(|responder_id, _| responder_ids.contains(responder_id));
+    }
+
+    fn record_outcome(&self, responder_id: &ResponderId, outcome: f64) {
+        let mut scores = self.scores.lock().expect("mutex poisoned");
+        let score = scores
+            .entry(responder_id.clone())
+            .or_insert(DEFAULT_HEALTH_SCORE);
+        *score = (1.0 - OUTCOME_WEIGHT) * *score + OUTCOME_WEIGHT * outcome;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn responder_id(n: u32) -> ResponderId {
+        ResponderId::from_str(&format!("node{}.test.com:8443", n)).unwrap()
+    }
+
+    #[test]
+    // Failures lower the score and successes raise it back.
+    fn test_record_outcomes() {
+        let scores = PeerHealthScores::default();
+        let peer = responder_id(1);
+        assert_eq!(scores.score(&peer), DEFAULT_HEALTH_SCORE);
+
+        scores.record_failure(&peer);
+        let after_failure = scores.score(&peer);
+        assert!(after_failure < DEFAULT_HEALTH_SCORE);
+
+        scores.record_failure(&peer);
+        assert!(scores.score(&peer) < after_failure);
+
+        for _ in 0..50 {
+            scores.record_success(&peer);
+        }
+        assert!(scores.score(&peer) > 0.99);
+    }
+
+    #[test]
+    // Healthier peers come first, and ties keep their order.
+    fn test_sort_by_score() {
+        let scores = PeerHealthScores::default();
+        scores.record_failure(&responder_id(1));
+        scores.record_failure(&responder_id(3));
+        scores.record_failure(&responder_id(3));
+
+        let mut responder_ids = vec![
+            responder_id(1),
+            responder_id(2),
+            responder_id(3),
+            responder_id(4),
+        ];
+        scores.sort_by_score(&mut responder_ids);
+        assert_eq!(
+            responder_ids,
+            vec![
+                responder_id(2),
+                responder_id(4),
+                responder_id(1),
+                responder_id(3)
+            ]
+        );
+    }
+}
@@ -0,0 +1,256 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Prioritized queue of messages waiting to be sent to a single peer.
+//!
+//! Consensus messages are sent before transaction relays, so that a burst of transactions does not
+//! delay SCP. Consensus messages for the newest slot are sent first, and messages for slots that
+//! are too far behind the local node's current slot are dropped. Staleness is based on the local
+//! slot rather than on the queued messages, since a relayed message may claim any slot.
+
+use crate::consensus_msg::ConsensusMsg;
+use common::NodeID;
+use consensus_enclave_api::WellFormedEncryptedTx;
+use scp::SlotIndex;
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::Arc,
+    time::Instant,
+};
+
+/// Number of slots, counting the current one, whose consensus messages are kept in the queue.
+/// Messages for the slot before the current one are still useful to peers that are finishing it.
+pub const QUEUED_SLOTS: SlotIndex = 2;
+
+/// A message waiting to be sent.
+pub enum QueuedMsg {
+    Consensus {
+        msg: Arc<ConsensusMsg>,
+        size: usize,
+        deadline: Instant,
+    },
+    ProposeTx {
+        encrypted_tx: Arc<WellFormedEncryptedTx>,
+        origin_node: Arc<NodeID>,
+        deadline: Instant,
+    },
+}
+
+#[derive(Default)]
+pub struct PeerQueue {
+    /// Consensus messages, by slot. Messages of a slot are kept in the order they were queued.
+    consensus_msgs: BTreeMap<SlotIndex, VecDeque<QueuedMsg>>,
+
+    /// Transaction relays, in the order they were queued.
+    tx_relays: VecDeque<QueuedMsg>,
+
+    /// The slot the local node is working on.
+    cur_slot: SlotIndex,
+}
+
+impl PeerQueue {
+    /// Queue a consensus message. Returns the number of messages dropped because they are for a
+    /// stale slot: 1 if that is the case of `msg`, 0 otherwise.
+    pub fn push_consensus_msg(
+        &mut self,
+        msg: Arc<ConsensusMsg>,
+        size: usize,
+        deadline: Instant,
+    ) -> usize {
+        let slot = msg.scp_msg.slot_index;
+        if self.is_stale(slot) {
+            return 1;
+        }
+
+        self.consensus_msgs
+            .entry(slot)
+            .or_insert_with(VecDeque::new)
+            .push_back(QueuedMsg::Consensus {
+                msg,
+                size,
+                deadline,
+            });
+        0
+    }
+
+    /// Set the slot the local node is working on. Returns the number of consensus messages dropped
+    /// because their slot became stale.
+    pub fn set_cur_slot(&mut self, cur_slot: SlotIndex) -> usize {
+        if cur_slot <= self.cur_slot {
+            return 0;
+        }
+        self.cur_slot = cur_slot;
+
+        let oldest_kept = (cur_slot + 1).saturating_sub(QUEUED_SLOTS);
+        let kept = self.consensus_msgs.split_off(&oldest_kept);
+        let dropped = self.consensus_msgs.values().map(VecDeque::len).sum();
+        self.consensus_msgs = kept;
+        dropped
+    }
+
+    /// Queue a transaction relay.
+    pub fn push_propose_tx(
+        &mut self,
+        encrypted_tx: Arc<WellFormedEncryptedTx>,
+        origin_node: Arc<NodeID>,
+        deadline: Instant,
+    ) {
+        self.tx_relays.push_back(QueuedMsg::ProposeTx {
+            encrypted_tx,
+            origin_node,
+            deadline,
+        });
+    }
+
+    /// Take the next message to send.
+    pub fn pop(&mut self) -> Option<QueuedMsg> {
+        if let Some(slot) = self.consensus_msgs.keys().next_back().cloned() {
+            let msgs = self
+                .consensus_msgs
+                .get_mut(&slot)
+                .expect("slot was just found");
+            let msg = msgs.pop_front();
+            if msgs.is_empty() {
+                self.consensus_msgs.remove(&slot);
+            }
+            return msg;
+        }
+        self.tx_relays.pop_front()
+    }
+
+    /// Number of queued consensus messages.
+    pub fn num_consensus_msgs(&self) -> usize {
+        self.consensus_msgs.values().map(VecDeque::len).sum()
+    }
+
+    /// Number of queued transaction relays.
+    pub fn num_tx_relays(&self) -> usize {
+        self.tx_relays.len()
+    }
+
+    fn is_stale(&self, slot: SlotIndex) -> bool {
+        self.cur_slot.saturating_sub(slot) >= QUEUED_SLOTS
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::ResponderId;
+    use keys::{Ed25519Pair, FromRandom, Signer};
+    use rand::SeedableRng;
+    use rand_hc::Hc128Rng as FixedRng;
+    use scp::{
+        msg::{Msg, NominatePayload},
+        QuorumSet, Topic,
+    };
+    use std::{collections::BTreeSet, str::FromStr};
+    use transaction::BlockID;
+
+    fn consensus_msg(slot_index: SlotIndex) -> Arc<ConsensusMsg> {
+        let mut seeded_rng: FixedRng = SeedableRng::from_seed([1u8; 32]);
+        let signer_key = Ed25519Pair::from_random(&mut seeded_rng);
+        let node_id = NodeID {
+            responder_id: ResponderId::from_str("node1.test.com:8443").unwrap(),
+            public_key: signer_key.public_key(),
+        };
+        let scp_msg = Msg::new(
+            node_id.clone(),
+            QuorumSet::new_with_node_ids(1, vec![node_id]),
+            slot_index,
+            Topic::Nominate(NominatePayload {
+                X: BTreeSet::default(),
+                Y: BTreeSet::default(),
+            }),
+        );
+        Arc::new(ConsensusMsg {
+            scp_msg,
+            prev_block_id: BlockID::default(),
+            signature: signer_key.try_sign(b"test").unwrap(),
+            prev_block_signature: None,
+        })
+    }
+
+    fn propose_tx(n: u8) -> Arc<WellFormedEncryptedTx> {
+        Arc::new(WellFormedEncryptedTx(vec![n]))
+    }
+
+    fn pop_slot(queue: &mut PeerQueue) -> Option<SlotIndex> {
+        match queue.pop() {
+            Some(QueuedMsg::Consensus { msg, .. }) => Some(msg.scp_msg.slot_index),
+            Some(QueuedMsg::ProposeTx { .. }) => panic!("expected a consensus msg"),
+            None => None,
+        }
+    }
+
+    #[test]
+    // Consensus messages are sent before transaction relays, regardless of when they were queued.
+    fn test_consensus_msgs_before_tx_relays() {
+        let mut queue = PeerQueue::default();
+        let node_id = Arc::new(consensus_msg(1).scp_msg.sender_id.clone());
+        let deadline = Instant::now();
+
+        queue.push_propose_tx(propose_tx(1), node_id.clone(), deadline);
+        queue.push_propose_tx(propose_tx(2), node_id, deadline);
+        assert_eq!(queue.push_consensus_msg(consensus_msg(1), 0, deadline), 0);
+        assert_eq!(queue.num_consensus_msgs(), 1);
+        assert_eq!(queue.num_tx_relays(), 2);
+
+        assert_eq!(pop_slot(&mut queue), Some(1));
+        for expected in &[1, 2] {
+            match queue.pop() {
+                Some(QueuedMsg::ProposeTx { encrypted_tx, .. }) => {
+                    assert_eq!(encrypted_tx.0, vec![*expected])
+                }
+                _ => panic!("expected a tx relay"),
+            }
+        }
+        assert!(queue.pop().is_none());
+        assert_eq!(queue.num_consensus_msgs(), 0);
+        assert_eq!(queue.num_tx_relays(), 0);
+    }
+
+    #[test]
+    // Messages for the newest slot are sent first, and messages for stale slots are dropped.
+    fn test_newest_slot_first() {
+        let mut queue = PeerQueue::default();
+        let deadline = Instant::now();
+        assert_eq!(queue.set_cur_slot(6), 0);
+
+        assert_eq!(queue.push_consensus_msg(consensus_msg(5), 0, deadline), 0);
+        assert_eq!(queue.push_consensus_msg(consensus_msg(5), 0, deadline), 0);
+        assert_eq!(queue.push_consensus_msg(consensus_msg(6), 0, deadline), 0);
+        assert_eq!(queue.num_consensus_msgs(), 3);
+
+        // Moving on to slot 7 makes slot 5 stale.
+        assert_eq!(queue.set_cur_slot(7), 2);
+
+        // Messages for stale slots are dropped right away.
+        assert_eq!(queue.push_consensus_msg(consensus_msg(5), 0, deadline), 1);
+
+        assert_eq!(queue.push_consensus_msg(consensus_msg(7), 0, deadline), 0);
+        assert_eq!(pop_slot(&mut queue), Some(7));
+        assert_eq!(pop_slot(&mut queue), Some(6));
+        assert_eq!(pop_slot(&mut queue), None);
+    }
+
+    #[test]
+    // A message for a far-future slot does not make messages for the current slot stale.
+    fn test_future_slot_does_not_stall_current_slot() {
+        let mut queue = PeerQueue::default();
+        let deadline = Instant::now();
+        queue.set_cur_slot(6);
+
+        assert_eq!(
+            queue.push_consensus_msg(consensus_msg(SlotIndex::max_value()), 0, deadline),
+            0
+        );
+        assert_eq!(queue.push_consensus_msg(consensus_msg(6), 0, deadline), 0);
+        assert_eq!(queue.push_consensus_msg(consensus_msg(5), 0, deadline), 0);
+        assert_eq!(queue.num_consensus_msgs(), 3);
+
+        assert_eq!(pop_slot(&mut queue), Some(SlotIndex::max_value()));
+        assert_eq!(pop_slot(&mut queue), Some(6));
+        assert_eq!(pop_slot(&mut queue), Some(5));
+        assert_eq!(pop_slot(&mut queue), None);
+    }
+}
@@ -5,6 +5,7 @@
 use crate::{
     consensus_msg::ConsensusMsg,
     error::RetryResult,
+    peer_exchange::PeerExchangeMsg,
     traits::{ConsensusConnection, RetryableConsensusConnection},
 };
 use common::{NodeID, ResponderId};
@@ -75,4 +76,18 @@ impl<CC: ConsensusConnection> RetryableConsensusConnection for SyncConnection<CC
             retry_iterator
         )
     }
+
+    fn exchange_peers(
+        &self,
+        msg: &PeerExchangeMsg,
+        retry_iterator: impl IntoIterator<Item = Duration>,
+    ) -> RetryResult<PeerExchangeMsg> {
+        impl_sync_connection_retry!(
+            self.write(),
+            self.logger(),
+            exchange_peers,
+            retry_iterator,
+            msg
+        )
+    }
 }
@@ -4,8 +4,11 @@
 
 use crate::{
     consensus_msg::ConsensusMsg,
+    counters::PeerCounters,
     error::Error,
-    threaded_broadcaster_retry::{FibonacciRetryPolicy, IteratorWithDeadlineExt, RetryPolicy},
+    peer_health::PeerHealthScores,
+    peer_queue::{PeerQueue, QueuedMsg},
+    threaded_broadcaster_retry::{DynamicRetryPolicy, IteratorWithDeadlineExt, RetryPolicy},
     traits::{ConsensusConnection, RetryableConsensusConnection},
 };
 use common::{
@@ -17,10 +20,11 @@ use consensus_enclave_api::WellFormedEncryptedTx;
 use mcconnection::{Connection, ConnectionManager, SyncConnection};
 use mcserial;
 use mcuri::ConnectionUri;
+use scp::SlotIndex;
 use std::{
     sync::{
         atomic::{AtomicBool, Ordering},
-        Arc,
+        Arc, Condvar, Mutex,
     },
     thread,
     time::{Duration, Instant},
@@ -38,7 +42,11 @@ const HISTORY_SIZE: usize = 10000;
 /// A thread is created per each peer to handle message delivery. This means a non-responsive
 /// peer does not slow message delivery to other peers, and does not block the caller of
 /// `broadcast_consensus_msg`.
-pub struct ThreadedBroadcaster<RP: RetryPolicy = FibonacciRetryPolicy> {
+/// Each peer thread sends consensus messages ahead of transaction relays, newest slot first, and
+/// drops consensus messages for slots that are stale compared to the slot set by `set_cur_slot`
+/// (see `PeerQueue`). Per-peer traffic is exported
+/// through the `peers_peer_gauge` metric.
+pub struct ThreadedBroadcaster<RP: RetryPolicy = DynamicRetryPolicy> {
     /// List of peers to communicate with.
     peer_threads: Vec<PeerThread>,
 
@@ -53,6 +61,12 @@ pub struct ThreadedBroadcaster<RP: RetryPolicy = FibonacciRetryPolicy> {
     // Retry policy.
     retry_policy: RP,
 
+    // Health of each peer, based on whether messages were delivered.
+    health_scores: PeerHealthScores,
+
+    // The slot the local node is working on.
+    cur_slot: SlotIndex,
+
     // Logger.
     logger: Logger,
 }
@@ -63,35 +77,95 @@ impl<RP: RetryPolicy> ThreadedBroadcaster<RP> {
         retry_policy: &RP,
         logger: Logger,
     ) -> Self {
-        let peer_threads: Vec<PeerThread> = manager
-            .conns()
+        let health_scores = PeerHealthScores::default();
+        let peer_threads: Vec<PeerThread> = Self::broadcast_conns(manager)
             .into_iter()
-            .filter(|conn| {
-                conn.uri()
-                    .get_param("broadcast-consensus-msgs")
-                    .unwrap_or_else(|| "1".to_string())
-                    == "1"
-            })
-            .map(|conn| {
-                let peer_name = conn.to_string();
-                PeerThread::new(
-                    conn,
-                    retry_policy,
-                    logger.new(o!(
-                        "mc.peers.peer_name" => peer_name,
-                    )),
-                )
-            })
+            .map(|conn| PeerThread::new(conn, retry_policy, &health_scores, &logger))
             .collect();
         Self {
             peer_threads,
             seen_msg_hashes: LruCache::new(HISTORY_SIZE),
             seen_tx_hashes: LruCache::new(HISTORY_SIZE),
             retry_policy: retry_policy.clone(),
+            health_scores,
+            cur_slot: 0,
             logger,
         }
     }
 
+    /// Set the slot the local node is working on. Queued consensus messages for slots too far
+    /// behind it are dropped.
+    pub fn set_cur_slot(&mut self, cur_slot: SlotIndex) {
+        self.cur_slot = cur_slot;
+        for peer_thread in self.peer_threads.iter() {
+            peer_thread.set_cur_slot(cur_slot);
+        }
+    }
+
+    /// Health scores of our peers, updated as messages are delivered or fail to be delivered.
+    pub fn health_scores(&self) -> PeerHealthScores {
+        self.health_scores.clone()
+    }
+
+    /// Synchronize the set of peers with the connections currently held by `manager`. Peers whose
+    /// URI is unchanged keep their thread and any messages queued for them.
+    pub fn update_peers<CC: ConsensusConnection + 'static>(
+        &mut self,
+        manager: &ConnectionManager<CC>,
+    ) {
+        let conns = Self::broadcast_conns(manager);
+        let uris: Vec<String> = conns.iter().map(|conn| conn.uri().to_string()).collect();
+
+        // Stop threads for peers that were removed or changed.
+        let (kept, mut removed): (Vec<PeerThread>, Vec<PeerThread>) = self
+            .peer_threads
+            .drain(..)
+            .partition(|peer_thread| uris.contains(&peer_thread.uri));
+        for peer_thread in removed.iter_mut() {
+            log::info!(self.logger, "Removing peer {}", peer_thread.uri);
+            peer_thread.stop();
+        }
+        self.peer_threads = kept;
+        let responder_ids: Vec<ResponderId> = self
+            .peer_threads
+            .iter()
+            .map(|peer_thread| peer_thread.responder_id().clone())
+            .collect();
+        self.health_scores.retain(&responder_ids);
+
+        // Start threads for new peers.
+        for conn in conns {
+            let uri = conn.uri().to_string();
+            if self
+                .peer_threads
+                .iter()
+                .all(|peer_thread| peer_thread.uri != uri)
+            {
+                log::info!(self.logger, "Adding peer {}", uri);
+                let peer_thread =
+                    PeerThread::new(conn, &self.retry_policy, &self.health_scores, &self.logger);
+                peer_thread.set_cur_slot(self.cur_slot);
+                self.peer_threads.push(peer_thread);
+            }
+        }
+    }
+
+    /// Connections we should broadcast consensus messages to.
+    fn broadcast_conns<CC: ConsensusConnection + 'static>(
+        manager: &ConnectionManager<CC>,
+    ) -> Vec<SyncConnection<CC>> {
+        manager
+            .conns()
+            .into_iter()
+            .filter(|conn| {
+                conn.uri()
+                    .get_param("broadcast-consensus-msgs")
+                    .unwrap_or_else(|| "1".to_string())
+                    == "1"
+            })
+            .collect()
+    }
+
     pub fn stop(&mut self) {
         for peer_thread in self.peer_threads.iter_mut() {
             peer_thread.stop();
@@ -134,7 +208,9 @@ impl<RP: RetryPolicy> ThreadedBroadcaster<RP> {
                 continue;
             }
 
-            if let Err(err) = peer_thread.send_consensus_msg(arc_msg.clone(), deadline) {
+            if let Err(err) =
+                peer_thread.send_consensus_msg(arc_msg.clone(), serialized_msg.len(), deadline)
+            {
                 log::error!(
                     self.logger,
                     "failed broadcasting consensus msg to {}: {:?}",
@@ -223,33 +299,25 @@ impl<RP: RetryPolicy> Drop for ThreadedBroadcaster<RP> {
     }
 }
 
-/// Possible messages sent to peer worker threads.
-enum ThreadMsg {
-    /// Send a consensus message.
-    HandleConsensusMsg {
-        msg: Arc<ConsensusMsg>,
-        deadline: Instant,
-    },
+/// State shared between a `PeerThread` and its worker thread.
+#[derive(Default)]
+struct PeerThreadState {
+    /// Messages waiting to be sent.
+    queue: PeerQueue,
 
-    /// Propose a transaction.
-    HandleProposeTx {
-        encrypted_tx: Arc<WellFormedEncryptedTx>,
-        origin_node: Arc<NodeID>,
-        deadline: Instant,
-    },
+    /// Test helpers: set once every message queued before them was processed.
+    barriers: Vec<Arc<AtomicBool>>,
 
-    /// Request the worker thread to stop.
-    StopTrigger,
-
-    /// Debug helper: Set the atomic variable to one. This allows our tests
-    /// to know all messaged submitted prior to this one have been processed.
-    Barrier(Arc<AtomicBool>),
+    /// Set to request the worker thread to stop.
+    stop_requested: bool,
 }
 
 /// A single peer thread.
 struct PeerThread {
     responder_id: ResponderId,
-    sender: crossbeam_channel::Sender<ThreadMsg>,
+    uri: String,
+    state: Arc<(Mutex<PeerThreadState>, Condvar)>,
+    counters: Arc<PeerCounters>,
     join_handle: Option<thread::JoinHandle<()>>,
 }
 
@@ -257,25 +325,43 @@ impl PeerThread {
     pub fn new<CC: ConsensusConnection + 'static, RP: RetryPolicy>(
         conn: SyncConnection<CC>,
         retry_policy: &RP,
-        logger: Logger,
+        health_scores: &PeerHealthScores,
+        logger: &Logger,
     ) -> Self {
-        let (sender, receiver) = crossbeam_channel::unbounded();
+        let state = Arc::new((Mutex::new(PeerThreadState::default()), Condvar::new()));
 
         let responder_id = conn.remote_responder_id();
+        let uri = conn.uri().to_string();
+        let counters = Arc::new(PeerCounters::new(&responder_id));
+        let logger = logger.new(o!(
+            "mc.peers.peer_name" => conn.to_string(),
+        ));
 
         let retry_policy = retry_policy.clone();
+        let health_scores = health_scores.clone();
+        let thread_state = state.clone();
+        let thread_counters = counters.clone();
 
         let join_handle = Some(
             thread::Builder::new()
                 .name(format!("{}", conn))
                 .spawn(move || {
-                    Self::thread_entrypoint(conn, retry_policy, receiver, logger);
+                    Self::thread_entrypoint(
+                        conn,
+                        retry_policy,
+                        health_scores,
+                        thread_state,
+                        thread_counters,
+                        logger,
+                    );
                 })
                 .expect("failed spawning peer thread"),
         );
         Self {
             responder_id,
-            sender,
+            uri,
+            state,
+            counters,
             join_handle,
         }
     }
@@ -287,11 +373,21 @@ impl PeerThread {
     pub fn send_consensus_msg(
         &self,
         msg: Arc<ConsensusMsg>,
+        size: usize,
         deadline: Instant,
     ) -> Result<(), Error> {
-        self.sender
-            .send(ThreadMsg::HandleConsensusMsg { msg, deadline })
-            .map_err(|_err| Error::ChannelSend)
+        self.enqueue(|queue, counters| {
+            let dropped = queue.push_consensus_msg(msg, size, deadline);
+            counters.msgs_dropped.add(dropped as i64);
+        })
+    }
+
+    pub fn set_cur_slot(&self, cur_slot: SlotIndex) {
+        // Only fails once the thread is stopping, at which point the queue no longer matters.
+        let _ = self.enqueue(|queue, counters| {
+            let dropped = queue.set_cur_slot(cur_slot);
+            counters.msgs_dropped.add(dropped as i64);
+        });
     }
 
     pub fn handle_propose_tx_msg(
@@ -300,13 +396,7 @@ impl PeerThread {
         origin_node: Arc<NodeID>,
         deadline: Instant,
     ) -> Result<(), Error> {
-        self.sender
-            .send(ThreadMsg::HandleProposeTx {
-                encrypted_tx,
-                origin_node,
-                deadline,
-            })
-            .map_err(|_err| Error::ChannelSend)
+        self.enqueue(|queue, _counters| queue.push_propose_tx(encrypted_tx, origin_node, deadline))
     }
 
     /// Tests helper: wait until a barrier message is processed (indicating
@@ -316,9 +406,12 @@ impl PeerThread {
         // Arbitrary number
         let deadline = Instant::now() + Duration::from_secs(10);
 
-        self.sender
-            .send(ThreadMsg::Barrier(atomic.clone()))
-            .expect("failed sending barrier message");
+        {
+            let (lock, condvar) = &*self.state;
+            let mut state = lock.lock().expect("mutex poisoned");
+            state.barriers.push(atomic.clone());
+            condvar.notify_one();
+        }
 
         while Instant::now() < deadline {
             if atomic.load(Ordering::Relaxed) {
@@ -333,94 +426,138 @@ impl PeerThread {
 
     pub fn stop(&mut self) {
         if let Some(join_handle) = self.join_handle.take() {
-            let _ = self.sender.send(ThreadMsg::StopTrigger);
+            {
+                let (lock, condvar) = &*self.state;
+                lock.lock().expect("mutex poisoned").stop_requested = true;
+                condvar.notify_one();
+            }
             let _ = join_handle.join();
         }
     }
 
+    // Add a message to the queue and wake up the worker thread.
+    fn enqueue(&self, f: impl FnOnce(&mut PeerQueue, &PeerCounters)) -> Result<(), Error> {
+        let (lock, condvar) = &*self.state;
+        let mut state = lock.lock().expect("mutex poisoned");
+        if state.stop_requested {
+            return Err(Error::ChannelSend);
+        }
+        f(&mut state.queue, &self.counters);
+        Self::update_queue_counters(&state.queue, &self.counters);
+        condvar.notify_one();
+        Ok(())
+    }
+
+    fn update_queue_counters(queue: &PeerQueue, counters: &PeerCounters) {
+        counters
+            .queued_consensus_msgs
+            .set(queue.num_consensus_msgs() as i64);
+        counters.queued_tx_relays.set(queue.num_tx_relays() as i64);
+    }
+
     fn thread_entrypoint<CC: ConsensusConnection + 'static, RP: RetryPolicy>(
         conn: SyncConnection<CC>,
         retry_policy: RP,
-        receiver: crossbeam_channel::Receiver<ThreadMsg>,
+        health_scores: PeerHealthScores,
+        state: Arc<(Mutex<PeerThreadState>, Condvar)>,
+        counters: Arc<PeerCounters>,
         logger: Logger,
     ) {
+        let (lock, condvar) = &*state;
         loop {
-            match receiver.recv() {
-                Ok(msg) => match msg {
-                    ThreadMsg::HandleConsensusMsg { msg, deadline } => {
-                        Self::do_send_consensus_msg(&conn, &retry_policy, msg, deadline, &logger)
+            // Wait for the next message, releasing barriers once everything queued before them
+            // has been processed.
+            let msg = {
+                let mut state = lock.lock().expect("mutex poisoned");
+                loop {
+                    if state.stop_requested {
+                        return;
                     }
-                    ThreadMsg::HandleProposeTx {
-                        encrypted_tx,
-                        origin_node,
-                        deadline,
-                    } => Self::do_handle_propose_tx_msg(
-                        &conn,
-                        &retry_policy,
-                        &encrypted_tx,
-                        &origin_node,
-                        deadline,
-                        &logger,
-                    ),
-                    ThreadMsg::StopTrigger => {
-                        break;
+                    if let Some(msg) = state.queue.pop() {
+                        Self::update_queue_counters(&state.queue, &counters);
+                        break msg;
                     }
-                    ThreadMsg::Barrier(barrier) => {
+                    for barrier in state.barriers.drain(..) {
                         barrier.store(true, Ordering::Relaxed);
                     }
-                },
-                Err(err) => {
-                    log::error!(logger, "Peer thread failed receiving: {:?}", err);
-                    break;
+                    state = condvar.wait(state).expect("mutex poisoned");
                 }
-            }
-        }
-    }
-
-    fn do_send_consensus_msg<CC: ConsensusConnection + 'static, RP: RetryPolicy>(
-        conn: &SyncConnection<CC>,
-        retry_policy: &RP,
-        arc_msg: Arc<ConsensusMsg>,
-        deadline: Instant,
-        logger: &Logger,
-    ) {
-        if Instant::now() > deadline {
-            return;
-        }
-
-        let retry_iterator = retry_policy.get_delay_iterator().with_deadline(deadline);
-
-        if let Err(err) = conn.send_consensus_msg(&*arc_msg, retry_iterator) {
-            log::error!(
-                logger,
-                "failed broadcasting send consensus msg to {}: {:?}",
-                conn,
-                err
+            };
+
+            Self::do_send_msg(
+                &conn,
+                &retry_policy,
+                &health_scores,
+                &counters,
+                msg,
+                &logger,
             );
         }
     }
 
-    fn do_handle_propose_tx_msg<CC: ConsensusConnection + 'static, RP: RetryPolicy>(
+    fn do_send_msg<CC: ConsensusConnection + 'static, RP: RetryPolicy>(
         conn: &SyncConnection<CC>,
         retry_policy: &RP,
-        encrypted_tx: &WellFormedEncryptedTx,
-        origin_node: &NodeID,
-        deadline: Instant,
+        health_scores: &PeerHealthScores,
+        counters: &PeerCounters,
+        msg: QueuedMsg,
         logger: &Logger,
     ) {
-        if Instant::now() > deadline {
+        let deadline = match &msg {
+            QueuedMsg::Consensus { deadline, .. } | QueuedMsg::ProposeTx { deadline, .. } => {
+                *deadline
+            }
+        };
+        let responder_id = conn.remote_responder_id();
+        if Instant::now() > deadline || !retry_policy.should_attempt(&responder_id) {
+            counters.msgs_dropped.inc();
             return;
         }
 
         let retry_iterator = retry_policy.get_delay_iterator().with_deadline(deadline);
 
-        if let Err(err) = conn.send_propose_tx(encrypted_tx, origin_node, retry_iterator) {
-            log::error!(
-                logger,
-                "failed broadcasting propose tx to {}: {:?}",
-                conn,
-                err
-            );
+        let start = Instant::now();
+        let result = match &msg {
+            QueuedMsg::Consensus { msg, size, .. } => {
+                conn.send_consensus_msg(msg, retry_iterator).map(|_| *size)
+            }
+            QueuedMsg::ProposeTx {
+                encrypted_tx,
+                origin_node,
+                ..
+            } => conn
+                .send_propose_tx(encrypted_tx, origin_node, retry_iterator)
+                .map(|_| encrypted_tx.0.len()),
+        };
+
+        retry_policy.record_outcome(&responder_id, result.is_ok());
+        match result {
+            Ok(size) => {
+                health_scores.record_success(&responder_id);
+                counters.msgs_sent.inc();
+                counters.bytes_sent.add(size as i64);
+                counters
+                    .send_latency_ms
+                    .set(start.elapsed().as_millis() as i64);
+            }
+            Err(err) => {
+                health_scores.record_failure(&responder_id);
+                counters.msgs_dropped.inc();
+                match msg {
+                    QueuedMsg::Consensus { .. } => log::error!(
+                        logger,
+                        "failed broadcasting send consensus msg to {}: {:?}",
+                        conn,
+                        err
+                    ),
+                    QueuedMsg::ProposeTx { .. } => log::error!(
+                        logger,
+                        "failed broadcasting propose tx to {}: {:?}",
+                        conn,
+                        err
+                    ),
+                }
+            }
         }
     }
 }
@@ -1,7 +1,14 @@
 // Copyright (c) 2018-2020 MobileCoin Inc.
 
-use retry::delay::Fibonacci;
-use std::time::{Duration, Instant};
+use crate::counters::OP_COUNTERS;
+use common::{HashMap, ResponderId};
+use retry::delay::{jitter, Fibonacci};
+use std::{
+    cmp::min,
+    iter,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 
 /// Default number of attempts to make at delivering each message.
 pub const DEFAULT_RETRY_MAX_ATTEMPTS: usize = 3;
@@ -12,6 +19,15 @@ pub const DEFAULT_RETRY_INITIAL_DELAY: Duration = Duration::from_secs(1);
 /// Maximal message age before we do not attempt to deliver it.
 pub const DEFAULT_MAX_MESSAGE_AGE: Duration = Duration::from_secs(30);
 
+/// Longest delay between two attempts of the exponential backoff policy.
+pub const DEFAULT_BACKOFF_MAX_DELAY: Duration = Duration::from_secs(10);
+
+/// Number of consecutive failed deliveries after which the circuit breaker stops trying a peer.
+pub const DEFAULT_CIRCUIT_BREAKER_FAILURE_THRESHOLD: u32 = 5;
+
+/// How long the circuit breaker waits before trying a peer again.
+pub const DEFAULT_CIRCUIT_BREAKER_COOLDOWN: Duration = Duration::from_secs(30);
+
 /// An abstraction of retry parameters used by `ThreadedBroadcaster`.
 pub trait RetryPolicy: Clone + Send + 'static {
     /// Return an iterator to be used by `retry::retry()`.
@@ -19,6 +35,15 @@ pub trait RetryPolicy: Clone + Send + 'static {
 
     /// Maximal message age to broadcast.
     fn get_max_message_age(&self) -> Duration;
+
+    /// Whether a message should be sent to `responder_id` at all. Messages that should not be
+    /// sent are dropped.
+    fn should_attempt(&self, _responder_id: &ResponderId) -> bool {
+        true
+    }
+
+    /// Called with the outcome of delivering a message to `responder_id`, after all retries.
+    fn record_outcome(&self, _responder_id: &ResponderId, _success: bool) {}
 }
 
 /// A simple retry policy, where each retry uses a delay that is the sum of the two previous delays.
@@ -74,6 +99,233 @@ impl FibonacciRetryPolicy {
     }
 }
 
+/// A retry policy where the delay doubles after each attempt, up to `max_delay`. Each delay is
+/// randomized between zero and its nominal value, so that peers recovering from an outage are not
+/// hit by every sender at once.
+#[derive(Clone)]
+pub struct ExponentialBackoffRetryPolicy {
+    /// Nominal delay before the first retry.
+    initial_delay: Duration,
+
+    /// Longest nominal delay between two attempts.
+    max_delay: Duration,
+
+    /// Maxmimal number of attempts to perform.
+    max_attempts: usize,
+
+    /// Maximal message age to process (messages older than this would get dropped).
+    max_message_age: Duration,
+}
+impl Default for ExponentialBackoffRetryPolicy {
+    fn default() -> Self {
+        Self {
+            initial_delay: DEFAULT_RETRY_INITIAL_DELAY,
+            max_delay: DEFAULT_BACKOFF_MAX_DELAY,
+            max_attempts: DEFAULT_RETRY_MAX_ATTEMPTS,
+            max_message_age: DEFAULT_MAX_MESSAGE_AGE,
+        }
+    }
+}
+impl RetryPolicy for ExponentialBackoffRetryPolicy {
+    fn get_delay_iterator(&self) -> Box<dyn Iterator<Item = Duration>> {
+        let max_delay = self.max_delay;
+        Box::new(
+            iter::successors(Some(min(self.initial_delay, max_delay)), move |delay| {
+                Some(min(*delay * 2, max_delay))
+            })
+            .map(jitter)
+            // See FibonacciRetryPolicy: the first attempt does not use the iterator.
+            .take(self.max_attempts.saturating_sub(1)),
+        )
+    }
+
+    fn get_max_message_age(&self) -> Duration {
+        self.max_message_age
+    }
+}
+impl ExponentialBackoffRetryPolicy {
+    pub fn max_attempts(&mut self, val: usize) -> &mut Self {
+        self.max_attempts = val;
+        self
+    }
+
+    pub fn initial_delay(&mut self, val: Duration) -> &mut Self {
+        self.initial_delay = val;
+        self
+    }
+
+    pub fn max_delay(&mut self, val: Duration) -> &mut Self {
+        self.max_delay = val;
+        self
+    }
+
+    pub fn max_message_age(&mut self, val: Duration) -> &mut Self {
+        self.max_message_age = val;
+        self
+    }
+}
+
+/// Circuit breaker state of a single peer.
+#[derive(Default)]
+struct CircuitState {
+    /// Number of deliveries that failed since the last successful one.
+    consecutive_failures: u32,
+
+    /// While set and in the future, messages to the peer are dropped without being attempted.
+    open_until: Option<Instant>,
+}
+
+/// A retry policy that gives up on a peer for `cooldown` after `failure_threshold` consecutive
+/// failed deliveries, so that a peer that is down does not use up the time of the thread
+/// delivering its messages. Once the cooldown is over, the next message is attempted: if it is
+/// delivered the peer is tried normally again, otherwise the peer is given up on for another
+/// cooldown. Retries of individual messages are delegated to the inner policy.
+///
+/// Clones share the state of each peer. Whether a peer is being given up on is exported through
+/// the `circuit_breaker_open` peer gauge.
+#[derive(Clone)]
+pub struct CircuitBreakerRetryPolicy<RP: RetryPolicy = FibonacciRetryPolicy> {
+    /// Policy used to retry individual messages.
+    inner: RP,
+
+    /// Number of consecutive failed deliveries after which a peer is given up on.
+    failure_threshold: u32,
+
+    /// How long a peer is given up on.
+    cooldown: Duration,
+
+    /// State of each peer.
+    circuits: Arc<Mutex<HashMap<ResponderId, CircuitState>>>,
+}
+impl<RP: RetryPolicy + Default> Default for CircuitBreakerRetryPolicy<RP> {
+    fn default() -> Self {
+        Self::new(RP::default())
+    }
+}
+impl<RP: RetryPolicy> RetryPolicy for CircuitBreakerRetryPolicy<RP> {
+    fn get_delay_iterator(&self) -> Box<dyn Iterator<Item = Duration>> {
+        self.inner.get_delay_iterator()
+    }
+
+    fn get_max_message_age(&self) -> Duration {
+        self.inner.get_max_message_age()
+    }
+
+    fn should_attempt(&self, responder_id: &ResponderId) -> bool {
+        let circuits = self.circuits.lock().expect("mutex poisoned");
+        match circuits
+            .get(responder_id)
+            .and_then(|circuit| circuit.open_until)
+        {
+            Some(open_until) => Instant::now() >= open_until,
+            None => self.inner.should_attempt(responder_id),
+        }
+    }
+
+    fn record_outcome(&self, responder_id: &ResponderId, success: bool) {
+        self.inner.record_outcome(responder_id, success);
+
+        let mut circuits = self.circuits.lock().expect("mutex poisoned");
+        let circuit = circuits.entry(responder_id.clone()).or_default();
+        let open_gauge = OP_COUNTERS.peer_gauge("circuit_breaker_open", &responder_id.to_string());
+        if success {
+            *circuit = CircuitState::default();
+            open_gauge.set(0);
+            return;
+        }
+
+        circuit.consecutive_failures = circuit.consecutive_failures.saturating_add(1);
+        if circuit.consecutive_failures >= self.failure_threshold {
+            circuit.open_until = Some(Instant::now() + self.cooldown);
+            open_gauge.set(1);
+            OP_COUNTERS
+                .peer_gauge("circuit_breaker_trips", &responder_id.to_string())
+                .inc();
+        }
+    }
+}
+impl<RP: RetryPolicy> CircuitBreakerRetryPolicy<RP> {
+    pub fn new(inner: RP) -> Self {
+        Self {
+            inner,
+            failure_threshold: DEFAULT_CIRCUIT_BREAKER_FAILURE_THRESHOLD,
+            cooldown: DEFAULT_CIRCUIT_BREAKER_COOLDOWN,
+            circuits: Arc::new(Mutex::new(HashMap::default())),
+        }
+    }
+
+    pub fn failure_threshold(&mut self, val: u32) -> &mut Self {
+        self.failure_threshold = val;
+        self
+    }
+
+    pub fn cooldown(&mut self, val: Duration) -> &mut Self {
+        self.cooldown = val;
+        self
+    }
+}
+
+/// A retry policy chosen at runtime, e.g. from the command line.
+#[derive(Clone)]
+pub enum DynamicRetryPolicy {
+    Fibonacci(FibonacciRetryPolicy),
+    ExponentialBackoff(ExponentialBackoffRetryPolicy),
+    CircuitBreaker(CircuitBreakerRetryPolicy<ExponentialBackoffRetryPolicy>),
+}
+impl Default for DynamicRetryPolicy {
+    fn default() -> Self {
+        Self::Fibonacci(FibonacciRetryPolicy::default())
+    }
+}
+impl RetryPolicy for DynamicRetryPolicy {
+    fn get_delay_iterator(&self) -> Box<dyn Iterator<Item = Duration>> {
+        match self {
+            Self::Fibonacci(policy) => policy.get_delay_iterator(),
+            Self::ExponentialBackoff(policy) => policy.get_delay_iterator(),
+            Self::CircuitBreaker(policy) => policy.get_delay_iterator(),
+        }
+    }
+
+    fn get_max_message_age(&self) -> Duration {
+        match self {
+            Self::Fibonacci(policy) => policy.get_max_message_age(),
+            Self::ExponentialBackoff(policy) => policy.get_max_message_age(),
+            Self::CircuitBreaker(policy) => policy.get_max_message_age(),
+        }
+    }
+
+    fn should_attempt(&self, responder_id: &ResponderId) -> bool {
+        match self {
+            Self::Fibonacci(policy) => policy.should_attempt(responder_id),
+            Self::ExponentialBackoff(policy) => policy.should_attempt(responder_id),
+            Self::CircuitBreaker(policy) => policy.should_attempt(responder_id),
+        }
+    }
+
+    fn record_outcome(&self, responder_id: &ResponderId, success: bool) {
+        match self {
+            Self::Fibonacci(policy) => policy.record_outcome(responder_id, success),
+            Self::ExponentialBackoff(policy) => policy.record_outcome(responder_id, success),
+            Self::CircuitBreaker(policy) => policy.record_outcome(responder_id, success),
+        }
+    }
+}
+impl From<FibonacciRetryPolicy> for DynamicRetryPolicy {
+    fn from(src: FibonacciRetryPolicy) -> Self {
+        Self::Fibonacci(src)
+    }
+}
+impl From<ExponentialBackoffRetryPolicy> for DynamicRetryPolicy {
+    fn from(src: ExponentialBackoffRetryPolicy) -> Self {
+        Self::ExponentialBackoff(src)
+    }
+}
+impl From<CircuitBreakerRetryPolicy<ExponentialBackoffRetryPolicy>> for DynamicRetryPolicy {
+    fn from(src: CircuitBreakerRetryPolicy<ExponentialBackoffRetryPolicy>) -> Self {
+        Self::CircuitBreaker(src)
+    }
+}
+
 /// An `Iterator` extension that adds the `.with_deadline()` method,
 /// forcing the `Iterator` to to terminate if a given deadline is exceeded.
 pub struct WithDeadline<I> {
@@ -113,3 +365,63 @@ pub trait IteratorWithDeadlineExt: Iterator {
 }
 
 impl<I: Iterator> IteratorWithDeadlineExt for I {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn responder_id() -> ResponderId {
+        ResponderId::from_str("node1.test.com:8443").unwrap()
+    }
+
+    #[test]
+    // Nominal delays double up to the maximum, and jitter never exceeds them.
+    fn test_exponential_backoff_delays() {
+        let delays: Vec<Duration> = ExponentialBackoffRetryPolicy::default()
+            .initial_delay(Duration::from_millis(100))
+            .max_delay(Duration::from_millis(300))
+            .max_attempts(5)
+            .get_delay_iterator()
+            .collect();
+
+        assert_eq!(delays.len(), 4);
+        for (delay, nominal) in delays.iter().zip(&[100, 200, 300, 300]) {
+            assert!(*delay <= Duration::from_millis(*nominal));
+        }
+    }
+
+    #[test]
+    // The circuit opens after the failure threshold is reached, and closes on success once the
+    // cooldown is over.
+    fn test_circuit_breaker() {
+        let mut policy = CircuitBreakerRetryPolicy::<FibonacciRetryPolicy>::default();
+        policy
+            .failure_threshold(2)
+            .cooldown(Duration::from_millis(100));
+        let peer = responder_id();
+
+        // Clones share state.
+        let other_policy = policy.clone();
+
+        assert!(policy.should_attempt(&peer));
+        policy.record_outcome(&peer, false);
+        assert!(policy.should_attempt(&peer));
+        policy.record_outcome(&peer, false);
+        assert!(!policy.should_attempt(&peer));
+        assert!(!other_policy.should_attempt(&peer));
+
+        // After the cooldown, a single attempt is allowed. Failing it opens the circuit again.
+        std::thread::sleep(Duration::from_millis(150));
+        assert!(policy.should_attempt(&peer));
+        policy.record_outcome(&peer, false);
+        assert!(!policy.should_attempt(&peer));
+
+        // Succeeding closes the circuit.
+        std::thread::sleep(Duration::from_millis(150));
+        assert!(policy.should_attempt(&peer));
+        policy.record_outcome(&peer, true);
+        policy.record_outcome(&peer, false);
+        assert!(policy.should_attempt(&peer));
+    }
+}
@@ -4,7 +4,7 @@
 
 use crate::{
     error::{Result, RetryResult},
-    ConsensusMsg,
+    ConsensusMsg, PeerExchangeMsg,
 };
 use common::{NodeID, ResponderId};
 use consensus_enclave_api::{TxContext, WellFormedEncryptedTx};
@@ -35,6 +35,9 @@ pub trait ConsensusConnection: Connection {
 
     /// Retrieve the most recent consensus message sent by this peer.
     fn fetch_latest_msg(&mut self) -> Result<Option<ConsensusMsg>>;
+
+    /// Send the peers we know of to the remote peer, and retrieve the peers it knows of.
+    fn exchange_peers(&mut self, msg: &PeerExchangeMsg) -> Result<PeerExchangeMsg>;
 }
 
 /// Retriable versions of the ConsensusConnection methods
@@ -68,4 +71,11 @@ pub trait RetryableConsensusConnection {
         &self,
         retry_iterator: impl IntoIterator<Item = Duration>,
     ) -> RetryResult<Option<ConsensusMsg>>;
+
+    /// Retryable version of the peer exchange
+    fn exchange_peers(
+        &self,
+        msg: &PeerExchangeMsg,
+        retry_iterator: impl IntoIterator<Item = Duration>,
+    ) -> RetryResult<PeerExchangeMsg>;
 }
@@ -8,10 +8,13 @@ use hex;
 use keys::{Ed25519Pair, FromRandom};
 use ledger_db::{test_utils::mock_ledger::MockLedger, Ledger};
 use mcconnection::{
-    BlockchainConnection, Connection, Error as ConnectionError, Result as ConnectionResult,
+    BlockContents, BlockSubscription, BlockchainConnection, Connection, Error as ConnectionError,
+    Result as ConnectionResult,
 };
 use mcuri::{ConnectionUri, ConsensusPeerUri as PeerUri};
-use peers::{ConsensusConnection, ConsensusMsg, Error as PeerError, Result as PeerResult};
+use peers::{
+    ConsensusConnection, ConsensusMsg, Error as PeerError, PeerExchangeMsg, Result as PeerResult,
+};
 use rand::SeedableRng;
 use rand_hc::Hc128Rng as FixedRng;
 use scp::{
@@ -76,6 +79,27 @@ impl<L: Ledger + Sync> MockPeerConnection<L> {
         }
     }
 
+    fn block_contents(&self, block_index: BlockIndex) -> ConnectionResult<BlockContents> {
+        let block = self
+            .ledger
+            .get_block(block_index)
+            .or(Err(ConnectionError::NotFound))?;
+        let transactions = self
+            .ledger
+            .get_transactions_by_block(block_index)
+            .or(Err(ConnectionError::NotFound))?;
+        let signature_set = self
+            .ledger
+            .get_block_signature_set(block_index)
+            .or(Err(ConnectionError::NotFound))?;
+        Ok(BlockContents {
+            block,
+            transactions,
+            signature: None,
+            signature_set,
+        })
+    }
+
     pub fn state(&self) -> MockPeerState {
         self.state.lock().expect("mutex poisoined").clone()
     }
@@ -170,7 +194,47 @@ impl<L: Ledger + Sync> BlockchainConnection for MockPeerConnection<L> {
     }
 
     fn fetch_block_height(&mut self) -> ConnectionResult<BlockIndex> {
-        unimplemented!()
+        thread::sleep(Duration::from_millis(self.latency_millis));
+
+        let num_blocks = self.ledger.num_blocks().unwrap();
+        Ok(num_blocks.saturating_sub(1))
+    }
+
+    fn fetch_block_by_id(&mut self, block_id: &BlockID) -> ConnectionResult<BlockContents> {
+        thread::sleep(Duration::from_millis(self.latency_millis));
+
+        let block_index = self
+            .ledger
+            .get_block_index_by_block_id(block_id)
+            .or(Err(ConnectionError::NotFound))?;
+        self.block_contents(block_index)
+    }
+
+    fn fetch_blocks_with_transactions(
+        &mut self,
+        range: Range<BlockIndex>,
+    ) -> ConnectionResult<Vec<BlockContents>> {
+        thread::sleep(Duration::from_millis(self.latency_millis));
+
+        let num_blocks = self.ledger.num_blocks().unwrap();
+        if range.start >= num_blocks {
+            return Err(ConnectionError::NotFound);
+        }
+
+        (range.start..min(range.end, num_blocks))
+            .map(|block_index| self.block_contents(block_index))
+            .collect()
+    }
+
+    /// Sends the blocks currently in the ledger, then ends the subscription.
+    fn subscribe_blocks(&mut self, from_index: BlockIndex) -> ConnectionResult<BlockSubscription> {
+        thread::sleep(Duration::from_millis(self.latency_millis));
+
+        let num_blocks = self.ledger.num_blocks().unwrap();
+        let blocks: Vec<ConnectionResult<BlockContents>> = (from_index..num_blocks)
+            .map(|block_index| self.block_contents(block_index))
+            .collect();
+        Ok(Box::new(blocks.into_iter()))
     }
 }
 
@@ -213,6 +277,10 @@ impl<L: Ledger + Sync> ConsensusConnection for MockPeerConnection<L> {
     fn fetch_latest_msg(&mut self) -> PeerResult<Option<ConsensusMsg>> {
         unimplemented!()
     }
+
+    fn exchange_peers(&mut self, _msg: &PeerExchangeMsg) -> PeerResult<PeerExchangeMsg> {
+        unimplemented!()
+    }
 }
 pub fn create_consensus_msg(
     ledger: &impl Ledger,
@@ -393,7 +461,9 @@ mod threaded_broadcaster_tests {
     use ledger_db::test_utils::get_mock_ledger;
     use mcconnection::ConnectionManager;
     use peers::{
-        ThreadedBroadcaster, ThreadedBroadcasterFibonacciRetryPolicy as FibonacciRetryPolicy,
+        ThreadedBroadcaster,
+        ThreadedBroadcasterCircuitBreakerRetryPolicy as CircuitBreakerRetryPolicy,
+        ThreadedBroadcasterFibonacciRetryPolicy as FibonacciRetryPolicy,
         DEFAULT_RETRY_MAX_ATTEMPTS,
     };
     use scp::QuorumSet;
@@ -634,6 +704,13 @@ mod threaded_broadcaster_tests {
             assert_eq!(peer3.msgs().len(), 1);
             assert_eq!(peer3.msgs()[0], msg1);
             assert_eq!(peer3.state().send_consensus_msg_call_count, 1);
+
+            // The failed delivery lowered peer2's health score.
+            let health_scores = broadcaster.health_scores();
+            assert!(
+                health_scores.score(&peer2.remote_responder_id())
+                    < health_scores.score(&peer3.remote_responder_id())
+            );
         }
 
         // Configure peer2 to fail after one attempt, we should see 2 calls made
@@ -667,4 +744,63 @@ mod threaded_broadcaster_tests {
             assert_eq!(peer3.state().send_consensus_msg_call_count, 2);
         }
     }
+
+    // A peer that keeps failing is not retried until the circuit breaker's cooldown is over.
+    #[test_with_logger]
+    fn test_circuit_breaker(logger: Logger) {
+        let (local_node_id, local_signer_key) = test_node_id_and_signer(1);
+        let node2_uri = test_peer_uri(2);
+        let node2 = NodeID::from(&node2_uri);
+
+        let ledger = get_mock_ledger(1);
+
+        let quorum_set = QuorumSet::new_with_node_ids(1, vec![node2]);
+        let mut peer2 =
+            MockPeerConnection::new(node2_uri, local_node_id.clone(), ledger.clone(), 0);
+        let peer_manager = ConnectionManager::new(vec![peer2.clone()], logger.clone());
+
+        let mut fibonacci = FibonacciRetryPolicy::default();
+        fibonacci.initial_delay(Duration::from_millis(10));
+        let mut retry_policy = CircuitBreakerRetryPolicy::new(fibonacci);
+        retry_policy
+            .failure_threshold(1)
+            .cooldown(Duration::from_secs(60));
+
+        let mut broadcaster = ThreadedBroadcaster::new(&peer_manager, &retry_policy, logger);
+
+        peer2.set_send_consensus_msg_should_error_count(100);
+
+        // The first message is attempted and fails, which opens the circuit.
+        let msg1 = create_consensus_msg(
+            &ledger,
+            local_node_id.clone(),
+            quorum_set.clone(),
+            1,
+            "msg1",
+            &local_signer_key,
+        );
+        broadcaster.broadcast_consensus_msg(&msg1.issuer_responder_id(), &msg1);
+        broadcaster.barrier();
+        assert_eq!(
+            peer2.state().send_consensus_msg_call_count,
+            DEFAULT_RETRY_MAX_ATTEMPTS
+        );
+
+        // The second message is dropped without being attempted.
+        let msg2 = create_consensus_msg(
+            &ledger,
+            local_node_id,
+            quorum_set,
+            1,
+            "msg2",
+            &local_signer_key,
+        );
+        broadcaster.broadcast_consensus_msg(&msg2.issuer_responder_id(), &msg2);
+        broadcaster.barrier();
+        assert_eq!(
+            peer2.state().send_consensus_msg_call_count,
+            DEFAULT_RETRY_MAX_ATTEMPTS
+        );
+        assert!(peer2.msgs().is_empty());
+    }
 }
@@ -0,0 +1,299 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+use bulletproofs::RangeProof;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use curve25519_dalek::{ristretto::CompressedRistretto, scalar::Scalar};
+use keys::{FromRandom, RistrettoPrivate, RistrettoPublic};
+use rand::{rngs::StdRng, RngCore, SeedableRng};
+use transaction::{
+    constants::MIN_RING_SIZE,
+    range_proofs::{check_range_proofs, check_range_proofs_batch},
+    ring_signature::{
+        Address, Commitment, RingMLSAG, RingMLSAGBatchItem, SignatureBatchItem,
+        SignatureRctBulletproofs, GENERATORS,
+    },
+};
+
+/// Batch sizes to benchmark.
+const NUM_SIGNATURES: [usize; 3] = [1, 16, 64];
+
+/// A signed message, with what its signature is verified against.
+struct SignedMessage {
+    message: [u8; 32],
+    rings: Vec<Vec<(Address, Commitment)>>,
+    output_commitments: Vec<Commitment>,
+    signature: SignatureRctBulletproofs,
+}
+
+impl SignedMessage {
+    /// Signs a random message spending `num_inputs` inputs to as many outputs.
+    fn random(num_inputs: usize, rng: &mut StdRng) -> Self {
+        let mut message = [0u8; 32];
+        rng.fill_bytes(&mut message);
+
+        let mut rings = Vec::new();
+        let mut real_input_indices = Vec::new();
+        let mut input_secrets = Vec::new();
+        for _i in 0..num_inputs {
+            let mut ring: Vec<(Address, Commitment)> = (0..MIN_RING_SIZE - 1)
+                .map(|_| {
+                    let address = RistrettoPublic::from_random(rng);
+                    let commitment = Commitment::from(
+                        GENERATORS.commit(Scalar::from(rng.next_u64()), Scalar::random(rng)),
+                    );
+                    (address, commitment)
+                })
+                .collect();
+
+            let onetime_private_key = RistrettoPrivate::from_random(rng);
+            let value = rng.next_u64();
+            let blinding = Scalar::random(rng);
+            let real_index = rng.next_u64() as usize % MIN_RING_SIZE;
+            ring.insert(
+                real_index,
+                (
+                    RistrettoPublic::from(&onetime_private_key),
+                    Commitment::from(GENERATORS.commit(Scalar::from(value), blinding)),
+                ),
+            );
+
+            rings.push(ring);
+            real_input_indices.push(real_index);
+            input_secrets.push((onetime_private_key, value, blinding));
+        }
+
+        // One output with the same value as each input.
+        let output_values_and_blindings: Vec<(u64, Scalar)> = input_secrets
+            .iter()
+            .map(|(_, value, _)| (*value, Scalar::random(rng)))
+            .collect();
+        let output_commitments = output_values_and_blindings
+            .iter()
+            .map(|(value, blinding)| {
+                Commitment::from(GENERATORS.commit(Scalar::from(*value), *blinding))
+            })
+            .collect();
+
+        let signature = SignatureRctBulletproofs::sign(
+            &message,
+            &rings,
+            &real_input_indices,
+            &input_secrets,
+            &output_values_and_blindings,
+            rng,
+        )
+        .unwrap();
+
+        Self {
+            message,
+            rings,
+            output_commitments,
+            signature,
+        }
+    }
+
+    fn batch_item(&self) -> SignatureBatchItem {
+        SignatureBatchItem {
+            signature: &self.signature,
+            message: &self.message,
+            rings: &self.rings,
+            output_commitments: &self.output_commitments,
+        }
+    }
+
+    /// The range proof, with the pseudo-output and output commitments it is checked against.
+    fn range_proof(&self) -> (RangeProof, Vec<CompressedRistretto>) {
+        let range_proof = RangeProof::from_bytes(&self.signature.range_proof_bytes).unwrap();
+        let commitments = self
+            .signature
+            .pseudo_output_commitments
+            .iter()
+            .cloned()
+            .chain(
+                self.output_commitments
+                    .iter()
+                    .map(|commitment| commitment.as_ref().compress()),
+            )
+            .collect();
+        (range_proof, commitments)
+    }
+
+    /// The MLSAG of each ring, with what it is verified against.
+    fn mlsag_batch_items<'a>(
+        &'a self,
+        pseudo_output_commitments: &'a [Commitment],
+    ) -> Vec<RingMLSAGBatchItem<'a>> {
+        self.signature
+            .ring_signatures
+            .iter()
+            .zip(&self.rings)
+            .zip(pseudo_output_commitments)
+            .map(
+                |((signature, ring), output_commitment)| RingMLSAGBatchItem {
+                    signature,
+                    message: &self.message,
+                    ring,
+                    output_commitment,
+                },
+            )
+            .collect()
+    }
+}
+
+/// Signs `num_signatures` random messages, each spending two inputs.
+fn random_signed_messages(num_signatures: usize, rng: &mut StdRng) -> Vec<SignedMessage> {
+    (0..num_signatures)
+        .map(|_| SignedMessage::random(2, rng))
+        .collect()
+}
+
+fn signature_benchmarks(c: &mut Criterion) {
+    let mut rng: StdRng = SeedableRng::from_seed([100u8; 32]);
+    let mut group = c.benchmark_group("SignatureRctBulletproofs");
+
+    for num_signatures in &NUM_SIGNATURES {
+        let signed_messages = random_signed_messages(*num_signatures, &mut rng);
+        let items: Vec<SignatureBatchItem> = signed_messages
+            .iter()
+            .map(SignedMessage::batch_item)
+            .collect();
+
+        group.throughput(Throughput::Elements(*num_signatures as u64));
+
+        group.bench_with_input(
+            BenchmarkId::new("::verify", num_signatures),
+            &signed_messages,
+            |b, signed_messages| {
+                b.iter(|| {
+                    for signed_message in signed_messages {
+                        signed_message
+                            .signature
+                            .verify(
+                                &signed_message.message,
+                                &signed_message.rings,
+                                &signed_message.output_commitments,
+                                &mut rng,
+                            )
+                            .unwrap();
+                    }
+                })
+            },
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("::verify_batch", num_signatures),
+            &items,
+            |b, items| {
+                b.iter(|| {
+                    let results = SignatureRctBulletproofs::verify_batch(items, &mut rng);
+                    assert!(results.iter().all(Result::is_ok));
+                })
+            },
+        );
+    }
+
+    group.finish();
+}
+
+fn range_proof_benchmarks(c: &mut Criterion) {
+    let mut rng: StdRng = SeedableRng::from_seed([101u8; 32]);
+    let mut group = c.benchmark_group("RangeProof");
+
+    for num_signatures in &NUM_SIGNATURES {
+        let range_proofs: Vec<(RangeProof, Vec<CompressedRistretto>)> =
+            random_signed_messages(*num_signatures, &mut rng)
+                .iter()
+                .map(SignedMessage::range_proof)
+                .collect();
+        let proofs: Vec<(&RangeProof, &[CompressedRistretto])> = range_proofs
+            .iter()
+            .map(|(range_proof, commitments)| (range_proof, &commitments[..]))
+            .collect();
+
+        group.throughput(Throughput::Elements(*num_signatures as u64));
+
+        group.bench_with_input(
+            BenchmarkId::new("check_range_proofs", num_signatures),
+            &proofs,
+            |b, proofs| {
+                b.iter(|| {
+                    for (range_proof, commitments) in proofs {
+                        check_range_proofs(range_proof, commitments, &mut rng).unwrap();
+                    }
+                })
+            },
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("check_range_proofs_batch", num_signatures),
+            &proofs,
+            |b, proofs| b.iter(|| check_range_proofs_batch(proofs, &mut rng).unwrap()),
+        );
+    }
+
+    group.finish();
+}
+
+fn mlsag_benchmarks(c: &mut Criterion) {
+    let mut rng: StdRng = SeedableRng::from_seed([102u8; 32]);
+    let mut group = c.benchmark_group("RingMLSAG");
+
+    for num_signatures in &NUM_SIGNATURES {
+        let signed_messages = random_signed_messages(*num_signatures, &mut rng);
+        let pseudo_output_commitments: Vec<Vec<Commitment>> = signed_messages
+            .iter()
+            .map(|signed_message| {
+                signed_message
+                    .signature
+                    .pseudo_output_commitments
+                    .iter()
+                    .map(|commitment| Commitment::from(commitment.decompress().unwrap()))
+                    .collect()
+            })
+            .collect();
+        let items: Vec<RingMLSAGBatchItem> = signed_messages
+            .iter()
+            .zip(&pseudo_output_commitments)
+            .flat_map(|(signed_message, pseudo_output_commitments)| {
+                signed_message.mlsag_batch_items(pseudo_output_commitments)
+            })
+            .collect();
+
+        group.throughput(Throughput::Elements(items.len() as u64));
+
+        group.bench_with_input(
+            BenchmarkId::new("::verify", num_signatures),
+            &items,
+            |b, items| {
+                b.iter(|| {
+                    for item in items {
+                        item.signature
+                            .verify(item.message, item.ring, item.output_commitment)
+                            .unwrap();
+                    }
+                })
+            },
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("::verify_batch", num_signatures),
+            &items,
+            |b, items| {
+                b.iter(|| {
+                    let results = RingMLSAG::verify_batch(items);
+                    assert!(results.iter().all(Result::is_ok));
+                })
+            },
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(10);
+    targets = signature_benchmarks, range_proof_benchmarks, mlsag_benchmarks
+}
+
+criterion_main!(benches);
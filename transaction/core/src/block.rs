@@ -3,6 +3,7 @@
 //! Blockchain data structures.
 
 use crate::{blake2b_256::Blake2b256, tx::TxOutMembershipElement, RedactedTx};
+use alloc::vec::Vec;
 use core::{
     convert::TryFrom,
     fmt::{Debug, Display, Formatter, Result as FmtResult},
@@ -312,18 +313,83 @@ impl Debug for BlockSignature {
     }
 }
 
+/// Signatures over the same block, collected from distinct signers.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockSignatureSet {
+    /// The signatures, at most one per signer.
+    signatures: Vec<BlockSignature>,
+}
+
+impl BlockSignatureSet {
+    /// Create a new BlockSignatureSet from a list of signatures. Only the first signature of each
+    /// signer is kept.
+    pub fn new(signatures: Vec<BlockSignature>) -> Self {
+        let mut set = Self::default();
+        for signature in signatures {
+            set.insert(signature);
+        }
+        set
+    }
+
+    /// Add a signature to the set.
+    ///
+    /// Returns false if the set already contains a signature from the same signer.
+    pub fn insert(&mut self, signature: BlockSignature) -> bool {
+        if self.contains_signer(signature.signer()) {
+            return false;
+        }
+        self.signatures.push(signature);
+        true
+    }
+
+    /// Check if the set contains a signature made by `signer`.
+    pub fn contains_signer(&self, signer: &Ed25519Public) -> bool {
+        self.signatures.iter().any(|sig| sig.signer() == signer)
+    }
+
+    /// Get the signatures.
+    pub fn signatures(&self) -> &[BlockSignature] {
+        &self.signatures
+    }
+
+    /// The number of signatures in the set.
+    pub fn len(&self) -> usize {
+        self.signatures.len()
+    }
+
+    /// Check if the set contains no signatures.
+    pub fn is_empty(&self) -> bool {
+        self.signatures.is_empty()
+    }
+
+    /// Count the signatures in the set that were made by one of `trusted_signers` and that are
+    /// valid signatures over `block`.
+    pub fn num_valid_trusted_signatures(
+        &self,
+        block: &Block,
+        trusted_signers: &[Ed25519Public],
+    ) -> usize {
+        self.signatures
+            .iter()
+            .filter(|sig| trusted_signers.contains(sig.signer()))
+            .filter(|sig| sig.verify(block).is_ok())
+            .count()
+    }
+}
+
 #[cfg(test)]
 mod block_tests {
     use crate::{
         account_keys::AccountKey,
         range::Range,
         tx::{TxOut, TxOutMembershipElement, TxOutMembershipHash},
-        Block, BlockContentsHash, BlockID, RedactedTx, BLOCK_VERSION,
+        Block, BlockContentsHash, BlockID, BlockSignature, BlockSignatureSet, RedactedTx,
+        BLOCK_VERSION,
     };
     use alloc::vec::Vec;
     use core::convert::TryFrom;
     use generic_array::GenericArray;
-    use keys::{FromRandom, RistrettoPrivate};
+    use keys::{Ed25519Pair, FromRandom, RistrettoPrivate};
     use rand::{rngs::StdRng, CryptoRng, RngCore, SeedableRng};
 
     fn get_block<RNG: CryptoRng + RngCore>(rng: &mut RNG) -> Block {
@@ -433,6 +499,53 @@ mod block_tests {
         assert!(!block.is_block_id_valid());
     }
 
+    #[test]
+    /// A BlockSignatureSet should keep at most one signature per signer.
+    fn test_block_signature_set_deduplicates_signers() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let block = get_block(&mut rng);
+        let signer_a = Ed25519Pair::from_random(&mut rng);
+        let signer_b = Ed25519Pair::from_random(&mut rng);
+
+        let sig_a = BlockSignature::from_block_and_keypair(&block, &signer_a).unwrap();
+        let sig_b = BlockSignature::from_block_and_keypair(&block, &signer_b).unwrap();
+
+        let mut set = BlockSignatureSet::new(vec![sig_a.clone(), sig_a.clone()]);
+        assert_eq!(set.len(), 1);
+        assert!(!set.insert(sig_a));
+        assert!(set.insert(sig_b));
+        assert_eq!(set.len(), 2);
+        assert!(set.contains_signer(&signer_b.public_key()));
+    }
+
+    #[test]
+    /// Only valid signatures from trusted signers should be counted.
+    fn test_block_signature_set_num_valid_trusted_signatures() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let block = get_block(&mut rng);
+        let other_block = get_block(&mut rng);
+        let signers: Vec<Ed25519Pair> =
+            (0..4).map(|_| Ed25519Pair::from_random(&mut rng)).collect();
+
+        let set = BlockSignatureSet::new(vec![
+            // Valid, trusted.
+            BlockSignature::from_block_and_keypair(&block, &signers[0]).unwrap(),
+            // Valid, trusted.
+            BlockSignature::from_block_and_keypair(&block, &signers[1]).unwrap(),
+            // Signs a different block.
+            BlockSignature::from_block_and_keypair(&other_block, &signers[2]).unwrap(),
+            // Valid, but not trusted.
+            BlockSignature::from_block_and_keypair(&block, &signers[3]).unwrap(),
+        ]);
+
+        let trusted_signers: Vec<_> = signers[..3].iter().map(|s| s.public_key()).collect();
+        assert_eq!(
+            set.num_valid_trusted_signatures(&block, &trusted_signers),
+            2
+        );
+        assert_eq!(set.num_valid_trusted_signatures(&block, &[]), 0);
+    }
+
     #[test]
     #[ignore]
     // TODO: Block::new should return an error if `tx_hashes` contains duplicates.
@@ -62,5 +62,5 @@ cfg_if::cfg_if! {
     }
 }
 
-/// Minimum allowed fee.
+/// Fee paid by every transaction, on top of its per-input and per-output fees.
 pub const BASE_FEE: u64 = 10;
@@ -0,0 +1,110 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Transaction fees.
+//!
+//! Each block version has a fee schedule. Version 0 blocks charge a flat `BASE_FEE`. From version
+//! 1, the minimum fee of a transaction grows with its number of inputs and outputs, since each input
+//! adds a ring signature and each output adds a range proof that validators must verify.
+
+use crate::{constants::BASE_FEE, BLOCK_VERSION};
+use serde::{Deserialize, Serialize};
+
+/// Fee schedule of version 0 blocks.
+const FEE_SCHEDULE_V0: FeeSchedule = FeeSchedule {
+    base_fee: BASE_FEE,
+    per_input_fee: 0,
+    per_output_fee: 0,
+};
+
+/// Fee schedule of version 1 blocks.
+const FEE_SCHEDULE_V1: FeeSchedule = FeeSchedule {
+    base_fee: BASE_FEE,
+    per_input_fee: 10,
+    per_output_fee: 2,
+};
+
+/// Determines the minimum fee a transaction must pay.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
+pub struct FeeSchedule {
+    /// Paid by every transaction.
+    pub base_fee: u64,
+
+    /// Paid for each input.
+    pub per_input_fee: u64,
+
+    /// Paid for each output, including change.
+    pub per_output_fee: u64,
+}
+
+impl FeeSchedule {
+    /// The fee schedule of blocks with the given version, if that version is known.
+    pub fn for_block_version(block_version: u32) -> Option<Self> {
+        match block_version {
+            0 => Some(FEE_SCHEDULE_V0),
+            1 => Some(FEE_SCHEDULE_V1),
+            _ => None,
+        }
+    }
+
+    /// The fee schedule of blocks with version `BLOCK_VERSION`.
+    pub fn current() -> Self {
+        Self::for_block_version(BLOCK_VERSION).expect("BLOCK_VERSION has no fee schedule")
+    }
+
+    /// The minimum fee of a transaction with the given number of inputs and outputs.
+    pub fn minimum_fee(&self, num_inputs: usize, num_outputs: usize) -> u64 {
+        self.base_fee
+            .saturating_add(self.per_input_fee.saturating_mul(num_inputs as u64))
+            .saturating_add(self.per_output_fee.saturating_mul(num_outputs as u64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn current_fee_schedule_exists() {
+        assert_eq!(
+            FeeSchedule::for_block_version(BLOCK_VERSION),
+            Some(FeeSchedule::current())
+        );
+    }
+
+    #[test]
+    fn fee_schedules_by_block_version() {
+        // Version 0 blocks charge a flat fee.
+        let fee_schedule_v0 = FeeSchedule::for_block_version(0).unwrap();
+        assert_eq!(fee_schedule_v0.minimum_fee(1, 1), BASE_FEE);
+        assert_eq!(fee_schedule_v0.minimum_fee(16, 17), BASE_FEE);
+
+        // Version 1 blocks charge for each input and output.
+        let fee_schedule_v1 = FeeSchedule::for_block_version(1).unwrap();
+        assert_eq!(fee_schedule_v1.minimum_fee(1, 1), BASE_FEE + 12);
+        assert_eq!(fee_schedule_v1.minimum_fee(16, 17), BASE_FEE + 194);
+
+        assert_eq!(FeeSchedule::for_block_version(2), None);
+    }
+
+    #[test]
+    fn minimum_fee_grows_with_inputs_and_outputs() {
+        let fee_schedule = FeeSchedule {
+            base_fee: 10,
+            per_input_fee: 3,
+            per_output_fee: 2,
+        };
+        assert_eq!(fee_schedule.minimum_fee(0, 0), 10);
+        assert_eq!(fee_schedule.minimum_fee(1, 2), 17);
+        assert_eq!(fee_schedule.minimum_fee(16, 1), 60);
+    }
+
+    #[test]
+    fn minimum_fee_saturates() {
+        let fee_schedule = FeeSchedule {
+            base_fee: 10,
+            per_input_fee: u64::max_value(),
+            per_output_fee: 2,
+        };
+        assert_eq!(fee_schedule.minimum_fee(2, 1), u64::max_value());
+    }
+}
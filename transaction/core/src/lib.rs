@@ -22,8 +22,10 @@ pub mod blake2b_256;
 mod block;
 pub mod constants;
 pub mod encrypted_fog_hint;
+pub mod fee_schedule;
 pub mod fog_hint;
 pub mod membership_proofs;
+pub mod memo;
 pub mod onetime_keys;
 pub mod range;
 pub mod range_proofs;
@@ -0,0 +1,222 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! A memo attached to an output, e.g. the ID of the invoice it pays.
+//!
+//! Memos have a fixed size, so that their length reveals nothing about their contents. They are
+//! encrypted to the recipient by masking them with a hash of the output's shared secret, like the
+//! output's amount.
+
+use alloc::vec::Vec;
+use blake2::{Blake2b, Digest};
+use core::{convert::TryFrom, fmt};
+use digestible::Digestible;
+use generic_array::{
+    typenum::{Unsigned, U64},
+    GenericArray,
+};
+use keys::RistrettoPublic;
+use prost::{
+    bytes::{Buf, BufMut},
+    encoding::{bytes, skip_field, DecodeContext, WireType},
+    DecodeError, Message,
+};
+use serde::{Deserialize, Serialize};
+
+/// The length of a memo, in bytes. Matches the output size of Blake2b, which masks it.
+pub type MemoSize = U64;
+pub const MEMO_LEN: usize = MemoSize::USIZE;
+
+/// Domain separator for the memo mask, so that it is unrelated to the amount's masks.
+const MEMO_MASK_DOMAIN_TAG: &[u8] = b"mc_memo";
+
+type Bytes = GenericArray<u8, MemoSize>;
+
+/// The plaintext of a memo.
+#[derive(Clone, Default, Eq, Hash, PartialEq, Serialize, Deserialize)]
+pub struct Memo {
+    bytes: Bytes,
+}
+
+impl AsRef<[u8]> for Memo {
+    fn as_ref(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+/// Creates a memo from at most `MEMO_LEN` bytes, padded with zeros.
+impl<'bytes> TryFrom<&'bytes [u8]> for Memo {
+    type Error = ();
+
+    fn try_from(slice: &'bytes [u8]) -> Result<Self, ()> {
+        if slice.len() > MEMO_LEN {
+            return Err(());
+        }
+        let mut bytes = Bytes::default();
+        bytes[..slice.len()].copy_from_slice(slice);
+        Ok(Self { bytes })
+    }
+}
+
+impl fmt::Debug for Memo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Memo({})", hex_fmt::HexFmt(self.as_ref()))
+    }
+}
+
+/// A memo, encrypted to the recipient of an output.
+#[derive(
+    Clone, PartialOrd, Ord, PartialEq, Eq, Hash, Serialize, Deserialize, Default, Digestible,
+)]
+pub struct EncryptedMemo {
+    bytes: Bytes,
+}
+
+impl EncryptedMemo {
+    /// Encrypts `memo` as `memo XOR Blake2B("mc_memo" || shared_secret)`.
+    ///
+    /// # Arguments
+    /// * `memo` - The memo.
+    /// * `shared_secret` - The output's shared secret, e.g. `rB`.
+    pub fn new(memo: &Memo, shared_secret: &RistrettoPublic) -> Self {
+        Self {
+            bytes: xor(&memo.bytes, &get_memo_mask(shared_secret)),
+        }
+    }
+
+    /// Decrypts the memo.
+    ///
+    /// # Arguments
+    /// * `shared_secret` - The output's shared secret, e.g. `rB`.
+    pub fn decrypt(&self, shared_secret: &RistrettoPublic) -> Memo {
+        Memo {
+            bytes: xor(&self.bytes, &get_memo_mask(shared_secret)),
+        }
+    }
+}
+
+impl AsRef<[u8]> for EncryptedMemo {
+    fn as_ref(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl<'bytes> TryFrom<&'bytes [u8]> for EncryptedMemo {
+    type Error = ();
+
+    fn try_from(slice: &'bytes [u8]) -> Result<Self, ()> {
+        if slice.len() == MEMO_LEN {
+            Ok(Self {
+                bytes: GenericArray::clone_from_slice(slice),
+            })
+        } else {
+            Err(())
+        }
+    }
+}
+
+impl Message for EncryptedMemo {
+    fn encode_raw<B>(&self, buf: &mut B)
+    where
+        B: BufMut,
+    {
+        bytes::encode(1, &self.bytes.to_vec(), buf)
+    }
+    fn merge_field<B>(
+        &mut self,
+        tag: u32,
+        wire_type: WireType,
+        buf: &mut B,
+        ctx: DecodeContext,
+    ) -> Result<(), DecodeError>
+    where
+        B: Buf,
+    {
+        if tag == 1 {
+            let mut vbuf = Vec::new();
+            bytes::merge(wire_type, &mut vbuf, buf, ctx)?;
+            *self = Self::try_from(&vbuf[..]).map_err(|_| {
+                DecodeError::new(alloc::format!(
+                    "EncryptedMemo: expected {} bytes, got {}",
+                    MEMO_LEN,
+                    vbuf.len()
+                ))
+            })?;
+            Ok(())
+        } else {
+            skip_field(wire_type, tag, buf, ctx)
+        }
+    }
+    fn encoded_len(&self) -> usize {
+        bytes::encoded_len(1, &alloc::vec![0u8; MEMO_LEN])
+    }
+    fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
+
+impl fmt::Debug for EncryptedMemo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "EncryptedMemo({})", hex_fmt::HexFmt(self.as_ref()))
+    }
+}
+
+/// Computes `Blake2B("mc_memo" || shared_secret)`.
+fn get_memo_mask(shared_secret: &RistrettoPublic) -> Bytes {
+    let mut hasher = Blake2b::new();
+    hasher.input(MEMO_MASK_DOMAIN_TAG);
+    hasher.input(&shared_secret.to_bytes());
+    hasher.result()
+}
+
+fn xor(a: &Bytes, b: &Bytes) -> Bytes {
+    let mut result = Bytes::default();
+    for ((r, a), b) in result.iter_mut().zip(a.iter()).zip(b.iter()) {
+        *r = a ^ b;
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use keys::FromRandom;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    // A memo should only be recovered with the shared secret it was encrypted with.
+    fn test_encrypt_decrypt() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let shared_secret = RistrettoPublic::from_random(&mut rng);
+        let memo = Memo::try_from(&b"invoice #1234"[..]).unwrap();
+
+        let encrypted_memo = EncryptedMemo::new(&memo, &shared_secret);
+        assert_ne!(encrypted_memo.as_ref(), memo.as_ref());
+        assert_eq!(encrypted_memo.decrypt(&shared_secret), memo);
+
+        let other_shared_secret = RistrettoPublic::from_random(&mut rng);
+        assert_ne!(encrypted_memo.decrypt(&other_shared_secret), memo);
+    }
+
+    #[test]
+    // Memos should be zero-padded to `MEMO_LEN` bytes.
+    fn test_memo_try_from() {
+        let memo = Memo::try_from(&b"invoice #1234"[..]).unwrap();
+        assert_eq!(&memo.as_ref()[..13], b"invoice #1234");
+        assert!(memo.as_ref()[13..].iter().all(|byte| *byte == 0));
+
+        assert!(Memo::try_from(&[7u8; MEMO_LEN][..]).is_ok());
+        assert!(Memo::try_from(&[7u8; MEMO_LEN + 1][..]).is_err());
+    }
+
+    #[test]
+    fn test_encrypted_memo_serde() {
+        let a = EncryptedMemo::try_from(&[17u8; MEMO_LEN][..]).unwrap();
+        let a_ser = mcserial::serialize(&a).unwrap();
+        let b: EncryptedMemo = mcserial::deserialize(&a_ser).unwrap();
+        assert_eq!(a, b);
+
+        let mut a_encoded = Vec::new();
+        a.encode(&mut a_encoded).unwrap();
+        assert_eq!(EncryptedMemo::decode(&a_encoded[..]).unwrap(), a);
+    }
+}
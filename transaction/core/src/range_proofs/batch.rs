@@ -0,0 +1,319 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Verification of many independent RangeProofs with a single multiscalar multiplication.
+//!
+//! `bulletproofs` verifies a proof by checking that a multiscalar multiplication over the
+//! generators and the proof's points is the identity, but it does not expose the scalars. This
+//! replays its verifier for each proof, and adds up the checks of all proofs with random weights,
+//! so that the terms of the generators are shared by all proofs.
+//!
+//! # References
+//! * [Bulletproofs](https://eprint.iacr.org/2017/1066.pdf), section 6.2.
+
+#![allow(non_snake_case)]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use bulletproofs::{ProofError, RangeProof};
+use core::{convert::TryInto, iter};
+use curve25519_dalek::{
+    ristretto::{CompressedRistretto, RistrettoPoint},
+    scalar::Scalar,
+    traits::{IsIdentity, VartimeMultiscalarMul},
+};
+use digest::{ExtendableOutput, Input, XofReader};
+use merlin::Transcript;
+use rand_core::{CryptoRng, RngCore};
+use sha3::Shake256;
+
+use super::{error::Error, resize_slice_to_pow2, BIT_SIZE, DOMAIN_SEPARATOR_LABEL};
+use crate::ring_signature::{BP_GENERATORS, GENERATORS};
+
+lazy_static! {
+    /// The `G` generators of each party of `BP_GENERATORS`.
+    static ref PARTY_G: Vec<Vec<RistrettoPoint>> = party_generators(b'G');
+
+    /// The `H` generators of each party of `BP_GENERATORS`.
+    static ref PARTY_H: Vec<Vec<RistrettoPoint>> = party_generators(b'H');
+}
+
+/// Derives the generators of each party of `BP_GENERATORS` the way `bulletproofs` does, from a
+/// SHAKE256 chain labelled with `label` and the party's index.
+fn party_generators(label: u8) -> Vec<Vec<RistrettoPoint>> {
+    (0..BP_GENERATORS.party_capacity)
+        .map(|party| {
+            let mut shake = Shake256::default();
+            shake.input(b"GeneratorsChain");
+            shake.input([label]);
+            shake.input((party as u32).to_le_bytes());
+            let mut reader = shake.xof_result();
+
+            (0..BP_GENERATORS.gens_capacity)
+                .map(|_| {
+                    let mut uniform_bytes = [0u8; 64];
+                    reader.read(&mut uniform_bytes);
+                    RistrettoPoint::from_uniform_bytes(&uniform_bytes)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Verifies many aggregated 64-bit RangeProofs, each for its own value commitments.
+///
+/// Returns Ok only if every proof is valid, and does not tell which ones are not. On error,
+/// check each proof with `check_range_proofs`.
+///
+/// # Arguments
+/// `proofs` - RangeProofs, each with the commitments to secret values it proves lie in [0,2^64).
+/// `rng` - Randomness.
+pub fn check_range_proofs_batch<T: RngCore + CryptoRng>(
+    proofs: &[(&RangeProof, &[CompressedRistretto])],
+    rng: &mut T,
+) -> Result<(), Error> {
+    let n = BIT_SIZE;
+
+    // Scalars of the generators, which are shared by all proofs. `G_scalars` and `H_scalars`
+    // hold the scalars of each party's generators, one party after the other.
+    let mut B_scalar = Scalar::zero();
+    let mut B_blinding_scalar = Scalar::zero();
+    let mut G_scalars: Vec<Scalar> = Vec::new();
+    let mut H_scalars: Vec<Scalar> = Vec::new();
+
+    // Points of each proof and its value commitments, with their scalars.
+    let mut scalars: Vec<Scalar> = Vec::new();
+    let mut points: Vec<Option<RistrettoPoint>> = Vec::new();
+
+    for (range_proof, commitments) in proofs {
+        // The length of `commitments` must be a power of 2. If not, resize it.
+        let value_commitments = resize_slice_to_pow2::<CompressedRistretto>(commitments)?;
+        let m = value_commitments.len();
+        if BP_GENERATORS.party_capacity < m {
+            return Err(ProofError::InvalidGeneratorsLength.into());
+        }
+        let proof = ProofParts::from_bytes(&range_proof.to_bytes())?;
+
+        // Replay the transcript of `RangeProof::verify_multiple` to recompute the challenges.
+        let mut transcript = Transcript::new(DOMAIN_SEPARATOR_LABEL);
+        transcript.append_message(b"dom-sep", b"rangeproof v1");
+        transcript.append_u64(b"n", n as u64);
+        transcript.append_u64(b"m", m as u64);
+        for V in &value_commitments {
+            transcript.append_message(b"V", V.as_bytes());
+        }
+        validate_and_append_point(&mut transcript, b"A", &proof.A)?;
+        validate_and_append_point(&mut transcript, b"S", &proof.S)?;
+        let y = challenge_scalar(&mut transcript, b"y");
+        let z = challenge_scalar(&mut transcript, b"z");
+        let zz = z * z;
+        validate_and_append_point(&mut transcript, b"T_1", &proof.T_1)?;
+        validate_and_append_point(&mut transcript, b"T_2", &proof.T_2)?;
+        let x = challenge_scalar(&mut transcript, b"x");
+        transcript.append_message(b"t_x", proof.t_x.as_bytes());
+        transcript.append_message(b"t_x_blinding", proof.t_x_blinding.as_bytes());
+        transcript.append_message(b"e_blinding", proof.e_blinding.as_bytes());
+        let w = challenge_scalar(&mut transcript, b"w");
+        let (u_sq, u_inv_sq, s) = proof.verification_scalars(n * m, &mut transcript)?;
+
+        // Batches the checks within the proof.
+        let c = Scalar::random(rng);
+        // Batches this proof with the others.
+        let weight = Scalar::random(rng);
+
+        let points_and_scalars = iter::once((proof.A, Scalar::one()))
+            .chain(iter::once((proof.S, x)))
+            .chain(iter::once((proof.T_1, c * x)))
+            .chain(iter::once((proof.T_2, c * x * x)))
+            .chain(proof.L_vec.iter().cloned().zip(u_sq))
+            .chain(proof.R_vec.iter().cloned().zip(u_inv_sq))
+            .chain(
+                value_commitments
+                    .iter()
+                    .cloned()
+                    .zip(exp_iter(z).map(|z_exp| c * zz * z_exp)),
+            );
+        for (point, scalar) in points_and_scalars {
+            points.push(point.decompress());
+            scalars.push(weight * scalar);
+        }
+
+        B_scalar +=
+            weight * (w * (proof.t_x - proof.a * proof.b) + c * (delta(n, m, &y, &z) - proof.t_x));
+        B_blinding_scalar += weight * (-proof.e_blinding - c * proof.t_x_blinding);
+
+        if G_scalars.len() < n * m {
+            G_scalars.resize(n * m, Scalar::zero());
+            H_scalars.resize(n * m, Scalar::zero());
+        }
+        let powers_of_2: Vec<Scalar> = exp_iter(Scalar::from(2u64)).take(n).collect();
+        let mut z_exp = Scalar::one();
+        let mut y_inv_exp = Scalar::one();
+        let y_inv = y.invert();
+        for party in 0..m {
+            for (bit, exp_2) in powers_of_2.iter().enumerate() {
+                let i = party * n + bit;
+                G_scalars[i] += weight * (-z - proof.a * s[i]);
+                H_scalars[i] +=
+                    weight * (z + y_inv_exp * (zz * z_exp * exp_2 - proof.b * s[n * m - 1 - i]));
+                y_inv_exp *= y_inv;
+            }
+            z_exp *= z;
+        }
+    }
+
+    let num_parties = G_scalars.len() / n;
+    points.push(Some(GENERATORS.B));
+    points.push(Some(GENERATORS.B_blinding));
+    for party_generators in PARTY_G[..num_parties]
+        .iter()
+        .chain(PARTY_H[..num_parties].iter())
+    {
+        points.extend(party_generators[..n].iter().cloned().map(Some));
+    }
+
+    let check = RistrettoPoint::optional_multiscalar_mul(
+        scalars
+            .iter()
+            .chain(iter::once(&B_scalar))
+            .chain(iter::once(&B_blinding_scalar))
+            .chain(G_scalars.iter())
+            .chain(H_scalars.iter()),
+        points,
+    )
+    .ok_or(ProofError::VerificationError)?;
+
+    if check.is_identity() {
+        Ok(())
+    } else {
+        Err(ProofError::VerificationError.into())
+    }
+}
+
+/// The contents of a RangeProof, which `bulletproofs` does not expose.
+struct ProofParts {
+    A: CompressedRistretto,
+    S: CompressedRistretto,
+    T_1: CompressedRistretto,
+    T_2: CompressedRistretto,
+    t_x: Scalar,
+    t_x_blinding: Scalar,
+    e_blinding: Scalar,
+
+    /// The inner product proof.
+    L_vec: Vec<CompressedRistretto>,
+    R_vec: Vec<CompressedRistretto>,
+    a: Scalar,
+    b: Scalar,
+}
+
+impl ProofParts {
+    /// Parses the output of `RangeProof::to_bytes`.
+    fn from_bytes(bytes: &[u8]) -> Result<Self, ProofError> {
+        // Seven elements, then L and R for each round of the inner product proof, then a and b.
+        if bytes.len() % 32 != 0 || bytes.len() < 9 * 32 || (bytes.len() / 32 - 9) % 2 != 0 {
+            return Err(ProofError::FormatError);
+        }
+        let elements: Vec<[u8; 32]> = bytes
+            .chunks(32)
+            .map(|chunk| chunk.try_into().unwrap())
+            .collect();
+        let scalar =
+            |bytes: [u8; 32]| Scalar::from_canonical_bytes(bytes).ok_or(ProofError::FormatError);
+
+        let num_elements = elements.len();
+        let lg_n = (num_elements - 9) / 2;
+        Ok(Self {
+            A: CompressedRistretto(elements[0]),
+            S: CompressedRistretto(elements[1]),
+            T_1: CompressedRistretto(elements[2]),
+            T_2: CompressedRistretto(elements[3]),
+            t_x: scalar(elements[4])?,
+            t_x_blinding: scalar(elements[5])?,
+            e_blinding: scalar(elements[6])?,
+            L_vec: (0..lg_n)
+                .map(|i| CompressedRistretto(elements[7 + 2 * i]))
+                .collect(),
+            R_vec: (0..lg_n)
+                .map(|i| CompressedRistretto(elements[8 + 2 * i]))
+                .collect(),
+            a: scalar(elements[num_elements - 2])?,
+            b: scalar(elements[num_elements - 1])?,
+        })
+    }
+
+    /// Replays the transcript of the inner product proof for `n` values, and returns the squares
+    /// of its challenges, the squares of their inverses, and the scalars `s`.
+    fn verification_scalars(
+        &self,
+        n: usize,
+        transcript: &mut Transcript,
+    ) -> Result<(Vec<Scalar>, Vec<Scalar>, Vec<Scalar>), ProofError> {
+        let lg_n = self.L_vec.len();
+        if lg_n >= 32 || n != (1 << lg_n) {
+            return Err(ProofError::VerificationError);
+        }
+
+        transcript.append_message(b"dom-sep", b"ipp v1");
+        transcript.append_u64(b"n", n as u64);
+
+        let mut challenges = Vec::with_capacity(lg_n);
+        for (L, R) in self.L_vec.iter().zip(self.R_vec.iter()) {
+            validate_and_append_point(transcript, b"L", L)?;
+            validate_and_append_point(transcript, b"R", R)?;
+            challenges.push(challenge_scalar(transcript, b"u"));
+        }
+
+        let mut challenges_inv = challenges.clone();
+        let allinv = Scalar::batch_invert(&mut challenges_inv);
+
+        let challenges_sq: Vec<Scalar> = challenges.iter().map(|u| u * u).collect();
+        let challenges_inv_sq: Vec<Scalar> = challenges_inv.iter().map(|u| u * u).collect();
+
+        // The challenges are in the order they were created, so s[i] for i with highest bit
+        // 2^k is s[i - 2^k] times the square of the (lg_n - 1 - k)^th challenge.
+        let mut s = Vec::with_capacity(n);
+        s.push(allinv);
+        for i in 1..n {
+            let lg_i = (31 - (i as u32).leading_zeros()) as usize;
+            let k = 1 << lg_i;
+            s.push(s[i - k] * challenges_sq[(lg_n - 1) - lg_i]);
+        }
+
+        Ok((challenges_sq, challenges_inv_sq, s))
+    }
+}
+
+/// Appends `point` to the transcript, provided it is not the identity.
+fn validate_and_append_point(
+    transcript: &mut Transcript,
+    label: &'static [u8],
+    point: &CompressedRistretto,
+) -> Result<(), ProofError> {
+    if point.is_identity() {
+        return Err(ProofError::VerificationError);
+    }
+    transcript.append_message(label, point.as_bytes());
+    Ok(())
+}
+
+/// A challenge scalar from the transcript.
+fn challenge_scalar(transcript: &mut Transcript, label: &'static [u8]) -> Scalar {
+    let mut bytes = [0u8; 64];
+    transcript.challenge_bytes(label, &mut bytes);
+    Scalar::from_bytes_mod_order_wide(&bytes)
+}
+
+/// The powers 1, x, x^2, ... of `x`.
+fn exp_iter(x: Scalar) -> impl Iterator<Item = Scalar> {
+    iter::successors(Some(Scalar::one()), move |exp_x| Some(exp_x * x))
+}
+
+/// delta(y, z) = (z - z^2) * <1^{nm}, y^{nm}> - z^3 * <1^n, 2^n> * <1^m, z^m>
+fn delta(n: usize, m: usize, y: &Scalar, z: &Scalar) -> Scalar {
+    let sum_y: Scalar = exp_iter(*y).take(n * m).sum();
+    let sum_2: Scalar = exp_iter(Scalar::from(2u64)).take(n).sum();
+    let sum_z: Scalar = exp_iter(*z).take(m).sum();
+
+    (z - z * z) * sum_y - z * z * z * sum_2 * sum_z
+}
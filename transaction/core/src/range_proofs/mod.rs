@@ -7,13 +7,18 @@ use curve25519_dalek::{ristretto::CompressedRistretto, scalar::Scalar};
 use merlin::Transcript;
 use rand_core::{CryptoRng, RngCore};
 
+mod batch;
 pub mod error;
 use crate::ring_signature::{Blinding, BP_GENERATORS, GENERATORS};
+pub use batch::check_range_proofs_batch;
 use error::Error;
 
 /// The domain separation label should be unique for each application.
 const DOMAIN_SEPARATOR_LABEL: &[u8] = b"range_proof";
 
+/// The number of bits of each value in a range proof.
+const BIT_SIZE: usize = 64;
+
 /// Create an aggregated 64-bit rangeproof for a set of values.
 ///
 /// Creates a proof that each secret value is in the range [0,2^64).
@@ -43,7 +48,7 @@ pub fn generate_range_proofs<T: RngCore + CryptoRng>(
         &mut Transcript::new(DOMAIN_SEPARATOR_LABEL),
         &values_padded,
         &blindings,
-        64,
+        BIT_SIZE,
         rng,
     )
     .map_err(Error::from)
@@ -70,7 +75,7 @@ pub fn check_range_proofs<T: RngCore + CryptoRng>(
             &GENERATORS,
             &mut Transcript::new(DOMAIN_SEPARATOR_LABEL),
             &resized_commitments,
-            64,
+            BIT_SIZE,
             rng,
         )
         .map_err(Error::from)
@@ -159,4 +164,35 @@ pub mod tests {
             Err(_e) => {} // This is expected.
         }
     }
+
+    #[test]
+    // `check_range_proofs_batch` should accept valid proofs of any size, and reject the batch if
+    // any proof does not agree with its commitments.
+    fn test_check_range_proofs_batch() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+
+        let proofs_and_commitments: Vec<(RangeProof, Vec<CompressedRistretto>)> = [1usize, 3, 4, 9]
+            .iter()
+            .map(|num_values| {
+                let values: Vec<u64> = (0..*num_values).map(|_| rng.next_u64()).collect();
+                let serials: Vec<Blinding> = values
+                    .iter()
+                    .map(|_| Blinding::from(Scalar::random(&mut rng)))
+                    .collect();
+                generate_range_proofs(&values, &serials, &mut rng).unwrap()
+            })
+            .collect();
+
+        let mut proofs: Vec<(&RangeProof, &[CompressedRistretto])> = proofs_and_commitments
+            .iter()
+            .map(|(proof, commitments)| (proof, &commitments[..]))
+            .collect();
+        assert_eq!(check_range_proofs_batch(&proofs, &mut rng), Ok(()));
+
+        // Give a proof for four values the commitments of the other one.
+        proofs[1].1 = &proofs_and_commitments[2].1;
+        assert!(check_range_proofs_batch(&proofs, &mut rng).is_err());
+        assert!(check_range_proofs(proofs[0].0, proofs[0].1, &mut rng).is_ok());
+        assert!(check_range_proofs(proofs[1].0, proofs[1].1, &mut rng).is_err());
+    }
 }
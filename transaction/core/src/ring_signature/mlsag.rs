@@ -6,7 +6,10 @@ use alloc::{vec, vec::Vec};
 use core::convert::TryInto;
 
 use blake2::{Blake2b, Digest};
-use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::{
+    ristretto::{CompressedRistretto, RistrettoPoint, VartimeRistrettoPrecomputation},
+    traits::{Identity, VartimeMultiscalarMul, VartimePrecomputedMultiscalarMul},
+};
 use digestible::Digestible;
 use keys::RistrettoPrivate;
 use mcserial::{
@@ -28,6 +31,12 @@ use crate::{
     },
 };
 
+lazy_static! {
+    /// Precomputed multiples of H, for `RingMLSAG::verify_batch`.
+    static ref H_PRECOMPUTATION: VartimeRistrettoPrecomputation =
+        VartimeRistrettoPrecomputation::new(&[GENERATORS.B_blinding]);
+}
+
 fn hash_to_point(address: &Address) -> RistrettoPoint {
     RistrettoPoint::hash_from_bytes::<Blake2b>(&address.to_bytes())
 }
@@ -46,6 +55,21 @@ pub struct RingMLSAG {
     pub key_image: KeyImage,
 }
 
+/// An MLSAG to verify as part of a batch, with what it is verified against.
+pub struct RingMLSAGBatchItem<'a> {
+    /// The MLSAG.
+    pub signature: &'a RingMLSAG,
+
+    /// The signed message.
+    pub message: &'a [u8; 32],
+
+    /// A ring of input onetime addresses and amount commitments.
+    pub ring: &'a [(Address, Commitment)],
+
+    /// Output amount commitment.
+    pub output_commitment: &'a Commitment,
+}
+
 const C_ZERO_TAG: u32 = 1;
 const RESPONSES_TAG: u32 = 2;
 const KEY_IMAGE_TAG: u32 = 3;
@@ -324,10 +348,18 @@ impl RingMLSAG {
             // * P_i is the i^th onetime public key.
             // * I is the key image of the real input's private key,
             // * Z_i is the i^th "commitment to zero" = output_commitment - i^th input_commitment.
-
-            let L0 = r[2 * i] * G + c_i * P_i.as_ref();
-            let R0 = r[2 * i] * hash_to_point(&P_i) + c_i * I;
-            let L1 = r[2 * i + 1] * H + c_i * (output_commitment.0 - input_commitment.0);
+            //
+            // Everything here is public, so each term is computed with a variable-time
+            // multiscalar multiplication.
+            let L0 = RistrettoPoint::vartime_multiscalar_mul(&[r[2 * i], c_i], &[G, *P_i.as_ref()]);
+            let R0 = RistrettoPoint::vartime_multiscalar_mul(
+                &[r[2 * i], c_i],
+                &[hash_to_point(&P_i), I],
+            );
+            let L1 = RistrettoPoint::vartime_multiscalar_mul(
+                &[r[2 * i + 1], c_i],
+                &[H, output_commitment.0 - input_commitment.0],
+            );
 
             recomputed_c[(i + 1) % ring_size] = {
                 let mut hasher = Blake2b::new();
@@ -345,6 +377,125 @@ impl RingMLSAG {
             Err(Error::InvalidSignature)
         }
     }
+
+    /// Verify many MLSAG signatures.
+    ///
+    /// Returns what `verify` would return for each signature, in order. Each challenge depends on
+    /// the previous one, so the challenges of a ring are still recomputed one at a time, but the
+    /// rings are stepped through together: the L0, R0 and L1 terms of the i^th link of every ring
+    /// are compressed with a single field inversion, instead of one inversion each. The terms in
+    /// G and H use precomputed multiples of G and H.
+    ///
+    /// # Arguments
+    /// * `items` - Signatures, and what each is verified against.
+    pub fn verify_batch(items: &[RingMLSAGBatchItem]) -> Vec<Result<(), Error>> {
+        // `double_and_compress_batch` compresses twice each point, so each term is computed at
+        // half its value, by halving its scalars.
+        let one_half = Scalar::from(2u64).invert();
+
+        let mut results = Vec::with_capacity(items.len());
+
+        // The index, uncompressed key image, and current challenge of each signature whose
+        // challenges are still being recomputed.
+        let mut pending: Vec<(usize, RistrettoPoint, Scalar)> = Vec::new();
+
+        for (j, item) in items.iter().enumerate() {
+            let ring_size = item.ring.len();
+            if ring_size == 0 {
+                results.push(Err(Error::InvalidRingSize(0)));
+                continue;
+            }
+            // `responses` must contain `2 * ring_size` elements.
+            let responses = &item.signature.responses;
+            if responses.len() != 2 * ring_size {
+                results.push(Err(Error::LengthMismatch(2 * ring_size, responses.len())));
+                continue;
+            }
+            let key_image: Result<RistrettoPoint, _> = item.signature.key_image.try_into();
+            match key_image {
+                Ok(I) => {
+                    results.push(Ok(()));
+                    pending.push((j, I, item.signature.c_zero));
+                }
+                Err(_e) => results.push(Err(Error::InvalidKeyImage)),
+            }
+        }
+
+        let mut i = 0;
+        while !pending.is_empty() {
+            // Halves of L0, R0 and L1 of the i^th link of each pending ring. See `verify`.
+            let mut halves: Vec<RistrettoPoint> = Vec::with_capacity(3 * pending.len());
+            for (j, I, c_i) in &pending {
+                let item = &items[*j];
+                let (P_i, input_commitment) = &item.ring[i];
+                let r = &item.signature.responses;
+                let half_r_0 = r[2 * i] * one_half;
+                let half_r_1 = r[2 * i + 1] * one_half;
+                let half_c_i = c_i * one_half;
+
+                // G is the Ristretto basepoint.
+                halves.push(RistrettoPoint::vartime_double_scalar_mul_basepoint(
+                    &half_c_i,
+                    P_i.as_ref(),
+                    &half_r_0,
+                ));
+                halves.push(RistrettoPoint::vartime_multiscalar_mul(
+                    &[half_r_0, half_c_i],
+                    &[hash_to_point(P_i), *I],
+                ));
+                halves.push(H_PRECOMPUTATION.vartime_mixed_multiscalar_mul(
+                    &[half_r_1],
+                    &[half_c_i],
+                    &[item.output_commitment.0 - input_commitment.0],
+                ));
+            }
+
+            let compressed = double_and_compress_batch(&halves);
+            for ((j, _I, c_i), terms) in pending.iter_mut().zip(compressed.chunks(3)) {
+                let mut hasher = Blake2b::new();
+                hasher.input(items[*j].message);
+                for term in terms {
+                    hasher.input(term.as_bytes());
+                }
+                *c_i = Scalar::from_hash::<Blake2b>(hasher);
+            }
+            i += 1;
+
+            // A ring is done once its challenges wrap around to c_0.
+            pending.retain(|(j, _I, c)| {
+                let item = &items[*j];
+                if i < item.ring.len() {
+                    return true;
+                }
+                if *c != item.signature.c_zero {
+                    results[*j] = Err(Error::InvalidSignature);
+                }
+                false
+            });
+        }
+
+        results
+    }
+}
+
+/// Compresses twice each of `points`, with a single field inversion.
+fn double_and_compress_batch(points: &[RistrettoPoint]) -> Vec<CompressedRistretto> {
+    // The batch inversion would fail for every point if one of them is the identity, so those are
+    // left out of it.
+    let identity = RistrettoPoint::identity();
+    let mut compressed =
+        RistrettoPoint::double_and_compress_batch(points.iter().filter(|P| **P != identity))
+            .into_iter();
+    points
+        .iter()
+        .map(|P| {
+            if *P == identity {
+                CompressedRistretto::identity()
+            } else {
+                compressed.next().unwrap()
+            }
+        })
+        .collect()
 }
 
 #[cfg(test)]
@@ -360,7 +511,10 @@ mod mlsag_tests {
     use crate::{
         onetime_keys::compute_key_image,
         proptest_fixtures::*,
-        ring_signature::{mlsag::RingMLSAG, Address, Commitment, Error, Scalar, GENERATORS},
+        ring_signature::{
+            mlsag::{RingMLSAG, RingMLSAGBatchItem},
+            Address, Commitment, Error, Scalar, GENERATORS,
+        },
     };
 
     extern crate std;
@@ -944,5 +1098,68 @@ mod mlsag_tests {
             assert_eq!(signature, recovered_signature);
         }
 
+        #[test]
+        // `verify_batch` should return what `verify` returns for each signature.
+        fn test_verify_batch_agrees_with_verify(
+            num_mixins in 1..17usize,
+            seed in any::<[u8; 32]>(),
+        ) {
+            let mut rng: StdRng = SeedableRng::from_seed(seed);
+
+            // Signatures over rings of different sizes. Every other one is signed with the wrong
+            // onetime private key.
+            let mut signed: Vec<(RingMLSAGParameters, RingMLSAG, Commitment)> = Vec::new();
+            for k in 0..6 {
+                let pseudo_output_blinding = Scalar::random(&mut rng);
+                let params =
+                    RingMLSAGParameters::random(num_mixins + k, pseudo_output_blinding, &mut rng);
+                let onetime_private_key = if k % 2 == 0 {
+                    params.onetime_private_key.clone()
+                } else {
+                    RistrettoPrivate::from_random(&mut rng)
+                };
+
+                let signature = RingMLSAG::sign(
+                    &params.message,
+                    &params.ring,
+                    params.real_index,
+                    &onetime_private_key,
+                    params.value,
+                    &params.blinding,
+                    &params.pseudo_output_blinding,
+                    &mut rng,
+                )
+                .unwrap();
+
+                let output_commitment = Commitment::from(
+                    GENERATORS.commit(Scalar::from(params.value), params.pseudo_output_blinding),
+                );
+                signed.push((params, signature, output_commitment));
+            }
+
+            // A signature with too few responses.
+            signed[2].1.responses.pop();
+
+            let items: Vec<RingMLSAGBatchItem> = signed
+                .iter()
+                .map(|(params, signature, output_commitment)| RingMLSAGBatchItem {
+                    signature,
+                    message: &params.message,
+                    ring: &params.ring,
+                    output_commitment,
+                })
+                .collect();
+            let expected: Vec<Result<(), Error>> = signed
+                .iter()
+                .map(|(params, signature, output_commitment)| {
+                    signature.verify(&params.message, &params.ring, output_commitment)
+                })
+                .collect();
+
+            assert_eq!(RingMLSAG::verify_batch(&items), expected);
+            assert!(expected[0].is_ok());
+            assert_eq!(expected[1], Err(Error::InvalidSignature));
+        }
+
     } // end proptest!
 }
@@ -13,7 +13,10 @@ use blake2::{Blake2b, Digest};
 use bulletproofs::RangeProof;
 use common::HashSet;
 use core::convert::TryInto;
-use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
+use curve25519_dalek::{
+    ristretto::{CompressedRistretto, RistrettoPoint},
+    traits::{Identity, VartimeMultiscalarMul},
+};
 use digestible::Digestible;
 use generic_array::GenericArray;
 use keys::RistrettoPrivate;
@@ -31,10 +34,10 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     onetime_keys::compute_key_image,
-    range_proofs::{check_range_proofs, generate_range_proofs},
+    range_proofs::{check_range_proofs, check_range_proofs_batch, generate_range_proofs},
     ring_signature::{
         encoding::{read_u8_32, write_u8_32},
-        mlsag::RingMLSAG,
+        mlsag::{RingMLSAG, RingMLSAGBatchItem},
         Address, Blinding, Commitment, Error, KeyImage, Scalar, GENERATORS,
     },
 };
@@ -54,6 +57,33 @@ pub struct SignatureRctBulletproofs {
     pub range_proof_bytes: Vec<u8>,
 }
 
+/// A signature to verify as part of a batch, with what it is verified against.
+pub struct SignatureBatchItem<'a> {
+    /// The signature.
+    pub signature: &'a SignatureRctBulletproofs,
+
+    /// The signed message.
+    pub message: &'a [u8; 32],
+
+    /// One or more rings of one-time addresses and amount commitments.
+    pub rings: &'a [Vec<(Address, Commitment)>],
+
+    /// Output amount commitments.
+    pub output_commitments: &'a [Commitment],
+}
+
+/// What is left to verify of a well-formed signature.
+struct UnverifiedParts {
+    /// Decompressed pseudo-output commitments.
+    pseudo_output_commitments: Vec<RistrettoPoint>,
+
+    /// The range proof.
+    range_proof: RangeProof,
+
+    /// Commitments that the range proof is checked against.
+    range_proof_commitments: Vec<CompressedRistretto>,
+}
+
 const RING_SIGNATURE_TAG: u32 = 1;
 const PSEUDO_OUTPUT_TAG: u32 = 2;
 const RANGE_PROOF_TAG: u32 = 3;
@@ -184,6 +214,160 @@ impl SignatureRctBulletproofs {
         output_commitments: &[Commitment],
         rng: &mut CSPRNG,
     ) -> Result<(), Error> {
+        let pseudo_output_commitments =
+            self.verify_except_value_conservation(message, rings, output_commitments, rng)?;
+        check_value_is_conserved(&pseudo_output_commitments, output_commitments)
+    }
+
+    /// Verify many signatures, e.g. those of all transactions in a block.
+    ///
+    /// Returns what `verify` would return for each signature, in order. The range proofs of all
+    /// signatures are checked with a single multiscalar multiplication, and so is value
+    /// conservation; only if one of these fails is it checked for each signature, to find the
+    /// invalid ones. The MLSAGs of all signatures are checked together with
+    /// `RingMLSAG::verify_batch`.
+    ///
+    /// # Arguments
+    /// * `items` - Signatures, and what each is verified against.
+    /// * `rng` -
+    pub fn verify_batch<CSPRNG: RngCore + CryptoRng>(
+        items: &[SignatureBatchItem],
+        rng: &mut CSPRNG,
+    ) -> Vec<Result<(), Error>> {
+        let mut results = Vec::with_capacity(items.len());
+
+        // Signatures that are well-formed, with what is left to verify.
+        let mut unverified: Vec<(usize, UnverifiedParts)> = Vec::new();
+        for (i, item) in items.iter().enumerate() {
+            match item
+                .signature
+                .unverified_parts(item.rings, item.output_commitments)
+            {
+                Ok(parts) => {
+                    results.push(Ok(()));
+                    unverified.push((i, parts));
+                }
+                Err(err) => results.push(Err(err)),
+            }
+        }
+
+        // Range proofs must be valid.
+        let range_proofs: Vec<(&RangeProof, &[CompressedRistretto])> = unverified
+            .iter()
+            .map(|(_i, parts)| (&parts.range_proof, &parts.range_proof_commitments[..]))
+            .collect();
+        if check_range_proofs_batch(&range_proofs, rng).is_err() {
+            for (i, parts) in &unverified {
+                if check_range_proofs(&parts.range_proof, &parts.range_proof_commitments, rng)
+                    .is_err()
+                {
+                    results[*i] = Err(Error::InvalidSignature);
+                }
+            }
+        }
+        unverified.retain(|(i, _parts)| results[*i].is_ok());
+
+        // Each MLSAG must be valid. A signature fails with the error of its first invalid MLSAG.
+        let pseudo_output_commitments: Vec<Vec<Commitment>> = unverified
+            .iter()
+            .map(|(_i, parts)| {
+                parts
+                    .pseudo_output_commitments
+                    .iter()
+                    .map(|point| Commitment::from(*point))
+                    .collect()
+            })
+            .collect();
+        let mut mlsag_items: Vec<RingMLSAGBatchItem> = Vec::new();
+        let mut mlsag_signature_indices: Vec<usize> = Vec::new();
+        for ((i, _parts), pseudo_outputs) in unverified.iter().zip(&pseudo_output_commitments) {
+            let item = &items[*i];
+            for ((ring_signature, ring), pseudo_output) in item
+                .signature
+                .ring_signatures
+                .iter()
+                .zip(item.rings)
+                .zip(pseudo_outputs)
+            {
+                mlsag_items.push(RingMLSAGBatchItem {
+                    signature: ring_signature,
+                    message: item.message,
+                    ring,
+                    output_commitment: pseudo_output,
+                });
+                mlsag_signature_indices.push(*i);
+            }
+        }
+        for (i, result) in mlsag_signature_indices
+            .iter()
+            .zip(RingMLSAG::verify_batch(&mlsag_items))
+        {
+            if results[*i].is_ok() {
+                results[*i] = result;
+            }
+        }
+        unverified.retain(|(i, _parts)| results[*i].is_ok());
+
+        // With random weights `w_j`, `sum_j w_j * (outputs_j - pseudo_outputs_j)` is zero only if
+        // each difference is, except with negligible probability.
+        let mut scalars: Vec<Scalar> = Vec::new();
+        let mut points: Vec<RistrettoPoint> = Vec::new();
+        for (i, parts) in &unverified {
+            let weight = Scalar::random(rng);
+            for output_commitment in items[*i].output_commitments {
+                scalars.push(weight);
+                points.push(output_commitment.0);
+            }
+            for pseudo_output_commitment in &parts.pseudo_output_commitments {
+                scalars.push(-weight);
+                points.push(*pseudo_output_commitment);
+            }
+        }
+
+        if RistrettoPoint::vartime_multiscalar_mul(&scalars, &points) != RistrettoPoint::identity()
+        {
+            for (i, parts) in &unverified {
+                results[*i] = check_value_is_conserved(
+                    &parts.pseudo_output_commitments,
+                    items[*i].output_commitments,
+                );
+            }
+        }
+
+        results
+    }
+
+    /// Verify everything but value conservation, and return the decompressed pseudo-output
+    /// commitments.
+    fn verify_except_value_conservation<CSPRNG: RngCore + CryptoRng>(
+        &self,
+        message: &[u8; 32],
+        rings: &[Vec<(Address, Commitment)>],
+        output_commitments: &[Commitment],
+        rng: &mut CSPRNG,
+    ) -> Result<Vec<RistrettoPoint>, Error> {
+        let parts = self.unverified_parts(rings, output_commitments)?;
+
+        // Range proof must be valid
+        check_range_proofs(&parts.range_proof, &parts.range_proof_commitments, rng)
+            .map_err(|_e| Error::InvalidSignature)?;
+
+        // Each MLSAG must be valid.
+        for (i, ring) in rings.iter().enumerate() {
+            let pseudo_output = parts.pseudo_output_commitments[i];
+            let ring_signature = &self.ring_signatures[i];
+            ring_signature.verify(message, ring, &Commitment::from(pseudo_output))?;
+        }
+
+        Ok(parts.pseudo_output_commitments)
+    }
+
+    /// Check that the signature is well-formed, and return what is left to verify.
+    fn unverified_parts(
+        &self,
+        rings: &[Vec<(Address, Commitment)>],
+        output_commitments: &[Commitment],
+    ) -> Result<UnverifiedParts, Error> {
         // Signature must contain one ring signature for each ring.
         if rings.len() != self.ring_signatures.len() {
             return Err(Error::LengthMismatch(
@@ -224,52 +408,27 @@ impl SignatureRctBulletproofs {
 
         let decompressed_pseudo_output_commitments: Vec<RistrettoPoint> = decompression_result?;
 
-        // Range proof must be valid
-        {
-            let compressed_output_commitments: Vec<CompressedRistretto> = output_commitments
-                .iter()
-                .map(|commitment| commitment.as_ref().compress())
-                .collect();
-
-            let commitments: Vec<CompressedRistretto> = self
-                .pseudo_output_commitments
-                .iter()
-                .chain(compressed_output_commitments.iter())
-                .cloned()
-                .collect();
-
-            let range_proof = RangeProof::from_bytes(&self.range_proof_bytes)
-                .map_err(|_e| Error::RangeProofError)?;
-
-            check_range_proofs(&range_proof, &commitments, rng)
-                .map_err(|_e| Error::InvalidSignature)?;
-        }
-
-        // Each MLSAG must be valid.
-        for (i, ring) in rings.iter().enumerate() {
-            let pseudo_output = decompressed_pseudo_output_commitments[i];
-            let ring_signature = &self.ring_signatures[i];
-            ring_signature.verify(message, ring, &Commitment::from(pseudo_output))?;
-        }
-
-        // Output commitments - pseudo_outputs must be zero.
-        {
-            let sum_of_output_commitments: RistrettoPoint = output_commitments
-                .iter()
-                .map(|commitment| commitment.0)
-                .sum();
+        // The range proof is checked against the pseudo-outputs and the outputs.
+        let compressed_output_commitments: Vec<CompressedRistretto> = output_commitments
+            .iter()
+            .map(|commitment| commitment.as_ref().compress())
+            .collect();
 
-            let sum_of_pseudo_output_commitments: RistrettoPoint =
-                decompressed_pseudo_output_commitments.iter().sum();
+        let range_proof_commitments: Vec<CompressedRistretto> = self
+            .pseudo_output_commitments
+            .iter()
+            .chain(compressed_output_commitments.iter())
+            .cloned()
+            .collect();
 
-            let difference = sum_of_output_commitments - sum_of_pseudo_output_commitments;
-            if difference != GENERATORS.commit(Scalar::zero(), Scalar::zero()) {
-                return Err(Error::ValueNotConserved);
-            }
-        }
+        let range_proof =
+            RangeProof::from_bytes(&self.range_proof_bytes).map_err(|_e| Error::RangeProofError)?;
 
-        // Signature is valid.
-        Ok(())
+        Ok(UnverifiedParts {
+            pseudo_output_commitments: decompressed_pseudo_output_commitments,
+            range_proof,
+            range_proof_commitments,
+        })
     }
 
     /// Key images spent by this signature.
@@ -281,6 +440,30 @@ impl SignatureRctBulletproofs {
     }
 }
 
+/// Output commitments - pseudo_outputs must be zero.
+///
+/// # Arguments
+/// * `pseudo_output_commitments` - Decompressed pseudo-output commitments.
+/// * `output_commitments` - Output amount commitments.
+fn check_value_is_conserved(
+    pseudo_output_commitments: &[RistrettoPoint],
+    output_commitments: &[Commitment],
+) -> Result<(), Error> {
+    let sum_of_output_commitments: RistrettoPoint = output_commitments
+        .iter()
+        .map(|commitment| commitment.0)
+        .sum();
+
+    let sum_of_pseudo_output_commitments: RistrettoPoint = pseudo_output_commitments.iter().sum();
+
+    let difference = sum_of_output_commitments - sum_of_pseudo_output_commitments;
+    if difference != GENERATORS.commit(Scalar::zero(), Scalar::zero()) {
+        return Err(Error::ValueNotConserved);
+    }
+
+    Ok(())
+}
+
 /// Sign, with optional check for inputs = outputs.
 ///
 /// # Arguments
@@ -422,7 +605,8 @@ mod rct_bulletproofs_tests {
         proptest_fixtures::*,
         range_proofs::generate_range_proofs,
         ring_signature::{
-            Address, Blinding, Commitment, Error, KeyImage, SignatureRctBulletproofs, GENERATORS,
+            Address, Blinding, Commitment, Error, KeyImage, SignatureBatchItem,
+            SignatureRctBulletproofs, GENERATORS,
         },
     };
 
@@ -781,6 +965,121 @@ mod rct_bulletproofs_tests {
             assert_eq!(result, Err(Error::InvalidSignature));
         }
 
+        #[test]
+        // `verify_batch` should accept valid signatures.
+        fn verify_batch_accepts_valid_signatures(
+            num_signatures in 1..5usize,
+            num_inputs in 1..4usize,
+            num_mixins in 1..11usize,
+            seed in any::<[u8; 32]>(),
+        ) {
+            let mut rng: StdRng = SeedableRng::from_seed(seed);
+            let mut signed = Vec::new();
+            for _i in 0..num_signatures {
+                let params = SignatureParams::random(num_inputs, num_mixins, &mut rng);
+                let signature = SignatureRctBulletproofs::sign(
+                    &params.message,
+                    &params.rings,
+                    &params.real_input_indices,
+                    &params.input_secrets,
+                    &params.output_values_and_blindings,
+                    &mut rng,
+                )
+                .unwrap();
+                let output_commitments = params.get_output_commitments();
+                signed.push((params, signature, output_commitments));
+            }
+
+            let items: Vec<SignatureBatchItem> = signed
+                .iter()
+                .map(|(params, signature, output_commitments)| SignatureBatchItem {
+                    signature,
+                    message: &params.message,
+                    rings: &params.rings,
+                    output_commitments,
+                })
+                .collect();
+
+            let results = SignatureRctBulletproofs::verify_batch(&items, &mut rng);
+            assert_eq!(results, vec![Ok(()); num_signatures]);
+        }
+
+        #[test]
+        // `verify_batch` should return the same error as `verify` for each invalid signature.
+        fn verify_batch_finds_invalid_signatures(
+            num_inputs in 1..4usize,
+            num_mixins in 1..11usize,
+            seed in any::<[u8; 32]>(),
+        ) {
+            let mut rng: StdRng = SeedableRng::from_seed(seed);
+            let mut signed = Vec::new();
+            for i in 0..5 {
+                let mut params = SignatureParams::random(num_inputs, num_mixins, &mut rng);
+
+                // The second signature does not conserve value.
+                if i == 1 {
+                    let (_value, blinding) = params.output_values_and_blindings[0];
+                    params.output_values_and_blindings[0] = (rng.next_u64(), blinding);
+                }
+
+                let mut signature = sign_with_balance_check(
+                    &params.message,
+                    &params.rings,
+                    &params.real_input_indices,
+                    &params.input_secrets,
+                    &params.output_values_and_blindings,
+                    false,
+                    &mut rng,
+                )
+                .unwrap();
+
+                // The third signature has an invalid MLSAG.
+                if i == 2 {
+                    signature.ring_signatures[0].key_image =
+                        KeyImage::from(RistrettoPoint::random(&mut rng));
+                }
+
+                let output_commitments = params.get_output_commitments();
+
+                // The fourth signature has a range proof for other values.
+                if i == 3 {
+                    let values =
+                        vec![13; signature.pseudo_output_commitments.len() + output_commitments.len()];
+                    let blindings: Vec<Blinding> = values
+                        .iter()
+                        .map(|_value| Blinding::from(Scalar::random(&mut rng)))
+                        .collect();
+                    let (range_proof, _commitments) =
+                        generate_range_proofs(&values, &blindings, &mut rng).unwrap();
+                    signature.range_proof_bytes = range_proof.to_bytes();
+                }
+
+                signed.push((params, signature, output_commitments));
+            }
+
+            let items: Vec<SignatureBatchItem> = signed
+                .iter()
+                .map(|(params, signature, output_commitments)| SignatureBatchItem {
+                    signature,
+                    message: &params.message,
+                    rings: &params.rings,
+                    output_commitments,
+                })
+                .collect();
+
+            let results = SignatureRctBulletproofs::verify_batch(&items, &mut rng);
+            assert_eq!(
+                results,
+                vec![
+                    Ok(()),
+                    Err(Error::ValueNotConserved),
+                    Err(Error::InvalidSignature),
+                    Err(Error::InvalidSignature),
+                    Ok(()),
+                ]
+            );
+        }
+
         #[test]
         // decode(encode(&signature)) should be the identity function.
         fn test_encode_decode(
@@ -7,8 +7,8 @@ use core::{
     fmt,
 };
 use curve25519_dalek::scalar::Scalar;
-use digestible::Digestible;
-use keys::{CompressedRistrettoPublic, FromRandom, RistrettoPrivate};
+use digestible::{Digest, Digestible};
+use keys::{CompressedRistrettoPublic, FromRandom, RistrettoPrivate, RistrettoPublic};
 use mcserial::{prost_message_helper32, ReprBytes32};
 use prost::Message;
 use rand_core::{CryptoRng, RngCore};
@@ -19,6 +19,7 @@ use crate::{
     amount::{Amount, AmountError},
     blake2b_256::Blake2b256,
     encrypted_fog_hint::EncryptedFogHint,
+    memo::{EncryptedMemo, Memo},
     onetime_keys::{compute_shared_secret, compute_tx_pubkey, create_onetime_public_key},
     range::Range,
     ring_signature::{Blinding, Commitment, KeyImage, SignatureRctBulletproofs, GENERATORS},
@@ -233,7 +234,7 @@ pub struct TxIn {
 }
 
 /// An output created by a transaction.
-#[derive(Clone, Deserialize, Eq, Hash, PartialEq, Serialize, Message, Digestible)]
+#[derive(Clone, Deserialize, Eq, Hash, PartialEq, Serialize, Message)]
 pub struct TxOut {
     /// The amount being sent.
     #[prost(message, required, tag = "1")]
@@ -250,6 +251,34 @@ pub struct TxOut {
     /// The encrypted account hint for the account server.
     #[prost(message, required, tag = "4")]
     pub e_account_hint: EncryptedFogHint,
+
+    /// The memo, encrypted to the recipient.
+    ///
+    /// Left out of the serialization when absent, so that outputs without a memo encode (and
+    /// hash, as Merkle leaves) the same as before memos existed.
+    #[prost(message, optional, tag = "5")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub e_memo: Option<EncryptedMemo>,
+}
+
+// Same as `#[derive(Digestible)]`, except that `e_memo` is only digested when present, so that the
+// hash of outputs without a memo is the same as before memos existed.
+impl Digestible for TxOut {
+    fn digest<D: Digest>(&self, hasher: &mut D) {
+        hasher.input(b"TxOut");
+        hasher.input(b"amount");
+        self.amount.digest(hasher);
+        hasher.input(b"target_key");
+        self.target_key.digest(hasher);
+        hasher.input(b"public_key");
+        self.public_key.digest(hasher);
+        hasher.input(b"e_account_hint");
+        self.e_account_hint.digest(hasher);
+        if let Some(e_memo) = &self.e_memo {
+            hasher.input(b"e_memo");
+            e_memo.digest(hasher);
+        }
+    }
 }
 
 impl TxOut {
@@ -267,23 +296,53 @@ impl TxOut {
         tx_private_key: &RistrettoPrivate,
         hint: EncryptedFogHint,
         rng: &mut RNG,
+    ) -> Result<Self, AmountError> {
+        Self::new_with_memo(value, recipient, tx_private_key, hint, None, rng)
+    }
+
+    /// Creates a TxOut that sends `value` to `recipient`, with a memo only the recipient can read.
+    ///
+    /// # Arguments
+    /// * `value` - Value of the output.
+    /// * `recipient` - Recipient's address.
+    /// * `tx_private_key` - The transaction's private key
+    /// * `hint` -
+    /// * `memo` - Memo to encrypt to the recipient, if any.
+    /// * `rng` - A cryptographic pseudorandom number generator.
+    pub fn new_with_memo<RNG: CryptoRng + RngCore>(
+        value: u64,
+        recipient: &PublicAddress,
+        tx_private_key: &RistrettoPrivate,
+        hint: EncryptedFogHint,
+        memo: Option<&Memo>,
+        rng: &mut RNG,
     ) -> Result<Self, AmountError> {
         let target_key = create_onetime_public_key(recipient, tx_private_key).into();
         let public_key = compute_tx_pubkey(tx_private_key, recipient.spend_public_key()).into();
 
-        let amount = {
-            let shared_secret = compute_shared_secret(recipient.view_public_key(), tx_private_key);
-            Amount::new(value, Blinding::from_random(rng), &shared_secret)
-        }?;
+        let shared_secret = compute_shared_secret(recipient.view_public_key(), tx_private_key);
+        let amount = Amount::new(value, Blinding::from_random(rng), &shared_secret)?;
+        let e_memo = memo.map(|memo| EncryptedMemo::new(memo, &shared_secret));
 
         Ok(TxOut {
             amount,
             target_key,
             public_key,
             e_account_hint: hint,
+            e_memo,
         })
     }
 
+    /// Decrypts the memo of this TxOut, if it has one.
+    ///
+    /// # Arguments
+    /// * `shared_secret` - The shared secret, e.g. `rB`.
+    pub fn decrypt_memo(&self, shared_secret: &RistrettoPublic) -> Option<Memo> {
+        self.e_memo
+            .as_ref()
+            .map(|e_memo| e_memo.decrypt(shared_secret))
+    }
+
     /// Blake2B256 hash of his TxOut.
     pub fn hash(&self) -> Hash {
         self.digest_with::<Blake2b256>().try_into().unwrap()
@@ -394,8 +453,10 @@ prost_message_helper32! { TxOutMembershipHash }
 #[cfg(test)]
 mod tests {
     use alloc::vec::Vec;
+    use core::convert::TryFrom;
 
-    use keys::RistrettoPublic;
+    use curve25519_dalek::{constants::RISTRETTO_BASEPOINT_POINT, ristretto::CompressedRistretto};
+    use keys::{CompressedRistrettoPublic, FromRandom, RistrettoPrivate, RistrettoPublic};
     use mcserial::ReprBytes32;
     use prost::Message;
 
@@ -406,6 +467,9 @@ mod tests {
         amount::Amount,
         constants::{BASE_FEE, FEE_SPEND_PUBLIC_KEY, FEE_VIEW_PRIVATE_KEY, FEE_VIEW_PUBLIC_KEY},
         encrypted_fog_hint::EncryptedFogHint,
+        get_tx_out_shared_secret,
+        membership_proofs::leaf_hash_fn,
+        memo::{EncryptedMemo, Memo, MEMO_LEN},
         ring_signature::{Blinding, CurvePoint, CurveScalar, KeyImage, SignatureRctBulletproofs},
         tx::{Tx, TxIn, TxOut, TxPrefix},
     };
@@ -424,6 +488,7 @@ mod tests {
                 target_key,
                 public_key,
                 e_account_hint: EncryptedFogHint::from(&[1u8; 128]),
+                e_memo: Some(EncryptedMemo::try_from(&[2u8; MEMO_LEN][..]).unwrap()),
             }
         };
 
@@ -470,6 +535,83 @@ mod tests {
         assert_eq!(tx, recovered_tx);
     }
 
+    #[test]
+    // The recipient of a TxOut should be able to decrypt its memo.
+    fn test_tx_out_memo() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let recipient = AccountKey::random(&mut rng);
+        let memo = Memo::try_from(&b"invoice #1234"[..]).unwrap();
+
+        let tx_out = TxOut::new_with_memo(
+            10,
+            &recipient.default_subaddress(),
+            &RistrettoPrivate::from_random(&mut rng),
+            EncryptedFogHint::fake_onetime_hint(&mut rng),
+            Some(&memo),
+            &mut rng,
+        )
+        .unwrap();
+
+        let shared_secret = get_tx_out_shared_secret(
+            recipient.view_private_key(),
+            &RistrettoPublic::try_from(&tx_out.public_key).unwrap(),
+        );
+        assert_eq!(tx_out.decrypt_memo(&shared_secret), Some(memo));
+
+        // Outputs created without a memo have none.
+        let tx_out = TxOut::new(
+            10,
+            &recipient.default_subaddress(),
+            &RistrettoPrivate::from_random(&mut rng),
+            EncryptedFogHint::fake_onetime_hint(&mut rng),
+            &mut rng,
+        )
+        .unwrap();
+        assert_eq!(tx_out.decrypt_memo(&shared_secret), None);
+    }
+
+    #[test]
+    // Outputs without a memo should hash, and serialize into Merkle leaves, exactly as they did
+    // before memos existed.
+    fn test_tx_out_without_memo_hash_unchanged() {
+        let tx_out = TxOut {
+            amount: Amount {
+                commitment: CurvePoint::from(RISTRETTO_BASEPOINT_POINT),
+                masked_value: CurveScalar::from_bytes_mod_order([1u8; 32]),
+                masked_blinding: Blinding::from_bytes_mod_order([2u8; 32]),
+            },
+            target_key: CompressedRistrettoPublic::from(CompressedRistretto([3u8; 32])),
+            public_key: CompressedRistrettoPublic::from(CompressedRistretto([4u8; 32])),
+            e_account_hint: EncryptedFogHint::from(&[5u8; 128]),
+            e_memo: None,
+        };
+        let expected_hash = [
+            0xc1, 0x0c, 0x3f, 0x53, 0xe4, 0x57, 0x0c, 0xbd, 0x44, 0x5b, 0xa0, 0x43, 0xb1, 0xd6,
+            0x26, 0xa0, 0x4b, 0x53, 0x4b, 0x63, 0xc6, 0xf9, 0x5f, 0x23, 0xf4, 0x5c, 0x1c, 0x7e,
+            0x62, 0x29, 0xd5, 0x18,
+        ];
+        let expected_leaf_hash = [
+            0xe0, 0xb6, 0xca, 0xe6, 0x95, 0xa5, 0x11, 0x35, 0x3a, 0xa1, 0x67, 0x2b, 0x25, 0x6a,
+            0x34, 0xc7, 0x76, 0x14, 0x03, 0x9b, 0xe0, 0xdf, 0x6f, 0x95, 0xc4, 0x0a, 0xba, 0xa5,
+            0x62, 0x34, 0x23, 0xa2,
+        ];
+        assert_eq!(tx_out.hash(), expected_hash);
+        assert_eq!(
+            leaf_hash_fn(&mcserial::serialize(&tx_out).unwrap()),
+            expected_leaf_hash
+        );
+
+        // A memo changes both, and survives serialization.
+        let tx_out = TxOut {
+            e_memo: Some(EncryptedMemo::try_from(&[6u8; MEMO_LEN][..]).unwrap()),
+            ..tx_out
+        };
+        let bytes = mcserial::serialize(&tx_out).unwrap();
+        assert_ne!(tx_out.hash(), expected_hash);
+        assert_ne!(leaf_hash_fn(&bytes), expected_leaf_hash);
+        assert_eq!(mcserial::deserialize::<TxOut>(&bytes).unwrap(), tx_out);
+    }
+
     #[test]
     fn generate_fee_view_key() {
         let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
@@ -4,4 +4,6 @@ mod error;
 mod validate;
 
 pub use error::{TransactionValidationError, TransactionValidationResult};
-pub use validate::{validate, validate_tombstone, validate_transaction_signature};
+pub use validate::{
+    validate, validate_tombstone, validate_transaction_signature, validate_transaction_signatures,
+};
@@ -9,9 +9,10 @@ use alloc::vec::Vec;
 use super::error::{TransactionValidationError, TransactionValidationResult};
 use crate::{
     constants::*,
+    fee_schedule::FeeSchedule,
     membership_proofs::{derive_proof_at_index, is_membership_proof_valid},
     range_proofs::check_range_proofs,
-    ring_signature::{Address, Commitment},
+    ring_signature::{Address, Commitment, SignatureBatchItem, SignatureRctBulletproofs},
     tx::{Tx, TxOut, TxOutMembershipProof, TxPrefix},
 };
 use bulletproofs::RangeProof;
@@ -46,7 +47,7 @@ pub fn validate<R: RngCore + CryptoRng>(
 
     validate_transaction_signature(&tx, csprng)?;
 
-    validate_transaction_fee(&tx)?;
+    validate_transaction_fee(&tx, &FeeSchedule::current())?;
 
     validate_key_images_are_unique(&tx)?;
 
@@ -170,8 +171,54 @@ pub fn validate_transaction_signature<R: RngCore + CryptoRng>(
     tx: &Tx,
     rng: &mut R,
 ) -> TransactionValidationResult<()> {
-    let tx_prefix_hash = tx.prefix.hash();
-    let message = tx_prefix_hash.as_bytes();
+    let (message, rings, output_commitments) = signed_data(tx)?;
+
+    tx.signature
+        .verify(&message, &rings, &output_commitments, rng)
+        .map_err(|_e| TransactionValidationError::InvalidTransactionSignature)
+}
+
+/// Validates the signatures of many transactions, e.g. those of a block, together.
+///
+/// Returns what `validate_transaction_signature` would return for each transaction, in order.
+pub fn validate_transaction_signatures<R: RngCore + CryptoRng>(
+    txs: &[&Tx],
+    rng: &mut R,
+) -> Vec<TransactionValidationResult<()>> {
+    let signed: Vec<_> = txs.iter().map(|tx| signed_data(tx)).collect();
+
+    let items: Vec<SignatureBatchItem> = txs
+        .iter()
+        .zip(signed.iter())
+        .filter_map(|(tx, signed_data)| match signed_data {
+            Ok((message, rings, output_commitments)) => Some(SignatureBatchItem {
+                signature: &tx.signature,
+                message,
+                rings,
+                output_commitments,
+            }),
+            Err(_) => None,
+        })
+        .collect();
+    let mut signature_results = SignatureRctBulletproofs::verify_batch(&items, rng).into_iter();
+
+    signed
+        .into_iter()
+        .map(|signed_data| {
+            signed_data?;
+            signature_results
+                .next()
+                .expect("one result per signature")
+                .map_err(|_e| TransactionValidationError::InvalidTransactionSignature)
+        })
+        .collect()
+}
+
+/// The message, rings and output commitments that a transaction's signature signs.
+fn signed_data(
+    tx: &Tx,
+) -> TransactionValidationResult<([u8; 32], Vec<Vec<(Address, Commitment)>>, Vec<Commitment>)> {
+    let message = *tx.prefix.hash().as_bytes();
 
     let mut rings: Vec<Vec<(Address, Commitment)>> = Vec::new();
     for tx_in in &tx.prefix.inputs {
@@ -186,14 +233,16 @@ pub fn validate_transaction_signature<R: RngCore + CryptoRng>(
 
     let output_commitments = tx.prefix.output_commitments();
 
-    tx.signature
-        .verify(message, &rings, &output_commitments, rng)
-        .map_err(|_e| TransactionValidationError::InvalidTransactionSignature)
+    Ok((message, rings, output_commitments))
 }
 
-/// The fee amount must be greater than or equal to `BASE_FEE`.
-fn validate_transaction_fee(tx: &Tx) -> TransactionValidationResult<()> {
-    if tx.prefix.fee < BASE_FEE {
+/// The fee amount must be greater than or equal to the minimum fee of the fee schedule.
+fn validate_transaction_fee(
+    tx: &Tx,
+    fee_schedule: &FeeSchedule,
+) -> TransactionValidationResult<()> {
+    let minimum_fee = fee_schedule.minimum_fee(tx.prefix.inputs.len(), tx.prefix.outputs.len());
+    if tx.prefix.fee < minimum_fee {
         Err(TransactionValidationError::TxFeeError)
     } else {
         Ok(())
@@ -329,6 +378,7 @@ mod tests {
     use crate::{
         account_keys::{AccountKey, PublicAddress},
         constants::{BASE_FEE, MIN_RING_SIZE},
+        fee_schedule::FeeSchedule,
         get_tx_out_shared_secret,
         onetime_keys::recover_onetime_private_key,
         ring_signature::{Commitment, KeyImage},
@@ -339,7 +389,8 @@ mod tests {
                 validate_key_images_are_unique, validate_membership_proofs,
                 validate_number_of_inputs, validate_number_of_outputs,
                 validate_ring_elements_are_unique, validate_ring_sizes, validate_tombstone,
-                validate_transaction_fee, validate_transaction_signature, MAX_TOMBSTONE_BLOCKS,
+                validate_transaction_fee, validate_transaction_signature,
+                validate_transaction_signatures, MAX_TOMBSTONE_BLOCKS,
             },
         },
     };
@@ -731,39 +782,130 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_validate_transaction_signatures() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let (valid_tx, _ledger) = create_test_tx();
+        let (other_valid_tx, _ledger) =
+            create_test_tx_with_amount(INITIALIZE_LEDGER_AMOUNT - BASE_FEE, BASE_FEE);
+
+        let mut altered_output_tx = valid_tx.clone();
+        altered_output_tx.prefix.outputs[0].amount.commitment = Commitment::from(46);
+
+        let mut altered_input_tx = valid_tx.clone();
+        altered_input_tx.prefix.inputs[0].ring.pop();
+
+        // Each result should be what `validate_transaction_signature` returns.
+        let txs = vec![
+            &valid_tx,
+            &altered_output_tx,
+            &other_valid_tx,
+            &altered_input_tx,
+        ];
+        let results = validate_transaction_signatures(&txs, &mut rng);
+        for (tx, result) in txs.iter().zip(results.iter()) {
+            assert_eq!(*result, validate_transaction_signature(tx, &mut rng));
+        }
+        assert_eq!(
+            results,
+            vec![
+                Ok(()),
+                Err(TransactionValidationError::InvalidTransactionSignature),
+                Ok(()),
+                Err(TransactionValidationError::InvalidTransactionSignature),
+            ]
+        );
+
+        // An empty batch is valid.
+        assert!(validate_transaction_signatures(&[], &mut rng).is_empty());
+    }
+
     #[test]
     fn test_validate_transaction_fee() {
+        // A schedule that charges for each input and output.
+        let fee_schedule = FeeSchedule::for_block_version(1).unwrap();
+        // The test transaction has one input and one output.
+        let minimum_fee = fee_schedule.minimum_fee(1, 1);
+
         {
             // Zero fees gets rejected
             let (tx, _ledger) = create_test_tx_with_amount(INITIALIZE_LEDGER_AMOUNT, 0);
             assert_eq!(
-                validate_transaction_fee(&tx),
+                validate_transaction_fee(&tx, &fee_schedule),
+                Err(TransactionValidationError::TxFeeError)
+            );
+        }
+
+        {
+            // Paying only the base fee gets rejected
+            let fee = fee_schedule.base_fee;
+            let (tx, _ledger) = create_test_tx_with_amount(INITIALIZE_LEDGER_AMOUNT - fee, fee);
+            assert_eq!(
+                validate_transaction_fee(&tx, &fee_schedule),
                 Err(TransactionValidationError::TxFeeError)
             );
         }
 
         {
             // Off by one fee gets rejected
-            let fee = BASE_FEE - 1;
+            let fee = minimum_fee - 1;
             let (tx, _ledger) = create_test_tx_with_amount(INITIALIZE_LEDGER_AMOUNT - fee, fee);
             assert_eq!(
-                validate_transaction_fee(&tx),
+                validate_transaction_fee(&tx, &fee_schedule),
                 Err(TransactionValidationError::TxFeeError)
             );
         }
 
         {
             // Exact fee amount is okay
-            let (tx, _ledger) =
-                create_test_tx_with_amount(INITIALIZE_LEDGER_AMOUNT - BASE_FEE, BASE_FEE);
-            assert_eq!(validate_transaction_fee(&tx), Ok(()));
+            let fee = minimum_fee;
+            let (tx, _ledger) = create_test_tx_with_amount(INITIALIZE_LEDGER_AMOUNT - fee, fee);
+            assert_eq!(validate_transaction_fee(&tx, &fee_schedule), Ok(()));
         }
 
         {
             // Overpaying fees is okay
-            let fee = BASE_FEE + 1;
+            let fee = minimum_fee + 1;
+            let (tx, _ledger) = create_test_tx_with_amount(INITIALIZE_LEDGER_AMOUNT - fee, fee);
+            assert_eq!(validate_transaction_fee(&tx, &fee_schedule), Ok(()));
+        }
+
+        {
+            // Each additional output raises the minimum fee
+            let fee = minimum_fee;
+            let (mut tx, _ledger) = create_test_tx_with_amount(INITIALIZE_LEDGER_AMOUNT - fee, fee);
+            let output = tx.prefix.outputs[0].clone();
+            tx.prefix.outputs.push(output);
+            assert_eq!(
+                validate_transaction_fee(&tx, &fee_schedule),
+                Err(TransactionValidationError::TxFeeError)
+            );
+        }
+    }
+
+    #[test]
+    // Version 0 blocks only require the base fee.
+    fn test_validate_transaction_fee_flat() {
+        let fee_schedule = FeeSchedule::for_block_version(0).unwrap();
+
+        {
+            // Off by one fee gets rejected
+            let fee = BASE_FEE - 1;
             let (tx, _ledger) = create_test_tx_with_amount(INITIALIZE_LEDGER_AMOUNT - fee, fee);
-            assert_eq!(validate_transaction_fee(&tx), Ok(()));
+            assert_eq!(
+                validate_transaction_fee(&tx, &fee_schedule),
+                Err(TransactionValidationError::TxFeeError)
+            );
+        }
+
+        {
+            // The base fee is okay, regardless of the number of outputs
+            let fee = BASE_FEE;
+            let (mut tx, _ledger) = create_test_tx_with_amount(INITIALIZE_LEDGER_AMOUNT - fee, fee);
+            assert_eq!(validate_transaction_fee(&tx, &fee_schedule), Ok(()));
+            let output = tx.prefix.outputs[0].clone();
+            tx.prefix.outputs.push(output);
+            assert_eq!(validate_transaction_fee(&tx, &fee_schedule), Ok(()));
         }
     }
 
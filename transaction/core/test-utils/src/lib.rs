@@ -9,6 +9,7 @@ use tempdir::TempDir;
 pub use transaction::{
     account_keys::{AccountKey, PublicAddress, DEFAULT_SUBADDRESS_INDEX},
     constants::{BASE_FEE, MIN_RING_SIZE},
+    fee_schedule::FeeSchedule,
     get_tx_out_shared_secret,
     onetime_keys::recover_onetime_private_key,
     range::Range,
@@ -28,7 +29,8 @@ pub fn create_ledger() -> LedgerDB {
     LedgerDB::open(path).unwrap()
 }
 
-/// Creates a transaction that sends the full value of `tx_out` to a single recipient.
+/// Creates a transaction that sends the full value of `tx_out` to a single recipient, less the
+/// minimum fee.
 ///
 /// # Arguments:
 /// * `ledger` - A ledger containing `tx_out`.
@@ -50,14 +52,15 @@ pub fn create_transaction<L: Ledger, R: RngCore + CryptoRng>(
     let shared_secret = get_tx_out_shared_secret(sender.view_private_key(), &tx_out_public_key);
     let (value, _blinding) = tx_out.amount.get_value(&shared_secret).unwrap();
 
-    assert!(value >= BASE_FEE);
+    let fee = FeeSchedule::current().minimum_fee(1, 1);
+    assert!(value >= fee);
     create_transaction_with_amount(
         ledger,
         tx_out,
         sender,
         recipient,
-        value - BASE_FEE,
-        BASE_FEE,
+        value - fee,
+        fee,
         tombstone_block,
         rng,
     )
@@ -124,7 +127,7 @@ pub fn create_transaction_with_amount<L: Ledger, R: RngCore + CryptoRng>(
 
     // Output
     transaction_builder
-        .add_output(amount, recipient, None, rng)
+        .add_output(amount, recipient, None, None, rng)
         .unwrap();
 
     // Tombstone block
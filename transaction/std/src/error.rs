@@ -41,6 +41,12 @@ pub enum TxBuilderError {
 
     #[fail(display = "Key error: {}", _0)]
     KeyError(keys::KeyError),
+
+    #[fail(display = "The output was not added to this transaction")]
+    UnknownOutput,
+
+    #[fail(display = "Payment proof: {}", _0)]
+    PaymentProof(PaymentProofError),
 }
 
 impl From<mcserial::encode::Error> for TxBuilderError {
@@ -67,8 +73,47 @@ impl From<keys::KeyError> for TxBuilderError {
     }
 }
 
+impl From<PaymentProofError> for TxBuilderError {
+    fn from(e: PaymentProofError) -> Self {
+        TxBuilderError::PaymentProof(e)
+    }
+}
+
 impl From<ring_signature::Error> for TxBuilderError {
     fn from(_: Error) -> Self {
         TxBuilderError::RingSignatureFailed
     }
 }
+
+#[derive(Debug, Fail)]
+pub enum PaymentProofError {
+    #[fail(display = "The transaction private key did not create the TxOut")]
+    WrongTxPrivateKey,
+
+    #[fail(display = "The proof is for another TxOut")]
+    TxOutMismatch,
+
+    #[fail(display = "The proof is invalid")]
+    InvalidProof,
+
+    #[fail(display = "The TxOut does not pay the recipient")]
+    WrongRecipient,
+
+    #[fail(display = "Bad Amount: {}", _0)]
+    BadAmount(AmountError),
+
+    #[fail(display = "Key error: {}", _0)]
+    KeyError(keys::KeyError),
+}
+
+impl From<AmountError> for PaymentProofError {
+    fn from(x: AmountError) -> Self {
+        PaymentProofError::BadAmount(x)
+    }
+}
+
+impl From<keys::KeyError> for PaymentProofError {
+    fn from(e: keys::KeyError) -> Self {
+        PaymentProofError::KeyError(e)
+    }
+}
@@ -4,9 +4,11 @@ mod block_builder;
 mod error;
 pub mod identity;
 mod input_credentials;
+mod payment_proof;
 mod transaction_builder;
 
 pub use block_builder::BlockBuilder;
-pub use error::TxBuilderError;
+pub use error::{PaymentProofError, TxBuilderError};
 pub use input_credentials::InputCredentials;
+pub use payment_proof::PaymentProof;
 pub use transaction_builder::TransactionBuilder;
@@ -0,0 +1,262 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Proof that the sender of a TxOut paid a given amount to a given address.
+//!
+//! A `ReceiverTxReceipt` only shows that a TxOut landed in the ledger. A `PaymentProof` additionally
+//! shows that whoever produced it knows the transaction private key `r` of the TxOut, i.e. created
+//! it, and which address and amount the TxOut pays.
+//!
+//! Knowledge of `r` is shown with a Chaum-Pedersen proof that `log_D(R) == log_C(S)`, where
+//! `R = r*D` is the TxOut's public key, `(C, D)` is the recipient's address, and `S = r*C` is the
+//! TxOut's shared secret. The recipient also knows `S`, but not `r`, and so cannot produce a proof.
+//!
+//! The proof reveals `S`, so anyone holding it can read the TxOut's amount and memo.
+
+#![allow(non_snake_case)]
+
+use crate::PaymentProofError;
+use blake2::{Blake2b, Digest};
+use common::Hash;
+use curve25519_dalek::{
+    constants::RISTRETTO_BASEPOINT_POINT, ristretto::RistrettoPoint, scalar::Scalar,
+};
+use keys::{CompressedRistrettoPublic, RistrettoPrivate, RistrettoPublic};
+use mcserial::ReprBytes32;
+use rand_core::{CryptoRng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use transaction::{
+    account_keys::PublicAddress,
+    onetime_keys::{compute_shared_secret, compute_tx_pubkey},
+    ring_signature::CurveScalar,
+    tx::TxOut,
+};
+
+const G: RistrettoPoint = RISTRETTO_BASEPOINT_POINT;
+
+/// Domain separator for the proof's challenge.
+const PAYMENT_PROOF_DOMAIN_TAG: &[u8] = b"mc_payment_proof";
+
+/// Proof that the sender of a TxOut paid a given amount to a given address.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct PaymentProof {
+    /// The address the TxOut pays.
+    pub recipient: PublicAddress,
+
+    /// Hash of the TxOut.
+    pub tx_out_hash: Hash,
+
+    /// The TxOut's shared secret `S = r*C`.
+    pub shared_secret: RistrettoPublic,
+
+    /// The challenge `c`.
+    pub challenge: CurveScalar,
+
+    /// The response `z = k + c*r`, for the random nonce `k`.
+    pub response: CurveScalar,
+}
+
+impl PaymentProof {
+    /// Creates a proof that `tx_out` pays `recipient`.
+    ///
+    /// # Arguments
+    /// * `tx_out` - The TxOut.
+    /// * `recipient` - The address `tx_out` was sent to.
+    /// * `tx_private_key` - The transaction private key `r` `tx_out` was created with.
+    /// * `rng` - Randomness.
+    pub fn new<RNG: CryptoRng + RngCore>(
+        tx_out: &TxOut,
+        recipient: &PublicAddress,
+        tx_private_key: &RistrettoPrivate,
+        rng: &mut RNG,
+    ) -> Result<Self, PaymentProofError> {
+        let R = RistrettoPublic::try_from(&tx_out.public_key)?;
+        if compute_tx_pubkey(tx_private_key, recipient.spend_public_key()) != R {
+            return Err(PaymentProofError::WrongTxPrivateKey);
+        }
+
+        let r: &Scalar = tx_private_key.as_ref();
+        let C = recipient.view_public_key().as_ref();
+        let D = recipient.spend_public_key().as_ref();
+        let S = compute_shared_secret(recipient.view_public_key(), tx_private_key);
+        let tx_out_hash = tx_out.hash();
+
+        let k = Scalar::random(rng);
+        let c = challenge(recipient, &tx_out_hash, &R, &S, &(k * D), &(k * C));
+        let z = k + c * r;
+
+        Ok(Self {
+            recipient: recipient.clone(),
+            tx_out_hash,
+            shared_secret: S,
+            challenge: CurveScalar::from(c),
+            response: CurveScalar::from(z),
+        })
+    }
+
+    /// Verifies the proof against `tx_out`, and returns the value `tx_out` pays the recipient.
+    ///
+    /// # Arguments
+    /// * `tx_out` - The TxOut the proof is for, e.g. as found in the ledger.
+    pub fn verify(&self, tx_out: &TxOut) -> Result<u64, PaymentProofError> {
+        if tx_out.hash() != self.tx_out_hash {
+            return Err(PaymentProofError::TxOutMismatch);
+        }
+
+        // The sender knows `r` such that `R = r*D` and `S = r*C`.
+        let R = RistrettoPublic::try_from(&tx_out.public_key)?;
+        let C = self.recipient.view_public_key().as_ref();
+        let D = self.recipient.spend_public_key().as_ref();
+        let S = self.shared_secret.as_ref();
+        let c: Scalar = self.challenge.into();
+        let z: Scalar = self.response.into();
+
+        let A_D = z * D - c * R.as_ref();
+        let A_C = z * C - c * S;
+        let expected_c = challenge(
+            &self.recipient,
+            &self.tx_out_hash,
+            &R,
+            &self.shared_secret,
+            &A_D,
+            &A_C,
+        );
+        if expected_c != c {
+            return Err(PaymentProofError::InvalidProof);
+        }
+
+        // `tx_out` is spendable by the recipient: its onetime key is `Hs(S)*G + D`.
+        let Hs: Scalar = {
+            let mut digest = Blake2b::new();
+            digest.input(S.compress().as_bytes());
+            Scalar::from_hash::<Blake2b>(digest)
+        };
+        if CompressedRistrettoPublic::from(Hs * G + D) != tx_out.target_key {
+            return Err(PaymentProofError::WrongRecipient);
+        }
+
+        let (value, _blinding) = tx_out.amount.get_value(&self.shared_secret)?;
+        Ok(value)
+    }
+}
+
+/// Computes the challenge `Hs(recipient, tx_out_hash, R, S, k*D, k*C)`.
+fn challenge(
+    recipient: &PublicAddress,
+    tx_out_hash: &Hash,
+    R: &RistrettoPublic,
+    S: &RistrettoPublic,
+    A_D: &RistrettoPoint,
+    A_C: &RistrettoPoint,
+) -> Scalar {
+    let mut digest = Blake2b::new();
+    digest.input(PAYMENT_PROOF_DOMAIN_TAG);
+    digest.input(recipient.view_public_key().to_bytes());
+    digest.input(recipient.spend_public_key().to_bytes());
+    digest.input(tx_out_hash);
+    digest.input(R.to_bytes());
+    digest.input(S.to_bytes());
+    digest.input(A_D.compress().as_bytes());
+    digest.input(A_C.compress().as_bytes());
+    Scalar::from_hash::<Blake2b>(digest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use keys::FromRandom;
+    use rand::{rngs::StdRng, SeedableRng};
+    use transaction::{account_keys::AccountKey, encrypted_fog_hint::EncryptedFogHint};
+
+    fn create_tx_out(
+        value: u64,
+        recipient: &PublicAddress,
+        rng: &mut StdRng,
+    ) -> (TxOut, RistrettoPrivate) {
+        let tx_private_key = RistrettoPrivate::from_random(rng);
+        let tx_out = TxOut::new(
+            value,
+            recipient,
+            &tx_private_key,
+            EncryptedFogHint::fake_onetime_hint(rng),
+            rng,
+        )
+        .unwrap();
+        (tx_out, tx_private_key)
+    }
+
+    #[test]
+    // A proof created by the sender should verify, and reveal the value paid.
+    fn test_verify() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let recipient = AccountKey::random(&mut rng).subaddress(3);
+        let (tx_out, tx_private_key) = create_tx_out(1234, &recipient, &mut rng);
+
+        let proof = PaymentProof::new(&tx_out, &recipient, &tx_private_key, &mut rng).unwrap();
+        assert_eq!(proof.verify(&tx_out).unwrap(), 1234);
+
+        // Proofs survive serialization.
+        let proof_ser = mcserial::serialize(&proof).unwrap();
+        let proof: PaymentProof = mcserial::deserialize(&proof_ser).unwrap();
+        assert_eq!(proof.verify(&tx_out).unwrap(), 1234);
+    }
+
+    #[test]
+    // Only the key the TxOut was created with can produce a proof.
+    fn test_new_wrong_tx_private_key() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let recipient = AccountKey::random(&mut rng).default_subaddress();
+        let (tx_out, _tx_private_key) = create_tx_out(1234, &recipient, &mut rng);
+
+        match PaymentProof::new(
+            &tx_out,
+            &recipient,
+            &RistrettoPrivate::from_random(&mut rng),
+            &mut rng,
+        ) {
+            Err(PaymentProofError::WrongTxPrivateKey) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    // A proof should not verify against another TxOut.
+    fn test_verify_other_tx_out() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let recipient = AccountKey::random(&mut rng).default_subaddress();
+        let (tx_out, tx_private_key) = create_tx_out(1234, &recipient, &mut rng);
+        let (other_tx_out, _) = create_tx_out(1234, &recipient, &mut rng);
+
+        let proof = PaymentProof::new(&tx_out, &recipient, &tx_private_key, &mut rng).unwrap();
+        match proof.verify(&other_tx_out) {
+            Err(PaymentProofError::TxOutMismatch) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    // Tampering with any part of the proof should invalidate it.
+    fn test_verify_tampered() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let recipient = AccountKey::random(&mut rng).default_subaddress();
+        let (tx_out, tx_private_key) = create_tx_out(1234, &recipient, &mut rng);
+        let proof = PaymentProof::new(&tx_out, &recipient, &tx_private_key, &mut rng).unwrap();
+
+        // Claiming the TxOut paid someone else.
+        let mut tampered = proof.clone();
+        tampered.recipient = AccountKey::random(&mut rng).default_subaddress();
+        assert!(tampered.verify(&tx_out).is_err());
+
+        let mut tampered = proof.clone();
+        tampered.shared_secret = RistrettoPublic::from_random(&mut rng);
+        assert!(tampered.verify(&tx_out).is_err());
+
+        let mut tampered = proof.clone();
+        tampered.response = CurveScalar::from(Scalar::random(&mut rng));
+        assert!(tampered.verify(&tx_out).is_err());
+
+        let mut tampered = proof;
+        tampered.challenge = CurveScalar::from(Scalar::random(&mut rng));
+        assert!(tampered.verify(&tx_out).is_err());
+    }
+}
@@ -7,15 +7,16 @@
 use keys::{FromRandom, RistrettoPrivate, RistrettoPublic};
 use std::collections::HashSet;
 
-use crate::{InputCredentials, TxBuilderError};
+use crate::{InputCredentials, PaymentProof, TxBuilderError};
 use curve25519_dalek::scalar::Scalar;
 use rand_core::{CryptoRng, RngCore};
 use std::convert::TryFrom;
 use transaction::{
     account_keys::PublicAddress,
-    constants::BASE_FEE,
     encrypted_fog_hint::EncryptedFogHint,
+    fee_schedule::FeeSchedule,
     fog_hint::FogHint,
+    memo::Memo,
     onetime_keys::compute_shared_secret,
     ring_signature::{Address, Commitment, SignatureRctBulletproofs},
     tx::{Tx, TxIn, TxOut, TxPrefix},
@@ -27,8 +28,10 @@ pub struct TransactionBuilder {
     input_credentials: Vec<InputCredentials>,
     outputs: Vec<TxOut>,
     output_shared_secrets: Vec<RistrettoPublic>,
+    output_tx_private_keys: Vec<RistrettoPrivate>,
+    output_recipients: Vec<PublicAddress>,
     tombstone_block: u64,
-    pub fee: u64,
+    fee: Option<u64>,
 }
 
 impl TransactionBuilder {
@@ -38,8 +41,10 @@ impl TransactionBuilder {
             input_credentials: Vec::new(),
             outputs: Vec::new(),
             output_shared_secrets: Vec::new(),
+            output_tx_private_keys: Vec::new(),
+            output_recipients: Vec::new(),
             tombstone_block: u64::max_value(),
-            fee: BASE_FEE,
+            fee: None,
         }
     }
 
@@ -57,6 +62,7 @@ impl TransactionBuilder {
     /// * `value` - The value of this output.
     /// * `recipient` - The recipient's public address
     /// * `recipient_fog_ingest_key` - The recipient's fog server's public key
+    /// * `memo` - Memo to encrypt to the recipient, e.g. an invoice ID
     /// * `rng` - RNG used to generate blinding for commitment
     ///
     pub fn add_output<RNG: CryptoRng + RngCore>(
@@ -64,17 +70,43 @@ impl TransactionBuilder {
         value: u64,
         recipient: &PublicAddress,
         recipient_fog_ingest_key: Option<&RistrettoPublic>,
+        memo: Option<&Memo>,
         rng: &mut RNG,
     ) -> Result<TxOut, TxBuilderError> {
-        let (tx_out, shared_secret) =
-            create_output(value, recipient, recipient_fog_ingest_key, rng)?;
+        let (tx_out, shared_secret, tx_private_key) =
+            create_output(value, recipient, recipient_fog_ingest_key, memo, rng)?;
 
         self.outputs.push(tx_out.clone());
         self.output_shared_secrets.push(shared_secret);
+        self.output_tx_private_keys.push(tx_private_key);
+        self.output_recipients.push(recipient.clone());
 
         Ok(tx_out)
     }
 
+    /// Creates a proof that an output added with `add_output` pays its recipient.
+    ///
+    /// # Arguments
+    /// * `tx_out` - The output, as returned by `add_output`.
+    /// * `rng` - Randomness.
+    pub fn payment_proof<RNG: CryptoRng + RngCore>(
+        &self,
+        tx_out: &TxOut,
+        rng: &mut RNG,
+    ) -> Result<PaymentProof, TxBuilderError> {
+        let index = self
+            .outputs
+            .iter()
+            .position(|output| output == tx_out)
+            .ok_or(TxBuilderError::UnknownOutput)?;
+        Ok(PaymentProof::new(
+            tx_out,
+            &self.output_recipients[index],
+            &self.output_tx_private_keys[index],
+            rng,
+        )?)
+    }
+
     /// Sets the tombstone block.
     ///
     /// # Arguments
@@ -83,12 +115,13 @@ impl TransactionBuilder {
         self.tombstone_block = tombstone_block;
     }
 
-    /// Sets the transaction fee.
+    /// Sets the transaction fee. Defaults to the minimum fee of the current fee schedule, given
+    /// the inputs and outputs added when the transaction is built.
     ///
     /// # Arguments
     /// * `fee` - Transaction fee.
     pub fn set_fee(&mut self, fee: u64) {
-        self.fee = fee;
+        self.fee = Some(fee);
     }
 
     /// Consume the builder and return the transaction.
@@ -118,7 +151,11 @@ impl TransactionBuilder {
             })
             .collect();
 
-        let tx_prefix = TxPrefix::new(inputs, self.outputs.clone(), self.fee);
+        let fee = self.fee.unwrap_or_else(|| {
+            FeeSchedule::current().minimum_fee(inputs.len(), self.outputs.len())
+        });
+
+        let tx_prefix = TxPrefix::new(inputs, self.outputs.clone(), fee);
 
         let tx_prefix_hash = tx_prefix.hash();
         let message = tx_prefix_hash.as_bytes();
@@ -205,13 +242,14 @@ fn create_output<RNG: CryptoRng + RngCore>(
     value: u64,
     recipient: &PublicAddress,
     ingest_pubkey: Option<&RistrettoPublic>,
+    memo: Option<&Memo>,
     rng: &mut RNG,
-) -> Result<(TxOut, RistrettoPublic), TxBuilderError> {
+) -> Result<(TxOut, RistrettoPublic, RistrettoPrivate), TxBuilderError> {
     let private_key = RistrettoPrivate::from_random(rng);
     let hint = create_fog_hint(recipient, ingest_pubkey, rng)?;
-    let tx_out = TxOut::new(value, recipient, &private_key, hint, rng)?;
+    let tx_out = TxOut::new_with_memo(value, recipient, &private_key, hint, memo, rng)?;
     let shared_secret = compute_shared_secret(recipient.view_public_key(), &private_key);
-    Ok((tx_out, shared_secret))
+    Ok((tx_out, shared_secret, private_key))
 }
 
 /// Creates an Encrypted Fog Hint for a recipient
@@ -243,6 +281,7 @@ pub mod transaction_builder_tests {
     use std::convert::TryFrom;
     use transaction::{
         account_keys::{AccountKey, DEFAULT_SUBADDRESS_INDEX},
+        get_tx_out_shared_secret,
         onetime_keys::*,
         tx::TxOutMembershipProof,
         validation::validate_transaction_signature,
@@ -269,8 +308,8 @@ pub mod transaction_builder_tests {
             recipient_and_amounts
                 .iter()
                 .map(|(recipient, amount)| {
-                    let (tx_out, _shared_secret) =
-                        create_output(*amount, &recipient, None, &mut rng).unwrap();
+                    let (tx_out, _shared_secret, _tx_private_key) =
+                        create_output(*amount, &recipient, None, None, &mut rng).unwrap();
                     tx_out
                 })
                 .collect()
@@ -308,10 +347,24 @@ pub mod transaction_builder_tests {
         )
         .unwrap();
 
+        let memo = Memo::try_from(&b"invoice #1234"[..]).unwrap();
+
+        // The builder defaults to the minimum fee of one input and one output.
+        let fee = FeeSchedule::current().minimum_fee(1, 1);
+
         let mut transaction_builder = TransactionBuilder::new();
         transaction_builder.add_input(input_credentials);
-        transaction_builder
-            .add_output(65536 - BASE_FEE, &bob.default_subaddress(), None, &mut rng)
+        let bob_tx_out = transaction_builder
+            .add_output(
+                65536 - fee,
+                &bob.default_subaddress(),
+                None,
+                Some(&memo),
+                &mut rng,
+            )
+            .unwrap();
+        let payment_proof = transaction_builder
+            .payment_proof(&bob_tx_out, &mut rng)
             .unwrap();
 
         let tx = transaction_builder.build(&mut rng).unwrap();
@@ -327,6 +380,8 @@ pub mod transaction_builder_tests {
         // The transaction should have one output.
         assert_eq!(tx.prefix.outputs.len(), 1);
 
+        assert_eq!(tx.prefix.fee, fee);
+
         // The output should belong to the correct recipient.
         {
             let tx_out: &TxOut = tx.prefix.outputs.get(0).unwrap();
@@ -335,6 +390,17 @@ pub mod transaction_builder_tests {
                 &RistrettoPublic::try_from(&tx_out.target_key).unwrap(),
                 &RistrettoPublic::try_from(&tx_out.public_key).unwrap()
             ));
+
+            // Only the recipient can read the memo.
+            let shared_secret = get_tx_out_shared_secret(
+                bob.view_private_key(),
+                &RistrettoPublic::try_from(&tx_out.public_key).unwrap(),
+            );
+            assert_eq!(tx_out.decrypt_memo(&shared_secret), Some(memo));
+
+            // The sender can prove the payment.
+            assert_eq!(payment_proof.recipient, bob.default_subaddress());
+            assert_eq!(payment_proof.verify(tx_out).unwrap(), 65536 - fee);
         }
 
         // The transaction should have a valid signature.